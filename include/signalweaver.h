@@ -0,0 +1,87 @@
+/* C API of signalweaver, built into the cdylib with `cargo build --release --features ffi`
+ * (libsignalweaver.so, .dylib or signalweaver.dll). Handles are opaque and freed by the
+ * caller with the matching sw_*_free function. */
+
+#ifndef SIGNALWEAVER_H
+#define SIGNALWEAVER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Outcome of a call; on failure sw_last_error() describes it. */
+typedef enum SwStatus {
+    SW_OK = 0,
+    SW_NULL_POINTER = 1,
+    SW_INVALID_ARGUMENT = 2,
+    SW_UNKNOWN_DETECTOR = 3,
+    SW_NOT_ENOUGH_BEATS = 4,
+    SW_PANIC = 5
+} SwStatus;
+
+typedef struct SwDetector SwDetector;
+typedef struct SwBeats SwBeats;
+
+/* A detected beat: time in seconds from the first sample, sample index, amplitude of the
+ * (pre-filtered) signal in mV and the confidence in it, from 0 to 1. */
+typedef struct SwBeat {
+    double time;
+    uint64_t sample;
+    double amplitude;
+    double confidence;
+} SwBeat;
+
+/* HRV of the RR intervals between beats, intervals in ms, powers (Welch) in ms^2; NaN where
+ * a measure could not be computed. */
+typedef struct SwHrv {
+    uint64_t count;
+    double mean_rr_ms;
+    double sdnn_ms;
+    double rmssd_ms;
+    double pnn50_percent;
+    double sdann_ms;
+    double mean_hr_bpm;
+    double vlf_ms2;
+    double lf_ms2;
+    double hf_ms2;
+    double lf_hf;
+} SwHrv;
+
+/* Library version, a static string. */
+const char *sw_version(void);
+
+/* The last error on the calling thread, valid until the next failing call on it. */
+const char *sw_last_error(void);
+
+/* A detector by name ("threshold", "pan-tompkins", "wavelet", "hamilton", "ensemble"; NULL
+ * for "threshold") for signals sampled at fs Hz, without pre-filtering. */
+SwStatus sw_detector_new(const char *name, double fs, SwDetector **out);
+
+/* Pre-filtering before detection: cutoffs and notch frequency in Hz, 0 leaving a stage out. */
+SwStatus sw_detector_set_filter(SwDetector *detector, double highpass, double lowpass,
+                                double notch);
+
+void sw_detector_free(SwDetector *detector);
+
+/* Detects the beats of len samples of one lead, in mV. */
+SwStatus sw_detect(const SwDetector *detector, const double *samples, size_t len,
+                   SwBeats **out);
+
+size_t sw_beats_len(const SwBeats *beats);
+
+/* The beats in time order, valid until the handle is freed. */
+const SwBeat *sw_beats_data(const SwBeats *beats);
+
+void sw_beats_free(SwBeats *beats);
+
+/* HRV of the beats; SW_NOT_ENOUGH_BEATS below three beats. */
+SwStatus sw_hrv(const SwBeats *beats, SwHrv *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
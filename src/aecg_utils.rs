@@ -0,0 +1,635 @@
+use crate::compression::open_input;
+use crate::datetime::days_from_civil;
+use crate::edf_utils::{find_label, millivolts_per, EdfAnnotation};
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// A recording read from an HL7 annotated ECG (aECG) XML file, the format of ECGs submitted
+/// to the FDA.
+#[derive(Debug, Clone)]
+pub struct AecgRecord {
+    /// Seconds since 1970-01-01 of the first sample, in the local time the file gives;
+    /// `None` when the sequence set holds relative times only.
+    pub start: Option<f64>,
+    pub sampling_frequency: f64,
+    /// The leads of the sequence set, in millivolts from the first sample on.
+    pub ecg: MultiLeadEcg,
+    /// The annotations of the series that have a time, e.g. beats and waves, with their
+    /// codes (`MDC_ECG_BEAT_NORMAL`, `MDC_ECG_WAVE_P`, ...) as text and onsets in seconds
+    /// from the first sample.
+    pub annotations: Vec<EdfAnnotation>,
+}
+
+impl AecgRecord {
+    /// Index of the lead with the given name (`II`, `V5`, ...), ignoring case; the error
+    /// lists the leads there are.
+    pub fn find_lead(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let names: Vec<&str> = self
+            .ecg
+            .leads
+            .iter()
+            .map(|lead| lead.name.as_str())
+            .collect();
+        find_label(&names, name)
+    }
+}
+
+// an XML element with its attributes, child elements and text, names without their
+// namespace prefix
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    // the `code` attribute of the `code` child
+    fn code(&self) -> Option<&str> {
+        self.child("code")?.attribute("code")
+    }
+
+    // every element of the given name below this one, outer ones first
+    fn descendants<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            child.descendants(name, found);
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+// replaces the predefined and numeric character references
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|n| n.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+// the name and attributes of a start tag, without its angle brackets
+fn parse_tag(tag: &str) -> Result<Element, Box<dyn Error>> {
+    let tag = tag.trim();
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let mut element = Element {
+        name: local_name(&tag[..name_end]).to_string(),
+        ..Element::default()
+    };
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let equals = rest
+            .find('=')
+            .ok_or_else(|| format!("Malformed XML attribute in <{}>", tag))?;
+        let name = rest[..equals].trim();
+        let value = rest[equals + 1..].trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|&quote| quote == '"' || quote == '\'')
+            .ok_or_else(|| format!("Unquoted XML attribute in <{}>", tag))?;
+        let end = value[1..]
+            .find(quote)
+            .ok_or_else(|| format!("Unterminated XML attribute in <{}>", tag))?;
+        element
+            .attributes
+            .push((local_name(name).to_string(), unescape(&value[1..end + 1])));
+        rest = value[end + 2..].trim_start();
+    }
+    Ok(element)
+}
+
+// the root element of an XML document; DTDs are skipped, not interpreted
+fn parse_xml(text: &str) -> Result<Element, Box<dyn Error>> {
+    let mut stack: Vec<Element> = Vec::new();
+    let mut rest = text;
+    loop {
+        let Some(start) = rest.find('<') else {
+            return Err("XML document ends before its root element closes".into());
+        };
+        if let Some(parent) = stack.last_mut() {
+            parent.text.push_str(&unescape(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        let skip_to = |rest: &str, end: &str| {
+            rest.find(end)
+                .map(|at| at + end.len())
+                .ok_or_else(|| format!("Unterminated XML markup, expected {}", end))
+        };
+        if rest.starts_with("<?") {
+            rest = &rest[skip_to(rest, "?>")?..];
+        } else if rest.starts_with("<!--") {
+            rest = &rest[skip_to(rest, "-->")?..];
+        } else if let Some(data) = rest.strip_prefix("<![CDATA[") {
+            let end = data.find("]]>").ok_or("Unterminated XML CDATA section")?;
+            if let Some(parent) = stack.last_mut() {
+                parent.text.push_str(&data[..end]);
+            }
+            rest = &data[end + 3..];
+        } else if rest.starts_with("<!") {
+            rest = &rest[skip_to(rest, ">")?..];
+        } else if let Some(tag) = rest.strip_prefix("</") {
+            let end = tag.find('>').ok_or("Unterminated XML end tag")?;
+            let name = local_name(tag[..end].trim());
+            let element = stack.pop().ok_or("XML end tag without a start tag")?;
+            if element.name != name {
+                return Err(format!("XML element <{}> closed by </{}>", element.name, name).into());
+            }
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => return Ok(element),
+            }
+            rest = &tag[end + 1..];
+        } else {
+            // the tag ends at the first `>` outside quotes
+            let mut quote = None;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| {
+                    match quote {
+                        Some(q) if c == q => quote = None,
+                        None if c == '"' || c == '\'' => quote = Some(c),
+                        _ => {}
+                    }
+                    quote.is_none() && c == '>'
+                })
+                .map(|(at, _)| at)
+                .ok_or("Unterminated XML start tag")?;
+            let tag = &rest[1..end];
+            match tag.strip_suffix('/') {
+                Some(tag) => {
+                    let element = parse_tag(tag)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                None => stack.push(parse_tag(tag)?),
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+}
+
+// seconds since 1970-01-01 of an HL7 TS value, `YYYYMMDDHHMMSS.UUUU` with the later parts
+// optional and any time zone offset ignored
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let value = value.split(['+', '-']).next()?;
+    let (digits, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let part = |from: usize, len: usize, default: i64| match digits.get(from..from + len) {
+        Some(part) => part.parse::<i64>().ok(),
+        None if digits.len() <= from => Some(default),
+        None => None,
+    };
+    let year = part(0, 4, -1).filter(|&year| year >= 0)?;
+    let month = part(4, 2, 1)?;
+    let day = part(6, 2, 1)?;
+    let seconds = 3600 * part(8, 2, 0)? + 60 * part(10, 2, 0)? + part(12, 2, 0)?;
+    let fraction = if fraction.is_empty() {
+        0.0
+    } else {
+        format!("0.{}", fraction).parse::<f64>().ok()?
+    };
+    let days = days_from_civil(year, month as u32, day as u32);
+    Some(days as f64 * 86_400.0 + seconds as f64 + fraction)
+}
+
+// seconds per unit of a time unit
+fn seconds_per(unit: &str) -> Option<f64> {
+    match unit {
+        "s" => Some(1.0),
+        "ms" => Some(1e-3),
+        "us" | "µs" => Some(1e-6),
+        "min" => Some(60.0),
+        _ => None,
+    }
+}
+
+// the `value` attribute of a physical quantity in seconds
+fn time_quantity(element: &Element) -> Option<f64> {
+    let value: f64 = element.attribute("value")?.trim().parse().ok()?;
+    Some(value * seconds_per(element.attribute("unit").unwrap_or("s"))?)
+}
+
+// the `value` attribute of a physical quantity in millivolts
+fn voltage_quantity(element: &Element) -> Result<f64, Box<dyn Error>> {
+    let value: f64 = element
+        .attribute("value")
+        .ok_or("aECG quantity without a value")?
+        .trim()
+        .parse()?;
+    let unit = element.attribute("unit").unwrap_or("uV");
+    let scale =
+        millivolts_per(unit).ok_or_else(|| format!("Unknown aECG voltage unit {:?}", unit))?;
+    Ok(value * scale)
+}
+
+// the lead name of an MDC code, e.g. II for MDC_ECG_LEAD_II and aVR for MDC_ECG_LEAD_AVR
+fn lead_name(code: &str) -> String {
+    let name = code.strip_prefix("MDC_ECG_LEAD_").unwrap_or(code);
+    match name {
+        "AVR" => "aVR".to_string(),
+        "AVL" => "aVL".to_string(),
+        "AVF" => "aVF".to_string(),
+        name => name.to_string(),
+    }
+}
+
+// first sample time (absolute or relative) and sample interval of the time sequence
+struct Timing {
+    absolute: bool,
+    head: f64,
+    increment: f64,
+}
+
+fn parse_timing(code: &str, value: &Element) -> Result<Timing, Box<dyn Error>> {
+    let absolute = code == "TIME_ABSOLUTE";
+    let head = value
+        .child("head")
+        .ok_or("aECG time sequence without a head")?;
+    let head = if absolute {
+        head.attribute("value").and_then(parse_timestamp)
+    } else {
+        time_quantity(head)
+    }
+    .ok_or("Invalid head of the aECG time sequence")?;
+    let increment = value
+        .child("increment")
+        .and_then(time_quantity)
+        .filter(|&increment| increment > 0.0)
+        .ok_or("aECG time sequence without a valid increment")?;
+    Ok(Timing {
+        absolute,
+        head,
+        increment,
+    })
+}
+
+// onset and duration of an annotation from its TIME_ABSOLUTE or TIME_RELATIVE region of
+// interest, in seconds from the first sample
+fn annotation_time(annotation: &Element, timing: &Timing) -> Option<(f64, Option<f64>)> {
+    let roi = annotation.child("support")?.child("supportingROI")?;
+    let boundary = roi
+        .children("component")
+        .filter_map(|component| component.child("boundary"))
+        .find(|boundary| {
+            boundary
+                .code()
+                .is_some_and(|code| code.starts_with("TIME_"))
+        })?;
+    let absolute = boundary.code() == Some("TIME_ABSOLUTE");
+    let value = boundary.child("value")?;
+    let time = |element: &Element| {
+        if absolute {
+            let time = parse_timestamp(element.attribute("value")?)?;
+            // relative to the sequence start, which is absolute too when given so
+            Some(time - if timing.absolute { timing.head } else { 0.0 })
+        } else {
+            Some(time_quantity(element)? - if timing.absolute { 0.0 } else { timing.head })
+        }
+    };
+    let low = time(value.child("low")?)?;
+    let high = value.child("high").and_then(time);
+    Some((low, high.map(|high| high - low)))
+}
+
+// the annotations below `annotation` that have a time, nested ones inheriting the time
+// of the annotation they belong to
+fn collect_annotations(
+    annotation: &Element,
+    inherited: Option<(f64, Option<f64>)>,
+    timing: &Timing,
+    annotations: &mut Vec<EdfAnnotation>,
+) {
+    let time = annotation_time(annotation, timing).or(inherited);
+    let value = annotation.child("value");
+    let text = match value.and_then(|value| value.attribute("code")) {
+        Some(code) => Some(code.to_string()),
+        None => annotation.code().map(|code| {
+            match value.and_then(|value| Some((value.attribute("value")?, value.attribute("unit"))))
+            {
+                Some((quantity, Some(unit))) => format!("{} {} {}", code, quantity, unit),
+                Some((quantity, None)) => format!("{} {}", code, quantity),
+                None => code.to_string(),
+            }
+        }),
+    };
+    if let (Some((onset, duration)), Some(text)) = (time, text) {
+        annotations.push(EdfAnnotation {
+            onset,
+            duration,
+            text,
+        });
+    }
+    for nested in annotation
+        .children("component")
+        .filter_map(|component| component.child("annotation"))
+    {
+        collect_annotations(nested, time, timing, annotations);
+    }
+}
+
+/// Reads an HL7 aECG file: the leads of the first sequence set of its rhythm series (or of
+/// its first series when none is coded RHYTHM), calibrated from their origin and scale, and
+/// the annotations of that series.
+pub fn read_aecg<P: AsRef<Path>>(path: P) -> Result<AecgRecord, Box<dyn Error>> {
+    let mut text = String::new();
+    open_input(path)?.read_to_string(&mut text)?;
+    let root = parse_xml(&text)?;
+    if root.name != "AnnotatedECG" {
+        return Err(format!("Not an HL7 aECG file, its root element is <{}>", root.name).into());
+    }
+
+    let mut series = Vec::new();
+    root.descendants("series", &mut series);
+    let series = series
+        .iter()
+        .find(|series| series.code() == Some("RHYTHM"))
+        .or(series.first())
+        .ok_or("aECG file without a series")?;
+    let sequence_set = series
+        .children("component")
+        .find_map(|component| component.child("sequenceSet"))
+        .ok_or("aECG series without a sequence set")?;
+
+    let mut timing = None;
+    let mut leads = Vec::new();
+    for sequence in sequence_set
+        .children("component")
+        .filter_map(|component| component.child("sequence"))
+    {
+        let code = sequence.code().unwrap_or_default();
+        let Some(value) = sequence.child("value") else {
+            continue;
+        };
+        if code.starts_with("TIME_") {
+            timing = Some(parse_timing(code, value)?);
+            continue;
+        }
+        let Some(digits) = value.child("digits") else {
+            warn!("aECG sequence {} holds no digits, skipping it", code);
+            continue;
+        };
+        let origin = value.child("origin").map(voltage_quantity).transpose()?;
+        let scale = value.child("scale").map(voltage_quantity).transpose()?;
+        let (origin, scale) = (origin.unwrap_or(0.0), scale.unwrap_or(1e-3));
+        let voltage = digits
+            .text
+            .split_whitespace()
+            .map(|digit| Ok(origin + scale * digit.parse::<f64>()?))
+            .collect::<Result<Vec<f64>, Box<dyn Error>>>()?;
+        leads.push(Lead {
+            name: lead_name(code),
+            voltage,
+        });
+    }
+    let timing = timing.ok_or("aECG sequence set without a time sequence")?;
+    if leads.is_empty() {
+        return Err("aECG sequence set without leads".into());
+    }
+
+    let num_samples = leads
+        .iter()
+        .map(|lead| lead.voltage.len())
+        .min()
+        .unwrap_or(0);
+    for lead in &mut leads {
+        lead.voltage.truncate(num_samples);
+    }
+    let ecg = MultiLeadEcg {
+        times: (0..num_samples)
+            .map(|i| i as f64 * timing.increment)
+            .collect(),
+        leads,
+    };
+
+    let mut annotations = Vec::new();
+    for annotation_set in series
+        .children("subjectOf")
+        .filter_map(|subject| subject.child("annotationSet"))
+    {
+        for annotation in annotation_set
+            .children("component")
+            .filter_map(|component| component.child("annotation"))
+        {
+            collect_annotations(annotation, None, &timing, &mut annotations);
+        }
+    }
+    annotations.sort_by(|a, b| a.onset.total_cmp(&b.onset));
+
+    info!(
+        "Total data points: {} in {} leads, {} annotations",
+        ecg.times.len(),
+        ecg.leads.len(),
+        annotations.len()
+    );
+
+    Ok(AecgRecord {
+        start: timing.absolute.then_some(timing.head),
+        sampling_frequency: 1.0 / timing.increment,
+        ecg,
+        annotations,
+    })
+}
+
+/// Reads one lead of an HL7 aECG file in millivolts.
+pub fn read_aecg_ecg_data<P: AsRef<Path>>(
+    path: P,
+    lead: usize,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let ecg = read_aecg(path)?.ecg;
+    let lead = ecg.leads.get(lead).ok_or_else(|| {
+        format!(
+            "Lead index {} out of range, the file has {} leads",
+            lead,
+            ecg.leads.len()
+        )
+    })?;
+
+    Ok(ecg
+        .times
+        .iter()
+        .zip(&lead.voltage)
+        .map(|(&time, &voltage)| EcgPoint { time, voltage })
+        .collect())
+}
+
+/// Reads every lead of an HL7 aECG file.
+pub fn read_aecg_leads<P: AsRef<Path>>(path: P) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    Ok(read_aecg(path)?.ecg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const FIXTURE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- two leads at 500 Hz with one annotated beat -->
+<AnnotatedECG xmlns="urn:hl7-org:v3" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <component><series>
+    <code code="RHYTHM"/>
+    <component><sequenceSet>
+      <component><sequence>
+        <code code="TIME_ABSOLUTE"/>
+        <value xsi:type="GLIST_TS">
+          <head value="20200605083000.000"/>
+          <increment value="2" unit="ms"/>
+        </value>
+      </sequence></component>
+      <component><sequence>
+        <code code="MDC_ECG_LEAD_II"/>
+        <value xsi:type="SLIST_PQ">
+          <origin value="0" unit="uV"/>
+          <scale value="5" unit="uV"/>
+          <digits>200 -200 0 400</digits>
+        </value>
+      </sequence></component>
+      <component><sequence>
+        <code code="MDC_ECG_LEAD_AVR"/>
+        <value xsi:type="SLIST_PQ">
+          <origin value="-1" unit="mV"/>
+          <scale value="0.01" unit="mV"/>
+          <digits>100 200 300</digits>
+        </value>
+      </sequence></component>
+    </sequenceSet></component>
+    <subjectOf><annotationSet>
+      <component><annotation>
+        <code code="MDC_ECG_BEAT"/>
+        <value xsi:type="CE" code="MDC_ECG_BEAT_NORMAL"/>
+        <support><supportingROI>
+          <component><boundary>
+            <code code="TIME_ABSOLUTE"/>
+            <value><low value="20200605083000.004"/><high value="20200605083000.104"/></value>
+          </boundary></component>
+        </supportingROI></support>
+        <component><annotation>
+          <code code="MDC_ECG_TIME_PD_QT"/>
+          <value xsi:type="PQ" value="400" unit="ms"/>
+        </annotation></component>
+      </annotation></component>
+    </annotationSet></subjectOf>
+  </series></component>
+</AnnotatedECG>
+"#;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn reads_a_fixture() {
+        let path = std::env::temp_dir().join(format!("signalweaver_{}.xml", std::process::id()));
+        fs::write(&path, FIXTURE).unwrap();
+        let record = read_aecg(&path);
+        fs::remove_file(&path).unwrap();
+
+        let record = record.unwrap();
+        let start = days_from_civil(2020, 6, 5) as f64 * 86_400.0 + 30_600.0;
+        assert_eq!(record.start, Some(start));
+        assert_close(record.sampling_frequency, 500.0);
+        // the leads are cut to the shortest one
+        assert_eq!(record.ecg.times.len(), 3);
+        assert_eq!(record.find_lead("avr").unwrap(), 1);
+        for (lead, expected) in record
+            .ecg
+            .leads
+            .iter()
+            .zip([[1.0, -1.0, 0.0], [0.0, 1.0, 2.0]])
+        {
+            for (&voltage, expected) in lead.voltage.iter().zip(expected) {
+                assert_close(voltage, expected);
+            }
+        }
+
+        let texts: Vec<&str> = record
+            .annotations
+            .iter()
+            .map(|annotation| annotation.text.as_str())
+            .collect();
+        assert_eq!(texts, ["MDC_ECG_BEAT_NORMAL", "MDC_ECG_TIME_PD_QT 400 ms"]);
+        for annotation in &record.annotations {
+            // the nested measurement takes the time of its beat
+            assert_close(annotation.onset, 0.004);
+            assert_close(annotation.duration.unwrap(), 0.1);
+        }
+    }
+
+    #[test]
+    fn parses_xml_markup() {
+        assert_eq!(
+            unescape("a &lt; b &amp;&#x41;&#66; &bogus; &"),
+            "a < b &AB &bogus; &"
+        );
+        let root = parse_xml(
+            "<!DOCTYPE x><x:root a='1 > 0'><![CDATA[<raw>]]><y b=\"&quot;\"/>tail</x:root>",
+        )
+        .unwrap();
+        assert_eq!(root.name, "root");
+        assert_eq!(root.attribute("a"), Some("1 > 0"));
+        assert_eq!(root.text, "<raw>tail");
+        assert_eq!(root.child("y").unwrap().attribute("b"), Some("\""));
+        assert!(parse_xml("<a><b></a>").is_err());
+        assert!(parse_xml("<a>").is_err());
+    }
+}
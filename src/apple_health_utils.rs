@@ -0,0 +1,233 @@
+use crate::compression::open_input;
+use crate::edf_utils::millivolts_per;
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
+use std::error::Error;
+use std::io::BufRead;
+use std::path::Path;
+use tracing::{info, warn};
+
+// rate of every Apple Watch recording, for exports that leave it out
+const APPLE_WATCH_FS: f64 = 512.0;
+// metadata lines an export starts with, more than enough to find the sample rate
+const METADATA_LINES: usize = 20;
+
+/// An ECG recorded by an Apple Watch, read from its Apple Health CSV export: metadata lines
+/// (`Name,...`, `Sample Rate,512 hertz`, `Unit,µV`, ...) followed by one sample per line.
+#[derive(Debug, Clone)]
+pub struct AppleHealthEcg {
+    pub name: String,
+    /// As the export gives it, e.g. `2021-03-04 09:10:11 +0100`.
+    pub recorded_date: String,
+    /// The watch's own verdict, e.g. `Sinus Rhythm` or `Atrial Fibrillation`.
+    pub classification: String,
+    /// `Lead I` for every Apple Watch recording.
+    pub lead: String,
+    pub sampling_frequency: f64,
+    /// Samples in millivolts.
+    pub voltage: Vec<f64>,
+}
+
+// a metadata line split into its key and value, quotes removed
+fn metadata(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(',')?;
+    // a byte order mark may precede the first key
+    let key = key
+        .trim_start_matches('\u{feff}')
+        .trim()
+        .trim_matches('"')
+        .trim();
+    (!key.is_empty() && key.parse::<f64>().is_err())
+        .then(|| (key, value.trim().trim_matches('"').trim()))
+}
+
+// a sample line, with a decimal point or a decimal comma
+fn sample(line: &str) -> Option<f64> {
+    let line = line.trim().trim_matches('"').trim();
+    line.parse()
+        .ok()
+        .or_else(|| line.replace(',', ".").parse().ok())
+}
+
+/// Whether the file starts like an Apple Health ECG export, with a `Sample Rate` line among
+/// its metadata.
+pub fn is_apple_health_ecg<P: AsRef<Path>>(path: P) -> bool {
+    let Ok(reader) = open_input(path) else {
+        return false;
+    };
+    reader
+        .lines()
+        .take(METADATA_LINES)
+        .map_while(Result::ok)
+        .any(|line| metadata(&line).is_some_and(|(key, _)| key == "Sample Rate"))
+}
+
+/// Reads an Apple Health ECG export, converting the samples (in µV unless its `Unit` line
+/// says otherwise) to millivolts.
+pub fn read_apple_health_ecg<P: AsRef<Path>>(path: P) -> Result<AppleHealthEcg, Box<dyn Error>> {
+    let reader = open_input(path)?;
+
+    let mut ecg = AppleHealthEcg {
+        name: String::new(),
+        recorded_date: String::new(),
+        classification: String::new(),
+        lead: "Lead I".to_string(),
+        sampling_frequency: APPLE_WATCH_FS,
+        voltage: Vec::new(),
+    };
+    let mut sample_rate = None;
+    let mut unit = "µV".to_string();
+    let mut voltages = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(value) = sample(&line) {
+            voltages.push(value);
+            continue;
+        }
+        let Some((key, value)) = metadata(&line) else {
+            return Err(format!(
+                "Line {} of the Apple Health export is neither a sample nor metadata: {:?}",
+                number + 1,
+                line
+            )
+            .into());
+        };
+        if !voltages.is_empty() {
+            return Err(format!("Metadata line {} after the samples", number + 1).into());
+        }
+        match key {
+            "Name" => ecg.name = value.to_string(),
+            "Recorded Date" => ecg.recorded_date = value.to_string(),
+            "Classification" => ecg.classification = value.to_string(),
+            "Lead" => ecg.lead = value.to_string(),
+            "Unit" => unit = value.to_string(),
+            "Sample Rate" => {
+                // `512 hertz`
+                let rate = value.split_whitespace().next().and_then(sample);
+                sample_rate = Some(
+                    rate.filter(|&rate| rate > 0.0)
+                        .ok_or_else(|| format!("Invalid sample rate {:?}", value))?,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    match sample_rate {
+        Some(rate) => ecg.sampling_frequency = rate,
+        None => warn!(
+            "The Apple Health export gives no sample rate, assuming {} Hz",
+            APPLE_WATCH_FS
+        ),
+    }
+    let scale = millivolts_per(&unit).unwrap_or_else(|| {
+        warn!(
+            "The Apple Health export is in {:?}, its values are used unconverted",
+            unit
+        );
+        1.0
+    });
+    ecg.voltage = voltages.iter().map(|voltage| voltage * scale).collect();
+
+    info!("Total data points: {}", ecg.voltage.len());
+
+    Ok(ecg)
+}
+
+/// Reads the samples of an Apple Health ECG export in millivolts.
+pub fn read_apple_health_ecg_data<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let ecg = read_apple_health_ecg(path)?;
+    let fs = ecg.sampling_frequency;
+    Ok(ecg
+        .voltage
+        .iter()
+        .enumerate()
+        .map(|(i, &voltage)| EcgPoint {
+            time: i as f64 / fs,
+            voltage,
+        })
+        .collect())
+}
+
+/// Reads an Apple Health ECG export as a recording of its one lead.
+pub fn read_apple_health_leads<P: AsRef<Path>>(path: P) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    let ecg = read_apple_health_ecg(path)?;
+    let fs = ecg.sampling_frequency;
+    Ok(MultiLeadEcg {
+        times: (0..ecg.voltage.len()).map(|i| i as f64 / fs).collect(),
+        leads: vec![Lead {
+            name: ecg.lead,
+            voltage: ecg.voltage,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const FIXTURE: &str = "\u{feff}Name,Jan Kowalski\n\
+        Date of Birth,\"1 Jan 1970\"\n\
+        Recorded Date,2021-03-04 09:10:11 +0100\n\
+        Classification,Sinus Rhythm\n\
+        Symptoms,\n\
+        Software Version,1.90\n\
+        Device,\"Watch4,4\"\n\
+        Sample Rate,256 hertz\n\
+        \n\
+        Lead,Lead I\n\
+        Unit,µV\n\
+        \n\
+        -12.5\n\
+        \"1000,5\"\n\
+        250\n";
+
+    // writes the export to a temporary file, removed by the caller
+    fn export(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("signalweaver_{}_{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_a_fixture_export() {
+        let path = export("apple.csv", FIXTURE);
+        let recognised = is_apple_health_ecg(&path);
+        let ecg = read_apple_health_ecg(&path);
+        let points = read_apple_health_ecg_data(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(recognised);
+        let ecg = ecg.unwrap();
+        assert_eq!(ecg.name, "Jan Kowalski");
+        assert_eq!(ecg.recorded_date, "2021-03-04 09:10:11 +0100");
+        assert_eq!(ecg.classification, "Sinus Rhythm");
+        assert_eq!(ecg.lead, "Lead I");
+        assert_eq!(ecg.sampling_frequency, 256.0);
+        assert_eq!(ecg.voltage.len(), 3);
+        for (voltage, expected) in ecg.voltage.iter().zip([-0.0125, 1.0005, 0.25]) {
+            assert!((voltage - expected).abs() < 1e-12, "{}", voltage);
+        }
+        assert_eq!(points.unwrap()[2].time, 2.0 / 256.0);
+    }
+
+    #[test]
+    fn rejects_other_csv_files() {
+        let samples = export("samples.csv", "time,voltage\n0,0.1\n");
+        let late = export("late.csv", "Sample Rate,512 hertz\n1\nUnit,mV\n");
+        let recognised = is_apple_health_ecg(&samples);
+        let read = read_apple_health_ecg(&late);
+        fs::remove_file(&samples).unwrap();
+        fs::remove_file(&late).unwrap();
+
+        assert!(!recognised);
+        assert!(read.is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::classification::BeatClass;
+use crate::models::{Detection, RrInterval};
+use arrow::array::{ArrayRef, Float64Array, StringArray, TimestampMillisecondArray, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::error::Error;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Columnar file layouts for loading tables into pandas, Polars or DuckDB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableFormat {
+    /// Apache Parquet, Snappy compressed.
+    Parquet,
+    /// Arrow IPC file (Feather v2).
+    Arrow,
+}
+
+/// The per-beat table: beat number from 1, time in seconds, the date and time of the beat
+/// when `start_datetime` (seconds since 1970-01-01 at time 0) is known, sample index,
+/// amplitude, detector, confidence and, unless `classes` is empty, the WFDB mnemonic of
+/// every beat's class. The columns match those of the CSV output.
+pub fn beats_table(
+    detections: &[Detection],
+    classes: &[BeatClass],
+    detector: &str,
+    start_datetime: Option<f64>,
+) -> Result<RecordBatch, ArrowError> {
+    let mut fields = vec![
+        Field::new("beat", DataType::UInt64, false),
+        Field::new("time", DataType::Float64, false),
+    ];
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(1..=detections.len() as u64)),
+        Arc::new(Float64Array::from_iter_values(
+            detections.iter().map(|detection| detection.time),
+        )),
+    ];
+    if let Some(start) = start_datetime {
+        fields.push(Field::new(
+            "datetime",
+            DataType::Timestamp(TimeUnit::Millisecond, None),
+            false,
+        ));
+        columns.push(Arc::new(TimestampMillisecondArray::from_iter_values(
+            detections
+                .iter()
+                .map(|detection| ((start + detection.time) * 1000.0).round() as i64),
+        )));
+    }
+    fields.extend([
+        Field::new("sample", DataType::UInt64, false),
+        Field::new("amplitude", DataType::Float64, false),
+        Field::new("detector", DataType::Utf8, false),
+        Field::new("confidence", DataType::Float64, false),
+    ]);
+    columns.extend([
+        Arc::new(UInt64Array::from_iter_values(
+            detections.iter().map(|detection| detection.sample as u64),
+        )) as ArrayRef,
+        Arc::new(Float64Array::from_iter_values(
+            detections.iter().map(|detection| detection.amplitude),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            detections.iter().map(|_| detector),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            detections.iter().map(|detection| detection.confidence),
+        )),
+    ]);
+    if !classes.is_empty() {
+        fields.push(Field::new("class", DataType::Utf8, false));
+        columns.push(Arc::new(StringArray::from_iter_values(
+            classes.iter().map(|class| class.symbol()),
+        )));
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// The RR series: time of the closing beat in seconds and the interval in milliseconds.
+pub fn rr_table(intervals: &[RrInterval]) -> Result<RecordBatch, ArrowError> {
+    let schema = Schema::new(vec![
+        Field::new("time", DataType::Float64, false),
+        Field::new("rr_ms", DataType::Float64, false),
+    ]);
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(Float64Array::from_iter_values(
+                intervals.iter().map(|rr| rr.time),
+            )),
+            Arc::new(Float64Array::from_iter_values(
+                intervals.iter().map(|rr| rr.rr_ms),
+            )),
+        ],
+    )
+}
+
+/// Writes a table as a Parquet or Arrow IPC file.
+pub fn write_table<W: Write + Send>(
+    writer: W,
+    table: &RecordBatch,
+    format: TableFormat,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        TableFormat::Parquet => {
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let mut writer = ArrowWriter::try_new(writer, table.schema(), Some(properties))?;
+            writer.write(table)?;
+            writer.close()?;
+        }
+        TableFormat::Arrow => {
+            let mut writer = FileWriter::try_new(writer, &table.schema())?;
+            writer.write(table)?;
+            writer.finish()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use crate::delineation::qrs_bounds;
+use crate::wfdb_utils::NORMAL_BEAT;
+
+// half-width of the window searched for the QRS onset and offset, in seconds
+const QRS_SEARCH: f64 = 0.1;
+// preceding RR intervals averaged for the prematurity of a beat
+const PREMATURITY_BEATS: usize = 8;
+// beats closer than this to the previous one cannot be physiological, in seconds
+const MIN_RR: f64 = 0.2;
+// WFDB annotation codes of a premature ventricular contraction and of an artifact
+const PVC: u16 = 5;
+const ARTIFACT: u16 = 16;
+
+/// Morphology and timing of one beat.
+#[derive(Debug, Clone, Copy)]
+pub struct BeatFeatures {
+    /// Duration of the QRS complex, from the first to the last steep sample, in seconds.
+    pub qrs_width: f64,
+    /// Peak-to-peak amplitude of the QRS complex.
+    pub amplitude: f64,
+    /// Area between the QRS complex and the level at its onset, in signal units times seconds.
+    pub area: f64,
+    /// Preceding RR interval over the mean of the eight before it; below 1 for an early beat,
+    /// 1 for the first beats.
+    pub prematurity: f64,
+    /// Preceding RR interval in seconds, infinite for the first beat.
+    pub rr: f64,
+}
+
+/// Class of a beat, after the WFDB annotation codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BeatClass {
+    Normal,
+    /// Premature ventricular contraction: early, wide and large.
+    Ventricular,
+    /// A noise spike or other non-QRS deflection picked up by the detector.
+    Artifact,
+}
+
+impl BeatClass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BeatClass::Normal => "normal",
+            BeatClass::Ventricular => "ventricular",
+            BeatClass::Artifact => "artifact",
+        }
+    }
+
+    /// The WFDB annotation mnemonic (`N`, `V` or `|`).
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BeatClass::Normal => "N",
+            BeatClass::Ventricular => "V",
+            BeatClass::Artifact => "|",
+        }
+    }
+
+    /// The WFDB annotation code (`NORMAL`, `PVC` or `ARFCT`).
+    pub fn wfdb_code(self) -> u16 {
+        match self {
+            BeatClass::Normal => NORMAL_BEAT,
+            BeatClass::Ventricular => PVC,
+            BeatClass::Artifact => ARTIFACT,
+        }
+    }
+}
+
+/// Measures every beat of `signal` (preferably band-pass filtered) at the given sample indices,
+/// which must be increasing.
+pub fn beat_features(signal: &[f64], fs: f64, beats: &[usize]) -> Vec<BeatFeatures> {
+    let radius = ((QRS_SEARCH * fs) as usize).max(1);
+    let mut features = Vec::with_capacity(beats.len());
+    for (i, &beat) in beats.iter().enumerate() {
+        let start = beat.saturating_sub(radius);
+        let end = (beat + radius + 1).min(signal.len());
+        let (qrs_width, amplitude, area) = if end > start + 1 {
+            qrs_morphology(&signal[start..end], fs)
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+
+        let rr = match i {
+            0 => f64::INFINITY,
+            _ => (beat - beats[i - 1]) as f64 / fs,
+        };
+        let preceding = &beats[i.saturating_sub(PREMATURITY_BEATS + 1)..i];
+        let prematurity = if i >= 2 {
+            let mean = (preceding[preceding.len() - 1] - preceding[0]) as f64
+                / (preceding.len() - 1) as f64
+                / fs;
+            rr / mean
+        } else {
+            1.0
+        };
+
+        features.push(BeatFeatures {
+            qrs_width,
+            amplitude,
+            area,
+            prematurity,
+            rr,
+        });
+    }
+    features
+}
+
+// width, peak-to-peak amplitude and area of the QRS complex in a window around a beat
+fn qrs_morphology(window: &[f64], fs: f64) -> (f64, f64, f64) {
+    let Some((onset, offset)) = qrs_bounds(window) else {
+        return (0.0, 0.0, 0.0);
+    };
+
+    let qrs = &window[onset..=offset];
+    let (min, max) = qrs
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let level = window[onset];
+    let area = qrs.iter().map(|v| (v - level).abs()).sum::<f64>() / fs;
+    ((offset - onset) as f64 / fs, max - min, area)
+}
+
+/// Labels every beat by comparing it with the median beat of the recording: beats following
+/// the previous one within 200 ms or of a quarter or four times the median amplitude are
+/// artifacts, early beats (under 85% of the recent mean RR) at least 1.3 times as wide as the
+/// median or wider than 120 ms, and of at least 1.3 times its area are ventricular.
+pub fn classify_beats(features: &[BeatFeatures]) -> Vec<BeatClass> {
+    let median_of = |value: fn(&BeatFeatures) -> f64| {
+        let mut values: Vec<f64> = features.iter().map(value).collect();
+        values.sort_unstable_by(f64::total_cmp);
+        values.get(values.len() / 2).copied().unwrap_or(0.0)
+    };
+    let width = median_of(|beat| beat.qrs_width);
+    let amplitude = median_of(|beat| beat.amplitude);
+    let area = median_of(|beat| beat.area);
+
+    features
+        .iter()
+        .map(|beat| {
+            if beat.rr < MIN_RR
+                || beat.amplitude > 4.0 * amplitude
+                || beat.amplitude < 0.25 * amplitude
+            {
+                BeatClass::Artifact
+            } else if beat.prematurity < 0.85
+                && (beat.qrs_width >= 1.3 * width || beat.qrs_width > 0.12)
+                && beat.area >= 1.3 * area
+            {
+                BeatClass::Ventricular
+            } else {
+                BeatClass::Normal
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Path that stands for standard input or output.
+pub const STDIO_PATH: &str = "-";
+
+/// Opens an input file, or standard input for `-`, decompressing gzip and zstd input on the
+/// fly. The compression is recognised from the leading magic bytes rather than the extension.
+pub fn open_input<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    let path = path.as_ref();
+    if path == Path::new(STDIO_PATH) {
+        return decompress(io::stdin().lock());
+    }
+    decompress(BufReader::new(File::open(path)?))
+}
+
+fn decompress<R: BufRead + 'static>(mut reader: R) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    let magic = reader.fill_buf()?;
+
+    if magic.starts_with(&GZIP_MAGIC) {
+        #[cfg(feature = "gzip")]
+        return Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(reader),
+        )));
+        #[cfg(not(feature = "gzip"))]
+        return Err("gzip input needs signalweaver built with the `gzip` feature".into());
+    }
+
+    if magic.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return Ok(Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        )));
+        #[cfg(not(feature = "zstd"))]
+        return Err("zstd input needs signalweaver built with the `zstd` feature".into());
+    }
+
+    Ok(Box::new(reader))
+}
+
+/// The path without a `.gz` or `.zst` extension, e.g. to find the format of `ecg.csv.gz`.
+pub fn strip_compression_extension(path: &Path) -> PathBuf {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") | Some("zst") => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// A value of a configuration file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Array(Vec<ConfigValue>),
+}
+
+/// Written as on the command line; arrays as their elements separated by commas.
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::String(value) => write!(f, "{}", value),
+            ConfigValue::Number(value) => write!(f, "{}", value),
+            ConfigValue::Boolean(value) => write!(f, "{}", value),
+            ConfigValue::Array(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A `key = value` setting, in the `[section]` it follows (`None` before any section).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub section: Option<String>,
+    pub key: String,
+    pub value: ConfigValue,
+}
+
+/// Reads a configuration file, see [`parse_config`].
+pub fn read_config<P: AsRef<Path>>(path: P) -> Result<Vec<ConfigEntry>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read the configuration {:?}: {}", path, e))?;
+    parse_config(&text).map_err(|e| format!("{:?}: {}", path, e).into())
+}
+
+/// Parses the TOML a configuration needs: `[section]` headers and `key = value` lines with
+/// strings, numbers, booleans and arrays of them (arrays may span lines), `#` comments.
+pub fn parse_config(text: &str) -> Result<Vec<ConfigEntry>, String> {
+    let mut entries: Vec<ConfigEntry> = Vec::new();
+    let mut section = None;
+    let mut lines = text.lines().enumerate();
+    while let Some((line_idx, line)) = lines.next() {
+        let at_line = |e: String| format!("line {}: {}", line_idx + 1, e);
+        let mut line = strip_comment(line).trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .map(str::trim)
+                .filter(|name| is_bare_key(name))
+                .ok_or_else(|| at_line(format!("invalid section header {:?}", line)))?;
+            section = Some(name.to_string());
+            continue;
+        }
+
+        let (key, _) = line
+            .split_once('=')
+            .ok_or_else(|| at_line(format!("expected key = value, got {:?}", line)))?;
+        let key = key.trim().to_string();
+        if !is_bare_key(&key) {
+            return Err(at_line(format!("invalid key {:?}", key)));
+        }
+        // an array continues until its brackets balance
+        while !balanced(&line) {
+            let (_, next) = lines
+                .next()
+                .ok_or_else(|| at_line("unterminated array".to_string()))?;
+            line.push(' ');
+            line.push_str(strip_comment(next).trim());
+        }
+        let (_, value) = line.split_once('=').unwrap_or_default();
+        let (value, rest) = parse_value(value.trim()).map_err(at_line)?;
+        if !rest.trim().is_empty() {
+            return Err(at_line(format!(
+                "unexpected {:?} after the value",
+                rest.trim()
+            )));
+        }
+        if entries
+            .iter()
+            .any(|entry| entry.section == section && entry.key == key)
+        {
+            return Err(at_line(format!("{} is set twice", key)));
+        }
+        entries.push(ConfigEntry {
+            section: section.clone(),
+            key,
+            value,
+        });
+    }
+    Ok(entries)
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// the line up to a `#` outside of strings
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+// whether every `[` outside of strings is closed
+fn balanced(line: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            _ => {}
+        }
+        escaped = false;
+    }
+    depth <= 0
+}
+
+// a value at the start of `text` and the text after it
+fn parse_value(text: &str) -> Result<(ConfigValue, &str), String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((ConfigValue::String(value), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    Some(c) => return Err(format!("unsupported escape \\{}", c)),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated string")?;
+        return Ok((
+            ConfigValue::String(rest[..end].to_string()),
+            &rest[end + 1..],
+        ));
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((ConfigValue::Array(values), after));
+            }
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected , or ] in an array".to_string());
+            }
+        }
+    }
+
+    let end = text.find([',', ']', ' ', '\t']).unwrap_or(text.len());
+    let (token, rest) = text.split_at(end);
+    let value = match token {
+        "true" => ConfigValue::Boolean(true),
+        "false" => ConfigValue::Boolean(false),
+        _ => ConfigValue::Number(
+            token
+                .replace('_', "")
+                .parse()
+                .map_err(|_| format!("invalid value {:?}", token))?,
+        ),
+    };
+    Ok((value, rest))
+}
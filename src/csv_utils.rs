@@ -1,40 +1,786 @@
-use crate::models::EcgPoint;
+use crate::compression::open_input;
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
 use std::error::Error;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, Lines};
 use std::path::Path;
+use tracing::{info, warn};
+
+/// A column of a CSV recording, by header name or by 0-based index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+impl std::str::FromStr for CsvColumn {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse::<usize>() {
+            Ok(index) => CsvColumn::Index(index),
+            Err(_) => CsvColumn::Name(s.to_string()),
+        })
+    }
+}
+
+impl CsvColumn {
+    /// Index of the column given the header fields (`None` for files without a header)
+    /// and the number of fields in a row.
+    pub fn resolve(
+        &self,
+        header: Option<&[String]>,
+        num_fields: usize,
+    ) -> Result<usize, Box<dyn Error>> {
+        match self {
+            CsvColumn::Index(index) if *index < num_fields => Ok(*index),
+            CsvColumn::Index(index) => Err(format!(
+                "Column {} out of range, the file has {} columns",
+                index, num_fields
+            )
+            .into()),
+            CsvColumn::Name(name) => {
+                let header = header.ok_or("Selecting a column by name needs a header line")?;
+                header
+                    .iter()
+                    .position(|field| field == name)
+                    .ok_or_else(|| {
+                        format!(
+                            "No column named '{}', available: {}",
+                            name,
+                            header.join(", ")
+                        )
+                        .into()
+                    })
+            }
+        }
+    }
+}
+
+// header name without a unit in brackets, and the unit: `Time (ms)` -> ("time", "ms")
+fn split_unit(name: &str) -> (String, String) {
+    let name = name.trim().to_lowercase();
+    match name.find(['(', '[']) {
+        Some(pos) => {
+            let unit = name[pos + 1..].trim_end_matches([')', ']']).trim();
+            (name[..pos].trim().to_string(), unit.to_string())
+        }
+        None => (name, String::new()),
+    }
+}
+
+fn is_time_name(name: &str) -> bool {
+    let (base, _) = split_unit(name);
+    matches!(
+        base.as_str(),
+        "time"
+            | "t"
+            | "s"
+            | "sec"
+            | "secs"
+            | "seconds"
+            | "ms"
+            | "msec"
+            | "milliseconds"
+            | "timestamp"
+            | "elapsed"
+            | "elapsed time"
+            | "time_s"
+            | "time_sec"
+            | "time_ms"
+    )
+}
+
+fn is_millisecond_name(name: &str) -> bool {
+    let (base, unit) = split_unit(name);
+    matches!(base.as_str(), "ms" | "msec" | "milliseconds" | "time_ms")
+        || matches!(unit.as_str(), "ms" | "msec")
+}
+
+fn is_voltage_name(name: &str) -> bool {
+    let (base, unit) = split_unit(name);
+    let leads = [
+        "i", "ii", "iii", "avr", "avl", "avf", "v1", "v2", "v3", "v4", "v5", "v6", "mlii",
+    ];
+    let lead = base.strip_prefix("lead").map_or(base.as_str(), str::trim);
+    ["ecg", "ekg", "mv", "uv", "µv", "volt", "signal"]
+        .iter()
+        .any(|word| base.contains(word) || unit.contains(word))
+        || leads.contains(&lead)
+}
+
+/// Where the times of a CSV recording come from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvTime {
+    /// A time column, with the factor converting its values to seconds.
+    Column { index: usize, scale: f64 },
+    /// No time column: times are the sample index divided by the sampling frequency.
+    Synthesized { fs: f64 },
+}
+
+/// Positions of the time and voltage columns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvLayout {
+    pub time: CsvTime,
+    pub voltage: usize,
+}
+
+impl CsvLayout {
+    fn time_index(&self) -> Option<usize> {
+        match self.time {
+            CsvTime::Column { index, .. } => Some(index),
+            CsvTime::Synthesized { .. } => None,
+        }
+    }
+
+    // time in seconds of the `sample`-th row
+    fn time(
+        &self,
+        fields: &[String],
+        sample: usize,
+        options: &CsvOptions,
+    ) -> Result<f64, Box<dyn Error>> {
+        match self.time {
+            CsvTime::Column { index, scale } => Ok(options.parse_number(&fields[index])? * scale),
+            CsvTime::Synthesized { fs } => Ok(sample as f64 / fs),
+        }
+    }
+}
+
+/// What to do with CSV rows holding a field that is not a finite number.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BadRows {
+    /// Stop with an error naming the line.
+    #[default]
+    Fail,
+    /// Leave the row out.
+    Skip,
+    /// Repeat the previous voltage of the lead; rows with a bad time are left out.
+    Impute,
+}
+
+impl std::str::FromStr for BadRows {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(BadRows::Fail),
+            "skip" => Ok(BadRows::Skip),
+            "impute" => Ok(BadRows::Impute),
+            _ => Err(format!(
+                "unknown bad row handling {:?}, expected fail, skip or impute",
+                s
+            )),
+        }
+    }
+}
+
+/// Layout of a CSV recording; the default reads `time,voltage` files with a header line.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Field separator; `;` and tab are common in European exports.
+    pub delimiter: char,
+    /// Numbers are written with a decimal comma (`0,002`).
+    pub decimal_comma: bool,
+    /// The first line names the columns.
+    pub has_header: bool,
+    /// Time column; found by header name (`time`, `t`, `sec`, `ms`, ...) when `None`,
+    /// falling back to the first column.
+    pub time_column: Option<CsvColumn>,
+    /// Voltage column where a single lead is read; found by header name (`ecg`, `mV`,
+    /// `lead II`, ...) when `None`, falling back to the first column after time.
+    pub column: Option<CsvColumn>,
+    /// The file holds voltage columns only, sampled at `sampling_frequency`. Single-column
+    /// files are always read this way.
+    pub voltage_only: bool,
+    /// Sampling frequency in Hz used to synthesize the times of voltage-only files.
+    pub sampling_frequency: Option<f64>,
+    /// Handling of malformed rows (unparsable numbers, NaN or infinite values).
+    pub bad_rows: BadRows,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            decimal_comma: false,
+            has_header: true,
+            time_column: None,
+            column: None,
+            voltage_only: false,
+            sampling_frequency: None,
+            bad_rows: BadRows::Fail,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Resolves the time and voltage columns from the header (`None` for files without one)
+    /// and the number of fields in a row. Times in a column named in milliseconds are
+    /// converted to seconds.
+    pub fn layout(
+        &self,
+        header: Option<&[String]>,
+        num_fields: usize,
+    ) -> Result<CsvLayout, Box<dyn Error>> {
+        let names = header.unwrap_or_default();
+        let time = if self.voltage_only || (num_fields == 1 && self.time_column.is_none()) {
+            let fs = self
+                .sampling_frequency
+                .filter(|&fs| fs > 0.0)
+                .ok_or("A CSV without a time column needs the sampling frequency")?;
+            CsvTime::Synthesized { fs }
+        } else {
+            let index = match &self.time_column {
+                Some(column) => column.resolve(header, num_fields)?,
+                None => names
+                    .iter()
+                    .position(|name| is_time_name(name))
+                    .unwrap_or(0),
+            };
+            let scale = match names.get(index) {
+                Some(name) if is_millisecond_name(name) => 0.001,
+                _ => 1.0,
+            };
+            CsvTime::Column { index, scale }
+        };
+
+        let time_index = match time {
+            CsvTime::Column { index, .. } => Some(index),
+            CsvTime::Synthesized { .. } => None,
+        };
+        let voltage = match &self.column {
+            Some(column) => column.resolve(header, num_fields)?,
+            None => names
+                .iter()
+                .enumerate()
+                .position(|(i, name)| Some(i) != time_index && is_voltage_name(name))
+                .or_else(|| (0..num_fields).find(|&i| Some(i) != time_index))
+                .ok_or("The CSV needs a voltage column besides the time")?,
+        };
+        if time_index == Some(voltage) {
+            return Err("The time and voltage columns must differ".into());
+        }
+
+        Ok(CsvLayout { time, voltage })
+    }
+
+    /// Splits a line into trimmed fields. Fields may be enclosed in double quotes, which
+    /// protects delimiters inside them; a doubled quote stands for a literal one.
+    pub fn split(&self, line: &str) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                c if c == self.delimiter && !quoted => {
+                    fields.push(field.trim().to_string());
+                    field.clear();
+                }
+                c => field.push(c),
+            }
+        }
+        fields.push(field.trim().to_string());
+        fields
+    }
+
+    /// Parses a finite number, with a decimal comma if the dialect uses one.
+    pub fn parse_number(&self, field: &str) -> Result<f64, Box<dyn Error>> {
+        let parsed = if self.decimal_comma {
+            field.replace(',', ".").parse::<f64>()
+        } else {
+            field.parse::<f64>()
+        };
+        match parsed {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(format!("Invalid number in CSV: {:?}", field).into()),
+        }
+    }
+}
+
+// data rows of a CSV split into fields, with the header taken off when there is one
+struct CsvRows<R> {
+    lines: Lines<R>,
+    options: CsvOptions,
+    header: Option<Vec<String>>,
+    started: bool,
+    // 1-based number of the last line read
+    line: usize,
+    // data rows returned so far
+    rows: usize,
+    // malformed rows left out and values imputed since the last report
+    skipped: usize,
+    imputed: usize,
+    first_bad_line: Option<usize>,
+}
+
+impl<R: BufRead> CsvRows<R> {
+    fn new(reader: R, options: CsvOptions) -> Self {
+        CsvRows {
+            lines: reader.lines(),
+            options,
+            header: None,
+            started: false,
+            line: 0,
+            rows: 0,
+            skipped: 0,
+            imputed: 0,
+            first_bad_line: None,
+        }
+    }
+
+    // 0-based index of the last data row, which places it in time in voltage-only files
+    fn sample(&self) -> usize {
+        self.rows.saturating_sub(1)
+    }
+
+    // applies `options.bad_rows` to a value of the current row: `None` leaves the row out
+    fn recover(
+        &mut self,
+        value: Result<f64, Box<dyn Error>>,
+        previous: Option<f64>,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        let err = match value {
+            Ok(value) => return Ok(Some(value)),
+            Err(err) => err,
+        };
+        if self.options.bad_rows == BadRows::Fail {
+            return Err(format!("line {}: {}", self.line, err).into());
+        }
+        self.first_bad_line.get_or_insert(self.line);
+        match previous {
+            Some(previous) if self.options.bad_rows == BadRows::Impute => {
+                self.imputed += 1;
+                Ok(Some(previous))
+            }
+            _ => {
+                self.skipped += 1;
+                Ok(None)
+            }
+        }
+    }
+
+    // summary of the malformed rows met since the last call
+    fn report(&mut self) {
+        if let Some(line) = self.first_bad_line.take() {
+            warn!(
+                "Malformed CSV rows: {} skipped, {} values imputed (first at line {})",
+                self.skipped, self.imputed, line
+            );
+            self.skipped = 0;
+            self.imputed = 0;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CsvRows<R> {
+    type Item = Result<Vec<String>, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+            let fields = self.options.split(&line);
+
+            // the header names the columns
+            if !self.started {
+                self.started = true;
+                if self.options.has_header {
+                    self.header = Some(fields);
+                    continue;
+                }
+            }
+            self.rows += 1;
+            return Some(Ok(fields));
+        }
+    }
+}
+
 pub fn read_ecg_data<P: AsRef<Path>>(path: P) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    read_ecg_csv(path, &CsvOptions::default())
+}
+
+/// Reads the time column together with one voltage column of a multi-lead CSV.
+pub fn read_ecg_column<P: AsRef<Path>>(
+    path: P,
+    column: &CsvColumn,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let options = CsvOptions {
+        column: Some(column.clone()),
+        ..CsvOptions::default()
+    };
+    read_ecg_csv(path, &options)
+}
+
+/// Reads the time column and the voltage column selected by `options.column`.
+pub fn read_ecg_csv<P: AsRef<Path>>(
+    path: P,
+    options: &CsvOptions,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
     // opening the file
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+    let reader = open_input(path)?;
 
-    let mut data = Vec::new();
-    let mut header_skipped = false;
+    let mut data: Vec<EcgPoint> = Vec::new();
+    let mut rows = CsvRows::new(reader, options.clone());
+    let mut layout = None;
 
     // reading each line
-    for line in reader.lines() {
-        let line = line?;
+    while let Some(fields) = rows.next() {
+        let fields = fields?;
+        let layout = match layout {
+            Some(layout) => layout,
+            None => *layout.insert(options.layout(rows.header.as_deref(), fields.len())?),
+        };
+
+        // parsing each line
+        let previous = data.last().map(|point| point.voltage);
+        if let Some(point) = parse_record(&fields, &layout, previous, &mut rows)? {
+            data.push(point);
+        }
+    }
+    rows.report();
+
+    // Log total data points
+    if !data.is_empty() {
+        info!("Total data points: {}", data.len());
+    }
+
+    Ok(data)
+}
+
+/// Reads every column besides time as a lead, named after the header (or by column index
+/// when there is none).
+pub fn read_ecg_leads<P: AsRef<Path>>(
+    path: P,
+    options: &CsvOptions,
+) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    let mut rows = CsvRows::new(open_input(path)?, options.clone());
 
-        // skipping the header
-        if !header_skipped {
-            header_skipped = true;
+    let mut ecg = MultiLeadEcg::default();
+    let mut layout = None;
+    while let Some(fields) = rows.next() {
+        let fields = fields?;
+        let layout = match layout {
+            Some(layout) => layout,
+            None => {
+                let found = options.layout(rows.header.as_deref(), fields.len())?;
+                ecg.leads = (0..fields.len())
+                    .filter(|&i| Some(i) != found.time_index())
+                    .map(|i| Lead {
+                        name: match &rows.header {
+                            Some(header) => header.get(i).cloned().unwrap_or_default(),
+                            None => i.to_string(),
+                        },
+                        voltage: Vec::new(),
+                    })
+                    .collect();
+                *layout.insert(found)
+            }
+        };
+
+        // rows missing a lead are skipped, as in read_ecg_data
+        let time_index = layout.time_index();
+        if fields.len() != ecg.leads.len() + usize::from(time_index.is_some()) {
+            continue;
+        }
+        let time = layout.time(&fields, rows.sample(), options);
+        let Some(time) = rows.recover(time, None)? else {
+            continue;
+        };
+        let voltages = fields
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != time_index)
+            .map(|(_, field)| field);
+        let mut row = Vec::with_capacity(ecg.leads.len());
+        for (lead, field) in ecg.leads.iter().zip(voltages) {
+            let previous = lead.voltage.last().copied();
+            match rows.recover(options.parse_number(field), previous)? {
+                Some(voltage) => row.push(voltage),
+                None => break,
+            }
+        }
+        // a lead value left out drops the whole row
+        if row.len() < ecg.leads.len() {
             continue;
         }
+        ecg.times.push(time);
+        for (lead, voltage) in ecg.leads.iter_mut().zip(row) {
+            lead.voltage.push(voltage);
+        }
+    }
+    rows.report();
 
-        // parsing each line
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() == 2 {
-            let time = parts[0].trim().parse::<f64>()?;
-            let voltage = parts[1].trim().parse::<f64>()?;
+    if !ecg.times.is_empty() {
+        info!(
+            "Total data points: {} in {} leads",
+            ecg.times.len(),
+            ecg.leads.len()
+        );
+    }
+
+    Ok(ecg)
+}
 
-            data.push(EcgPoint { time, voltage });
+// time and voltage of the current row of `rows`; rows too short for the layout are skipped,
+// malformed ones handled as `bad_rows` says with `previous` as the voltage to impute
+fn parse_record<R: BufRead>(
+    fields: &[String],
+    layout: &CsvLayout,
+    previous: Option<f64>,
+    rows: &mut CsvRows<R>,
+) -> Result<Option<EcgPoint>, Box<dyn Error>> {
+    if fields.len() <= layout.time_index().unwrap_or(0).max(layout.voltage) {
+        return Ok(None);
+    }
+    let time = layout.time(fields, rows.sample(), &rows.options);
+    let Some(time) = rows.recover(time, None)? else {
+        return Ok(None);
+    };
+    let voltage = rows.options.parse_number(&fields[layout.voltage]);
+    let Some(voltage) = rows.recover(voltage, previous)? else {
+        return Ok(None);
+    };
+    Ok(Some(EcgPoint { time, voltage }))
+}
+
+/// Reads the CSV lazily in blocks of `chunk_size` points, so that long recordings can be
+/// fed to a `StreamingDetector` without holding the whole file in memory.
+pub fn read_ecg_chunks<P: AsRef<Path>>(
+    path: P,
+    chunk_size: usize,
+) -> Result<EcgChunks<Box<dyn BufRead>>, Box<dyn Error>> {
+    Ok(EcgChunks::new(open_input(path)?, chunk_size))
+}
+
+/// Iterator over blocks of points of a CSV recording, see `read_ecg_chunks`.
+pub struct EcgChunks<R> {
+    rows: CsvRows<R>,
+    chunk_size: usize,
+    // resolved from the header on the first call
+    layout: Option<CsvLayout>,
+    // voltage of the last point returned, imputed for a malformed first row of a block
+    last_voltage: Option<f64>,
+}
+
+impl<R: BufRead> EcgChunks<R> {
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        EcgChunks {
+            rows: CsvRows::new(reader, CsvOptions::default()),
+            chunk_size: chunk_size.max(1),
+            layout: None,
+            last_voltage: None,
         }
     }
 
-    // Print total data points
-    if !data.is_empty() {
-        println!("Total data points: {}", data.len());
+    /// CSV dialect and columns to read.
+    pub fn with_options(mut self, options: CsvOptions) -> Self {
+        self.rows.options = options;
+        self
     }
+}
 
-    Ok(data)
+impl<R: BufRead> Iterator for EcgChunks<R> {
+    type Item = Result<Vec<EcgPoint>, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk: Vec<EcgPoint> = Vec::with_capacity(self.chunk_size);
+        while chunk.len() < self.chunk_size {
+            let fields = match self.rows.next() {
+                Some(Ok(fields)) => fields,
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.rows.report();
+                    break;
+                }
+            };
+
+            let layout = match self.layout {
+                Some(layout) => layout,
+                None => {
+                    let header = self.rows.header.as_deref();
+                    match self.rows.options.layout(header, fields.len()) {
+                        Ok(layout) => *self.layout.insert(layout),
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+            };
+
+            let previous = chunk
+                .last()
+                .map(|point| point.voltage)
+                .or(self.last_voltage);
+            match parse_record(&fields, &layout, previous, &mut self.rows) {
+                Ok(Some(point)) => chunk.push(point),
+                Ok(None) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        if chunk.is_empty() {
+            None
+        } else {
+            self.last_voltage = chunk.last().map(|point| point.voltage);
+            Some(Ok(chunk))
+        }
+    }
+}
+
+/// Reads beat times in seconds from a positions file: one time per line, or a CSV whose
+/// header names a `time` column. Lines that do not parse are skipped.
+pub fn read_beat_times<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, Box<dyn Error>> {
+    let reader = open_input(path)?;
+
+    let mut times = Vec::new();
+    let mut column = 0;
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let fields: Vec<&str> = line.split([',', '\t']).map(str::trim).collect();
+        if line_idx == 0 {
+            if let Some(idx) = fields.iter().position(|field| *field == "time") {
+                column = idx;
+                continue;
+            }
+        }
+        if let Some(time) = fields
+            .get(column)
+            .and_then(|field| field.parse::<f64>().ok())
+        {
+            times.push(time);
+        }
+    }
+
+    Ok(times)
+}
+
+/// Unit of the values of an RR interval list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RrUnit {
+    Milliseconds,
+    Seconds,
+}
+
+impl std::str::FromStr for RrUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ms" => Ok(RrUnit::Milliseconds),
+            "s" => Ok(RrUnit::Seconds),
+            _ => Err(format!("unknown RR unit {:?}, expected ms or s", s)),
+        }
+    }
+}
+
+fn is_rr_name(name: &str) -> bool {
+    let (base, _) = split_unit(name);
+    matches!(
+        base.as_str(),
+        "rr" | "rri" | "rr interval" | "rr_interval" | "rr intervals" | "nn" | "ibi" | "interval"
+    )
+}
+
+/// Reads a plain list of RR intervals, one per line or in a CSV column, and returns them in
+/// milliseconds. The column is `options.column`, else the one whose header names RR
+/// intervals (`rr`, `RR (ms)`, `ibi`, ...), else the first; a first line that is all numbers
+/// is read as data even when `options.has_header` is set. Without a `unit` the values are in
+/// the unit the header gives, or in seconds when their median is below 10. Zero and negative
+/// intervals (lost beats) are left out.
+pub fn read_rr_csv<P: AsRef<Path>>(
+    path: P,
+    options: &CsvOptions,
+    unit: Option<RrUnit>,
+) -> Result<Vec<f64>, Box<dyn Error>> {
+    let reader = open_input(path)?;
+    let mut rows = CsvRows::new(
+        reader,
+        CsvOptions {
+            has_header: false,
+            ..options.clone()
+        },
+    );
+
+    let mut header: Option<Vec<String>> = None;
+    let mut column = None;
+    let mut values: Vec<f64> = Vec::new();
+    while let Some(fields) = rows.next() {
+        let fields = fields?;
+        if fields.iter().all(String::is_empty) {
+            continue;
+        }
+        // the first row names the columns unless it holds numbers only
+        if column.is_none()
+            && header.is_none()
+            && options.has_header
+            && fields
+                .iter()
+                .any(|field| options.parse_number(field).is_err())
+        {
+            header = Some(fields);
+            continue;
+        }
+        let index = match column {
+            Some(index) => index,
+            None => *column.insert(match &options.column {
+                Some(column) => column.resolve(header.as_deref(), fields.len())?,
+                None => header
+                    .as_deref()
+                    .and_then(|names| names.iter().position(|name| is_rr_name(name)))
+                    .unwrap_or(0),
+            }),
+        };
+        let Some(field) = fields.get(index) else {
+            continue;
+        };
+        let value = options.parse_number(field);
+        if let Some(value) = rows.recover(value, values.last().copied())? {
+            values.push(value);
+        }
+    }
+    rows.report();
+
+    let lost = values.iter().filter(|&&rr| rr <= 0.0).count();
+    if lost > 0 {
+        warn!("Left out {} zero or negative RR intervals", lost);
+        values.retain(|&rr| rr > 0.0);
+    }
+
+    let header_unit = header
+        .as_deref()
+        .zip(column)
+        .and_then(|(names, index)| names.get(index))
+        .map(|name| split_unit(name).1)
+        .and_then(|unit| match unit.as_str() {
+            "ms" | "msec" => Some(RrUnit::Milliseconds),
+            "s" | "sec" => Some(RrUnit::Seconds),
+            _ => None,
+        });
+    let unit = match unit.or(header_unit) {
+        Some(unit) => unit,
+        None => {
+            let mut sorted = values.clone();
+            sorted.sort_by(f64::total_cmp);
+            match sorted.get(sorted.len() / 2) {
+                Some(&median) if median < 10.0 => RrUnit::Seconds,
+                _ => RrUnit::Milliseconds,
+            }
+        }
+    };
+    if unit == RrUnit::Seconds {
+        for rr in &mut values {
+            *rr *= 1000.0;
+        }
+    }
+
+    info!("Total RR intervals: {}", values.len());
+
+    Ok(values)
 }
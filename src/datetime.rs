@@ -0,0 +1,67 @@
+/// The date `days` after 1970-01-01 as year, month and day (Howard Hinnant's algorithm).
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+/// Days from 1970-01-01 to the date, the inverse of [`civil_from_days`].
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let shifted_month = (i64::from(month) + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// `YYYY-MM-DDTHH:MM:SS.sss` of a time in seconds since 1970-01-01, to the millisecond and
+/// without a time zone.
+pub fn iso_datetime(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as i64;
+    let (days, of_day) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        of_day / 3_600_000,
+        of_day / 60_000 % 60,
+        of_day / 1000 % 60,
+        of_day % 1000
+    )
+}
+
+/// Seconds since 1970-01-01 of `YYYY-MM-DDTHH:MM[:SS[.sss]]` (a space may stand for the
+/// `T`), the inverse of [`iso_datetime`]; `None` if it does not parse.
+pub fn parse_iso_datetime(value: &str) -> Option<f64> {
+    let (date, time) = value.trim().split_once(['T', ' '])?;
+    let mut date = date.split('-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    if date.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut time = time.split(':');
+    let hours: u32 = time.next()?.parse().ok()?;
+    let minutes: u32 = time.next()?.parse().ok()?;
+    let seconds: f64 = time.next().map_or(Some(0.0), |s| s.parse().ok())?;
+    if time.next().is_some() || hours > 23 || minutes > 59 || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    let days = days_from_civil(year, month as u32, day as u32);
+    Some(days as f64 * 86_400.0 + f64::from(hours * 3600 + minutes * 60) + seconds)
+}
@@ -0,0 +1,159 @@
+// half-width of the window searched for the QRS onset and offset, in seconds
+const QRS_SEARCH: f64 = 0.1;
+// the QRS spans the samples whose slope exceeds this fraction of the steepest one
+const SLOPE_FRACTION: f64 = 0.2;
+// the P wave is sought this long before the QRS onset, at most up to the previous T wave
+const P_SEARCH: f64 = 0.25;
+const P_GAP: f64 = 0.02;
+// the T wave is sought from this long after the QRS offset to this fraction of the RR interval
+const T_GAP: f64 = 0.04;
+const T_FRACTION: f64 = 0.7;
+// longest QT an RR interval's T search window extends to, in seconds
+const MAX_QT: f64 = 0.6;
+// P and T waves smaller than this fraction of the QRS amplitude are taken as absent
+const MIN_WAVE: f64 = 0.03;
+
+/// A wave of the ECG: sample indices of its onset, peak and offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wave {
+    pub onset: usize,
+    pub peak: usize,
+    pub offset: usize,
+}
+
+/// The waves of one beat; P or T is `None` when no wave stands out in its search window.
+#[derive(Debug, Clone, Copy)]
+pub struct BeatWaves {
+    pub p: Option<Wave>,
+    pub qrs: Wave,
+    pub t: Option<Wave>,
+}
+
+impl BeatWaves {
+    /// From the P onset to the QRS onset, in seconds.
+    pub fn pr_interval(&self, fs: f64) -> Option<f64> {
+        self.p
+            .map(|p| (self.qrs.onset as f64 - p.onset as f64) / fs)
+    }
+
+    /// From the QRS onset to its offset, in seconds.
+    pub fn qrs_duration(&self, fs: f64) -> f64 {
+        (self.qrs.offset - self.qrs.onset) as f64 / fs
+    }
+
+    /// From the QRS onset to the T offset, in seconds.
+    pub fn qt_interval(&self, fs: f64) -> Option<f64> {
+        self.t
+            .map(|t| (t.offset as f64 - self.qrs.onset as f64) / fs)
+    }
+}
+
+/// The QRS onset and offset in `window`: the first and last sample whose slope reaches a
+/// fifth of the steepest one. `None` for a constant window.
+pub(crate) fn qrs_bounds(window: &[f64]) -> Option<(usize, usize)> {
+    let slopes: Vec<f64> = window
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).abs())
+        .collect();
+    let steepest = slopes.iter().cloned().fold(0.0, f64::max);
+    if steepest <= 0.0 {
+        return None;
+    }
+    let steep = |slope: &f64| *slope >= SLOPE_FRACTION * steepest;
+    let onset = slopes.iter().position(steep)?;
+    let offset = slopes.iter().rposition(steep)? + 1;
+    Some((onset, offset))
+}
+
+/// Delineates every beat of `signal` (band-pass filtered without phase shift, so the waves
+/// keep their position) at the given increasing sample indices. The QRS bounds come from
+/// the slope, the P and T waves are the largest deviations from the chord across their
+/// search windows (before the QRS onset, and after the QRS offset up to 70% of the next RR
+/// interval), with onset and offset at the points furthest from the chords between the wave
+/// peak and the window ends.
+pub fn delineate(signal: &[f64], fs: f64, beats: &[usize]) -> Vec<BeatWaves> {
+    let radius = ((QRS_SEARCH * fs) as usize).max(1);
+    let seconds = |value: f64| (value * fs) as usize;
+    let mut waves: Vec<BeatWaves> = Vec::with_capacity(beats.len());
+    for (i, &beat) in beats.iter().enumerate() {
+        let start = beat.saturating_sub(radius);
+        let end = (beat + radius + 1).min(signal.len());
+        let qrs = match qrs_bounds(&signal[start..end]) {
+            Some((onset, offset)) => Wave {
+                onset: start + onset,
+                peak: beat,
+                offset: start + offset,
+            },
+            None => Wave {
+                onset: beat,
+                peak: beat,
+                offset: beat,
+            },
+        };
+        let qrs_amplitude = signal[qrs.onset..=qrs.offset]
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            });
+        let min_wave = MIN_WAVE * (qrs_amplitude.1 - qrs_amplitude.0);
+
+        // P: before the QRS onset, after the previous beat's T wave or QRS
+        let p_end = qrs.onset.saturating_sub(seconds(P_GAP));
+        let previous_end = waves
+            .last()
+            .map(|previous| previous.t.map_or(previous.qrs.offset, |t| t.offset));
+        let p_start = qrs
+            .onset
+            .saturating_sub(seconds(P_SEARCH))
+            .max(previous_end.unwrap_or(0));
+        let p = find_wave(signal, p_start, p_end, min_wave);
+
+        // T: after the QRS offset, within 70% of the RR interval to the next beat
+        let rr = beats
+            .get(i + 1)
+            .map_or(seconds(MAX_QT / T_FRACTION), |next| next - beat);
+        let t_start = qrs.offset + seconds(T_GAP);
+        let t_end = (beat + (T_FRACTION * rr as f64) as usize)
+            .min(qrs.onset + seconds(MAX_QT))
+            .min(signal.len().saturating_sub(1));
+        let t = find_wave(signal, t_start, t_end, min_wave);
+
+        waves.push(BeatWaves { p, qrs, t });
+    }
+    waves
+}
+
+// the largest deviation from the chord over signal[start..=end], with the knees either side
+fn find_wave(signal: &[f64], start: usize, end: usize, min_wave: f64) -> Option<Wave> {
+    if end <= start + 2 || end >= signal.len() {
+        return None;
+    }
+    let peak = furthest_from_chord(signal, start, end)?;
+    let (chord_value, _) = chord_distance(signal, start, end, peak);
+    if (signal[peak] - chord_value).abs() < min_wave {
+        return None;
+    }
+    let onset = furthest_from_chord(signal, start, peak).unwrap_or(start);
+    let offset = furthest_from_chord(signal, peak, end).unwrap_or(end);
+    Some(Wave {
+        onset,
+        peak,
+        offset,
+    })
+}
+
+// the chord's value at `i` and the signal's distance from it
+fn chord_distance(signal: &[f64], start: usize, end: usize, i: usize) -> (f64, f64) {
+    let slope = (signal[end] - signal[start]) / (end - start) as f64;
+    let value = signal[start] + slope * (i - start) as f64;
+    (value, (signal[i] - value).abs())
+}
+
+// the sample strictly between `start` and `end` furthest from the chord joining them
+fn furthest_from_chord(signal: &[f64], start: usize, end: usize) -> Option<usize> {
+    (start + 1..end).max_by(|&a, &b| {
+        chord_distance(signal, start, end, a)
+            .1
+            .total_cmp(&chord_distance(signal, start, end, b).1)
+    })
+}
@@ -0,0 +1,275 @@
+use crate::cancel::CancelToken;
+use crate::detectors::{fuse_votes, DetectorConfig, QrsDetector, ThresholdDetector};
+use crate::filters::FilterConfig;
+use crate::models::{Detection, EcgPoint, MultiLeadEcg};
+use crate::polarity::{align_to_polarity, qrs_polarity};
+use crate::progress::Progress;
+use crate::sampling::{estimate_sampling, report_sampling};
+use crate::template::template_correlations;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{info, warn};
+
+pub fn detect_qrs_complexes(ecg_data: &[EcgPoint]) -> Vec<f64> {
+    detect_qrs_complexes_with(ecg_data, &ThresholdDetector::default())
+}
+
+/// Settings of the segmented detection pipeline.
+#[derive(Debug, Clone)]
+pub struct DetectionOptions {
+    /// Sampling frequency in Hz; estimated from the median sample interval when `None`.
+    pub fs: Option<f64>,
+    /// Length of the independently processed segments, in seconds.
+    pub segment_length: f64,
+    /// Context added on both sides of each segment, in seconds, so beats at segment edges
+    /// are seen with their full surroundings. Each beat is kept only by the segment it
+    /// falls in.
+    pub segment_overlap: f64,
+    /// Filtering applied to the whole signal before detection (none by default).
+    pub filter: FilterConfig,
+    /// Move each beat onto the dominant QRS deflection of its segment, so the fiducial point
+    /// does not jump between R and S waves across the recording.
+    pub align_polarity: bool,
+    /// Reject the detections whose complex correlates with the running average QRS template
+    /// below this coefficient (see `template::verify_beats`), such as T waves and noise
+    /// spikes; no verification when `None`.
+    pub template_threshold: Option<f64>,
+    /// Its `dedup_interval` drops the duplicate beats of overlapping segments; the other
+    /// constants belong to the detector (see [`ThresholdDetector::new`]).
+    pub detector_config: DetectorConfig,
+    /// Told the number of segments processed after each one.
+    pub progress: Progress,
+    /// Drop the beats whose confidence is below this (see [`Detection::confidence`]).
+    pub min_confidence: Option<f64>,
+    /// Checked before each segment; once cancelled, detection stops and finds no beats.
+    pub cancel: CancelToken,
+}
+
+impl Default for DetectionOptions {
+    fn default() -> Self {
+        DetectionOptions {
+            fs: None,
+            segment_length: 30.0,
+            segment_overlap: 2.0,
+            filter: FilterConfig::default(),
+            align_polarity: false,
+            template_threshold: None,
+            detector_config: DetectorConfig::default(),
+            progress: Progress::default(),
+            min_confidence: None,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+// same segmented pipeline as detect_qrs_complexes, but with any detector
+pub fn detect_qrs_complexes_with(ecg_data: &[EcgPoint], detector: &dyn QrsDetector) -> Vec<f64> {
+    detect_with_options(ecg_data, detector, &DetectionOptions::default())
+}
+
+pub fn detect_with_options(
+    ecg_data: &[EcgPoint],
+    detector: &dyn QrsDetector,
+    options: &DetectionOptions,
+) -> Vec<f64> {
+    detect_beats(ecg_data, detector, options)
+        .iter()
+        .map(|detection| detection.time)
+        .collect()
+}
+
+// the sampling frequency the time stamps imply, else 200 Hz
+fn estimated_fs(times: &[f64]) -> f64 {
+    match estimate_sampling(times) {
+        Some(estimate) => {
+            report_sampling(&estimate);
+            estimate.fs
+        }
+        None => {
+            warn!("Could not estimate the sampling frequency, assuming 200 Hz");
+            200.0
+        }
+    }
+}
+
+/// The segmented pipeline returning full detections rather than times only.
+pub fn detect_beats(
+    ecg_data: &[EcgPoint],
+    detector: &dyn QrsDetector,
+    options: &DetectionOptions,
+) -> Vec<Detection> {
+    // if no data, return empty vector
+    if ecg_data.is_empty() {
+        return Vec::new();
+    }
+
+    // calculating sampling frequency
+    let fs = options.fs.unwrap_or_else(|| {
+        let times: Vec<f64> = ecg_data.iter().map(|point| point.time).collect();
+        estimated_fs(&times)
+    });
+
+    // Filter the whole recording at once so segments carry no filter transients
+    let voltage: Vec<f64> = ecg_data.iter().map(|point| point.voltage).collect();
+    let voltage = options.filter.apply(&voltage, fs);
+
+    // Process the data in segments to handle long ECGs
+    let segment_size = ((options.segment_length * fs) as usize).max(1);
+    let total_segments = ecg_data.len().div_ceil(segment_size);
+    let overlap = (options.segment_overlap.max(0.0) * fs) as usize;
+
+    let detect_segment = |segment_idx: usize| -> Vec<Detection> {
+        let start_idx = segment_idx * segment_size;
+        let end_idx = std::cmp::min((segment_idx + 1) * segment_size, ecg_data.len());
+
+        // extending the segment by the overlap on both sides
+        let context_start = start_idx.saturating_sub(overlap);
+        let context_end = std::cmp::min(end_idx + overlap, ecg_data.len());
+
+        if context_end - context_start < (2.0 * fs) as usize {
+            // Skip segments shorter than 2 seconds
+            return Vec::new();
+        }
+
+        // Process this segment
+        let segment = &voltage[context_start..context_end];
+        let polarity = options.align_polarity.then(|| qrs_polarity(segment, fs));
+
+        // Keep detections inside the segment proper
+        detector
+            .detect_scored(segment, fs)
+            .into_iter()
+            .map(|(idx, confidence)| match polarity {
+                Some(polarity) => (align_to_polarity(segment, fs, idx, polarity), confidence),
+                None => (idx, confidence),
+            })
+            .map(|(idx, confidence)| (context_start + idx, confidence))
+            .filter(|&(sample, _)| sample >= start_idx && sample < end_idx)
+            .map(|(sample, confidence)| Detection {
+                time: ecg_data[sample].time,
+                sample,
+                amplitude: voltage[sample],
+                confidence,
+            })
+            .collect()
+    };
+    let done = AtomicU64::new(0);
+    let detect_and_report = |segment_idx: usize| -> Vec<Detection> {
+        if options.cancel.is_cancelled() {
+            return Vec::new();
+        }
+        let detections = detect_segment(segment_idx);
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        options.progress.report(done, total_segments as u64);
+        detections
+    };
+
+    // Segments are independent, so they are spread over the thread pool when available
+    #[cfg(feature = "parallel")]
+    let mut all_detections: Vec<Detection> = {
+        use rayon::prelude::*;
+        (0..total_segments)
+            .into_par_iter()
+            .flat_map_iter(detect_and_report)
+            .collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let mut all_detections: Vec<Detection> =
+        (0..total_segments).flat_map(detect_and_report).collect();
+    if options.cancel.is_cancelled() {
+        return Vec::new();
+    }
+
+    // Sort all detections in case segments were processed out of order
+    all_detections.sort_by_key(|detection| detection.sample);
+
+    // Remove duplicates from segment overlaps
+    let mut final_detections: Vec<Detection> = Vec::new();
+    for detection in all_detections {
+        match final_detections.last() {
+            // 200ms minimum by default
+            Some(last) if detection.time - last.time < options.detector_config.dedup_interval => {}
+            _ => final_detections.push(detection),
+        }
+    }
+
+    if let Some(threshold) = options.template_threshold {
+        let samples: Vec<usize> = final_detections
+            .iter()
+            .map(|detection| detection.sample)
+            .collect();
+        let correlations = template_correlations(&voltage, fs, &samples, threshold);
+        let found = final_detections.len();
+        let mut correlations = correlations.into_iter();
+        final_detections.retain_mut(|detection| match correlations.next().flatten() {
+            Some(r) if r < threshold => false,
+            Some(r) => {
+                detection.confidence *= r.clamp(0.0, 1.0);
+                true
+            }
+            None => true,
+        });
+        info!(
+            "Template matching rejected {} of {} detections",
+            found - final_detections.len(),
+            found
+        );
+    }
+
+    if let Some(min_confidence) = options.min_confidence {
+        final_detections.retain(|detection| detection.confidence >= min_confidence);
+    }
+    final_detections
+}
+
+/// Runs the segmented pipeline on every lead and keeps the beats seen in at least
+/// `min_leads` leads (a majority by default) within ±`tolerance` seconds, which suppresses
+/// artifacts confined to one lead. Each fused beat is taken from the median lead detection
+/// and scored with the share of agreeing leads.
+pub fn detect_beats_multi_lead(
+    ecg: &MultiLeadEcg,
+    detector: &dyn QrsDetector,
+    options: &DetectionOptions,
+    min_leads: Option<usize>,
+    tolerance: f64,
+) -> Vec<Detection> {
+    if ecg.times.len() < 2 || ecg.leads.is_empty() {
+        return Vec::new();
+    }
+    let fs = options.fs.unwrap_or_else(|| estimated_fs(&ecg.times));
+    let options = DetectionOptions {
+        fs: Some(fs),
+        ..options.clone()
+    };
+
+    // all detections tagged with their lead, in sample order
+    let leads = ecg.leads.len() as u64;
+    let mut candidates: Vec<(Detection, usize)> = (0..ecg.leads.len())
+        .flat_map(|lead| {
+            let options = DetectionOptions {
+                progress: options.progress.part(lead as u64, leads),
+                ..options.clone()
+            };
+            detect_beats(&ecg.points(lead), detector, &options)
+                .into_iter()
+                .map(move |detection| (detection, lead))
+        })
+        .collect();
+    if options.cancel.is_cancelled() {
+        return Vec::new();
+    }
+    candidates.sort_by_key(|&(detection, lead)| (detection.sample, lead));
+    let tagged: Vec<(usize, usize)> = candidates
+        .iter()
+        .map(|&(detection, lead)| (detection.sample, lead))
+        .collect();
+
+    let min_leads = min_leads.unwrap_or(ecg.leads.len() / 2 + 1).max(1);
+    let window = (2.0 * tolerance * fs) as usize;
+    fuse_votes(&tagged, window, min_leads)
+        .into_iter()
+        .map(|(member, leads)| Detection {
+            confidence: leads as f64 / ecg.leads.len() as f64,
+            ..candidates[member].0
+        })
+        .collect()
+}
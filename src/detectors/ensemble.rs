@@ -0,0 +1,105 @@
+use super::QrsDetector;
+
+// beats from different detectors within ±50ms are taken as the same beat
+const DEFAULT_TOLERANCE: f64 = 0.05;
+
+/// Runs several detectors and keeps the beats most of them agree on. Each fused beat is
+/// placed at the median of the member detections and scored with the share of the detectors
+/// agreeing on it.
+pub struct EnsembleDetector {
+    detectors: Vec<Box<dyn QrsDetector>>,
+    min_votes: usize,
+    tolerance: f64,
+}
+
+impl EnsembleDetector {
+    /// `min_votes` defaults to a strict majority of the detectors.
+    pub fn new(detectors: Vec<Box<dyn QrsDetector>>, min_votes: Option<usize>) -> Self {
+        let min_votes = min_votes.unwrap_or(detectors.len() / 2 + 1).max(1);
+        EnsembleDetector {
+            detectors,
+            min_votes,
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Matching window half-width in seconds.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn members(&self) -> Vec<&str> {
+        self.detectors.iter().map(|d| d.name()).collect()
+    }
+}
+
+impl QrsDetector for EnsembleDetector {
+    fn name(&self) -> &str {
+        "ensemble"
+    }
+
+    fn detect(&self, signal: &[f64], fs: f64) -> Vec<usize> {
+        self.detect_scored(signal, fs)
+            .into_iter()
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    fn detect_scored(&self, signal: &[f64], fs: f64) -> Vec<(usize, f64)> {
+        // all detections tagged with the detector that produced them
+        let mut detections: Vec<(usize, usize)> = self
+            .detectors
+            .iter()
+            .enumerate()
+            .flat_map(|(detector_idx, detector)| {
+                detector
+                    .detect(signal, fs)
+                    .into_iter()
+                    .map(move |idx| (idx, detector_idx))
+            })
+            .collect();
+        detections.sort_unstable();
+
+        let tolerance = (self.tolerance * fs) as usize;
+        fuse_votes(&detections, 2 * tolerance, self.min_votes)
+            .into_iter()
+            .map(|(member, votes)| {
+                (
+                    detections[member].0,
+                    votes as f64 / self.detectors.len() as f64,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Clusters sorted `(sample, voter)` pairs spanning at most `window` samples and keeps the
+/// clusters with at least `min_votes` distinct voters, as the index of the median member
+/// and the number of voters.
+pub(crate) fn fuse_votes(
+    tagged: &[(usize, usize)],
+    window: usize,
+    min_votes: usize,
+) -> Vec<(usize, usize)> {
+    let mut fused = Vec::new();
+    let mut start = 0;
+    while start < tagged.len() {
+        // everything up to `window` after the first member
+        let mut end = start + 1;
+        while end < tagged.len() && tagged[end].0 - tagged[start].0 <= window {
+            end += 1;
+        }
+        let cluster = &tagged[start..end];
+
+        let mut voters: Vec<usize> = cluster.iter().map(|&(_, voter)| voter).collect();
+        voters.sort_unstable();
+        voters.dedup();
+
+        if voters.len() >= min_votes {
+            fused.push((start + cluster.len() / 2, voters.len()));
+        }
+        start = end;
+    }
+    fused
+}
@@ -0,0 +1,142 @@
+use super::{argmax, moving_average, refine_peak, QrsDetector};
+use crate::filters::bandpass;
+
+/// Hamilton–Tompkins (1986) detector: 8–16 Hz band-pass, absolute derivative and 80 ms
+/// moving-window integration. Running means of the last eight QRS and noise peaks set the
+/// detection threshold, and a missed beat is searched back once 1.5×RR passes without one.
+pub struct HamiltonDetector;
+
+// detection threshold coefficient between the noise and QRS peak levels
+const THRESHOLD_COEFFICIENT: f64 = 0.3125;
+const PEAK_BUFFER: usize = 8;
+
+struct PeakBuffer {
+    values: Vec<f64>,
+}
+
+impl PeakBuffer {
+    fn new(initial: f64) -> Self {
+        PeakBuffer {
+            values: vec![initial; PEAK_BUFFER],
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        self.values.remove(0);
+        self.values.push(value);
+    }
+
+    fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+}
+
+impl QrsDetector for HamiltonDetector {
+    fn name(&self) -> &str {
+        "hamilton"
+    }
+
+    fn detect(&self, signal: &[f64], fs: f64) -> Vec<usize> {
+        let learning_len = (2.0 * fs) as usize;
+        if signal.len() < learning_len || learning_len == 0 {
+            return Vec::new();
+        }
+
+        // band-pass, absolute derivative and 80ms integration
+        let filtered = bandpass(signal, fs, 8.0, 16.0);
+        let mut derivative = vec![0.0; filtered.len()];
+        for i in 1..filtered.len() {
+            derivative[i] = (filtered[i] - filtered[i - 1]).abs();
+        }
+        let window = ((0.08 * fs) as usize).max(1);
+        let integrated = moving_average(&derivative, window);
+
+        let refractory = (0.2 * fs) as usize;
+        let t_wave_limit = (0.36 * fs) as usize;
+
+        // initial peak levels from the first 2 seconds
+        let learning_max = integrated[..learning_len]
+            .iter()
+            .cloned()
+            .fold(0.0, f64::max);
+        let mut qrs_peaks = PeakBuffer::new(0.5 * learning_max);
+        let mut noise_peaks = PeakBuffer::new(0.0);
+        let mut rr_intervals = PeakBuffer::new(fs); // 1 second until beats are found
+
+        let mut beats: Vec<(usize, f64)> = Vec::new();
+        let mut noise_since_beat: Vec<(usize, f64)> = Vec::new();
+
+        for idx in 1..integrated.len().saturating_sub(1) {
+            let value = integrated[idx];
+            if !(value > integrated[idx - 1] && value >= integrated[idx + 1]) {
+                continue;
+            }
+
+            let threshold = noise_peaks.mean()
+                + THRESHOLD_COEFFICIENT * (qrs_peaks.mean() - noise_peaks.mean());
+
+            if let Some(&(last_idx, _)) = beats.last() {
+                if idx - last_idx < refractory {
+                    continue;
+                }
+
+                // search back for the largest peak above half the threshold
+                if (idx - last_idx) as f64 > 1.5 * rr_intervals.mean() {
+                    let missed = noise_since_beat
+                        .iter()
+                        .filter(|&&(i, peak)| i > last_idx + refractory && peak > 0.5 * threshold)
+                        .max_by(|a, b| a.1.total_cmp(&b.1))
+                        .copied();
+                    if let Some((missed_idx, peak)) = missed {
+                        qrs_peaks.push(peak);
+                        rr_intervals.push((missed_idx - last_idx) as f64);
+                        beats.push((missed_idx, peak));
+                        noise_since_beat.clear();
+                    }
+                }
+            }
+
+            let last = beats.last().copied();
+            if let Some((last_idx, _)) = last {
+                if idx - last_idx < refractory {
+                    continue;
+                }
+            }
+
+            // peaks shortly after a beat at less than half its size are T waves
+            let is_t_wave = match last {
+                Some((last_idx, last_peak)) => {
+                    idx - last_idx < t_wave_limit && value < 0.5 * last_peak
+                }
+                None => false,
+            };
+
+            if value > threshold && !is_t_wave {
+                qrs_peaks.push(value);
+                if let Some((last_idx, _)) = last {
+                    rr_intervals.push((idx - last_idx) as f64);
+                }
+                beats.push((idx, value));
+                noise_since_beat.clear();
+            } else {
+                noise_peaks.push(value);
+                noise_since_beat.push((idx, value));
+            }
+        }
+
+        // the QRS lies within the integration window before the integrated peak
+        let filtered_abs: Vec<f64> = filtered.iter().map(|v| v.abs()).collect();
+        let search_window = (0.05 * fs) as usize;
+        let mut positions: Vec<usize> = beats
+            .iter()
+            .map(|&(idx, _)| {
+                let start = idx.saturating_sub(window);
+                let filtered_idx = start + argmax(&filtered_abs[start..=idx]);
+                refine_peak(signal, filtered_idx, 2 * search_window, search_window)
+            })
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+}
@@ -0,0 +1,214 @@
+mod ensemble;
+mod hamilton;
+mod pan_tompkins;
+mod threshold;
+mod wavelet;
+
+pub(crate) use ensemble::fuse_votes;
+pub use ensemble::EnsembleDetector;
+pub use hamilton::HamiltonDetector;
+pub use pan_tompkins::PanTompkinsDetector;
+pub use threshold::ThresholdDetector;
+pub use wavelet::WaveletDetector;
+
+/// A QRS detection algorithm working on a single-lead signal. Detectors are shared
+/// between threads when segments are processed in parallel.
+pub trait QrsDetector: Send + Sync {
+    /// Name used to select the detector at runtime.
+    fn name(&self) -> &str;
+
+    /// Returns the sample indices of detected QRS complexes, in ascending order.
+    fn detect(&self, signal: &[f64], fs: f64) -> Vec<usize>;
+
+    /// Like `detect`, with a confidence in [0, 1] for each beat; by default its deflection
+    /// from the signal mean relative to the median deflection of the beats (see
+    /// [`amplitude_scores`]).
+    fn detect_scored(&self, signal: &[f64], fs: f64) -> Vec<(usize, f64)> {
+        amplitude_scores(signal, &self.detect(signal, fs))
+    }
+}
+
+/// Scores each beat with its deflection from the signal mean divided by the median
+/// deflection of all beats, capped at 1, so beats much smaller than the typical one (noise
+/// picked as a beat, missed-beat search-backs) score low.
+pub fn amplitude_scores(signal: &[f64], beats: &[usize]) -> Vec<(usize, f64)> {
+    if signal.is_empty() || beats.is_empty() {
+        return Vec::new();
+    }
+    let mean = signal.iter().sum::<f64>() / signal.len() as f64;
+    let deflections: Vec<f64> = beats
+        .iter()
+        .map(|&idx| (signal[idx] - mean).abs())
+        .collect();
+    let mut sorted = deflections.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    beats
+        .iter()
+        .zip(deflections)
+        .map(|(&idx, deflection)| {
+            let score = if median > 0.0 {
+                (deflection / median).min(1.0)
+            } else {
+                1.0
+            };
+            (idx, score)
+        })
+        .collect()
+}
+
+/// Timing and threshold constants of the amplitude-threshold detector and of merging beats,
+/// in seconds. The refractory period between beats follows the heart rate, so the defaults
+/// hold from bradycardia to the RR intervals under 500 ms of children and tachycardia.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectorConfig {
+    /// Shortest distance between two beats of the threshold detector.
+    pub min_peak_distance: f64,
+    /// Threshold detector beats also keep this fraction of the running average RR interval
+    /// apart, the larger of two closer ones staying; 0 leaves `min_peak_distance` alone.
+    pub refractory_fraction: f64,
+    /// Weight of each beat in that running average RR interval; 0 keeps it at the median
+    /// interval of the segment's well-separated peaks.
+    pub refractory_adaptation: f64,
+    /// A threshold detector candidate is the extremum of this much signal on either side.
+    pub search_window: f64,
+    /// Each threshold detector beat moves to the largest absolute deflection within this
+    /// distance.
+    pub refinement_window: f64,
+    /// Without adaptation, candidates exceed this many standard deviations of the
+    /// mean-subtracted signal; with it, the beats above it start the running average.
+    pub threshold_factor: f64,
+    /// Weight of each beat in the running (exponential) average of beat amplitudes the
+    /// threshold detector follows; 0 keeps the fixed `threshold_factor` × SD per segment.
+    pub adaptation: f64,
+    /// With adaptation, candidates exceed this fraction of the running average amplitude
+    /// (and at least one standard deviation).
+    pub peak_fraction: f64,
+    /// The start of the signal over which the threshold detector learns the beat amplitude
+    /// before committing beats.
+    pub warm_up: f64,
+    /// Detect beats in the warm-up afterwards with the learnt levels; otherwise the beats
+    /// there are dropped, which with a segment overlap as long as the warm-up only affects
+    /// the start of the recording.
+    pub reprocess_warm_up: bool,
+    /// Beats closer than this to the previous one are dropped as duplicates from the overlap
+    /// of segments or streaming windows, whatever the detector.
+    pub dedup_interval: f64,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        DetectorConfig {
+            min_peak_distance: 0.2,
+            refractory_fraction: 0.4,
+            refractory_adaptation: 0.125,
+            search_window: 0.15,
+            refinement_window: 0.08,
+            threshold_factor: 2.0,
+            adaptation: 0.125,
+            peak_fraction: 0.5,
+            warm_up: 2.0,
+            reprocess_warm_up: true,
+            dedup_interval: 0.2,
+        }
+    }
+}
+
+/// Detectors available for selection by name.
+pub struct DetectorRegistry {
+    detectors: Vec<Box<dyn QrsDetector>>,
+}
+
+impl DetectorRegistry {
+    /// An empty registry; use `DetectorRegistry::default()` for the built-in detectors.
+    pub fn new() -> Self {
+        DetectorRegistry {
+            detectors: Vec::new(),
+        }
+    }
+
+    /// Adds a detector, replacing any already registered under the same name.
+    pub fn register(&mut self, detector: Box<dyn QrsDetector>) {
+        self.detectors.retain(|d| d.name() != detector.name());
+        self.detectors.push(detector);
+    }
+
+    /// Takes a detector out of the registry, e.g. to build an ensemble from it.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn QrsDetector>> {
+        let idx = self.detectors.iter().position(|d| d.name() == name)?;
+        Some(self.detectors.remove(idx))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn QrsDetector> {
+        self.detectors
+            .iter()
+            .find(|d| d.name() == name)
+            .map(|d| d.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.detectors.iter().map(|d| d.name()).collect()
+    }
+
+    /// The built-in detectors, the threshold detector (alone and in the ensemble) with the
+    /// given constants.
+    pub fn with_config(config: DetectorConfig) -> Self {
+        let mut registry = DetectorRegistry::new();
+        registry.register(Box::new(ThresholdDetector::new(config)));
+        registry.register(Box::new(PanTompkinsDetector));
+        registry.register(Box::new(WaveletDetector));
+        registry.register(Box::new(HamiltonDetector));
+        registry.register(Box::new(EnsembleDetector::new(
+            vec![
+                Box::new(ThresholdDetector::new(config)),
+                Box::new(PanTompkinsDetector),
+                Box::new(WaveletDetector),
+                Box::new(HamiltonDetector),
+            ],
+            None,
+        )));
+        registry
+    }
+}
+
+impl Default for DetectorRegistry {
+    fn default() -> Self {
+        DetectorRegistry::with_config(DetectorConfig::default())
+    }
+}
+
+// causal moving average over the last `window` samples
+pub(crate) fn moving_average(data: &[f64], window: usize) -> Vec<f64> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut sum = 0.0;
+    for i in 0..data.len() {
+        sum += data[i];
+        if i >= window {
+            sum -= data[i - window];
+        }
+        result.push(sum / window as f64);
+    }
+    result
+}
+
+pub(crate) fn argmax(data: &[f64]) -> usize {
+    let mut best = 0;
+    for (i, &value) in data.iter().enumerate() {
+        if value > data[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+// index of the largest deviation from the signal mean in [idx - before, idx + after]
+pub(crate) fn refine_peak(signal: &[f64], idx: usize, before: usize, after: usize) -> usize {
+    let mean = signal.iter().sum::<f64>() / signal.len() as f64;
+    let start = idx.saturating_sub(before);
+    let end = std::cmp::min(idx + after, signal.len() - 1);
+    let deviations: Vec<f64> = signal[start..=end]
+        .iter()
+        .map(|v| (v - mean).abs())
+        .collect();
+    start + argmax(&deviations)
+}
@@ -0,0 +1,233 @@
+use super::{argmax, moving_average, refine_peak, QrsDetector};
+use crate::filters::bandpass;
+
+/// Pan–Tompkins (1985) detector: 5–15 Hz band-pass, derivative, squaring and
+/// moving-window integration, followed by dual adaptive thresholds with
+/// search-back for missed beats and T-wave discrimination.
+pub struct PanTompkinsDetector;
+
+// a peak of the integrated signal and the matching band-passed peak
+#[derive(Clone, Copy)]
+struct Candidate {
+    integrated_idx: usize,
+    filtered_idx: usize,
+    integrated_peak: f64,
+    filtered_peak: f64,
+}
+
+struct Levels {
+    signal: f64,
+    noise: f64,
+}
+
+impl Levels {
+    fn threshold(&self) -> f64 {
+        self.noise + 0.25 * (self.signal - self.noise)
+    }
+}
+
+impl QrsDetector for PanTompkinsDetector {
+    fn name(&self) -> &str {
+        "pan-tompkins"
+    }
+
+    fn detect(&self, signal: &[f64], fs: f64) -> Vec<usize> {
+        let learning_len = (2.0 * fs) as usize;
+        if signal.len() < learning_len || learning_len == 0 {
+            return Vec::new();
+        }
+
+        // Step 1: band-pass to the QRS energy band
+        let filtered = bandpass(signal, fs, 5.0, 15.0);
+
+        // Step 2: five-point derivative
+        let mut derivative = vec![0.0; filtered.len()];
+        for i in 4..filtered.len() {
+            derivative[i] =
+                (2.0 * filtered[i] + filtered[i - 1] - filtered[i - 3] - 2.0 * filtered[i - 4])
+                    * fs
+                    / 8.0;
+        }
+
+        // Step 3: squaring and moving-window integration (150 ms)
+        let window = ((0.15 * fs) as usize).max(1);
+        let integrated = moving_average(
+            &derivative.iter().map(|d| d * d).collect::<Vec<_>>(),
+            window,
+        );
+
+        let refractory = (0.2 * fs) as usize; // 200ms physiological refractory period
+        let t_wave_limit = (0.36 * fs) as usize; // T-wave check below 360ms
+
+        // Step 4: learning phase over the first 2 seconds
+        let learning_max = integrated[..learning_len]
+            .iter()
+            .cloned()
+            .fold(0.0, f64::max);
+        let learning_mean = integrated[..learning_len].iter().sum::<f64>() / learning_len as f64;
+        let mut integrated_levels = Levels {
+            signal: 0.25 * learning_max,
+            noise: 0.5 * learning_mean,
+        };
+        let filtered_abs: Vec<f64> = filtered.iter().map(|v| v.abs()).collect();
+        let filtered_max = filtered_abs[..learning_len]
+            .iter()
+            .cloned()
+            .fold(0.0, f64::max);
+        let filtered_mean = filtered_abs[..learning_len].iter().sum::<f64>() / learning_len as f64;
+        let mut filtered_levels = Levels {
+            signal: 0.25 * filtered_max,
+            noise: 0.5 * filtered_mean,
+        };
+
+        let mut beats: Vec<Candidate> = Vec::new();
+        let mut noise_candidates: Vec<Candidate> = Vec::new();
+        let mut last_slope = 0.0;
+        let mut rr_recent: Vec<usize> = Vec::new();
+        let mut rr_selected: Vec<usize> = Vec::new();
+        let mut rr_average: Option<f64> = None;
+
+        // Step 5: adaptive thresholding of the integrated peaks
+        for integrated_idx in 1..integrated.len().saturating_sub(1) {
+            let value = integrated[integrated_idx];
+            if !(value > integrated[integrated_idx - 1] && value >= integrated[integrated_idx + 1])
+            {
+                continue;
+            }
+
+            // the band-passed peak lies within the integration window before the integrated peak
+            let window_start = integrated_idx.saturating_sub(window);
+            let filtered_idx = argmax(&filtered_abs[window_start..=integrated_idx]) + window_start;
+            let candidate = Candidate {
+                integrated_idx,
+                filtered_idx,
+                integrated_peak: value,
+                filtered_peak: filtered_abs[filtered_idx],
+            };
+
+            if let Some(last) = beats.last().copied() {
+                let distance = integrated_idx - last.integrated_idx;
+                if distance < refractory {
+                    continue;
+                }
+
+                // search back for a missed beat when the RR limit of 166% is exceeded
+                if let Some(average) = rr_average {
+                    if distance as f64 > 1.66 * average {
+                        let missed = noise_candidates
+                            .iter()
+                            .filter(|c| {
+                                c.integrated_idx > last.integrated_idx + refractory
+                                    && c.integrated_peak > 0.5 * integrated_levels.threshold()
+                                    && c.filtered_peak > 0.5 * filtered_levels.threshold()
+                            })
+                            .max_by(|a, b| a.integrated_peak.total_cmp(&b.integrated_peak))
+                            .copied();
+                        if let Some(missed) = missed {
+                            integrated_levels.signal =
+                                0.25 * missed.integrated_peak + 0.75 * integrated_levels.signal;
+                            filtered_levels.signal =
+                                0.25 * missed.filtered_peak + 0.75 * filtered_levels.signal;
+                            update_rr(
+                                &mut rr_recent,
+                                &mut rr_selected,
+                                &mut rr_average,
+                                missed.integrated_idx - last.integrated_idx,
+                            );
+                            beats.push(missed);
+                        }
+                    }
+                }
+            }
+
+            let last = beats.last().copied();
+            if let Some(last) = last {
+                if integrated_idx - last.integrated_idx < refractory {
+                    continue;
+                }
+            }
+
+            let is_signal = candidate.integrated_peak > integrated_levels.threshold()
+                && candidate.filtered_peak > filtered_levels.threshold();
+
+            let slope = derivative[window_start..=integrated_idx]
+                .iter()
+                .fold(0.0, |acc: f64, d| acc.max(d.abs()));
+            let is_t_wave = match last {
+                Some(last) => {
+                    integrated_idx - last.integrated_idx < t_wave_limit && slope < 0.5 * last_slope
+                }
+                None => false,
+            };
+
+            if is_signal && !is_t_wave {
+                integrated_levels.signal =
+                    0.125 * candidate.integrated_peak + 0.875 * integrated_levels.signal;
+                filtered_levels.signal =
+                    0.125 * candidate.filtered_peak + 0.875 * filtered_levels.signal;
+                if let Some(last) = last {
+                    update_rr(
+                        &mut rr_recent,
+                        &mut rr_selected,
+                        &mut rr_average,
+                        integrated_idx - last.integrated_idx,
+                    );
+                }
+                last_slope = slope;
+                beats.push(candidate);
+                noise_candidates.clear();
+            } else {
+                integrated_levels.noise =
+                    0.125 * candidate.integrated_peak + 0.875 * integrated_levels.noise;
+                filtered_levels.noise =
+                    0.125 * candidate.filtered_peak + 0.875 * filtered_levels.noise;
+                noise_candidates.push(candidate);
+            }
+        }
+
+        // Step 6: locate the R (or S) wave in the raw signal around each band-passed peak,
+        // allowing for the group delay of the causal filters
+        let search_window = (0.05 * fs) as usize;
+        let mut positions: Vec<usize> = beats
+            .iter()
+            .map(|beat| refine_peak(signal, beat.filtered_idx, 2 * search_window, search_window))
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+}
+
+// RR average 1 uses the last 8 intervals, RR average 2 only the regular ones
+fn update_rr(
+    rr_recent: &mut Vec<usize>,
+    rr_selected: &mut Vec<usize>,
+    rr_average: &mut Option<f64>,
+    rr: usize,
+) {
+    rr_recent.push(rr);
+    if rr_recent.len() > 8 {
+        rr_recent.remove(0);
+    }
+
+    let is_regular = match *rr_average {
+        Some(average) => (rr as f64) > 0.92 * average && (rr as f64) < 1.16 * average,
+        None => true,
+    };
+    if is_regular {
+        rr_selected.push(rr);
+        if rr_selected.len() > 8 {
+            rr_selected.remove(0);
+        }
+    } else if rr_recent.len() == 8
+        && rr_recent.iter().all(|&r| {
+            let average = rr_average.unwrap_or(r as f64);
+            (r as f64) <= 0.92 * average || (r as f64) >= 1.16 * average
+        })
+    {
+        // rhythm changed: restart the regular average from the recent intervals
+        rr_selected.clone_from(rr_recent);
+    }
+
+    *rr_average = Some(rr_selected.iter().sum::<usize>() as f64 / rr_selected.len() as f64);
+}
@@ -0,0 +1,320 @@
+use super::{DetectorConfig, QrsDetector};
+use std::cmp::Ordering;
+
+// peaks this far apart (in seconds) seed the running RR average of the refractory period:
+// beyond the T waves 250-350 ms after a QRS complex, yet below two RR intervals at 300 bpm
+const SEED_DISTANCE: f64 = 0.36;
+
+/// Amplitude-threshold detector: local extrema above a threshold that follows the running
+/// average amplitude of the beats (or a fixed 2×SD of the mean-subtracted signal), refined to
+/// the absolute maximum of the raw signal. The average is learnt over a warm-up at the start
+/// of the signal before beats are committed. The constants are taken from its
+/// [`DetectorConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct ThresholdDetector {
+    pub config: DetectorConfig,
+}
+
+impl ThresholdDetector {
+    pub fn new(config: DetectorConfig) -> Self {
+        ThresholdDetector { config }
+    }
+}
+
+impl QrsDetector for ThresholdDetector {
+    fn name(&self) -> &str {
+        "threshold"
+    }
+
+    fn detect(&self, signal: &[f64], fs: f64) -> Vec<usize> {
+        if signal.is_empty() {
+            return Vec::new();
+        }
+
+        // Step 1: Normalization
+        let mean: f64 = signal.iter().sum::<f64>() / signal.len() as f64;
+        let normalized: Vec<f64> = signal.iter().map(|&v| v - mean).collect();
+
+        // Step 2: Find QRS complexes directly
+        find_qrs_peaks_direct(&normalized, signal, fs, &self.config)
+    }
+}
+
+fn find_qrs_peaks_direct(
+    voltage: &[f64],
+    raw: &[f64],
+    fs: f64,
+    config: &DetectorConfig,
+) -> Vec<usize> {
+    let mut qrs_positions = Vec::new();
+
+    // Constants adjusted for physiological values, by default 200ms minimum between QRS
+    // complexes (the rate-aware refractory period below keeps them further apart) and a
+    // 150ms search window
+    let min_peak_distance = (config.min_peak_distance * fs) as usize;
+    let window_size = (config.search_window * fs) as usize;
+
+    // not enough samples for a single search window on both sides
+    if voltage.len() <= 2 * window_size {
+        return qrs_positions;
+    }
+
+    // Calculate voltage variability
+    let std_dev = calculate_std_dev(voltage);
+    // 2×SD by default
+    let threshold = config.threshold_factor * std_dev;
+    // an adaptive threshold never falls below 1 SD
+    let floor = if config.adaptation > 0.0 {
+        threshold.min(std_dev)
+    } else {
+        threshold
+    };
+
+    // Find all potential peaks (both positive and negative)
+    let mut peak_candidates = Vec::new();
+
+    for i in window_size..(voltage.len() - window_size) {
+        // Check if this point is a significant local extrema
+        let is_positive_peak = voltage[i] > 0.0
+            && voltage[i]
+                >= *voltage[i - window_size..i]
+                    .iter()
+                    .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                    .unwrap_or(&f64::NEG_INFINITY)
+            && voltage[i]
+                >= *voltage[i + 1..i + window_size]
+                    .iter()
+                    .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                    .unwrap_or(&f64::NEG_INFINITY)
+            && voltage[i].abs() > floor;
+
+        let is_negative_peak = voltage[i] < 0.0
+            && voltage[i]
+                <= *voltage[i - window_size..i]
+                    .iter()
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                    .unwrap_or(&f64::INFINITY)
+            && voltage[i]
+                <= *voltage[i + 1..i + window_size]
+                    .iter()
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                    .unwrap_or(&f64::INFINITY)
+            && voltage[i].abs() > floor;
+
+        if is_positive_peak || is_negative_peak {
+            peak_candidates.push((i, voltage[i].abs()));
+        }
+    }
+    if config.adaptation > 0.0 {
+        peak_candidates = adaptive_candidates(&peak_candidates, threshold, std_dev, fs, config);
+    } else if !config.reprocess_warm_up {
+        let warm_up = warm_up_len(config, fs);
+        peak_candidates.retain(|&(idx, _)| idx >= warm_up);
+    }
+
+    // Sort peaks by amplitude (largest first)
+    peak_candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+    // Filter peaks keeping only the strongest ones that are sufficiently far apart
+    let mut selected_peaks = Vec::new();
+
+    for &(idx, _) in &peak_candidates {
+        // Check if this peak is far enough from all previously selected peaks
+        let is_isolated = selected_peaks
+            .iter()
+            .all(|&prev_idx| (idx as isize - prev_idx as isize).abs() > min_peak_distance as isize);
+
+        if is_isolated {
+            selected_peaks.push(idx);
+        }
+    }
+
+    // Sort selected peaks by position
+    selected_peaks.sort_unstable();
+    if config.refractory_fraction > 0.0 {
+        selected_peaks = rate_aware_refractory(&selected_peaks, voltage, fs, config);
+    }
+
+    // For each selected peak, find the exact R or S wave
+    // by looking in a window centered on the peak
+    for &idx in &selected_peaks {
+        // Determine the window to search for the R or S wave
+        let search_window = (config.refinement_window * fs) as usize; // 80ms by default
+        let start = idx.saturating_sub(search_window);
+        let end = std::cmp::min(idx + search_window, raw.len());
+
+        if start >= end {
+            continue;
+        }
+
+        // Find the absolute maximum voltage in the window
+        // This will be either the R peak or S peak
+        let mut max_abs_idx = start;
+        let mut max_abs_value = raw[start].abs();
+
+        for (i, &value) in raw.iter().enumerate().take(end).skip(start + 1) {
+            let abs_voltage = value.abs();
+            if abs_voltage > max_abs_value {
+                max_abs_idx = i;
+                max_abs_value = abs_voltage;
+            }
+        }
+
+        qrs_positions.push(max_abs_idx);
+    }
+
+    qrs_positions
+}
+
+// the candidates (in time order) above a fraction of the exponential average of the
+// amplitudes of the beats before them, so the threshold follows amplitude changes within the
+// segment such as posture shifts. The average is learnt over the warm-up first, starting
+// from the candidates there above the fixed threshold, and halves whenever 1.66 average RR
+// intervals pass without a beat, so lost beats are found again.
+fn adaptive_candidates(
+    candidates: &[(usize, f64)],
+    threshold: f64,
+    std_dev: f64,
+    fs: f64,
+    config: &DetectorConfig,
+) -> Vec<(usize, f64)> {
+    let warm_up = warm_up_len(config, fs);
+    let strong = |in_warm_up: bool| -> Vec<f64> {
+        candidates
+            .iter()
+            .filter(|&&(idx, amplitude)| amplitude > threshold && (!in_warm_up || idx < warm_up))
+            .map(|&(_, amplitude)| amplitude)
+            .collect()
+    };
+    // the whole signal when the warm-up holds no clear beat
+    let mut learning = strong(true);
+    if learning.is_empty() {
+        learning = strong(false);
+    }
+    if learning.is_empty() {
+        return Vec::new();
+    }
+
+    let mut tracker = Tracker {
+        level: learning.iter().sum::<f64>() / learning.len() as f64,
+        interval: fs,
+        last_beat: None,
+    };
+    let (in_warm_up, after) = candidates.split_at(candidates.partition_point(|c| c.0 < warm_up));
+    for &(idx, amplitude) in in_warm_up {
+        tracker.step(idx, amplitude, std_dev, fs, config);
+    }
+    // detecting again from the start with the learnt levels
+    let committed = if config.reprocess_warm_up {
+        tracker.last_beat = None;
+        candidates
+    } else {
+        after
+    };
+    committed
+        .iter()
+        .copied()
+        .filter(|&(idx, amplitude)| tracker.step(idx, amplitude, std_dev, fs, config))
+        .collect()
+}
+
+// keeps the peaks (in time order) at least `refractory_fraction` of the running average
+// interval between them apart, the larger of two closer ones, so the refractory period
+// shortens as the heart rate rises. The average starts from the median interval of the
+// peaks kept `SEED_DISTANCE` apart, so T waves and noise peaks passing the shorter
+// `min_peak_distance` do not halve it.
+fn rate_aware_refractory(
+    peaks: &[usize],
+    voltage: &[f64],
+    fs: f64,
+    config: &DetectorConfig,
+) -> Vec<usize> {
+    let seeds = keep_apart(peaks, voltage, SEED_DISTANCE * fs);
+    let mut intervals: Vec<usize> = seeds.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    if intervals.is_empty() {
+        return peaks.to_vec();
+    }
+    intervals.sort_unstable();
+    let mut average = intervals[intervals.len() / 2] as f64;
+    let rate = config.refractory_adaptation.clamp(0.0, 1.0);
+
+    let mut kept: Vec<usize> = Vec::new();
+    for &idx in peaks {
+        match kept.last_mut() {
+            Some(last) if ((idx - *last) as f64) < config.refractory_fraction * average => {
+                if voltage[idx].abs() > voltage[*last].abs() {
+                    *last = idx;
+                }
+            }
+            Some(last) => {
+                average += rate * ((idx - *last) as f64 - average);
+                kept.push(idx);
+            }
+            None => kept.push(idx),
+        }
+    }
+    kept
+}
+
+// the peaks (in time order) at least `distance` samples apart, the larger of two closer ones
+fn keep_apart(peaks: &[usize], voltage: &[f64], distance: f64) -> Vec<usize> {
+    let mut kept: Vec<usize> = Vec::new();
+    for &idx in peaks {
+        match kept.last_mut() {
+            Some(last) if ((idx - *last) as f64) < distance => {
+                if voltage[idx].abs() > voltage[*last].abs() {
+                    *last = idx;
+                }
+            }
+            _ => kept.push(idx),
+        }
+    }
+    kept
+}
+
+fn warm_up_len(config: &DetectorConfig, fs: f64) -> usize {
+    (config.warm_up.max(0.0) * fs) as usize
+}
+
+// the running beat amplitude and RR interval (in samples) of the adaptive threshold
+struct Tracker {
+    level: f64,
+    interval: f64,
+    last_beat: Option<usize>,
+}
+
+impl Tracker {
+    // whether the candidate at `idx` is a beat, updating the averages with it when it is
+    fn step(
+        &mut self,
+        idx: usize,
+        amplitude: f64,
+        std_dev: f64,
+        fs: f64,
+        config: &DetectorConfig,
+    ) -> bool {
+        let rate = config.adaptation.min(1.0);
+        let since = idx.saturating_sub(self.last_beat.unwrap_or(0));
+        if since as f64 > 1.66 * self.interval {
+            self.level *= 0.5;
+        }
+        if amplitude <= (config.peak_fraction * self.level).max(std_dev) {
+            return false;
+        }
+        self.level += rate * (amplitude - self.level);
+        if let Some(last) = self.last_beat.filter(|&last| idx > last) {
+            if idx - last >= (config.min_peak_distance * fs) as usize {
+                self.interval += rate * ((idx - last) as f64 - self.interval);
+            }
+        }
+        self.last_beat = Some(idx);
+        true
+    }
+}
+
+fn calculate_std_dev(data: &[f64]) -> f64 {
+    let mean: f64 = data.iter().sum::<f64>() / data.len() as f64;
+    let variance: f64 =
+        data.iter().map(|&x| (x - mean) * (x - mean)).sum::<f64>() / data.len() as f64;
+    variance.sqrt()
+}
@@ -0,0 +1,118 @@
+use super::QrsDetector;
+
+/// Wavelet detector after Li et al. (1995): undecimated (à trous) quadratic spline wavelet
+/// transform, QRS complexes located at the zero crossing between a pair of opposite-sign
+/// modulus maxima at the scale closest to 2^3 at 250 Hz.
+pub struct WaveletDetector;
+
+impl QrsDetector for WaveletDetector {
+    fn name(&self) -> &str {
+        "wavelet"
+    }
+
+    fn detect(&self, signal: &[f64], fs: f64) -> Vec<usize> {
+        if signal.len() < fs as usize || fs < 8.0 {
+            return Vec::new();
+        }
+
+        let mean = signal.iter().sum::<f64>() / signal.len() as f64;
+        let centered: Vec<f64> = signal.iter().map(|v| v - mean).collect();
+
+        // scale 2^3 at 250 Hz, i.e. a characteristic width of about 32 ms
+        let scale = ((0.032 * fs).log2().round() as u32).clamp(1, 6);
+        let detail = wavelet_detail(&centered, scale);
+
+        // Threshold: 30% of the median of the maxima over 2 second blocks
+        let block = (2.0 * fs) as usize;
+        let mut block_maxima: Vec<f64> = detail
+            .chunks(block)
+            .map(|chunk| chunk.iter().fold(0.0, |acc: f64, v| acc.max(v.abs())))
+            .collect();
+        block_maxima.sort_by(|a, b| a.total_cmp(b));
+        let threshold = 0.3 * block_maxima[block_maxima.len() / 2];
+
+        // modulus maxima above the threshold
+        let maxima: Vec<usize> = (1..detail.len() - 1)
+            .filter(|&i| {
+                let magnitude = detail[i].abs();
+                magnitude > threshold
+                    && magnitude >= detail[i - 1].abs()
+                    && magnitude > detail[i + 1].abs()
+            })
+            .collect();
+
+        // opposite-sign pairs no further apart than 120 ms; the R wave sits at the zero crossing
+        let max_pair_distance = (0.12 * fs) as usize;
+        let mut candidates: Vec<(usize, f64)> = Vec::new();
+        for pair in maxima.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            if second - first > max_pair_distance
+                || detail[first].signum() == detail[second].signum()
+            {
+                continue;
+            }
+            let crossing = (first..second)
+                .find(|&i| detail[i].signum() != detail[i + 1].signum())
+                .unwrap_or(first);
+            candidates.push((crossing, detail[first].abs() + detail[second].abs()));
+        }
+
+        // keep the strongest candidates outside the 200ms refractory period of each other;
+        // weak candidates within 360ms of a stronger beat are taken for T waves
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let refractory = (0.2 * fs) as usize;
+        let t_wave_limit = (0.36 * fs) as usize;
+        let mut selected: Vec<(usize, f64)> = Vec::new();
+        for &(idx, score) in &candidates {
+            let accepted = selected.iter().all(|&(prev, prev_score)| {
+                let distance = idx.abs_diff(prev);
+                distance > refractory && (distance > t_wave_limit || score > 0.5 * prev_score)
+            });
+            if accepted {
+                selected.push((idx, score));
+            }
+        }
+
+        // refine to the absolute extremum of the signal within 50ms
+        let search_window = (0.05 * fs) as usize;
+        let mut positions: Vec<usize> = selected
+            .iter()
+            .map(|&(idx, _)| {
+                let start = idx.saturating_sub(search_window);
+                let end = std::cmp::min(idx + search_window, centered.len() - 1);
+                (start..=end)
+                    .max_by(|&a, &b| centered[a].abs().total_cmp(&centered[b].abs()))
+                    .unwrap_or(idx)
+            })
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+}
+
+// detail coefficients at scale 2^scale of the quadratic spline wavelet, computed with the
+// à trous algorithm (filters dilated by 2^(j-1) instead of decimating the signal)
+fn wavelet_detail(signal: &[f64], scale: u32) -> Vec<f64> {
+    let n = signal.len();
+    let at = |data: &[f64], i: isize| data[i.clamp(0, n as isize - 1) as usize];
+
+    let mut approximation = signal.to_vec();
+    for j in 1..scale {
+        let step = 1isize << (j - 1);
+        approximation = (0..n as isize)
+            .map(|i| {
+                (at(&approximation, i - step)
+                    + 3.0 * at(&approximation, i)
+                    + 3.0 * at(&approximation, i + step)
+                    + at(&approximation, i + 2 * step))
+                    / 8.0
+            })
+            .collect();
+    }
+
+    let step = 1isize << (scale - 1);
+    (0..n as isize)
+        .map(|i| at(&approximation, i + step) - at(&approximation, i))
+        .collect()
+}
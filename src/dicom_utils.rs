@@ -0,0 +1,586 @@
+use crate::compression::open_input;
+use crate::edf_utils::{find_label, millivolts_per};
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+use tracing::{info, warn};
+
+type Tag = (u16, u16);
+
+const PATIENT_NAME: Tag = (0x0010, 0x0010);
+const PATIENT_ID: Tag = (0x0010, 0x0020);
+const ACQUISITION_DATETIME: Tag = (0x0008, 0x002A);
+const TRANSFER_SYNTAX: Tag = (0x0002, 0x0010);
+const WAVEFORM_SEQUENCE: Tag = (0x5400, 0x0100);
+const NUMBER_OF_CHANNELS: Tag = (0x003A, 0x0005);
+const NUMBER_OF_SAMPLES: Tag = (0x003A, 0x0010);
+const SAMPLING_FREQUENCY: Tag = (0x003A, 0x001A);
+const MULTIPLEX_GROUP_LABEL: Tag = (0x003A, 0x0020);
+const CHANNEL_DEFINITIONS: Tag = (0x003A, 0x0200);
+const CHANNEL_LABEL: Tag = (0x003A, 0x0203);
+const CHANNEL_SOURCE: Tag = (0x003A, 0x0208);
+const CHANNEL_SENSITIVITY: Tag = (0x003A, 0x0210);
+const SENSITIVITY_UNITS: Tag = (0x003A, 0x0211);
+const SENSITIVITY_CORRECTION: Tag = (0x003A, 0x0212);
+const CHANNEL_BASELINE: Tag = (0x003A, 0x0213);
+const CODE_VALUE: Tag = (0x0008, 0x0100);
+const CODE_MEANING: Tag = (0x0008, 0x0104);
+const BITS_ALLOCATED: Tag = (0x5400, 0x1004);
+const SAMPLE_INTERPRETATION: Tag = (0x5400, 0x1006);
+const WAVEFORM_DATA: Tag = (0x5400, 0x1010);
+
+const ITEM: Tag = (0xFFFE, 0xE000);
+const ITEM_END: Tag = (0xFFFE, 0xE00D);
+const SEQUENCE_END: Tag = (0xFFFE, 0xE0DD);
+const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+
+// sequences whose VR an implicit VR transfer syntax leaves to the dictionary
+const SEQUENCES: [Tag; 4] = [
+    WAVEFORM_SEQUENCE,
+    CHANNEL_DEFINITIONS,
+    CHANNEL_SOURCE,
+    SENSITIVITY_UNITS,
+];
+
+/// The waveform of a DICOM waveform object (12-lead, general or ambulatory ECG), read from
+/// one multiplex group.
+#[derive(Debug, Clone)]
+pub struct DicomWaveform {
+    pub patient_name: String,
+    pub patient_id: String,
+    /// Acquisition DateTime as the file gives it (`YYYYMMDDHHMMSS.FFFFFF`), empty when absent.
+    pub acquisition_datetime: String,
+    /// Label of the multiplex group read, e.g. `RHYTHM`.
+    pub group_label: String,
+    pub sampling_frequency: f64,
+    /// The channels of the group in millivolts, named after their sources (`I`, `V5`, ...).
+    pub ecg: MultiLeadEcg,
+}
+
+impl DicomWaveform {
+    /// Index of the lead with the given name (`II`, `V5`, ...), ignoring case; the error
+    /// lists the leads there are.
+    pub fn find_lead(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let names: Vec<&str> = self
+            .ecg
+            .leads
+            .iter()
+            .map(|lead| lead.name.as_str())
+            .collect();
+        find_label(&names, name)
+    }
+}
+
+enum Value<'a> {
+    Bytes(&'a [u8]),
+    Items(Vec<DataSet<'a>>),
+}
+
+// the elements of a data set or sequence item
+#[derive(Default)]
+struct DataSet<'a> {
+    elements: Vec<(Tag, Value<'a>)>,
+}
+
+impl<'a> DataSet<'a> {
+    fn bytes(&self, tag: Tag) -> Option<&'a [u8]> {
+        self.elements
+            .iter()
+            .find_map(|(element, value)| match value {
+                Value::Bytes(bytes) if *element == tag => Some(*bytes),
+                _ => None,
+            })
+    }
+
+    fn items(&self, tag: Tag) -> &[DataSet<'a>] {
+        self.elements
+            .iter()
+            .find_map(|(element, value)| match value {
+                Value::Items(items) if *element == tag => Some(items.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    // a string value without its padding
+    fn text(&self, tag: Tag) -> String {
+        self.bytes(tag).map_or_else(String::new, |bytes| {
+            String::from_utf8_lossy(bytes)
+                .trim_end_matches(['\0', ' '])
+                .trim()
+                .to_string()
+        })
+    }
+
+    // the first value of a decimal or integer string
+    fn number(&self, tag: Tag) -> Option<f64> {
+        self.text(tag).split('\\').next()?.trim().parse().ok()
+    }
+
+    fn short(&self, tag: Tag) -> Option<u16> {
+        let bytes = self.bytes(tag)?;
+        Some(u16::from_le_bytes([*bytes.first()?, *bytes.get(1)?]))
+    }
+
+    fn long(&self, tag: Tag) -> Option<u32> {
+        let bytes = self.bytes(tag)?.get(..4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+// reads little-endian data elements, with explicit VRs or implicit ones
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    explicit: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or("DICOM file ends inside a data element")?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, Box<dyn Error>> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn tag(&mut self) -> Result<Tag, Box<dyn Error>> {
+        Ok((self.u16()?, self.u16()?))
+    }
+
+    // the elements up to the byte offset `end`, or up to an item delimiter without one
+    fn data_set(&mut self, end: Option<usize>) -> Result<DataSet<'a>, Box<dyn Error>> {
+        let mut data_set = DataSet::default();
+        loop {
+            match end {
+                Some(end) if self.position >= end => break,
+                None if self.position >= self.bytes.len() => {
+                    return Err("DICOM item without its delimiter".into())
+                }
+                _ => {}
+            }
+            match self.element()? {
+                Some(element) => data_set.elements.push(element),
+                None => break,
+            }
+        }
+        Ok(data_set)
+    }
+
+    // the next data element, `None` at an item delimiter
+    fn element(&mut self) -> Result<Option<(Tag, Value<'a>)>, Box<dyn Error>> {
+        let tag = self.tag()?;
+        if tag == ITEM_END {
+            self.u32()?;
+            return Ok(None);
+        }
+        let (vr, length) = if self.explicit {
+            let vr = self.take(2)?;
+            let length = match vr {
+                b"OB" | b"OD" | b"OF" | b"OL" | b"OV" | b"OW" | b"SQ" | b"SV" | b"UC" | b"UN"
+                | b"UR" | b"UT" | b"UV" => {
+                    self.take(2)?;
+                    self.u32()?
+                }
+                _ => self.u16()? as u32,
+            };
+            (Some(vr), length)
+        } else {
+            (None, self.u32()?)
+        };
+
+        let is_sequence = match vr {
+            Some(vr) => vr == b"SQ",
+            None => length == UNDEFINED_LENGTH || SEQUENCES.contains(&tag),
+        };
+        let value = if is_sequence {
+            Value::Items(self.sequence(length)?)
+        } else if length == UNDEFINED_LENGTH {
+            return Err(format!(
+                "Unsupported DICOM element ({:04X},{:04X}) of undefined length",
+                tag.0, tag.1
+            )
+            .into());
+        } else {
+            Value::Bytes(self.take(length as usize)?)
+        };
+        Ok(Some((tag, value)))
+    }
+
+    fn sequence(&mut self, length: u32) -> Result<Vec<DataSet<'a>>, Box<dyn Error>> {
+        let end = (length != UNDEFINED_LENGTH).then(|| self.position + length as usize);
+        let mut items = Vec::new();
+        loop {
+            match end {
+                Some(end) if self.position >= end => break,
+                _ => {}
+            }
+            let tag = self.tag()?;
+            let length = self.u32()?;
+            match tag {
+                SEQUENCE_END => break,
+                ITEM => {
+                    let end = (length != UNDEFINED_LENGTH).then(|| self.position + length as usize);
+                    items.push(self.data_set(end)?);
+                }
+                _ => {
+                    return Err(format!(
+                        "Unexpected DICOM element ({:04X},{:04X}) in a sequence",
+                        tag.0, tag.1
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(items)
+    }
+}
+
+// the data set of a DICOM file after its preamble and file meta information
+fn parse_dicom(bytes: &[u8]) -> Result<DataSet<'_>, Box<dyn Error>> {
+    if bytes.get(128..132) != Some(b"DICM") {
+        return Err("Not a DICOM file (no DICM prefix after the preamble)".into());
+    }
+    let mut parser = Parser {
+        bytes,
+        position: 132,
+        explicit: true,
+    };
+    // the file meta information is group 0002, always explicit VR little endian
+    let mut meta = DataSet::default();
+    while bytes.get(parser.position..parser.position + 2) == Some(&[0x02, 0x00]) {
+        meta.elements.extend(parser.element()?);
+    }
+    let transfer_syntax = meta.text(TRANSFER_SYNTAX);
+
+    parser.explicit = match transfer_syntax.as_str() {
+        "1.2.840.10008.1.2" => false,
+        "1.2.840.10008.1.2.2" => {
+            return Err("Big endian DICOM files are not supported".into());
+        }
+        "1.2.840.10008.1.2.1.99" => {
+            return Err("Deflated DICOM files are not supported".into());
+        }
+        _ => true,
+    };
+    parser.data_set(Some(bytes.len()))
+}
+
+// name of a channel after its source, e.g. II for "Lead II", or its label
+fn channel_name(channel: &DataSet, index: usize) -> String {
+    let source = channel.items(CHANNEL_SOURCE).first();
+    let meaning = source.map(|source| source.text(CODE_MEANING));
+    match meaning.filter(|meaning| !meaning.is_empty()) {
+        Some(meaning) => meaning
+            .strip_prefix("Lead ")
+            .unwrap_or(&meaning)
+            .trim()
+            .to_string(),
+        None => {
+            let label = channel.text(CHANNEL_LABEL);
+            if label.is_empty() {
+                format!("Channel {}", index + 1)
+            } else {
+                label
+            }
+        }
+    }
+}
+
+// millivolts of one sample unit and the baseline in sample units of a channel definition
+fn channel_calibration(channel: &DataSet, name: &str) -> (f64, f64) {
+    let sensitivity = channel.number(CHANNEL_SENSITIVITY).unwrap_or(1.0);
+    let correction = channel.number(SENSITIVITY_CORRECTION).unwrap_or(1.0);
+    let baseline = channel.number(CHANNEL_BASELINE).unwrap_or(0.0);
+    let unit = channel
+        .items(SENSITIVITY_UNITS)
+        .first()
+        .map(|unit| unit.text(CODE_VALUE))
+        .unwrap_or_default();
+    let scale = millivolts_per(&unit).unwrap_or_else(|| {
+        warn!(
+            "DICOM channel {} is in {:?}, its values are used unconverted",
+            name, unit
+        );
+        1.0
+    });
+    (sensitivity * correction * scale, baseline)
+}
+
+// the channels of a multiplex group, each in millivolts
+fn read_group(group: &DataSet) -> Result<Vec<Lead>, Box<dyn Error>> {
+    let channels = group
+        .short(NUMBER_OF_CHANNELS)
+        .ok_or("DICOM multiplex group without a number of channels")? as usize;
+    let samples = group
+        .long(NUMBER_OF_SAMPLES)
+        .ok_or("DICOM multiplex group without a number of samples")? as usize;
+    let bits = group.short(BITS_ALLOCATED).unwrap_or(16);
+    let interpretation = group.text(SAMPLE_INTERPRETATION);
+    let data = group
+        .bytes(WAVEFORM_DATA)
+        .ok_or("DICOM multiplex group without waveform data")?;
+    let definitions = group.items(CHANNEL_DEFINITIONS);
+    if channels == 0 || definitions.len() != channels {
+        return Err(format!(
+            "DICOM multiplex group of {} channels with {} channel definitions",
+            channels,
+            definitions.len()
+        )
+        .into());
+    }
+
+    let sample: fn(&[u8]) -> f64 = match (bits, interpretation.as_str()) {
+        (16, "SS") => |bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        (16, "US") => |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        (8, "SB") => |bytes| bytes[0] as i8 as f64,
+        (8, "UB") => |bytes| bytes[0] as f64,
+        (bits, interpretation) => {
+            return Err(format!(
+                "Unsupported DICOM waveform samples: {} bits, interpretation {:?}",
+                bits, interpretation
+            )
+            .into())
+        }
+    };
+    let bytes = bits as usize / 8;
+    let frames = (data.len() / (channels * bytes)).min(samples);
+    if frames < samples {
+        warn!(
+            "The DICOM waveform data holds {} of its {} samples",
+            frames, samples
+        );
+    }
+
+    Ok(definitions
+        .iter()
+        .enumerate()
+        .map(|(channel, definition)| {
+            let name = channel_name(definition, channel);
+            let (scale, baseline) = channel_calibration(definition, &name);
+            let voltage = (0..frames)
+                .map(|frame| {
+                    let at = (frame * channels + channel) * bytes;
+                    (sample(&data[at..at + bytes]) + baseline) * scale
+                })
+                .collect();
+            Lead { name, voltage }
+        })
+        .collect())
+}
+
+/// Reads a DICOM waveform object: the multiplex group labelled RHYTHM (the first group when
+/// none is), its channels calibrated from their sensitivity, correction factor and baseline.
+/// Files in implicit or explicit VR little endian are read; big endian and deflated ones are
+/// not.
+pub fn read_dicom<P: AsRef<Path>>(path: P) -> Result<DicomWaveform, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    open_input(path)?.read_to_end(&mut bytes)?;
+    let data_set = parse_dicom(&bytes)?;
+
+    let groups = data_set.items(WAVEFORM_SEQUENCE);
+    let group = groups
+        .iter()
+        .find(|group| {
+            group
+                .text(MULTIPLEX_GROUP_LABEL)
+                .eq_ignore_ascii_case("RHYTHM")
+        })
+        .or(groups.first())
+        .ok_or("DICOM file without a waveform sequence")?;
+    let fs = group
+        .number(SAMPLING_FREQUENCY)
+        .filter(|&fs| fs > 0.0)
+        .ok_or("DICOM multiplex group without a valid sampling frequency")?;
+    let leads = read_group(group)?;
+    let num_samples = leads.first().map_or(0, |lead| lead.voltage.len());
+    let ecg = MultiLeadEcg {
+        times: (0..num_samples).map(|i| i as f64 / fs).collect(),
+        leads,
+    };
+
+    info!(
+        "Total data points: {} in {} leads",
+        ecg.times.len(),
+        ecg.leads.len()
+    );
+
+    Ok(DicomWaveform {
+        patient_name: data_set.text(PATIENT_NAME),
+        patient_id: data_set.text(PATIENT_ID),
+        acquisition_datetime: data_set.text(ACQUISITION_DATETIME),
+        group_label: group.text(MULTIPLEX_GROUP_LABEL),
+        sampling_frequency: fs,
+        ecg,
+    })
+}
+
+/// Reads one lead of a DICOM waveform object in millivolts.
+pub fn read_dicom_ecg_data<P: AsRef<Path>>(
+    path: P,
+    lead: usize,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let ecg = read_dicom(path)?.ecg;
+    let lead = ecg.leads.get(lead).ok_or_else(|| {
+        format!(
+            "Lead index {} out of range, the file has {} leads",
+            lead,
+            ecg.leads.len()
+        )
+    })?;
+
+    Ok(ecg
+        .times
+        .iter()
+        .zip(&lead.voltage)
+        .map(|(&time, &voltage)| EcgPoint { time, voltage })
+        .collect())
+}
+
+/// Reads every lead of a DICOM waveform object.
+pub fn read_dicom_leads<P: AsRef<Path>>(path: P) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    Ok(read_dicom(path)?.ecg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tag_bytes(tag: Tag) -> Vec<u8> {
+        [tag.0.to_le_bytes(), tag.1.to_le_bytes()].concat()
+    }
+
+    // an explicit VR little endian element
+    fn element(tag: Tag, vr: &[u8; 2], value: &[u8]) -> Vec<u8> {
+        let mut bytes = tag_bytes(tag);
+        bytes.extend_from_slice(vr);
+        if matches!(vr, b"OB" | b"OW" | b"SQ") {
+            bytes.extend_from_slice(&[0, 0]);
+            bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        } else {
+            bytes.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        }
+        bytes.extend_from_slice(value);
+        bytes
+    }
+
+    // a sequence whose items have explicit lengths
+    fn sequence(tag: Tag, items: &[Vec<u8>]) -> Vec<u8> {
+        let mut value = Vec::new();
+        for item in items {
+            value.extend(tag_bytes(ITEM));
+            value.extend_from_slice(&(item.len() as u32).to_le_bytes());
+            value.extend_from_slice(item);
+        }
+        element(tag, b"SQ", &value)
+    }
+
+    fn channel(meaning: &str) -> Vec<u8> {
+        [
+            sequence(
+                CHANNEL_SOURCE,
+                &[element(CODE_MEANING, b"LO", meaning.as_bytes())],
+            ),
+            element(CHANNEL_SENSITIVITY, b"DS", b"5 "),
+            sequence(SENSITIVITY_UNITS, &[element(CODE_VALUE, b"SH", b"uV")]),
+            element(CHANNEL_BASELINE, b"DS", b"0 "),
+        ]
+        .concat()
+    }
+
+    // a two-lead 500 Hz waveform in explicit VR little endian, the waveform sequence and its
+    // item of undefined length
+    fn dicom_file(samples: &[i16]) -> Vec<u8> {
+        let mut bytes = vec![0; 128];
+        bytes.extend_from_slice(b"DICM");
+        bytes.extend(element(TRANSFER_SYNTAX, b"UI", b"1.2.840.10008.1.2.1\0"));
+        bytes.extend(element(PATIENT_NAME, b"PN", b"Doe^Jane"));
+        bytes.extend(element(PATIENT_ID, b"LO", b"P-17"));
+
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let group = [
+            element(MULTIPLEX_GROUP_LABEL, b"CS", b"RHYTHM"),
+            element(NUMBER_OF_CHANNELS, b"US", &2u16.to_le_bytes()),
+            element(NUMBER_OF_SAMPLES, b"UL", &3u32.to_le_bytes()),
+            element(SAMPLING_FREQUENCY, b"DS", b"500 "),
+            sequence(
+                CHANNEL_DEFINITIONS,
+                &[channel("Lead I"), channel("Lead II")],
+            ),
+            element(BITS_ALLOCATED, b"US", &16u16.to_le_bytes()),
+            element(SAMPLE_INTERPRETATION, b"CS", b"SS"),
+            element(WAVEFORM_DATA, b"OW", &data),
+        ]
+        .concat();
+        bytes.extend(tag_bytes(WAVEFORM_SEQUENCE));
+        bytes.extend_from_slice(b"SQ\0\0");
+        bytes.extend_from_slice(&UNDEFINED_LENGTH.to_le_bytes());
+        bytes.extend(tag_bytes(ITEM));
+        bytes.extend_from_slice(&UNDEFINED_LENGTH.to_le_bytes());
+        bytes.extend(group);
+        for delimiter in [ITEM_END, SEQUENCE_END] {
+            bytes.extend(tag_bytes(delimiter));
+            bytes.extend_from_slice(&[0; 4]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_a_hand_built_waveform() {
+        let bytes = dicom_file(&[200, 400, -200, 0, 0, -1000]);
+        let path = std::env::temp_dir().join(format!("signalweaver_{}.dcm", std::process::id()));
+        fs::write(&path, &bytes).unwrap();
+        let waveform = read_dicom(&path);
+        fs::remove_file(&path).unwrap();
+
+        let waveform = waveform.unwrap();
+        assert_eq!(waveform.patient_name, "Doe^Jane");
+        assert_eq!(waveform.patient_id, "P-17");
+        assert_eq!(waveform.group_label, "RHYTHM");
+        assert_eq!(waveform.sampling_frequency, 500.0);
+        assert_eq!(waveform.ecg.times, vec![0.0, 0.002, 0.004]);
+        assert_eq!(waveform.find_lead("ii").unwrap(), 1);
+        for (lead, expected) in waveform
+            .ecg
+            .leads
+            .iter()
+            .zip([[1.0, -1.0, 0.0], [2.0, 0.0, -5.0]])
+        {
+            for (voltage, expected) in lead.voltage.iter().zip(expected) {
+                assert!((voltage - expected).abs() < 1e-9, "{}", voltage);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_and_foreign_files() {
+        let bytes = dicom_file(&[200, 400, -200, 0, 0, -1000]);
+        // inside the waveform data and before the item delimiter
+        for len in [100, 140, bytes.len() - 20, bytes.len() - 12] {
+            assert!(parse_dicom(&bytes[..len]).is_err(), "{} bytes", len);
+        }
+        let mut big_endian = bytes.clone();
+        let syntax = big_endian
+            .windows(20)
+            .position(|window| window == b"1.2.840.10008.1.2.1\0")
+            .unwrap();
+        big_endian[syntax + 18] = b'2';
+        let error = parse_dicom(&big_endian)
+            .err()
+            .map(|error| error.to_string());
+        assert!(error.is_some_and(|error| error.contains("Big endian")));
+    }
+}
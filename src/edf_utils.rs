@@ -1,5 +1,12 @@
+use crate::compression::open_input;
+use crate::datetime::days_from_civil;
+use crate::filters::FilterConfig;
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
 use edf::Reader;
 use std::error::Error;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use tracing::{info, warn};
 
 pub fn print_edf_signals(file_path: &str) -> Result<(), Box<dyn Error>> {
     let edf_file = Reader::from_path(file_path)?;
@@ -9,3 +16,1346 @@ pub fn print_edf_signals(file_path: &str) -> Result<(), Box<dyn Error>> {
     println!("##data\n{}", edf_file.data);
     Ok(())
 }
+
+/// Fields of one signal from the EDF signal header.
+#[derive(Debug, Clone)]
+pub struct EdfSignalInfo {
+    pub label: String,
+    pub transducer: String,
+    pub physical_dimension: String,
+    pub physical_min: f64,
+    pub physical_max: f64,
+    pub digital_min: f64,
+    pub digital_max: f64,
+    pub prefiltering: String,
+    pub samples_per_record: usize,
+}
+
+impl EdfSignalInfo {
+    /// Physical units per digital step.
+    pub fn gain(&self) -> f64 {
+        (self.physical_max - self.physical_min) / (self.digital_max - self.digital_min)
+    }
+
+    pub fn to_physical(&self, digital: i32) -> f64 {
+        self.physical_min + (digital as f64 - self.digital_min) * self.gain()
+    }
+
+    /// Whether this is an EDF+ annotation signal rather than a recorded one.
+    pub fn is_annotations(&self) -> bool {
+        matches!(self.label.trim(), "EDF Annotations" | "BDF Annotations")
+    }
+
+    /// Millivolts per physical unit for voltage dimensions (`V`, `mV`, `uV`, `µV`, `nV`),
+    /// `None` for other dimensions or none.
+    pub fn millivolts_per_unit(&self) -> Option<f64> {
+        millivolts_per(&self.physical_dimension)
+    }
+}
+
+/// Millivolts per unit of a voltage unit (`V`, `mV`, `uV`, `µV`, `nV`), `None` for others.
+pub fn millivolts_per(unit: &str) -> Option<f64> {
+    match unit.trim() {
+        "V" => Some(1000.0),
+        "mV" | "mv" => Some(1.0),
+        "uV" | "uv" | "µV" | "μV" => Some(1e-3),
+        "nV" => Some(1e-6),
+        _ => None,
+    }
+}
+
+/// The fixed part of the EDF header followed by the per-signal headers.
+#[derive(Debug, Clone)]
+pub struct EdfHeader {
+    /// `0` for EDF, `BIOSEMI` for BDF (stored after a 0xFF byte).
+    pub version: String,
+    pub patient: String,
+    pub recording: String,
+    pub start_date: String,
+    pub start_time: String,
+    pub header_bytes: usize,
+    pub reserved: String,
+    /// -1 when unknown (recording still in progress).
+    pub num_records: i64,
+    pub record_duration: f64,
+    pub signals: Vec<EdfSignalInfo>,
+}
+
+impl EdfHeader {
+    /// Sampling frequency of the given signal in Hz.
+    pub fn sampling_frequency(&self, signal_index: usize) -> f64 {
+        self.signals[signal_index].samples_per_record as f64 / self.record_duration
+    }
+
+    /// Seconds after midnight of the start time (`hh.mm.ss`), `None` when it is malformed.
+    pub fn start_clock(&self) -> Option<f64> {
+        let fields: Vec<f64> = self
+            .start_time
+            .split('.')
+            .map(|field| field.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        match fields[..] {
+            [hours, minutes, seconds] => Some(3600.0 * hours + 60.0 * minutes + seconds),
+            _ => None,
+        }
+    }
+
+    /// The start of the recording in seconds since 1970-01-01, from the start date
+    /// (`dd.mm.yy`, years 85–99 in the 1900s and the others in the 2000s) and start time, on
+    /// the recording's local clock; `None` when either is malformed.
+    pub fn start_datetime(&self) -> Option<f64> {
+        let fields: Vec<u32> = self
+            .start_date
+            .split('.')
+            .map(|field| field.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        let [day, month, year] = fields[..] else {
+            return None;
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year > 99 {
+            return None;
+        }
+        let year = if year >= 85 { 1900 + year } else { 2000 + year };
+        let days = days_from_civil(i64::from(year), month, day);
+        Some(days as f64 * 86_400.0 + self.start_clock()?)
+    }
+
+    /// Index of the signal with the given label, ignoring case and surrounding spaces; the
+    /// error lists the labels there are.
+    pub fn find_signal(&self, label: &str) -> Result<usize, Box<dyn Error>> {
+        let labels: Vec<&str> = self
+            .signals
+            .iter()
+            .map(|signal| signal.label.as_str())
+            .collect();
+        find_label(&labels, label)
+    }
+
+    /// Whether this is a BioSemi BDF file, with 24-bit samples instead of EDF's 16-bit ones.
+    pub fn is_bdf(&self) -> bool {
+        self.version == BDF_VERSION
+    }
+
+    /// Whether the data records of this EDF+D (or BDF+D) file may be separated by gaps, their
+    /// start times given by the time-keeping annotations.
+    pub fn is_discontinuous(&self) -> bool {
+        matches!(self.reserved.get(..5), Some("EDF+D" | "BDF+D"))
+    }
+
+    /// Whether this is an EDF+ (or BDF+) file, with structured identification fields.
+    pub fn is_edf_plus(&self) -> bool {
+        matches!(self.reserved.get(..4), Some("EDF+" | "BDF+"))
+    }
+
+    /// Bytes per sample: 3 for BDF, 2 for EDF.
+    pub fn sample_bytes(&self) -> usize {
+        if self.is_bdf() {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Byte offset of a signal's samples within a data record.
+    pub fn signal_offset(&self, signal_index: usize) -> usize {
+        self.signals[..signal_index]
+            .iter()
+            .map(|s| s.samples_per_record * self.sample_bytes())
+            .sum()
+    }
+
+    /// Size of one data record in bytes.
+    pub fn record_bytes(&self) -> usize {
+        self.signals
+            .iter()
+            .map(|signal| signal.samples_per_record * self.sample_bytes())
+            .sum()
+    }
+}
+
+/// Index of `label` among `labels`, ignoring case and surrounding spaces, with the error
+/// listing the labels with their indices.
+pub fn find_label(labels: &[&str], label: &str) -> Result<usize, Box<dyn Error>> {
+    let wanted = label.trim();
+    labels
+        .iter()
+        .position(|candidate| candidate.trim().eq_ignore_ascii_case(wanted))
+        .ok_or_else(|| {
+            let available: Vec<String> = labels
+                .iter()
+                .enumerate()
+                .map(|(i, label)| format!("{} {:?}", i, label.trim()))
+                .collect();
+            format!(
+                "No signal labelled {:?}, available: {}",
+                wanted,
+                available.join(", ")
+            )
+            .into()
+        })
+}
+
+/// Prints the recording fields of the header and one line per signal: index, label,
+/// sampling frequency, physical dimension and range, transducer and prefiltering.
+pub fn print_edf_header(header: &EdfHeader) {
+    println!("Patient: {}", header.patient);
+    println!("Recording: {}", header.recording);
+    println!("Start: {} {}", header.start_date, header.start_time);
+    println!(
+        "Data records: {} of {} s",
+        header.num_records, header.record_duration
+    );
+    println!("index\tlabel\tfs (Hz)\tdimension\tphysical range\ttransducer\tprefiltering");
+    for (i, signal) in header.signals.iter().enumerate() {
+        println!(
+            "{}\t{}\t{}\t{}\t{} to {}\t{}\t{}",
+            i,
+            signal.label,
+            header.sampling_frequency(i),
+            signal.physical_dimension,
+            signal.physical_min,
+            signal.physical_max,
+            signal.transducer,
+            signal.prefiltering
+        );
+    }
+}
+
+// a header field of `len` bytes: printable ASCII (other characters as `?`), padded with spaces
+fn edf_field(value: &str, len: usize) -> Vec<u8> {
+    let mut field: Vec<u8> = value
+        .chars()
+        .map(|c| {
+            if c == ' ' || c.is_ascii_graphic() {
+                c as u8
+            } else {
+                b'?'
+            }
+        })
+        .take(len)
+        .collect();
+    field.resize(len, b' ');
+    field
+}
+
+// a number in at most 8 characters, with as many decimals as fit
+fn edf_number(value: f64) -> String {
+    let shortest = value.to_string();
+    if shortest.len() <= 8 {
+        return shortest;
+    }
+    (0..=7)
+        .rev()
+        .map(|decimals| format!("{:.*}", decimals, value))
+        .find(|number| number.len() <= 8)
+        .unwrap_or(shortest)
+}
+
+/// Writes the header, computing the header size from the number of signals.
+pub fn write_edf_header<W: Write>(writer: &mut W, header: &EdfHeader) -> io::Result<()> {
+    let num_signals = header.signals.len();
+    if header.is_bdf() {
+        writer.write_all(&[0xFF])?;
+        writer.write_all(&edf_field(BDF_VERSION, 7))?;
+    } else {
+        writer.write_all(&edf_field(&header.version, 8))?;
+    }
+    writer.write_all(&edf_field(&header.patient, 80))?;
+    writer.write_all(&edf_field(&header.recording, 80))?;
+    writer.write_all(&edf_field(&header.start_date, 8))?;
+    writer.write_all(&edf_field(&header.start_time, 8))?;
+    writer.write_all(&edf_field(&(256 * (num_signals + 1)).to_string(), 8))?;
+    writer.write_all(&edf_field(&header.reserved, 44))?;
+    writer.write_all(&edf_field(&header.num_records.to_string(), 8))?;
+    writer.write_all(&edf_field(&edf_number(header.record_duration), 8))?;
+    writer.write_all(&edf_field(&num_signals.to_string(), 4))?;
+
+    // width and value of each signal header field
+    type SignalField = (usize, fn(&EdfSignalInfo) -> String);
+    let fields: [SignalField; 10] = [
+        (16, |signal| signal.label.clone()),
+        (80, |signal| signal.transducer.clone()),
+        (8, |signal| signal.physical_dimension.clone()),
+        (8, |signal| edf_number(signal.physical_min)),
+        (8, |signal| edf_number(signal.physical_max)),
+        (8, |signal| edf_number(signal.digital_min)),
+        (8, |signal| edf_number(signal.digital_max)),
+        (80, |signal| signal.prefiltering.clone()),
+        (8, |signal| signal.samples_per_record.to_string()),
+        (32, |_| String::new()),
+    ];
+    for (len, field) in fields {
+        for signal in &header.signals {
+            writer.write_all(&edf_field(&field(signal), len))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_field<R: Read>(reader: &mut R, len: usize) -> Result<String, Box<dyn Error>> {
+    let mut buffer = vec![0u8; len];
+    reader.read_exact(&mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).trim().to_string())
+}
+
+fn read_number<R: Read, T>(reader: &mut R, len: usize, name: &str) -> Result<T, Box<dyn Error>>
+where
+    T: std::str::FromStr,
+{
+    let field = read_field(reader, len)?;
+    field
+        .parse::<T>()
+        .map_err(|_| format!("Invalid EDF header field '{}': {:?}", name, field).into())
+}
+
+// each signal header field is stored for all signals before the next field starts
+fn read_signal_fields<R: Read>(
+    reader: &mut R,
+    num_signals: usize,
+    len: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    (0..num_signals).map(|_| read_field(reader, len)).collect()
+}
+
+fn parse_fields<T: std::str::FromStr>(
+    fields: &[String],
+    name: &str,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    fields
+        .iter()
+        .map(|field| {
+            field.parse::<T>().map_err(|_| {
+                format!("Invalid EDF signal header field '{}': {:?}", name, field).into()
+            })
+        })
+        .collect()
+}
+
+// the version of a BDF header, after its first byte 0xFF
+const BDF_VERSION: &str = "BIOSEMI";
+
+/// Reads an EDF, EDF+, BDF or BDF+ header.
+pub fn read_edf_header<R: Read>(reader: &mut R) -> Result<EdfHeader, Box<dyn Error>> {
+    let mut version = read_field(reader, 8)?;
+    if version.ends_with(BDF_VERSION) {
+        version = BDF_VERSION.to_string();
+    }
+    let patient = read_field(reader, 80)?;
+    let recording = read_field(reader, 80)?;
+    let start_date = read_field(reader, 8)?;
+    let start_time = read_field(reader, 8)?;
+    let header_bytes = read_number(reader, 8, "header bytes")?;
+    let reserved = read_field(reader, 44)?;
+    let num_records = read_number(reader, 8, "number of data records")?;
+    let record_duration = read_number(reader, 8, "duration of a data record")?;
+    let num_signals: usize = read_number(reader, 4, "number of signals")?;
+
+    let labels = read_signal_fields(reader, num_signals, 16)?;
+    let transducers = read_signal_fields(reader, num_signals, 80)?;
+    let dimensions = read_signal_fields(reader, num_signals, 8)?;
+    let physical_mins = parse_fields(
+        &read_signal_fields(reader, num_signals, 8)?,
+        "physical minimum",
+    )?;
+    let physical_maxs = parse_fields(
+        &read_signal_fields(reader, num_signals, 8)?,
+        "physical maximum",
+    )?;
+    let digital_mins = parse_fields(
+        &read_signal_fields(reader, num_signals, 8)?,
+        "digital minimum",
+    )?;
+    let digital_maxs = parse_fields(
+        &read_signal_fields(reader, num_signals, 8)?,
+        "digital maximum",
+    )?;
+    let prefilterings = read_signal_fields(reader, num_signals, 80)?;
+    let samples = parse_fields(
+        &read_signal_fields(reader, num_signals, 8)?,
+        "number of samples",
+    )?;
+    read_signal_fields(reader, num_signals, 32)?; // reserved
+
+    let signals = (0..num_signals)
+        .map(|i| EdfSignalInfo {
+            label: labels[i].clone(),
+            transducer: transducers[i].clone(),
+            physical_dimension: dimensions[i].clone(),
+            physical_min: physical_mins[i],
+            physical_max: physical_maxs[i],
+            digital_min: digital_mins[i],
+            digital_max: digital_maxs[i],
+            prefiltering: prefilterings[i].clone(),
+            samples_per_record: samples[i],
+        })
+        .collect();
+
+    Ok(EdfHeader {
+        version,
+        patient,
+        recording,
+        start_date,
+        start_time,
+        header_bytes,
+        reserved,
+        num_records,
+        record_duration,
+        signals,
+    })
+}
+
+// where a requested signal is in a data record and its calibration to millivolts
+struct SignalLayout {
+    index: usize,
+    // byte range within a data record
+    start: usize,
+    end: usize,
+    scale: f64,
+}
+
+fn signal_layouts(
+    header: &EdfHeader,
+    signal_indices: &[usize],
+) -> Result<Vec<SignalLayout>, Box<dyn Error>> {
+    signal_indices
+        .iter()
+        .map(|&signal_index| {
+            if signal_index >= header.signals.len() {
+                return Err(format!(
+                    "Signal index {} out of range, the file has {} signals",
+                    signal_index,
+                    header.signals.len()
+                )
+                .into());
+            }
+            let signal = &header.signals[signal_index];
+            if signal.digital_max <= signal.digital_min || !signal.gain().is_finite() {
+                return Err(format!(
+                    "Invalid calibration of EDF signal {:?}: digital range {} to {}",
+                    signal.label, signal.digital_min, signal.digital_max
+                )
+                .into());
+            }
+            let scale = signal.millivolts_per_unit().unwrap_or_else(|| {
+                warn!(
+                    "EDF signal {:?} is in {:?}, its values are used unconverted",
+                    signal.label, signal.physical_dimension
+                );
+                1.0
+            });
+            let start = header.signal_offset(signal_index);
+            Ok(SignalLayout {
+                index: signal_index,
+                start,
+                end: start + signal.samples_per_record * header.sample_bytes(),
+                scale,
+            })
+        })
+        .collect()
+}
+
+// reads the next data record, false at the end of the file (a partial last record is left
+// out)
+fn read_record<R: Read>(reader: &mut R, record: &mut [u8]) -> Result<bool, Box<dyn Error>> {
+    match reader.read_exact(record) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// appends the samples of one signal in a data record, converted to millivolts
+fn decode_signal(record: &[u8], header: &EdfHeader, layout: &SignalLayout, values: &mut Vec<f64>) {
+    let signal = &header.signals[layout.index];
+    for sample in record[layout.start..layout.end].chunks_exact(header.sample_bytes()) {
+        let digital = match *sample {
+            [low, high] => i16::from_le_bytes([low, high]) as i32,
+            // 24-bit BDF samples, sign-extended by the arithmetic shift
+            [low, middle, high] => i32::from_le_bytes([0, low, middle, high]) >> 8,
+            _ => unreachable!("samples have 2 or 3 bytes"),
+        };
+        values.push(signal.to_physical(digital) * layout.scale);
+    }
+}
+
+// byte range of the annotation signal whose first list in every data record gives the
+// record's start time, for discontinuous files; `None` for continuous ones, whose records
+// follow each other
+fn time_keeping_range(header: &EdfHeader) -> Result<Option<(usize, usize)>, Box<dyn Error>> {
+    if !header.is_discontinuous() {
+        return Ok(None);
+    }
+    let index = header
+        .signals
+        .iter()
+        .position(EdfSignalInfo::is_annotations)
+        .ok_or("The EDF+D file has no annotation signal giving the record start times")?;
+    let start = header.signal_offset(index);
+    let end = start + header.signals[index].samples_per_record * header.sample_bytes();
+    Ok(Some((start, end)))
+}
+
+// start time of a data record from its time-keeping annotation, `+onset` up to the first 0x14
+fn record_onset(record: &[u8], (start, end): (usize, usize)) -> Result<f64, Box<dyn Error>> {
+    let list = &record[start..end];
+    let timing = list.split(|&byte| byte == 0x14).next().unwrap_or_default();
+    let timing = String::from_utf8_lossy(timing);
+    timing
+        .trim_matches(char::from(0))
+        .parse()
+        .map_err(|_| format!("Invalid EDF+ record start time {:?}", timing).into())
+}
+
+// times of consecutive samples of a signal from the start of its data records: from
+// `onsets` when the file is discontinuous, counting from 0 when `onsets` is empty
+fn sample_times(
+    count: usize,
+    first: usize,
+    samples_per_record: usize,
+    fs: f64,
+    onsets: &[f64],
+) -> Vec<f64> {
+    (0..count)
+        .map(|i| {
+            if onsets.is_empty() {
+                (first + i) as f64 / fs
+            } else {
+                onsets[i / samples_per_record] + (i % samples_per_record) as f64 / fs
+            }
+        })
+        .collect()
+}
+
+// the signals read from the data records, in millivolts or in their physical units when these
+// are not a voltage, with the start time of every record of a discontinuous file (none for a
+// continuous one)
+struct EdfRecords {
+    signals: Vec<Vec<f64>>,
+    onsets: Vec<f64>,
+}
+
+// reads the data records after the header
+fn read_edf_records<R: Read>(
+    reader: &mut R,
+    header: &EdfHeader,
+    signal_indices: &[usize],
+) -> Result<EdfRecords, Box<dyn Error>> {
+    let layouts = signal_layouts(header, signal_indices)?;
+    let time_keeping = time_keeping_range(header)?;
+    let mut signals = vec![Vec::new(); signal_indices.len()];
+    let mut onsets = Vec::new();
+    let mut record = vec![0u8; header.record_bytes()];
+
+    // reading record by record until the end of the file
+    while read_record(reader, &mut record)? {
+        if let Some(range) = time_keeping {
+            onsets.push(record_onset(&record, range)?);
+        }
+        for (layout, values) in layouts.iter().zip(signals.iter_mut()) {
+            decode_signal(&record, header, layout, values);
+        }
+    }
+
+    Ok(EdfRecords { signals, onsets })
+}
+
+/// Reads one EDF signal lazily, data record by data record, in blocks of at least
+/// `chunk_size` points, so that multi-channel 24-hour recordings can be fed to a
+/// `StreamingDetector` without holding the file in memory. Values are calibrated like
+/// those of [`read_edf_ecg_data`].
+pub fn read_edf_chunks<P: AsRef<Path>>(
+    path: P,
+    signal_index: usize,
+    chunk_size: usize,
+) -> Result<EdfChunks<Box<dyn BufRead>>, Box<dyn Error>> {
+    EdfChunks::new(open_input(path)?, signal_index, chunk_size)
+}
+
+/// Iterator over blocks of points of one EDF signal, see `read_edf_chunks`.
+pub struct EdfChunks<R> {
+    reader: R,
+    header: EdfHeader,
+    layout: SignalLayout,
+    time_keeping: Option<(usize, usize)>,
+    record: Vec<u8>,
+    chunk_size: usize,
+    // points returned so far, for the times of the next block
+    samples_read: usize,
+    finished: bool,
+}
+
+impl<R: Read> EdfChunks<R> {
+    /// Reads the header; the data records are read as blocks are requested.
+    pub fn new(
+        mut reader: R,
+        signal_index: usize,
+        chunk_size: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let header = read_edf_header(&mut reader)?;
+        let layout = signal_layouts(&header, &[signal_index])?.remove(0);
+        let time_keeping = time_keeping_range(&header)?;
+        let record = vec![0u8; header.record_bytes()];
+        Ok(EdfChunks {
+            reader,
+            header,
+            layout,
+            time_keeping,
+            record,
+            chunk_size: chunk_size.max(1),
+            samples_read: 0,
+            finished: false,
+        })
+    }
+
+    pub fn header(&self) -> &EdfHeader {
+        &self.header
+    }
+
+    /// Sampling frequency of the signal read.
+    pub fn sampling_frequency(&self) -> f64 {
+        self.header.sampling_frequency(self.layout.index)
+    }
+}
+
+impl<R: Read> Iterator for EdfChunks<R> {
+    type Item = Result<Vec<EcgPoint>, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut voltage = Vec::with_capacity(self.chunk_size);
+        let mut onsets = Vec::new();
+        while !self.finished && voltage.len() < self.chunk_size {
+            let onset = match read_record(&mut self.reader, &mut self.record) {
+                Ok(true) => self
+                    .time_keeping
+                    .map(|range| record_onset(&self.record, range))
+                    .transpose(),
+                Ok(false) => {
+                    self.finished = true;
+                    break;
+                }
+                Err(err) => Err(err),
+            };
+            match onset {
+                Ok(onset) => onsets.extend(onset),
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+            decode_signal(&self.record, &self.header, &self.layout, &mut voltage);
+        }
+        if voltage.is_empty() {
+            return None;
+        }
+
+        let signal = &self.header.signals[self.layout.index];
+        let times = sample_times(
+            voltage.len(),
+            self.samples_read,
+            signal.samples_per_record,
+            self.sampling_frequency(),
+            &onsets,
+        );
+        self.samples_read += voltage.len();
+        let chunk = times
+            .into_iter()
+            .zip(voltage)
+            .map(|(time, voltage)| EcgPoint { time, voltage })
+            .collect();
+        Some(Ok(chunk))
+    }
+}
+
+/// Reads one EDF signal calibrated to millivolts (to its physical units when they are not a
+/// voltage), with times derived from its sampling frequency, so that amplitude thresholds in
+/// mV hold for recordings stored in µV.
+pub fn read_edf_ecg_data<P: AsRef<Path>>(
+    path: P,
+    signal_index: usize,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let mut reader = open_input(path)?;
+
+    let header = read_edf_header(&mut reader)?;
+    let EdfRecords { signals, onsets } = read_edf_records(&mut reader, &header, &[signal_index])?;
+    let times = sample_times(
+        signals[0].len(),
+        0,
+        header.signals[signal_index].samples_per_record,
+        header.sampling_frequency(signal_index),
+        &onsets,
+    );
+
+    let data: Vec<EcgPoint> = times
+        .into_iter()
+        .zip(&signals[0])
+        .map(|(time, &voltage)| EcgPoint { time, voltage })
+        .collect();
+
+    info!("Total data points: {}", data.len());
+
+    Ok(data)
+}
+
+/// Reads the EDF signals sampled like the first one as leads of a common recording, in
+/// millivolts like [`read_edf_ecg_data`]; EDF+ annotations and signals at other rates are
+/// left out.
+pub fn read_edf_leads<P: AsRef<Path>>(path: P) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    let mut reader = open_input(path)?;
+
+    let header = read_edf_header(&mut reader)?;
+    let is_lead = |signal: &EdfSignalInfo| !signal.is_annotations();
+    let Some(first) = header.signals.iter().find(|signal| is_lead(signal)) else {
+        return Ok(MultiLeadEcg::default());
+    };
+    let signal_indices: Vec<usize> = (0..header.signals.len())
+        .filter(|&i| {
+            let signal = &header.signals[i];
+            is_lead(signal) && signal.samples_per_record == first.samples_per_record
+        })
+        .collect();
+    let EdfRecords { signals, onsets } = read_edf_records(&mut reader, &header, &signal_indices)?;
+
+    let num_samples = signals.first().map_or(0, Vec::len);
+    let ecg = MultiLeadEcg {
+        times: sample_times(
+            num_samples,
+            0,
+            first.samples_per_record,
+            header.sampling_frequency(signal_indices[0]),
+            &onsets,
+        ),
+        leads: signal_indices
+            .iter()
+            .zip(signals)
+            .map(|(&i, voltage)| Lead {
+                name: header.signals[i].label.clone(),
+                voltage,
+            })
+            .collect(),
+    };
+
+    info!(
+        "Total data points: {} in {} leads",
+        ecg.times.len(),
+        ecg.leads.len()
+    );
+
+    Ok(ecg)
+}
+
+/// An event of the annotation signals of an EDF+ file, e.g. a sleep stage or a button press.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdfAnnotation {
+    /// Seconds from the start of the recording.
+    pub onset: f64,
+    /// In seconds, `None` for events without a duration.
+    pub duration: Option<f64>,
+    pub text: String,
+}
+
+/// Reads the events of the `EDF Annotations` signals of an EDF+ file, ordered by onset; the
+/// time-keeping annotations that start every data record are left out. A plain EDF file has
+/// no events.
+pub fn read_edf_annotations<P: AsRef<Path>>(path: P) -> Result<Vec<EdfAnnotation>, Box<dyn Error>> {
+    let mut reader = open_input(path)?;
+
+    let header = read_edf_header(&mut reader)?;
+    let ranges: Vec<(usize, usize)> = (0..header.signals.len())
+        .filter(|&i| header.signals[i].is_annotations())
+        .map(|i| {
+            let start = header.signal_offset(i);
+            (
+                start,
+                start + header.signals[i].samples_per_record * header.sample_bytes(),
+            )
+        })
+        .collect();
+    if ranges.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut annotations = Vec::new();
+    let mut record = vec![0u8; header.record_bytes()];
+    while read_record(&mut reader, &mut record)? {
+        for &(start, end) in &ranges {
+            annotations.extend(parse_annotations(&record[start..end])?);
+        }
+    }
+    annotations.sort_by(|a, b| a.onset.total_cmp(&b.onset));
+
+    info!("Found {} EDF+ annotations", annotations.len());
+
+    Ok(annotations)
+}
+
+// the events of the time-stamped annotation lists (TALs) of one data record: `+onset`,
+// optionally 0x15 and the duration, then every text followed by 0x14, and a 0 byte closing
+// the list; the record is padded with 0 bytes
+fn parse_annotations(bytes: &[u8]) -> Result<Vec<EdfAnnotation>, Box<dyn Error>> {
+    let mut annotations = Vec::new();
+    for list in bytes
+        .split(|&byte| byte == 0)
+        .filter(|list| !list.is_empty())
+    {
+        let mut fields = list.split(|&byte| byte == 0x14);
+        let timing = String::from_utf8_lossy(fields.next().unwrap_or_default());
+        let (onset, duration) = match timing.split_once('\u{15}') {
+            Some((onset, duration)) => (onset, Some(duration)),
+            None => (timing.as_ref(), None),
+        };
+        let invalid = || format!("Invalid EDF+ annotation timing {:?}", timing);
+        let onset: f64 = onset.parse().map_err(|_| invalid())?;
+        let duration = match duration {
+            Some(duration) => Some(duration.parse::<f64>().map_err(|_| invalid())?),
+            None => None,
+        };
+        for text in fields.filter(|text| !text.is_empty()) {
+            annotations.push(EdfAnnotation {
+                onset,
+                duration,
+                text: String::from_utf8_lossy(text).into_owned(),
+            });
+        }
+    }
+    Ok(annotations)
+}
+
+// `+onset` or `-onset` of a time-stamped annotation list, to 0.1 ms
+fn tal_time(seconds: f64) -> String {
+    let seconds = (seconds * 10_000.0).round() / 10_000.0;
+    if seconds < 0.0 {
+        seconds.to_string()
+    } else {
+        format!("+{}", seconds)
+    }
+}
+
+// a time-stamped annotation list of one event, whose text cannot hold the separators
+fn annotation_list(annotation: &EdfAnnotation) -> Vec<u8> {
+    let mut list = tal_time(annotation.onset).into_bytes();
+    if let Some(duration) = annotation.duration {
+        list.push(0x15);
+        list.extend_from_slice(tal_time(duration).trim_start_matches('+').as_bytes());
+    }
+    list.push(0x14);
+    list.extend(annotation.text.bytes().map(|byte| {
+        if matches!(byte, 0 | 0x14 | 0x15) {
+            b' '
+        } else {
+            byte
+        }
+    }));
+    list.extend_from_slice(&[0x14, 0]);
+    list
+}
+
+// EDF+ (or BDF+) patient and recording fields for a plain EDF header, keeping what they held
+// as an additional subfield
+fn edf_plus_identification(header: &mut EdfHeader) {
+    let extra = |field: &str| {
+        let field = field.split_whitespace().collect::<Vec<_>>().join("_");
+        if field.is_empty() || field == "X" {
+            String::new()
+        } else {
+            format!(" {}", field)
+        }
+    };
+    if header.patient.split_whitespace().count() < 4 {
+        header.patient = format!("X X X X{}", extra(&header.patient));
+    }
+    if !header.recording.starts_with("Startdate ") {
+        const MONTHS: [&str; 12] = [
+            "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+        ];
+        let date = header
+            .start_datetime()
+            .map(|seconds| {
+                let days = (seconds / 86_400.0).floor() as i64;
+                let (year, month, day) = crate::datetime::civil_from_days(days);
+                format!("{:02}-{}-{}", day, MONTHS[month as usize - 1], year)
+            })
+            .unwrap_or_else(|| "X".to_string());
+        header.recording = format!("Startdate {} X X X{}", date, extra(&header.recording));
+    }
+    header.reserved = if header.is_bdf() { "BDF+C" } else { "EDF+C" }.to_string();
+}
+
+// the annotation lists of every data record, starting with the time-keeping annotation when
+// `time_keeping`; each event goes into the record holding its onset
+fn annotation_records(
+    num_records: usize,
+    record_duration: f64,
+    time_keeping: bool,
+    annotations: &[EdfAnnotation],
+) -> Vec<Vec<u8>> {
+    let mut lists: Vec<Vec<u8>> = (0..num_records)
+        .map(|record| {
+            if time_keeping {
+                format!(
+                    "{}\u{14}\u{14}\0",
+                    tal_time(record as f64 * record_duration)
+                )
+                .into_bytes()
+            } else {
+                Vec::new()
+            }
+        })
+        .collect();
+    if num_records > 0 {
+        for annotation in annotations {
+            let record = (annotation.onset / record_duration).floor().max(0.0) as usize;
+            lists[record.min(num_records - 1)].extend(annotation_list(annotation));
+        }
+    }
+    lists
+}
+
+// an annotation signal of the header's sample size holding the longest of the lists
+fn annotation_signal(header: &EdfHeader, lists: &[Vec<u8>]) -> EdfSignalInfo {
+    let samples_per_record = lists
+        .iter()
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0)
+        .div_ceil(header.sample_bytes())
+        .max(1);
+    let (label, digital_max) = if header.is_bdf() {
+        ("BDF Annotations", 8_388_607.0)
+    } else {
+        ("EDF Annotations", 32_767.0)
+    };
+    EdfSignalInfo {
+        label: label.to_string(),
+        transducer: String::new(),
+        physical_dimension: String::new(),
+        physical_min: -1.0,
+        physical_max: 1.0,
+        digital_min: -digital_max - 1.0,
+        digital_max,
+        prefiltering: String::new(),
+        samples_per_record,
+    }
+}
+
+/// Copies an EDF or EDF+ file with the events added as a new `EDF Annotations` signal, e.g.
+/// the detected beats for viewing in EDFbrowser. The recorded signals are copied unchanged;
+/// a plain EDF file becomes a continuous EDF+ one (BDF files likewise BDF+), with the
+/// time-keeping annotation every data record needs. Each event goes into the data record
+/// holding its onset.
+pub fn write_edf_with_annotations<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    annotations: &[EdfAnnotation],
+) -> Result<(), Box<dyn Error>> {
+    let mut header = read_edf_header(reader)?;
+    if header.num_records < 0 {
+        return Err("The EDF file does not give its number of data records".into());
+    }
+    let num_records = header.num_records as usize;
+    let record_bytes = header.record_bytes();
+    let time_keeping = !header.signals.iter().any(EdfSignalInfo::is_annotations);
+
+    let lists = annotation_records(
+        num_records,
+        header.record_duration,
+        time_keeping,
+        annotations,
+    );
+    let annotation_signal = annotation_signal(&header, &lists);
+    let list_bytes = annotation_signal.samples_per_record * header.sample_bytes();
+
+    if time_keeping {
+        edf_plus_identification(&mut header);
+    }
+    header.signals.push(annotation_signal);
+    write_edf_header(writer, &header)?;
+
+    let mut record = vec![0u8; record_bytes];
+    for (index, mut list) in lists.into_iter().enumerate() {
+        if !read_record(reader, &mut record)? {
+            return Err(format!(
+                "The EDF file ends after {} of its {} data records",
+                index, num_records
+            )
+            .into());
+        }
+        writer.write_all(&record)?;
+        list.resize(list_bytes, 0);
+        writer.write_all(&list)?;
+    }
+    Ok(())
+}
+
+/// Copies an EDF or BDF file with the patient identification and the recording fields
+/// replaced by unknown values (`X`, in the EDF+ subfield layout for EDF+ files), the data
+/// records unchanged. `clear_date` also replaces the start date by 01.01.85, the EDF+
+/// convention for an anonymized date, keeping the time of day. Annotation texts are copied
+/// as they are.
+pub fn anonymize_edf<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    clear_date: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut header = read_edf_header(reader)?;
+    if header.is_edf_plus() {
+        header.patient = "X X X X".to_string();
+        let date = match header.recording.split_whitespace().nth(1) {
+            Some(date) if !clear_date => date.to_string(),
+            _ => "X".to_string(),
+        };
+        header.recording = format!("Startdate {} X X X", date);
+    } else {
+        header.patient = "X".to_string();
+        header.recording = "X".to_string();
+    }
+    if clear_date {
+        header.start_date = "01.01.85".to_string();
+    }
+    write_edf_header(writer, &header)?;
+    io::copy(reader, writer)?;
+    Ok(())
+}
+
+/// Identification and start of a recording written by [`write_edf`].
+#[derive(Debug, Clone)]
+pub struct EdfRecordingInfo {
+    pub patient: String,
+    pub recording: String,
+    /// `dd.mm.yy`
+    pub start_date: String,
+    /// `hh.mm.ss`
+    pub start_time: String,
+    /// Filtering of every signal written, e.g. `HP:0.5Hz LP:40Hz` (see [`edf_prefiltering`]).
+    pub prefiltering: String,
+}
+
+/// The prefiltering field of signals filtered with `config`, in the EDF convention
+/// (`HP:0.5Hz LP:40Hz N:50Hz`).
+pub fn edf_prefiltering(config: &FilterConfig) -> String {
+    let mut fields = Vec::new();
+    if let Some(highpass) = config.highpass {
+        fields.push(format!("HP:{}Hz", highpass));
+    }
+    if let Some(lowpass) = config.lowpass {
+        fields.push(format!("LP:{}Hz", lowpass));
+    }
+    if let Some(notch) = config.notch {
+        fields.push(format!("N:{}Hz", notch));
+    }
+    fields.join(" ")
+}
+
+impl Default for EdfRecordingInfo {
+    /// Unknown patient and recording, starting at the EDF+ placeholder date 01.01.85.
+    fn default() -> Self {
+        EdfRecordingInfo {
+            patient: "X".to_string(),
+            recording: "X".to_string(),
+            start_date: "01.01.85".to_string(),
+            start_time: "00.00.00".to_string(),
+            prefiltering: String::new(),
+        }
+    }
+}
+
+// the shortest data record duration up to a minute holding a whole number of samples
+fn record_duration(fs: f64) -> Option<f64> {
+    (1..=60)
+        .map(f64::from)
+        .find(|duration| ((fs * duration).round() - fs * duration).abs() < 1e-6)
+}
+
+/// Writes the leads, in mV and sampled at `fs`, as a 16-bit EDF file, e.g. signals filtered
+/// or resampled by the crate. Each lead is stored with the physical range of its values
+/// (rounded outwards to 0.001 mV); the last data record is filled up by repeating the last
+/// sample.
+pub fn write_edf<W: Write>(
+    writer: &mut W,
+    ecg: &MultiLeadEcg,
+    fs: f64,
+    info: &EdfRecordingInfo,
+) -> Result<(), Box<dyn Error>> {
+    write_edf_file(writer, ecg, fs, info, None)
+}
+
+/// Writes the leads like [`write_edf`] as a continuous EDF+ file, with the events as its
+/// annotation signal; the identification fields are given the EDF+ layout when they lack it.
+pub fn write_edf_plus<W: Write>(
+    writer: &mut W,
+    ecg: &MultiLeadEcg,
+    fs: f64,
+    info: &EdfRecordingInfo,
+    annotations: &[EdfAnnotation],
+) -> Result<(), Box<dyn Error>> {
+    write_edf_file(writer, ecg, fs, info, Some(annotations))
+}
+
+// EDF, or EDF+ when there are annotations to write
+fn write_edf_file<W: Write>(
+    writer: &mut W,
+    ecg: &MultiLeadEcg,
+    fs: f64,
+    info: &EdfRecordingInfo,
+    annotations: Option<&[EdfAnnotation]>,
+) -> Result<(), Box<dyn Error>> {
+    let duration = record_duration(fs).ok_or_else(|| {
+        format!(
+            "No data record of up to 60 s holds a whole number of samples at {} Hz",
+            fs
+        )
+    })?;
+    let samples_per_record = (fs * duration).round() as usize;
+    let num_samples = ecg.times.len();
+    let num_records = num_samples.div_ceil(samples_per_record);
+
+    let signals: Vec<EdfSignalInfo> = ecg
+        .leads
+        .iter()
+        .map(|lead| {
+            let (min, max) = lead
+                .voltage
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+                    (min.min(v), max.max(v))
+                });
+            let (min, max) = if min < max {
+                (min, max)
+            } else {
+                (min - 1.0, min + 1.0)
+            };
+            // the range as the header stores it, so that the calibration reads back exactly
+            let stored = |value: f64| edf_number(value).parse().unwrap_or(value);
+            EdfSignalInfo {
+                label: lead.name.clone(),
+                transducer: String::new(),
+                physical_dimension: "mV".to_string(),
+                physical_min: stored((min * 1000.0).floor() / 1000.0),
+                physical_max: stored((max * 1000.0).ceil() / 1000.0),
+                digital_min: -32768.0,
+                digital_max: 32767.0,
+                prefiltering: info.prefiltering.clone(),
+                samples_per_record,
+            }
+        })
+        .collect();
+    let mut header = EdfHeader {
+        version: "0".to_string(),
+        patient: info.patient.clone(),
+        recording: info.recording.clone(),
+        start_date: info.start_date.clone(),
+        start_time: info.start_time.clone(),
+        header_bytes: 256 * (signals.len() + 1),
+        reserved: String::new(),
+        num_records: num_records as i64,
+        record_duration: duration,
+        signals,
+    };
+    if header.start_datetime().is_none() {
+        return Err(format!(
+            "Invalid EDF start {} {}, expected dd.mm.yy and hh.mm.ss",
+            info.start_date, info.start_time
+        )
+        .into());
+    }
+    let lists =
+        annotations.map(|annotations| annotation_records(num_records, duration, true, annotations));
+    if let Some(lists) = &lists {
+        edf_plus_identification(&mut header);
+        header.signals.push(annotation_signal(&header, lists));
+    }
+    write_edf_header(writer, &header)?;
+
+    let mut record = Vec::with_capacity(header.record_bytes());
+    for index in 0..num_records {
+        record.clear();
+        for (lead, signal) in ecg.leads.iter().zip(&header.signals) {
+            let gain = signal.gain();
+            for sample in index * samples_per_record..(index + 1) * samples_per_record {
+                let voltage = lead.voltage[sample.min(num_samples - 1)];
+                let digital = (signal.digital_min + (voltage - signal.physical_min) / gain)
+                    .round()
+                    .clamp(signal.digital_min, signal.digital_max);
+                record.extend_from_slice(&(digital as i16).to_le_bytes());
+            }
+        }
+        if let Some(lists) = &lists {
+            // the annotation signal comes last, padded with 0 bytes
+            record.extend_from_slice(&lists[index]);
+            record.resize(header.record_bytes(), 0);
+        }
+        writer.write_all(&record)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn field(value: &str, len: usize) -> String {
+        format!("{:<1$}", value, len)
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("signalweaver_{}_{}", std::process::id(), name))
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64], tolerance: f64) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() <= tolerance, "{} != {}", a, e);
+        }
+    }
+
+    #[test]
+    fn parses_a_hand_built_header() {
+        let mut header = String::new();
+        for (value, len) in [
+            ("0", 8),
+            ("X M 01-JAN-1970 X", 80),
+            ("Startdate 02-MAR-2021 X X X", 80),
+            ("02.03.21", 8),
+            ("10.20.30", 8),
+            ("768", 8),
+            ("EDF+C", 44),
+            ("12", 8),
+            ("0.5", 8),
+            ("2", 4),
+        ] {
+            header += &field(value, len);
+        }
+        // each signal field is stored for both signals before the next field
+        for (values, len) in [
+            (["ECG I", "EDF Annotations"], 16),
+            (["AgAgCl electrode", ""], 80),
+            (["uV", ""], 8),
+            (["-3276.8", "-1"], 8),
+            (["3276.7", "1"], 8),
+            (["-32768", "-32768"], 8),
+            (["32767", "32767"], 8),
+            (["HP:0.5Hz", ""], 80),
+            (["128", "30"], 8),
+            (["", ""], 32),
+        ] {
+            header += &field(values[0], len);
+            header += &field(values[1], len);
+        }
+        assert_eq!(header.len(), 256 + 256 * 2);
+
+        let mut reader = header.as_bytes();
+        let header = read_edf_header(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(header.start_date, "02.03.21");
+        assert_eq!(header.header_bytes, 768);
+        assert_eq!(header.num_records, 12);
+        assert!(header.is_edf_plus() && !header.is_discontinuous() && !header.is_bdf());
+        assert_eq!(header.sampling_frequency(0), 256.0);
+        assert_eq!(header.signals[0].physical_dimension, "uV");
+        assert_eq!(header.signals[0].prefiltering, "HP:0.5Hz");
+        assert_eq!(header.signals[0].to_physical(-32768), -3276.8);
+        assert!(header.signals[1].is_annotations());
+        assert_eq!(header.record_bytes(), 2 * (128 + 30));
+        assert_eq!(header.start_clock(), Some(37_230.0));
+    }
+
+    #[test]
+    fn reads_24_bit_bdf_samples() {
+        let calibration = 8_388_608.0;
+        let header = EdfHeader {
+            version: BDF_VERSION.to_string(),
+            patient: "X".to_string(),
+            recording: "X".to_string(),
+            start_date: "01.01.85".to_string(),
+            start_time: "00.00.00".to_string(),
+            header_bytes: 512,
+            reserved: "24BIT".to_string(),
+            num_records: 1,
+            record_duration: 1.0,
+            signals: vec![EdfSignalInfo {
+                label: "ECG".to_string(),
+                transducer: String::new(),
+                physical_dimension: "uV".to_string(),
+                physical_min: -calibration,
+                physical_max: calibration - 1.0,
+                digital_min: -calibration,
+                digital_max: calibration - 1.0,
+                prefiltering: String::new(),
+                samples_per_record: 4,
+            }],
+        };
+        let mut bytes = Vec::new();
+        write_edf_header(&mut bytes, &header).unwrap();
+        assert_eq!(bytes[0], 0xFF);
+        for sample in [1000i32, -1000, 8_388_607, -8_388_608] {
+            bytes.extend_from_slice(&sample.to_le_bytes()[..3]);
+        }
+        let path = temp_path("bdf.bdf");
+        fs::write(&path, &bytes).unwrap();
+        let data = read_edf_ecg_data(&path, 0);
+        fs::remove_file(&path).unwrap();
+
+        let data = data.unwrap();
+        let voltages: Vec<f64> = data.iter().map(|point| point.voltage).collect();
+        assert_close(&voltages, &[1.0, -1.0, 8388.607, -8388.608], 1e-9);
+        assert_eq!(data[3].time, 0.75);
+    }
+
+    #[test]
+    fn round_trips_edf_plus() {
+        let fs = 250.0;
+        let times: Vec<f64> = (0..300).map(|i| i as f64 / fs).collect();
+        let ecg = MultiLeadEcg {
+            leads: vec![
+                Lead {
+                    name: "I".to_string(),
+                    voltage: times.iter().map(|t| (10.0 * t).sin()).collect(),
+                },
+                Lead {
+                    name: "II".to_string(),
+                    voltage: times.iter().map(|t| 2.0 * t - 0.5).collect(),
+                },
+            ],
+            times,
+        };
+        let annotations = vec![EdfAnnotation {
+            onset: 0.5,
+            duration: Some(0.25),
+            text: "artifact".to_string(),
+        }];
+        let mut bytes = Vec::new();
+        write_edf_plus(
+            &mut bytes,
+            &ecg,
+            fs,
+            &EdfRecordingInfo::default(),
+            &annotations,
+        )
+        .unwrap();
+        let path = temp_path("round_trip.edf");
+        fs::write(&path, &bytes).unwrap();
+        let leads = read_edf_leads(&path);
+        let events = read_edf_annotations(&path);
+        fs::remove_file(&path).unwrap();
+
+        let leads = leads.unwrap();
+        assert_eq!(events.unwrap(), annotations);
+        // two 1 s records, the second finished with the last sample
+        assert_eq!(leads.times.len(), 500);
+        assert_eq!(leads.leads.len(), 2);
+        for (read, written) in leads.leads.iter().zip(&ecg.leads) {
+            assert_eq!(read.name, written.name);
+            let (min, max) = written
+                .voltage
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+                    (min.min(v), max.max(v))
+                });
+            // within a digital step of the stored range
+            let step = (max - min + 0.002) / 65535.0;
+            assert_close(&read.voltage[..300], &written.voltage, step);
+            assert_close(&read.voltage[300..], &[written.voltage[299]; 200], step);
+        }
+    }
+}
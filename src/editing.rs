@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::io::BufRead;
+
+/// A change to a list of beat times (seconds), one line of an edit script: `add 12.5`,
+/// `remove 12.5`, `remove 100-105` for every beat in a range, or `move 12.5 12.54`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeatEdit {
+    Add(f64),
+    Remove(f64),
+    RemoveRange(f64, f64),
+    Move(f64, f64),
+}
+
+impl std::str::FromStr for BeatEdit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "expected add <time>, remove <time>, remove <from>-<to> or move <time> <time>, got {:?}",
+                s
+            )
+        };
+        let number = |field: &str| field.parse::<f64>().ok().filter(|v| v.is_finite());
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let edit = match fields.as_slice() {
+            ["add", time] => number(time).map(BeatEdit::Add),
+            // a dash after the first character separates a range
+            ["remove", time] => match time.char_indices().skip(1).find(|&(_, c)| c == '-') {
+                Some((dash, _)) => number(&time[..dash])
+                    .zip(number(&time[dash + 1..]))
+                    .filter(|(from, to)| from <= to)
+                    .map(|(from, to)| BeatEdit::RemoveRange(from, to)),
+                None => number(time).map(BeatEdit::Remove),
+            },
+            ["move", from, to] => number(from)
+                .zip(number(to))
+                .map(|(from, to)| BeatEdit::Move(from, to)),
+            _ => None,
+        };
+        edit.ok_or_else(invalid)
+    }
+}
+
+/// Reads an edit script: one edit per line, blank lines and `#` comments skipped.
+pub fn read_edit_script<R: BufRead>(reader: R) -> Result<Vec<BeatEdit>, Box<dyn Error>> {
+    let mut edits = Vec::new();
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let edit = line
+            .parse()
+            .map_err(|e| format!("Edit script line {}: {}", line_idx + 1, e))?;
+        edits.push(edit);
+    }
+    Ok(edits)
+}
+
+/// How many beats a script added, removed and moved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EditCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub moved: usize,
+}
+
+/// Index of the beat nearest `time` in the sorted `times`, if it lies within `tolerance`
+/// seconds.
+pub fn nearest_beat(times: &[f64], time: f64, tolerance: f64) -> Option<usize> {
+    let next = times.partition_point(|&t| t < time);
+    [next.checked_sub(1), Some(next)]
+        .into_iter()
+        .flatten()
+        .filter(|&i| i < times.len() && (times[i] - time).abs() <= tolerance)
+        .min_by(|&a, &b| (times[a] - time).abs().total_cmp(&(times[b] - time).abs()))
+}
+
+/// Applies the edits in order to the beat `times`, which end up sorted; `remove` and `move`
+/// act on the beat nearest the time given, which must lie within `tolerance` seconds of it.
+pub fn apply_edits(
+    times: &mut Vec<f64>,
+    edits: &[BeatEdit],
+    tolerance: f64,
+) -> Result<EditCounts, Box<dyn Error>> {
+    times.sort_by(f64::total_cmp);
+    let mut counts = EditCounts::default();
+    let nearest = |times: &[f64], time: f64| {
+        nearest_beat(times, time, tolerance)
+            .ok_or_else(|| format!("No beat within {} s of {} s", tolerance, time))
+    };
+    let insert = |times: &mut Vec<f64>, time: f64| {
+        let index = times.partition_point(|&t| t < time);
+        times.insert(index, time);
+    };
+    for edit in edits {
+        match *edit {
+            BeatEdit::Add(time) => {
+                insert(times, time);
+                counts.added += 1;
+            }
+            BeatEdit::Remove(time) => {
+                times.remove(nearest(times, time)?);
+                counts.removed += 1;
+            }
+            BeatEdit::RemoveRange(from, to) => {
+                let before = times.len();
+                times.retain(|&t| t < from || t > to);
+                counts.removed += before - times.len();
+            }
+            BeatEdit::Move(from, to) => {
+                times.remove(nearest(times, from)?);
+                insert(times, to);
+                counts.moved += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
@@ -0,0 +1,321 @@
+// C API of the `cdylib`, declared in include/signalweaver.h. Handles are opaque and freed
+// by the caller with the matching `*_free` function; panics are caught at the boundary.
+
+use crate::detection::{detect_beats, DetectionOptions};
+use crate::detectors::{DetectorRegistry, QrsDetector};
+use crate::filters::FilterConfig;
+use crate::hrv::{self, SpectralMethod};
+use crate::models::EcgPoint;
+use crate::rr::rr_intervals;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Outcome of a call; the values are part of the ABI and never change. On failure
+/// `sw_last_error` describes what went wrong on the calling thread.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwStatus {
+    Ok = 0,
+    /// A required pointer argument was NULL.
+    NullPointer = 1,
+    /// A number out of range or a string that is not UTF-8.
+    InvalidArgument = 2,
+    /// No detector goes by the given name.
+    UnknownDetector = 3,
+    /// Too few beats for the requested analysis.
+    NotEnoughBeats = 4,
+    /// The library panicked; the outputs are untouched.
+    Panic = 5,
+}
+
+/// A detector with its settings.
+pub struct SwDetector {
+    detector: Box<dyn QrsDetector>,
+    options: DetectionOptions,
+}
+
+/// The beats `sw_detect` found.
+pub struct SwBeats {
+    beats: Vec<SwBeat>,
+}
+
+/// A detected beat, as in [`crate::models::Detection`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SwBeat {
+    /// Time in seconds from the first sample.
+    pub time: f64,
+    pub sample: u64,
+    pub amplitude: f64,
+    pub confidence: f64,
+}
+
+/// Time and frequency domain HRV of the beats; NaN for a measure that could not be computed
+/// (SDANN of recordings under 10 minutes, the spectrum of short ones).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SwHrv {
+    pub count: u64,
+    pub mean_rr_ms: f64,
+    pub sdnn_ms: f64,
+    pub rmssd_ms: f64,
+    pub pnn50_percent: f64,
+    pub sdann_ms: f64,
+    pub mean_hr_bpm: f64,
+    /// Welch powers in ms².
+    pub vlf_ms2: f64,
+    pub lf_ms2: f64,
+    pub hf_ms2: f64,
+    pub lf_hf: f64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn fail(status: SwStatus, message: &str) -> SwStatus {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    status
+}
+
+// runs the body with panics turned into SW_PANIC
+fn guard(body: impl FnOnce() -> SwStatus) -> SwStatus {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(status) => status,
+        Err(_) => fail(SwStatus::Panic, "signalweaver panicked"),
+    }
+}
+
+/// The version of the library, a static NUL-terminated string.
+#[no_mangle]
+pub extern "C" fn sw_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Describes the last failed call on this thread; empty before the first failure. The
+/// string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn sw_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// Creates a detector by name (`threshold`, `pan-tompkins`, `wavelet`, `hamilton`,
+/// `ensemble`; NULL for `threshold`) for signals sampled at `fs` Hz, without pre-filtering.
+///
+/// # Safety
+///
+/// `name` is NULL or a NUL-terminated string, and `out` points to writable storage for a
+/// handle.
+#[no_mangle]
+pub unsafe extern "C" fn sw_detector_new(
+    name: *const c_char,
+    fs: f64,
+    out: *mut *mut SwDetector,
+) -> SwStatus {
+    guard(|| {
+        if out.is_null() {
+            return fail(SwStatus::NullPointer, "out is NULL");
+        }
+        let name = if name.is_null() {
+            "threshold"
+        } else {
+            match CStr::from_ptr(name).to_str() {
+                Ok(name) => name,
+                Err(_) => return fail(SwStatus::InvalidArgument, "name is not UTF-8"),
+            }
+        };
+        if !(fs.is_finite() && fs > 0.0) {
+            return fail(SwStatus::InvalidArgument, "fs must be positive");
+        }
+        let Some(detector) = DetectorRegistry::default().remove(name) else {
+            return fail(
+                SwStatus::UnknownDetector,
+                &format!("unknown detector {:?}", name),
+            );
+        };
+        let options = DetectionOptions {
+            fs: Some(fs),
+            ..DetectionOptions::default()
+        };
+        *out = Box::into_raw(Box::new(SwDetector { detector, options }));
+        SwStatus::Ok
+    })
+}
+
+/// Sets the pre-filtering of the detector: high-pass, low-pass and notch frequencies in Hz,
+/// 0 leaving a stage out.
+///
+/// # Safety
+///
+/// `detector` is NULL or a handle from `sw_detector_new` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn sw_detector_set_filter(
+    detector: *mut SwDetector,
+    highpass: f64,
+    lowpass: f64,
+    notch: f64,
+) -> SwStatus {
+    guard(|| {
+        let Some(detector) = detector.as_mut() else {
+            return fail(SwStatus::NullPointer, "detector is NULL");
+        };
+        let stage = |frequency: f64| (frequency > 0.0).then_some(frequency);
+        if [highpass, lowpass, notch]
+            .iter()
+            .any(|frequency| !frequency.is_finite())
+        {
+            return fail(
+                SwStatus::InvalidArgument,
+                "filter frequencies must be finite",
+            );
+        }
+        detector.options.filter = FilterConfig {
+            highpass: stage(highpass),
+            lowpass: stage(lowpass),
+            notch: stage(notch),
+            zero_phase: false,
+        };
+        SwStatus::Ok
+    })
+}
+
+/// Frees a detector; NULL is ignored.
+///
+/// # Safety
+///
+/// `detector` is NULL or a handle from `sw_detector_new` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn sw_detector_free(detector: *mut SwDetector) {
+    if !detector.is_null() {
+        drop(Box::from_raw(detector));
+    }
+}
+
+/// Detects the beats of `len` samples of one lead, in mV.
+///
+/// # Safety
+///
+/// `detector` is a live handle, `samples` points to `len` doubles (it may be NULL when
+/// `len` is 0) and `out` points to writable storage for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn sw_detect(
+    detector: *const SwDetector,
+    samples: *const f64,
+    len: usize,
+    out: *mut *mut SwBeats,
+) -> SwStatus {
+    guard(|| {
+        let Some(detector) = detector.as_ref() else {
+            return fail(SwStatus::NullPointer, "detector is NULL");
+        };
+        if out.is_null() || (samples.is_null() && len > 0) {
+            return fail(SwStatus::NullPointer, "samples or out is NULL");
+        }
+        let samples = if len == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(samples, len)
+        };
+        let fs = detector.options.fs.unwrap_or(1.0);
+        let points: Vec<EcgPoint> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, &voltage)| EcgPoint {
+                time: i as f64 / fs,
+                voltage,
+            })
+            .collect();
+        let beats = detect_beats(&points, detector.detector.as_ref(), &detector.options)
+            .into_iter()
+            .map(|detection| SwBeat {
+                time: detection.time,
+                sample: detection.sample as u64,
+                amplitude: detection.amplitude,
+                confidence: detection.confidence,
+            })
+            .collect();
+        *out = Box::into_raw(Box::new(SwBeats { beats }));
+        SwStatus::Ok
+    })
+}
+
+/// Number of beats; 0 for NULL.
+///
+/// # Safety
+///
+/// `beats` is NULL or a handle from `sw_detect` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn sw_beats_len(beats: *const SwBeats) -> usize {
+    beats.as_ref().map_or(0, |beats| beats.beats.len())
+}
+
+/// The beats in time order, `sw_beats_len` of them, valid until the handle is freed; NULL
+/// for NULL.
+///
+/// # Safety
+///
+/// `beats` is NULL or a handle from `sw_detect` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn sw_beats_data(beats: *const SwBeats) -> *const SwBeat {
+    beats
+        .as_ref()
+        .map_or(ptr::null(), |beats| beats.beats.as_ptr())
+}
+
+/// Frees beats; NULL is ignored.
+///
+/// # Safety
+///
+/// `beats` is NULL or a handle from `sw_detect` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn sw_beats_free(beats: *mut SwBeats) {
+    if !beats.is_null() {
+        drop(Box::from_raw(beats));
+    }
+}
+
+/// HRV of the RR intervals between the beats; needs at least three beats.
+///
+/// # Safety
+///
+/// `beats` is a live handle and `out` points to writable storage for an `SwHrv`.
+#[no_mangle]
+pub unsafe extern "C" fn sw_hrv(beats: *const SwBeats, out: *mut SwHrv) -> SwStatus {
+    guard(|| {
+        let Some(beats) = beats.as_ref() else {
+            return fail(SwStatus::NullPointer, "beats is NULL");
+        };
+        if out.is_null() {
+            return fail(SwStatus::NullPointer, "out is NULL");
+        }
+        let times: Vec<f64> = beats.beats.iter().map(|beat| beat.time).collect();
+        let intervals = rr_intervals(&times);
+        let Some(time_domain) = hrv::time_domain(&intervals) else {
+            return fail(
+                SwStatus::NotEnoughBeats,
+                "at least two RR intervals are needed for HRV",
+            );
+        };
+        let frequency_domain = hrv::frequency_domain(&intervals, SpectralMethod::Welch);
+        let spectral = |power: fn(&hrv::FrequencyDomainHrv) -> f64| {
+            frequency_domain.as_ref().map_or(f64::NAN, power)
+        };
+        *out = SwHrv {
+            count: time_domain.count as u64,
+            mean_rr_ms: time_domain.mean_rr,
+            sdnn_ms: time_domain.sdnn,
+            rmssd_ms: time_domain.rmssd,
+            pnn50_percent: time_domain.pnn50,
+            sdann_ms: time_domain.sdann.unwrap_or(f64::NAN),
+            mean_hr_bpm: time_domain.mean_hr,
+            vlf_ms2: spectral(|spectral| spectral.vlf_power),
+            lf_ms2: spectral(|spectral| spectral.lf_power),
+            hf_ms2: spectral(|spectral| spectral.hf_power),
+            lf_hf: spectral(|spectral| spectral.lf_hf),
+        };
+        SwStatus::Ok
+    })
+}
@@ -0,0 +1,216 @@
+use crate::datetime::iso_datetime;
+use crate::hrv::HrvMetrics;
+use crate::rhythm::{EventKind, EventSummary};
+use std::io::{self, Write};
+
+const LOINC: &str = "http://loinc.org";
+const SNOMED: &str = "http://snomed.info/sct";
+const UCUM: &str = "http://unitsofmeasure.org";
+const CATEGORIES: &str = "http://terminology.hl7.org/CodeSystem/observation-category";
+
+/// Who and when the Observations are about.
+#[derive(Debug, Clone)]
+pub struct FhirContext<'a> {
+    /// Reference to the patient, e.g. `Patient/123`.
+    pub subject: Option<&'a str>,
+    /// First and last beat in seconds since 1970-01-01, as the effective period; left out
+    /// when the recording's start date and time are unknown.
+    pub period: Option<(f64, f64)>,
+    /// Time zone of the period, `Z` or `+HH:MM` / `-HH:MM`.
+    pub utc_offset: &'a str,
+}
+
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// a CodeableConcept with at most one coding
+fn concept(coding: Option<(&str, &str, &str)>, text: &str) -> String {
+    match coding {
+        Some((system, code, display)) => format!(
+            "{{\"coding\": [{{\"system\": {}, \"code\": {}, \"display\": {}}}], \"text\": {}}}",
+            json_string(system),
+            json_string(code),
+            json_string(display),
+            json_string(text)
+        ),
+        None => format!("{{\"text\": {}}}", json_string(text)),
+    }
+}
+
+// a UCUM Quantity
+fn quantity(value: f64, unit: &str) -> String {
+    format!(
+        "{{\"value\": {}, \"unit\": {}, \"system\": {}, \"code\": {}}}",
+        value,
+        json_string(unit),
+        json_string(UCUM),
+        json_string(unit)
+    )
+}
+
+fn component(code: &str, value: f64, unit: &str) -> String {
+    format!(
+        "{{\"code\": {}, \"valueQuantity\": {}}}",
+        code,
+        quantity(value, unit)
+    )
+}
+
+// the UCUM unit of a metric, from the suffix of its name
+fn metric_unit(name: &str) -> &'static str {
+    if name.ends_with("_ms2") {
+        "ms2"
+    } else if name.ends_with("_ms") {
+        "ms"
+    } else if name.ends_with("_percent") {
+        "%"
+    } else if name.ends_with("_bpm") {
+        "/min"
+    } else if name.ends_with("_seconds") {
+        "s"
+    } else {
+        "1"
+    }
+}
+
+// LOINC codes of the HRV measures that have one
+fn metric_loinc(section: &str, name: &str) -> Option<(&'static str, &'static str)> {
+    match (section, name) {
+        ("time_domain", "sdnn_ms") => Some((
+            "80404-7",
+            "R-R interval.standard deviation (Heart rate variability)",
+        )),
+        ("time_domain", "mean_hr_bpm") => Some(("8867-4", "Heart rate")),
+        _ => None,
+    }
+}
+
+// the finding of an event kind, with its SNOMED CT code where it has an unambiguous one
+fn finding(kind: EventKind) -> (&'static str, Option<&'static str>) {
+    match kind {
+        EventKind::AtrialFibrillation => ("Atrial fibrillation", Some("49436004")),
+        EventKind::Bradycardia => ("Bradycardia", Some("48867003")),
+        EventKind::Tachycardia => ("Tachycardia", Some("3424008")),
+        EventKind::Pause => ("Pause", None),
+        EventKind::Asystole => ("Asystole", Some("397829000")),
+        EventKind::Couplet => ("Ventricular couplet", None),
+        EventKind::VentricularRun => ("Run of ventricular beats", None),
+        EventKind::Bigeminy => ("Ventricular bigeminy", None),
+        EventKind::Trigeminy => ("Ventricular trigeminy", None),
+    }
+}
+
+impl FhirContext<'_> {
+    // the fields every Observation shares: status, category, subject and effective period
+    fn common(&self, category: &str) -> String {
+        let mut fields = format!(
+            "\"resourceType\": \"Observation\", \"status\": \"final\", \"category\": \
+             [{{\"coding\": [{{\"system\": {}, \"code\": {}}}]}}]",
+            json_string(CATEGORIES),
+            json_string(category)
+        );
+        if let Some(subject) = self.subject {
+            fields.push_str(&format!(
+                ", \"subject\": {{\"reference\": {}}}",
+                json_string(subject)
+            ));
+        }
+        if let Some((start, end)) = self.period {
+            fields.push_str(&format!(
+                ", \"effectivePeriod\": {{\"start\": {}, \"end\": {}}}",
+                json_string(&format!("{}{}", iso_datetime(start), self.utc_offset)),
+                json_string(&format!("{}{}", iso_datetime(end), self.utc_offset))
+            ));
+        }
+        fields
+    }
+}
+
+/// Writes a FHIR R4 Bundle (type `collection`) of Observations: the mean heart rate
+/// (LOINC 8867-4, a vital sign), one heart rate rhythm finding (LOINC 8884-9) per event
+/// kind of `findings` with episodes, giving their count, total and longest duration and
+/// burden as components, and an HRV panel with a component per finite measure of `panel`. Measures
+/// are coded by LOINC where a code exists and named `section.metric` otherwise.
+pub fn write_fhir_bundle<W: Write>(
+    writer: &mut W,
+    context: &FhirContext,
+    mean_hr: f64,
+    findings: &[EventSummary],
+    panel: &[&dyn HrvMetrics],
+) -> io::Result<()> {
+    let mut resources = vec![format!(
+        "{{{}, \"code\": {}, \"valueQuantity\": {}}}",
+        context.common("vital-signs"),
+        concept(Some((LOINC, "8867-4", "Heart rate")), "Mean heart rate"),
+        quantity(mean_hr, "/min")
+    )];
+
+    for summary in findings.iter().filter(|summary| summary.episodes > 0) {
+        let (text, code) = finding(summary.kind);
+        let components = [
+            format!(
+                "{{\"code\": {}, \"valueInteger\": {}}}",
+                concept(None, "Episodes"),
+                summary.episodes
+            ),
+            component(&concept(None, "Total duration"), summary.total, "s"),
+            component(&concept(None, "Longest episode"), summary.longest, "s"),
+            component(&concept(None, "Burden"), summary.burden, "%"),
+        ];
+        resources.push(format!(
+            "{{{}, \"code\": {}, \"valueCodeableConcept\": {}, \"component\": [{}]}}",
+            context.common("procedure"),
+            concept(Some((LOINC, "8884-9", "Heart rate rhythm")), "Heart rhythm"),
+            concept(code.map(|code| (SNOMED, code, text)), text),
+            components.join(", ")
+        ));
+    }
+
+    let components: Vec<String> = panel
+        .iter()
+        .flat_map(|section| {
+            let name = section.section();
+            section
+                .metrics()
+                .into_iter()
+                .filter_map(move |(metric, value)| {
+                    let value = value.filter(|value| value.is_finite())?;
+                    let text = format!("{}.{}", name, metric);
+                    let code =
+                        metric_loinc(name, metric).map(|(code, display)| (LOINC, code, display));
+                    Some(component(&concept(code, &text), value, metric_unit(metric)))
+                })
+        })
+        .collect();
+    if !components.is_empty() {
+        resources.push(format!(
+            "{{{}, \"code\": {}, \"component\": [{}]}}",
+            context.common("procedure"),
+            concept(None, "Heart rate variability panel"),
+            components.join(", ")
+        ));
+    }
+
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"resourceType\": \"Bundle\",")?;
+    writeln!(writer, "  \"type\": \"collection\",")?;
+    writeln!(writer, "  \"entry\": [")?;
+    for (i, resource) in resources.iter().enumerate() {
+        let separator = if i + 1 < resources.len() { "," } else { "" };
+        writeln!(writer, "    {{\"resource\": {}}}{}", resource, separator)?;
+    }
+    writeln!(writer, "  ]")?;
+    writeln!(writer, "}}")
+}
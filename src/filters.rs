@@ -0,0 +1,218 @@
+use std::f64::consts::PI;
+
+const BUTTERWORTH_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+/// Second-order IIR section (RBJ audio-EQ cookbook coefficients, normalized by a0).
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    pub fn lowpass(fs: f64, cutoff: f64) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(fs, cutoff, BUTTERWORTH_Q);
+        Self::normalized(
+            (1.0 - cos_w0) / 2.0,
+            1.0 - cos_w0,
+            (1.0 - cos_w0) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    pub fn highpass(fs: f64, cutoff: f64) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(fs, cutoff, BUTTERWORTH_Q);
+        Self::normalized(
+            (1.0 + cos_w0) / 2.0,
+            -(1.0 + cos_w0),
+            (1.0 + cos_w0) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    pub fn notch(fs: f64, center: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(fs, center, q);
+        Self::normalized(
+            1.0,
+            -2.0 * cos_w0,
+            1.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    fn prewarp(fs: f64, frequency: f64, q: f64) -> (f64, f64) {
+        let w0 = 2.0 * PI * frequency / fs;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn normalized(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    /// Causal filtering (transposed direct form II).
+    pub fn apply(&self, signal: &[f64]) -> Vec<f64> {
+        let mut state = [0.0; 2];
+        signal.iter().map(|&x| self.step(x, &mut state)).collect()
+    }
+
+    // one sample through the section, `state` holds the two delay elements
+    fn step(&self, x: f64, state: &mut [f64; 2]) -> f64 {
+        let y = self.b0 * x + state[0];
+        state[0] = self.b1 * x - self.a1 * y + state[1];
+        state[1] = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// A causal filter cascade that keeps its state between calls, for sample-by-sample input.
+pub struct StreamingFilter {
+    sections: Vec<Biquad>,
+    states: Vec<[f64; 2]>,
+    offset: Option<f64>,
+}
+
+impl StreamingFilter {
+    /// Zero-phase filtering needs the whole signal, so `zero_phase` is ignored here.
+    pub fn new(config: &FilterConfig, fs: f64) -> Self {
+        let sections = config.sections(fs);
+        StreamingFilter {
+            states: vec![[0.0; 2]; sections.len()],
+            sections,
+            offset: None,
+        }
+    }
+
+    pub fn process(&mut self, x: f64) -> f64 {
+        if self.sections.is_empty() {
+            return x;
+        }
+        // starting from zero, as in FilterConfig::apply
+        let offset = *self.offset.get_or_insert(x);
+        let mut y = x - offset;
+        for (section, state) in self.sections.iter().zip(self.states.iter_mut()) {
+            y = section.step(y, state);
+        }
+        y
+    }
+}
+
+/// Butterworth-style band-pass built from a high-pass and a low-pass section.
+pub fn bandpass(signal: &[f64], fs: f64, low_cutoff: f64, high_cutoff: f64) -> Vec<f64> {
+    // starting from zero, as in FilterConfig::apply, so a DC offset causes no step transient
+    let offset = signal.first().copied().unwrap_or(0.0);
+    let shifted: Vec<f64> = signal.iter().map(|v| v - offset).collect();
+    let high_passed = Biquad::highpass(fs, low_cutoff).apply(&shifted);
+    Biquad::lowpass(fs, high_cutoff).apply(&high_passed)
+}
+
+/// Pre-filtering applied to the whole signal before peak search; cutoffs in Hz,
+/// `None` disables a stage.
+#[derive(Debug, Clone, Default)]
+pub struct FilterConfig {
+    /// High-pass cutoff against baseline wander (typically 0.5 Hz).
+    pub highpass: Option<f64>,
+    /// Low-pass cutoff against muscle noise (typically 40 Hz).
+    pub lowpass: Option<f64>,
+    /// Powerline frequency to notch out (50 or 60 Hz).
+    pub notch: Option<f64>,
+    /// Filter forwards and backwards so fiducial points keep their position.
+    pub zero_phase: bool,
+}
+
+// quality factor of the powerline notch, roughly a 1.7 Hz wide stop band at 50 Hz
+const NOTCH_Q: f64 = 30.0;
+
+impl FilterConfig {
+    /// Recommended ECG pre-filtering: 0.5–40 Hz band-pass, no notch.
+    pub fn ecg() -> Self {
+        FilterConfig {
+            highpass: Some(0.5),
+            lowpass: Some(40.0),
+            notch: None,
+            zero_phase: false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.highpass.is_none() && self.lowpass.is_none() && self.notch.is_none()
+    }
+
+    /// The enabled sections in application order; stages at or above Nyquist are skipped.
+    pub fn sections(&self, fs: f64) -> Vec<Biquad> {
+        let nyquist = fs / 2.0;
+        let mut sections = Vec::new();
+        if let Some(cutoff) = self.highpass.filter(|&f| f > 0.0 && f < nyquist) {
+            sections.push(Biquad::highpass(fs, cutoff));
+        }
+        if let Some(cutoff) = self.lowpass.filter(|&f| f > 0.0 && f < nyquist) {
+            sections.push(Biquad::lowpass(fs, cutoff));
+        }
+        if let Some(center) = self.notch.filter(|&f| f > 0.0 && f < nyquist) {
+            sections.push(Biquad::notch(fs, center, NOTCH_Q));
+        }
+        sections
+    }
+
+    pub fn apply(&self, signal: &[f64], fs: f64) -> Vec<f64> {
+        let sections = self.sections(fs);
+        if sections.is_empty() || signal.is_empty() {
+            return signal.to_vec();
+        }
+
+        // starting from zero avoids a large step transient from the DC offset
+        let offset = signal[0];
+        let shifted: Vec<f64> = signal.iter().map(|v| v - offset).collect();
+        if self.zero_phase {
+            return filtfilt(&sections, &shifted, fs as usize);
+        }
+
+        let mut filtered = shifted;
+        for section in &sections {
+            filtered = section.apply(&filtered);
+        }
+        filtered
+    }
+}
+
+/// Zero-phase filtering: the cascade runs forwards, then backwards over the reversed result,
+/// doubling the attenuation and cancelling the phase shift. The signal is extended at both
+/// ends by `pad` samples of odd reflection to tame the edge transients.
+pub fn filtfilt(sections: &[Biquad], signal: &[f64], pad: usize) -> Vec<f64> {
+    if signal.len() < 2 {
+        return signal.to_vec();
+    }
+    let pad = pad.min(signal.len() - 1);
+    let first = signal[0];
+    let last = signal[signal.len() - 1];
+
+    let mut extended = Vec::with_capacity(signal.len() + 2 * pad);
+    extended.extend((1..=pad).rev().map(|i| 2.0 * first - signal[i]));
+    extended.extend_from_slice(signal);
+    extended.extend((1..=pad).map(|i| 2.0 * last - signal[signal.len() - 1 - i]));
+
+    for section in sections {
+        extended = section.apply(&extended);
+    }
+    extended.reverse();
+    for section in sections {
+        extended = section.apply(&extended);
+    }
+    extended.reverse();
+
+    extended[pad..pad + signal.len()].to_vec()
+}
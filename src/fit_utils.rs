@@ -0,0 +1,192 @@
+use crate::compression::open_input;
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+// global number of the hrv message, whose field 0 holds beat-to-beat intervals
+const HRV_MESSAGE: u16 = 78;
+const HRV_TIME_FIELD: u8 = 0;
+// a uint16 interval value marking no interval
+const INVALID_INTERVAL: u16 = 0xFFFF;
+
+// the layout a definition message gives to the data messages of one local type
+#[derive(Clone)]
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    // field number and size in bytes, in their order in a data message
+    fields: Vec<(u8, usize)>,
+    // total size of the developer fields following the others
+    developer_bytes: usize,
+}
+
+fn take<'a>(bytes: &'a [u8], position: &mut usize, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+    let field = bytes
+        .get(*position..*position + len)
+        .ok_or("FIT file ends inside a record")?;
+    *position += len;
+    Ok(field)
+}
+
+/// Reads the beat-to-beat intervals (ms) of the hrv messages of a Garmin FIT activity file,
+/// as chest straps record them. The file's CRC is not verified.
+pub fn read_fit_rr<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    open_input(path)?.read_to_end(&mut bytes)?;
+
+    let header_size = *bytes.first().ok_or("Empty FIT file")? as usize;
+    if header_size < 12 || bytes.get(8..12) != Some(b".FIT") {
+        return Err("Not a FIT file (no .FIT signature in its header)".into());
+    }
+    let data_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let records = &bytes[..(header_size + data_size).min(bytes.len())];
+
+    let mut definitions: Vec<Option<Definition>> = vec![None; 16];
+    let mut intervals = Vec::new();
+    let mut position = header_size;
+    while position < records.len() {
+        let header = take(records, &mut position, 1)?[0];
+        // compressed timestamp headers carry a local type in bits 5 and 6 and are data
+        let (definition, developer, local) = if header & 0x80 != 0 {
+            (false, false, (header >> 5) & 0x03)
+        } else {
+            (header & 0x40 != 0, header & 0x20 != 0, header & 0x0F)
+        };
+
+        if definition {
+            let fixed = take(records, &mut position, 5)?;
+            let big_endian = fixed[1] == 1;
+            let global = if big_endian {
+                u16::from_be_bytes([fixed[2], fixed[3]])
+            } else {
+                u16::from_le_bytes([fixed[2], fixed[3]])
+            };
+            let count = fixed[4] as usize;
+            let fields = take(records, &mut position, 3 * count)?
+                .chunks_exact(3)
+                .map(|field| (field[0], field[1] as usize))
+                .collect();
+            let mut developer_bytes = 0;
+            if developer {
+                let count = take(records, &mut position, 1)?[0] as usize;
+                developer_bytes = take(records, &mut position, 3 * count)?
+                    .chunks_exact(3)
+                    .map(|field| field[1] as usize)
+                    .sum();
+            }
+            definitions[local as usize] = Some(Definition {
+                global,
+                big_endian,
+                fields,
+                developer_bytes,
+            });
+            continue;
+        }
+
+        let layout = definitions[local as usize]
+            .as_ref()
+            .ok_or_else(|| format!("FIT data message of undefined local type {}", local))?;
+        for &(number, size) in &layout.fields {
+            let field = take(records, &mut position, size)?;
+            if layout.global != HRV_MESSAGE || number != HRV_TIME_FIELD {
+                continue;
+            }
+            // an array of uint16 intervals in 1/1000 s
+            for value in field.chunks_exact(2) {
+                let value = if layout.big_endian {
+                    u16::from_be_bytes([value[0], value[1]])
+                } else {
+                    u16::from_le_bytes([value[0], value[1]])
+                };
+                if value != INVALID_INTERVAL && value > 0 {
+                    intervals.push(value as f64);
+                }
+            }
+        }
+        take(records, &mut position, layout.developer_bytes)?;
+    }
+
+    info!("Total RR intervals: {}", intervals.len());
+
+    Ok(intervals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // a FIT file of the given records with a 14-byte header; the CRCs are left 0
+    fn fit_file(records: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![14, 0x10, 0x08, 0x08];
+        bytes.extend_from_slice(&(records.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b".FIT\0\0");
+        bytes.extend_from_slice(records);
+        bytes.extend_from_slice(&[0, 0]);
+        bytes
+    }
+
+    fn intervals(values: &[u16], big_endian: bool) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| {
+                if big_endian {
+                    value.to_be_bytes()
+                } else {
+                    value.to_le_bytes()
+                }
+            })
+            .collect()
+    }
+
+    fn read_bytes(bytes: &[u8], name: &str) -> Result<Vec<f64>, Box<dyn Error>> {
+        let path =
+            std::env::temp_dir().join(format!("signalweaver_{}_{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        let intervals = read_fit_rr(&path);
+        fs::remove_file(&path).unwrap();
+        intervals
+    }
+
+    fn records() -> Vec<u8> {
+        let mut records = Vec::new();
+        // local 0: a record message (timestamp and heart rate), skipped
+        records.extend_from_slice(&[0x40, 0, 0, 20, 0, 2, 253, 4, 0x86, 3, 1, 0x02]);
+        records.extend_from_slice(&[0x00, 1, 2, 3, 4, 72]);
+        // local 1: hrv messages of five intervals and a 2-byte developer field
+        records.extend_from_slice(&[0x61, 0, 0, 78, 0, 1, 0, 10, 0x84, 1, 0, 2, 0]);
+        records.push(0x01);
+        records.extend(intervals(&[800, 810, 0xFFFF, 0xFFFF, 0xFFFF], false));
+        records.extend_from_slice(&[9, 9]);
+        // local 2: big-endian hrv messages of two intervals
+        records.extend_from_slice(&[0x42, 0, 1, 0, 78, 1, 0, 4, 0x84]);
+        records.push(0x02);
+        records.extend(intervals(&[795, 820], true));
+        // a compressed timestamp header of local type 1
+        records.push(0x80 | (1 << 5) | 7);
+        records.extend(intervals(&[830, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF], false));
+        records.extend_from_slice(&[9, 9]);
+        records
+    }
+
+    #[test]
+    fn reads_hrv_messages() {
+        let intervals = read_bytes(&fit_file(&records()), "hrv.fit").unwrap();
+        assert_eq!(intervals, vec![800.0, 810.0, 795.0, 820.0, 830.0]);
+    }
+
+    #[test]
+    fn rejects_truncated_and_foreign_files() {
+        let mut records = records();
+        records.truncate(records.len() - 3);
+        let mut truncated = fit_file(&records);
+        // the header still gives the full data size
+        truncated[4] += 3;
+        truncated.truncate(truncated.len() - 2);
+        assert!(read_bytes(&truncated, "truncated.fit").is_err());
+        assert!(read_bytes(b"\x0e\x10\x08\x08\0\0\0\0.FTI\0\0", "foreign.fit").is_err());
+        // a data message before its definition
+        assert!(read_bytes(&fit_file(&[0x03, 1, 2]), "undefined.fit").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::classification::BeatClass;
+use crate::hrv::{HrvMetrics, Metric, TrendWindow};
+use crate::models::{Detection, RrInterval};
+use hdf5::types::VarLenUnicode;
+use hdf5::{File, Group, H5Type};
+use std::error::Error;
+use std::path::Path;
+
+/// The signals and derived series of one recording, as `write_hdf5` stores them.
+pub struct Hdf5Recording<'a> {
+    /// The recording the results come from.
+    pub source: &'a str,
+    /// Sampling rate of the signals in Hz, unknown for RR interval input.
+    pub fs: Option<f64>,
+    /// Time of the first sample in seconds.
+    pub start: f64,
+    /// The lead the beats were detected in, in mV; empty when there is none (fused leads,
+    /// RR interval input).
+    pub raw: &'a [f64],
+    /// The same lead as the detector filters it.
+    pub filtered: &'a [f64],
+    pub detections: &'a [Detection],
+    /// Empty, or the class of every beat.
+    pub classes: &'a [BeatClass],
+    pub intervals: &'a [RrInterval],
+    pub trend: &'a [TrendWindow],
+}
+
+fn write_series<T: H5Type>(group: &Group, name: &str, values: &[T]) -> hdf5::Result<()> {
+    group.new_dataset_builder().with_data(values).create(name)?;
+    Ok(())
+}
+
+fn write_text(group: &Group, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let value: VarLenUnicode = value.parse()?;
+    group
+        .new_attr::<VarLenUnicode>()
+        .create(name)?
+        .write_scalar(&value)?;
+    Ok(())
+}
+
+fn write_number(group: &Group, name: &str, value: f64) -> hdf5::Result<()> {
+    group.new_attr::<f64>().create(name)?.write_scalar(&value)
+}
+
+/// Writes a recording to one HDF5 file, which h5py, MATLAB (`h5read`) and R open natively:
+///
+/// - `/signals/raw` and `/signals/filtered` in mV, with `sampling_frequency` (Hz),
+///   `start_time` (s) and `units` attributes, when there is a signal;
+/// - `/beats/time` (s), `sample`, `amplitude`, `confidence` and, for classified beats, `class`
+///   (WFDB mnemonics);
+/// - `/rr/time` (s, of the closing beat) and `rr_ms`;
+/// - `/hrv_trend/start`, `end` (s) and one dataset per HRV measure of the windows, NaN where
+///   a measure could not be computed.
+///
+/// The root carries the `source` recording as an attribute.
+pub fn write_hdf5<P: AsRef<Path>>(
+    path: P,
+    recording: &Hdf5Recording,
+) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    write_text(&file, "source", recording.source)?;
+
+    if !recording.raw.is_empty() {
+        let signals = file.create_group("signals")?;
+        write_series(&signals, "raw", recording.raw)?;
+        write_series(&signals, "filtered", recording.filtered)?;
+        if let Some(fs) = recording.fs {
+            write_number(&signals, "sampling_frequency", fs)?;
+        }
+        write_number(&signals, "start_time", recording.start)?;
+        write_text(&signals, "units", "mV")?;
+    }
+
+    let beats = file.create_group("beats")?;
+    let detections = recording.detections;
+    let column = |value: fn(&Detection) -> f64| detections.iter().map(value).collect::<Vec<_>>();
+    write_series(&beats, "time", &column(|detection| detection.time))?;
+    let samples: Vec<u64> = detections
+        .iter()
+        .map(|detection| detection.sample as u64)
+        .collect();
+    write_series(&beats, "sample", &samples)?;
+    write_series(
+        &beats,
+        "amplitude",
+        &column(|detection| detection.amplitude),
+    )?;
+    write_series(
+        &beats,
+        "confidence",
+        &column(|detection| detection.confidence),
+    )?;
+    if !recording.classes.is_empty() {
+        let classes = recording
+            .classes
+            .iter()
+            .map(|class| class.symbol().parse())
+            .collect::<Result<Vec<VarLenUnicode>, _>>()?;
+        write_series(&beats, "class", &classes)?;
+    }
+
+    let rr = file.create_group("rr")?;
+    let intervals = recording.intervals;
+    let times: Vec<f64> = intervals.iter().map(|rr| rr.time).collect();
+    let rr_ms: Vec<f64> = intervals.iter().map(|rr| rr.rr_ms).collect();
+    write_series(&rr, "time", &times)?;
+    write_series(&rr, "rr_ms", &rr_ms)?;
+
+    let trend = file.create_group("hrv_trend")?;
+    let windows = recording.trend;
+    let starts: Vec<f64> = windows.iter().map(|window| window.start).collect();
+    let ends: Vec<f64> = windows.iter().map(|window| window.end).collect();
+    write_series(&trend, "start", &starts)?;
+    write_series(&trend, "end", &ends)?;
+    // the measures of the first window with spectral measures name the datasets
+    let names: Vec<&str> = windows
+        .iter()
+        .find(|window| window.frequency_domain.is_some())
+        .or(windows.first())
+        .map(trend_metrics)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    for name in names {
+        let values: Vec<f64> = windows
+            .iter()
+            .map(|window| {
+                trend_metrics(window)
+                    .into_iter()
+                    .find(|(metric, _)| *metric == name)
+                    .and_then(|(_, value)| value)
+                    .unwrap_or(f64::NAN)
+            })
+            .collect();
+        write_series(&trend, name, &values)?;
+    }
+
+    Ok(())
+}
+
+// the time domain and, when computed, spectral measures of a trend window
+fn trend_metrics(window: &TrendWindow) -> Vec<Metric> {
+    let mut metrics = window.time_domain.metrics();
+    if let Some(frequency_domain) = &window.frequency_domain {
+        metrics.extend(frequency_domain.metrics());
+    }
+    metrics
+}
@@ -0,0 +1,128 @@
+use super::{HrvMetrics, Metric};
+use crate::models::RrInterval;
+
+/// Poincaré-based heart rate asymmetry descriptors (Guzik, Porta, Ehlers indices and the
+/// Piskorski–Guzik variance decomposition). Decelerations are points above the line of
+/// identity (RRn+1 > RRn), accelerations points below it. Indices are in percent,
+/// SD measures in ms, contributions (C) as fractions.
+#[derive(Debug, Clone)]
+pub struct HeartRateAsymmetry {
+    pub guzik_index: f64,
+    pub porta_index: f64,
+    pub ehlers_index: f64,
+    pub sd1: f64,
+    pub sd1a: f64,
+    pub sd1d: f64,
+    pub sd2: f64,
+    pub sd2a: f64,
+    pub sd2d: f64,
+    pub sdnn_a: f64,
+    pub sdnn_d: f64,
+    pub c1a: f64,
+    pub c1d: f64,
+    pub c2a: f64,
+    pub c2d: f64,
+    pub ca: f64,
+    pub cd: f64,
+}
+
+impl HrvMetrics for HeartRateAsymmetry {
+    fn section(&self) -> &'static str {
+        "asymmetry"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("guzik_index", Some(self.guzik_index)),
+            ("porta_index", Some(self.porta_index)),
+            ("ehlers_index", Some(self.ehlers_index)),
+            ("sd1_ms", Some(self.sd1)),
+            ("sd1a_ms", Some(self.sd1a)),
+            ("sd1d_ms", Some(self.sd1d)),
+            ("sd2_ms", Some(self.sd2)),
+            ("sd2a_ms", Some(self.sd2a)),
+            ("sd2d_ms", Some(self.sd2d)),
+            ("sdnna_ms", Some(self.sdnn_a)),
+            ("sdnnd_ms", Some(self.sdnn_d)),
+            ("c1a", Some(self.c1a)),
+            ("c1d", Some(self.c1d)),
+            ("c2a", Some(self.c2a)),
+            ("c2d", Some(self.c2d)),
+            ("ca", Some(self.ca)),
+            ("cd", Some(self.cd)),
+        ]
+    }
+}
+
+/// Needs at least two RR intervals (one Poincaré point).
+pub fn asymmetry(intervals: &[RrInterval]) -> Option<HeartRateAsymmetry> {
+    if intervals.len() < 2 {
+        return None;
+    }
+
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+    let pairs: Vec<(f64, f64)> = rr.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    let n = pairs.len() as f64;
+
+    let mean_x = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+
+    // squared distances to the line of identity (SD1) and to the perpendicular
+    // line through the centroid (SD2), split by acceleration/deceleration
+    let (mut sd1a_sum, mut sd1d_sum) = (0.0, 0.0);
+    let (mut sd2a_sum, mut sd2d_sum) = (0.0, 0.0);
+    let mut accelerations = 0usize;
+    let mut decelerations = 0usize;
+    for &(x, y) in &pairs {
+        let d1 = (y - x) * (y - x) / 2.0;
+        let d2 = (x + y - mean_x - mean_y) * (x + y - mean_x - mean_y) / 2.0;
+        if y > x {
+            decelerations += 1;
+            sd1d_sum += d1;
+            sd2d_sum += d2;
+        } else if y < x {
+            accelerations += 1;
+            sd1a_sum += d1;
+            sd2a_sum += d2;
+        } else {
+            // points on the line of identity contribute equally to both halves
+            sd2a_sum += d2 / 2.0;
+            sd2d_sum += d2 / 2.0;
+        }
+    }
+
+    let sd1a_sq = sd1a_sum / n;
+    let sd1d_sq = sd1d_sum / n;
+    let sd2a_sq = sd2a_sum / n;
+    let sd2d_sq = sd2d_sum / n;
+    let sd1_sq = sd1a_sq + sd1d_sq;
+    let sd2_sq = sd2a_sq + sd2d_sq;
+    let sdnn_a_sq = (sd1a_sq + sd2a_sq) / 2.0;
+    let sdnn_d_sq = (sd1d_sq + sd2d_sq) / 2.0;
+    let sdnn_sq = (sd1_sq + sd2_sq) / 2.0;
+
+    let off_line = (accelerations + decelerations) as f64;
+    let differences: Vec<f64> = pairs.iter().map(|&(x, y)| y - x).collect();
+    let squares: f64 = differences.iter().map(|d| d * d).sum();
+    let cubes: f64 = differences.iter().map(|d| d * d * d).sum();
+
+    Some(HeartRateAsymmetry {
+        guzik_index: 100.0 * sd1d_sum / (sd1a_sum + sd1d_sum),
+        porta_index: 100.0 * accelerations as f64 / off_line,
+        ehlers_index: cubes / squares.powf(1.5),
+        sd1: sd1_sq.sqrt(),
+        sd1a: sd1a_sq.sqrt(),
+        sd1d: sd1d_sq.sqrt(),
+        sd2: sd2_sq.sqrt(),
+        sd2a: sd2a_sq.sqrt(),
+        sd2d: sd2d_sq.sqrt(),
+        sdnn_a: sdnn_a_sq.sqrt(),
+        sdnn_d: sdnn_d_sq.sqrt(),
+        c1a: sd1a_sq / sd1_sq,
+        c1d: sd1d_sq / sd1_sq,
+        c2a: sd2a_sq / sd2_sq,
+        c2d: sd2d_sq / sd2_sq,
+        ca: sdnn_a_sq / sdnn_sq,
+        cd: sdnn_d_sq / sdnn_sq,
+    })
+}
@@ -0,0 +1,177 @@
+use super::{HrvMetrics, Metric};
+use crate::models::RrInterval;
+
+// largest change of the Malik and Karlsson rules, as a fraction of the reference interval
+const RULE_TOLERANCE: f64 = 0.2;
+
+/// Rule flagging ectopic beats and artifacts in an RR series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RrFilter {
+    /// Differs by more than the given percentage both from the last accepted interval and
+    /// from the previous one, so a filter locked onto an old reference recovers after a
+    /// genuine change in rate.
+    Percentage(f64),
+    /// Falls below the given quantile or above its complement (e.g. 0.01: outside the 1st to
+    /// 99th percentile of the recording).
+    Quantile(f64),
+    /// Differs by more than 20% from the previous interval (Malik et al.).
+    Malik,
+    /// Differs by more than 20% from the mean of its two neighbours (Karlsson et al.).
+    Karlsson,
+}
+
+/// What happens to the flagged intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RrEdit {
+    /// Leave them out of the series.
+    Delete,
+    /// Replace them by linear interpolation between the closest accepted intervals.
+    Interpolate,
+}
+
+/// A cleaned RR series with the number of edited intervals.
+#[derive(Debug, Clone)]
+pub struct CleanedRr {
+    pub intervals: Vec<RrInterval>,
+    /// Intervals before cleaning.
+    pub total: usize,
+    /// Intervals deleted or interpolated.
+    pub edited: usize,
+    pub edit: RrEdit,
+}
+
+impl CleanedRr {
+    /// Fraction of the intervals edited.
+    pub fn edited_fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.edited as f64 / self.total as f64
+    }
+}
+
+impl HrvMetrics for CleanedRr {
+    fn section(&self) -> &'static str {
+        "rr_cleaning"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let (deleted, interpolated) = match self.edit {
+            RrEdit::Delete => (self.edited, 0),
+            RrEdit::Interpolate => (0, self.edited),
+        };
+        vec![
+            ("total", Some(self.total as f64)),
+            ("deleted", Some(deleted as f64)),
+            ("interpolated", Some(interpolated as f64)),
+            ("edited_percent", Some(100.0 * self.edited_fraction())),
+        ]
+    }
+}
+
+/// Flags the intervals `filter` rejects.
+pub fn flag_intervals(intervals: &[RrInterval], filter: RrFilter) -> Vec<bool> {
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+    let differs = |value: f64, reference: f64, tolerance: f64| {
+        (value - reference).abs() > tolerance * reference
+    };
+    match filter {
+        RrFilter::Percentage(percent) => {
+            let tolerance = percent / 100.0;
+            let mut accepted: Option<f64> = None;
+            let mut previous: Option<f64> = None;
+            rr.iter()
+                .map(|&value| {
+                    let flagged = match (accepted, previous) {
+                        (Some(accepted), Some(previous)) => {
+                            differs(value, accepted, tolerance)
+                                && differs(value, previous, tolerance)
+                        }
+                        _ => false,
+                    };
+                    if !flagged {
+                        accepted = Some(value);
+                    }
+                    previous = Some(value);
+                    flagged
+                })
+                .collect()
+        }
+        RrFilter::Quantile(quantile) => {
+            let mut sorted = rr.clone();
+            sorted.sort_unstable_by(f64::total_cmp);
+            let Some(last) = sorted.len().checked_sub(1) else {
+                return Vec::new();
+            };
+            let quantile = quantile.clamp(0.0, 0.5);
+            let low = sorted[(quantile * last as f64).round() as usize];
+            let high = sorted[((1.0 - quantile) * last as f64).round() as usize];
+            rr.iter()
+                .map(|&value| value < low || value > high)
+                .collect()
+        }
+        RrFilter::Malik => (0..rr.len())
+            .map(|i| i > 0 && differs(rr[i], rr[i - 1], RULE_TOLERANCE))
+            .collect(),
+        RrFilter::Karlsson => (0..rr.len())
+            .map(|i| {
+                let neighbours: Vec<f64> = [i.checked_sub(1), Some(i + 1)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|j| rr.get(j).copied())
+                    .collect();
+                if neighbours.is_empty() {
+                    return false;
+                }
+                let reference = neighbours.iter().sum::<f64>() / neighbours.len() as f64;
+                differs(rr[i], reference, RULE_TOLERANCE)
+            })
+            .collect(),
+    }
+}
+
+/// Applies `filter` and deletes or interpolates the flagged intervals. Interpolated
+/// intervals keep their times; flagged intervals at either end of the series are set to the
+/// closest accepted one.
+pub fn clean_rr(intervals: &[RrInterval], filter: RrFilter, edit: RrEdit) -> CleanedRr {
+    let flagged = flag_intervals(intervals, filter);
+    let edited = flagged.iter().filter(|&&flag| flag).count();
+    let kept: Vec<usize> = (0..intervals.len()).filter(|&i| !flagged[i]).collect();
+
+    let cleaned = match edit {
+        RrEdit::Delete => kept.iter().map(|&i| intervals[i]).collect(),
+        RrEdit::Interpolate if kept.is_empty() => Vec::new(),
+        RrEdit::Interpolate => intervals
+            .iter()
+            .enumerate()
+            .map(|(i, interval)| {
+                if !flagged[i] {
+                    return *interval;
+                }
+                // the accepted intervals on either side
+                let after = kept.partition_point(|&k| k < i);
+                let rr_ms = match (after.checked_sub(1).map(|k| kept[k]), kept.get(after)) {
+                    (Some(before), Some(&after)) => {
+                        let weight = (i - before) as f64 / (after - before) as f64;
+                        intervals[before].rr_ms
+                            + weight * (intervals[after].rr_ms - intervals[before].rr_ms)
+                    }
+                    (Some(before), None) => intervals[before].rr_ms,
+                    (None, Some(&after)) => intervals[after].rr_ms,
+                    (None, None) => interval.rr_ms,
+                };
+                RrInterval {
+                    time: interval.time,
+                    rr_ms,
+                }
+            })
+            .collect(),
+    };
+
+    CleanedRr {
+        intervals: cleaned,
+        total: intervals.len(),
+        edited,
+        edit,
+    }
+}
@@ -0,0 +1,158 @@
+use super::{std_dev, HrvMetrics, Metric};
+use crate::cancel::CancelToken;
+use crate::models::RrInterval;
+
+/// Coarse-graining scales of the multiscale entropy.
+pub const MSE_SCALES: usize = 20;
+
+const MSE_NAMES: [&str; MSE_SCALES] = [
+    "mse_1", "mse_2", "mse_3", "mse_4", "mse_5", "mse_6", "mse_7", "mse_8", "mse_9", "mse_10",
+    "mse_11", "mse_12", "mse_13", "mse_14", "mse_15", "mse_16", "mse_17", "mse_18", "mse_19",
+    "mse_20",
+];
+
+/// Settings of the entropy measures.
+#[derive(Debug, Clone)]
+pub struct EntropyOptions {
+    /// Template length.
+    pub m: usize,
+    /// Tolerance as a fraction of the standard deviation of the RR series.
+    pub r: f64,
+    /// Checked before each template; once cancelled, [`entropy`] gives up and returns `None`.
+    pub cancel: CancelToken,
+}
+
+impl Default for EntropyOptions {
+    fn default() -> Self {
+        EntropyOptions {
+            m: 2,
+            r: 0.2,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+/// Sample entropy (Richman and Moorman, 2000), approximate entropy (Pincus, 1991) and
+/// multiscale entropy (Costa et al., 2002) of the RR series. The multiscale entropy is the
+/// sample entropy of the series averaged over non-overlapping windows of 1 to 20 intervals,
+/// with the tolerance of the original series. Sample entropies are `None` when no template
+/// matches.
+#[derive(Debug, Clone)]
+pub struct EntropyMeasures {
+    pub m: usize,
+    pub r: f64,
+    pub sample_entropy: Option<f64>,
+    pub approximate_entropy: f64,
+    pub multiscale: Vec<Option<f64>>,
+}
+
+impl HrvMetrics for EntropyMeasures {
+    fn section(&self) -> &'static str {
+        "entropy"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let mut metrics = vec![
+            ("m", Some(self.m as f64)),
+            ("r", Some(self.r)),
+            ("sampen", self.sample_entropy),
+            ("apen", Some(self.approximate_entropy)),
+        ];
+        metrics.extend(
+            MSE_NAMES
+                .iter()
+                .copied()
+                .zip(self.multiscale.iter().copied()),
+        );
+        metrics
+    }
+}
+
+/// Needs more than `m + 1` RR intervals. The sample entropy takes O(n²) time in the number
+/// of intervals.
+pub fn entropy(intervals: &[RrInterval], options: &EntropyOptions) -> Option<EntropyMeasures> {
+    let m = options.m.max(1);
+    if intervals.len() <= m + 1 {
+        return None;
+    }
+
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+    let tolerance = options.r * std_dev(&rr);
+    let cancel = &options.cancel;
+    let multiscale = (1..=MSE_SCALES)
+        .map(|scale| {
+            let coarse: Vec<f64> = rr
+                .chunks_exact(scale)
+                .map(|chunk| chunk.iter().sum::<f64>() / scale as f64)
+                .collect();
+            sample_entropy(&coarse, m, tolerance, cancel)
+        })
+        .collect();
+    let sample = sample_entropy(&rr, m, tolerance, cancel);
+    let approximate = approximate_entropy(&rr, m, tolerance, cancel)?;
+    if cancel.is_cancelled() {
+        return None;
+    }
+
+    Some(EntropyMeasures {
+        m,
+        r: options.r,
+        sample_entropy: sample,
+        approximate_entropy: approximate,
+        multiscale,
+    })
+}
+
+// whether the templates of length `m` starting at `i` and `j` stay within `tolerance`
+fn matches(series: &[f64], i: usize, j: usize, m: usize, tolerance: f64) -> bool {
+    (0..m).all(|k| (series[i + k] - series[j + k]).abs() <= tolerance)
+}
+
+// −ln(A / B), A and B counting the pairs of distinct templates of length m + 1 and m that
+// match, over the same n − m starting points; `None` as well when cancelled
+fn sample_entropy(series: &[f64], m: usize, tolerance: f64, cancel: &CancelToken) -> Option<f64> {
+    if series.len() <= m + 1 {
+        return None;
+    }
+    let starts = series.len() - m;
+    let (mut shorter, mut longer) = (0u64, 0u64);
+    for i in 0..starts {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        for j in i + 1..starts {
+            if matches(series, i, j, m, tolerance) {
+                shorter += 1;
+                if (series[i + m] - series[j + m]).abs() <= tolerance {
+                    longer += 1;
+                }
+            }
+        }
+    }
+    (shorter > 0 && longer > 0).then(|| -(longer as f64 / shorter as f64).ln())
+}
+
+// Φm − Φm+1, Φ being the mean log fraction of templates matching each template, itself
+// included; `None` when cancelled
+fn approximate_entropy(
+    series: &[f64],
+    m: usize,
+    tolerance: f64,
+    cancel: &CancelToken,
+) -> Option<f64> {
+    let phi = |m: usize| {
+        let starts = series.len() - m + 1;
+        let mut sum = 0.0;
+        for i in 0..starts {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let count = (0..starts)
+                .filter(|&j| matches(series, i, j, m, tolerance))
+                .count();
+            sum += (count as f64 / starts as f64).ln();
+        }
+        Some(sum / starts as f64)
+    };
+    Some(phi(m)? - phi(m + 1)?)
+}
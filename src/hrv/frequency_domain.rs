@@ -0,0 +1,131 @@
+use super::{HrvMetrics, Metric};
+use crate::models::RrInterval;
+use crate::spectrum::{lomb_scargle, welch, Spectrum};
+
+pub const VLF_BAND: (f64, f64) = (0.0033, 0.04);
+pub const LF_BAND: (f64, f64) = (0.04, 0.15);
+pub const HF_BAND: (f64, f64) = (0.15, 0.4);
+
+// tachogram resampling rate and Welch segment (256 samples = 64 s)
+const RESAMPLING_FS: f64 = 4.0;
+const WELCH_SEGMENT: usize = 256;
+// upper bound of the Lomb-Scargle grid and its coarsest allowed resolution
+const LOMB_MAX_FREQUENCY: f64 = 0.5;
+const LOMB_MAX_STEP: f64 = 0.0005;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectralMethod {
+    /// Linear resampling of the tachogram at 4 Hz followed by Welch's method.
+    Welch,
+    /// Lomb–Scargle periodogram on the unevenly sampled RR series.
+    LombScargle,
+}
+
+/// Spectral HRV powers in ms², normalized units in percent.
+#[derive(Debug, Clone)]
+pub struct FrequencyDomainHrv {
+    pub method: SpectralMethod,
+    pub vlf_power: f64,
+    pub lf_power: f64,
+    pub hf_power: f64,
+    pub total_power: f64,
+    pub lf_hf: f64,
+    pub lf_nu: f64,
+    pub hf_nu: f64,
+}
+
+impl HrvMetrics for FrequencyDomainHrv {
+    fn section(&self) -> &'static str {
+        match self.method {
+            SpectralMethod::Welch => "frequency_domain_welch",
+            SpectralMethod::LombScargle => "frequency_domain_lomb_scargle",
+        }
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("vlf_ms2", Some(self.vlf_power)),
+            ("lf_ms2", Some(self.lf_power)),
+            ("hf_ms2", Some(self.hf_power)),
+            ("total_ms2", Some(self.total_power)),
+            ("lf_hf", Some(self.lf_hf)),
+            ("lf_nu", Some(self.lf_nu)),
+            ("hf_nu", Some(self.hf_nu)),
+        ]
+    }
+}
+
+/// Spectrum of the RR tachogram; `None` when the series is too short for a spectrum.
+pub fn rr_spectrum(intervals: &[RrInterval], method: SpectralMethod) -> Option<Spectrum> {
+    if intervals.len() < 4 {
+        return None;
+    }
+    let times: Vec<f64> = intervals.iter().map(|interval| interval.time).collect();
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+
+    let spectrum = match method {
+        SpectralMethod::Welch => {
+            let resampled = resample_linear(&times, &rr, RESAMPLING_FS);
+            welch(&resampled, RESAMPLING_FS, WELCH_SEGMENT)
+        }
+        SpectralMethod::LombScargle => {
+            let duration = times[times.len() - 1] - times[0];
+            let step = (1.0 / (4.0 * duration)).max(LOMB_MAX_STEP);
+            let count = (LOMB_MAX_FREQUENCY / step) as usize;
+            let frequencies: Vec<f64> = (0..=count).map(|k| k as f64 * step).collect();
+            lomb_scargle(&times, &rr, &frequencies)
+        }
+    };
+
+    if spectrum.frequencies.len() < 2 {
+        return None;
+    }
+    Some(spectrum)
+}
+
+pub fn frequency_domain(
+    intervals: &[RrInterval],
+    method: SpectralMethod,
+) -> Option<FrequencyDomainHrv> {
+    let spectrum = rr_spectrum(intervals, method)?;
+
+    let vlf_power = spectrum.band_power(VLF_BAND.0, VLF_BAND.1);
+    let lf_power = spectrum.band_power(LF_BAND.0, LF_BAND.1);
+    let hf_power = spectrum.band_power(HF_BAND.0, HF_BAND.1);
+    let total_power = vlf_power + lf_power + hf_power;
+
+    Some(FrequencyDomainHrv {
+        method,
+        vlf_power,
+        lf_power,
+        hf_power,
+        total_power,
+        lf_hf: lf_power / hf_power,
+        lf_nu: 100.0 * lf_power / (lf_power + hf_power),
+        hf_nu: 100.0 * hf_power / (lf_power + hf_power),
+    })
+}
+
+// evenly resamples (times, values) by linear interpolation
+fn resample_linear(times: &[f64], values: &[f64], fs: f64) -> Vec<f64> {
+    let start = times[0];
+    let end = times[times.len() - 1];
+    let count = ((end - start) * fs) as usize + 1;
+
+    let mut resampled = Vec::with_capacity(count);
+    let mut j = 0;
+    for i in 0..count {
+        let t = start + i as f64 / fs;
+        while j + 2 < times.len() && times[j + 1] < t {
+            j += 1;
+        }
+        let span = times[j + 1] - times[j];
+        let fraction = if span > 0.0 {
+            (t - times[j]) / span
+        } else {
+            0.0
+        };
+        resampled.push(values[j] + fraction.clamp(0.0, 1.0) * (values[j + 1] - values[j]));
+    }
+    resampled
+}
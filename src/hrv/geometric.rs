@@ -0,0 +1,132 @@
+use super::{HrvMetrics, Metric};
+use crate::models::RrInterval;
+
+/// Bin width of the geometric measures, 1/128 s in ms as recommended by the Task Force (1996).
+pub const GEOMETRIC_BIN_MS: f64 = 1000.0 / 128.0;
+
+/// Counts of values in bins of `width` starting at `start`.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub start: f64,
+    pub width: f64,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// Start and end of every bin with its count.
+    pub fn bins(&self) -> impl Iterator<Item = (f64, f64, usize)> + '_ {
+        self.counts.iter().enumerate().map(|(i, &count)| {
+            let start = self.start + i as f64 * self.width;
+            (start, start + self.width, count)
+        })
+    }
+}
+
+/// Histogram of the finite `values` in bins of `width` aligned to multiples of it; `None`
+/// without values or with a width that is not positive.
+pub fn histogram(values: &[f64], width: f64) -> Option<Histogram> {
+    if width.is_nan() || width <= 0.0 {
+        return None;
+    }
+    let finite = || values.iter().copied().filter(|value| value.is_finite());
+    let min = finite().fold(f64::INFINITY, f64::min);
+    let max = finite().fold(f64::NEG_INFINITY, f64::max);
+    if min > max {
+        return None;
+    }
+    let first = (min / width).floor() as i64;
+    let bins = ((max / width).floor() as i64 - first + 1) as usize;
+    let mut counts = vec![0; bins];
+    for value in finite() {
+        let bin = ((value / width).floor() as i64 - first) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+    Some(Histogram {
+        start: first as f64 * width,
+        width,
+        counts,
+    })
+}
+
+/// Histograms of the RR intervals (ms) and of the instantaneous heart rate (bpm).
+pub fn rr_histogram(intervals: &[RrInterval], width: f64) -> Option<Histogram> {
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+    histogram(&rr, width)
+}
+
+pub fn hr_histogram(intervals: &[RrInterval], width: f64) -> Option<Histogram> {
+    let hr: Vec<f64> = intervals
+        .iter()
+        .map(|interval| 60_000.0 / interval.rr_ms)
+        .collect();
+    histogram(&hr, width)
+}
+
+/// Geometric HRV measures of the RR histogram in 1/128 s bins: the HRV triangular index
+/// (intervals over the count of the modal bin) and TINN, the base width in ms of the
+/// triangle fitting the histogram best in the least-squares sense.
+#[derive(Debug, Clone)]
+pub struct GeometricHrv {
+    pub triangular_index: f64,
+    pub tinn: f64,
+}
+
+impl HrvMetrics for GeometricHrv {
+    fn section(&self) -> &'static str {
+        "geometric"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("hrv_triangular_index", Some(self.triangular_index)),
+            ("tinn_ms", Some(self.tinn)),
+        ]
+    }
+}
+
+/// Needs at least two RR intervals.
+pub fn geometric(intervals: &[RrInterval]) -> Option<GeometricHrv> {
+    if intervals.len() < 2 {
+        return None;
+    }
+    let histogram = rr_histogram(intervals, GEOMETRIC_BIN_MS)?;
+    let counts = &histogram.counts;
+    let (mode, &peak) = counts.iter().enumerate().max_by_key(|&(_, count)| *count)?;
+
+    // the triangle rises from zero at bin `n` to the peak at the mode and falls to zero at
+    // bin `m`, both possibly one bin beyond the histogram
+    let bins = counts.len() as i64;
+    let mode = mode as i64;
+    let count = |i: i64| {
+        if (0..bins).contains(&i) {
+            counts[i as usize] as f64
+        } else {
+            0.0
+        }
+    };
+    let mut best = (f64::INFINITY, 0);
+    for n in -1..mode {
+        for m in mode + 1..=bins {
+            let error: f64 = (-1..=bins)
+                .map(|i| {
+                    let triangle = if i <= n || i >= m {
+                        0.0
+                    } else if i <= mode {
+                        peak as f64 * (i - n) as f64 / (mode - n) as f64
+                    } else {
+                        peak as f64 * (m - i) as f64 / (m - mode) as f64
+                    };
+                    (count(i) - triangle).powi(2)
+                })
+                .sum();
+            if error < best.0 {
+                best = (error, m - n);
+            }
+        }
+    }
+
+    Some(GeometricHrv {
+        triangular_index: intervals.len() as f64 / peak as f64,
+        tinn: best.1 as f64 * histogram.width,
+    })
+}
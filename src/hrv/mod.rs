@@ -0,0 +1,54 @@
+mod asymmetry;
+mod cleaning;
+mod entropy;
+mod frequency_domain;
+mod geometric;
+mod periods;
+mod poincare;
+mod prsa;
+pub mod report;
+mod runs;
+mod time_domain;
+mod trend;
+mod turbulence;
+
+pub use asymmetry::{asymmetry, HeartRateAsymmetry};
+pub use cleaning::{clean_rr, flag_intervals, CleanedRr, RrEdit, RrFilter};
+pub use entropy::{entropy, EntropyMeasures, EntropyOptions, MSE_SCALES};
+pub use frequency_domain::{
+    frequency_domain, rr_spectrum, FrequencyDomainHrv, SpectralMethod, HF_BAND, LF_BAND, VLF_BAND,
+};
+pub use geometric::{
+    geometric, histogram, hr_histogram, rr_histogram, GeometricHrv, Histogram, GEOMETRIC_BIN_MS,
+};
+pub use periods::{parse_clock, period_hrv, ClockPeriod, PeriodDelta, PeriodHrv};
+pub use poincare::{poincare, poincare_points, PoincarePlot};
+pub use prsa::{prsa, PhaseRectifiedAverages};
+pub use runs::{monotonic_runs, MonotonicRuns, RunsRow};
+pub use time_domain::{time_domain, TimeDomainHrv};
+pub use trend::{hrv_trend, TrendWindow};
+pub use turbulence::{turbulence, HeartRateTurbulence};
+
+/// A metric name with its value, `None` when it cannot be computed for the recording.
+pub type Metric = (&'static str, Option<f64>);
+
+/// A group of HRV results that can be written by the `report` writers.
+pub trait HrvMetrics {
+    fn section(&self) -> &str;
+    fn metrics(&self) -> Vec<Metric>;
+}
+
+pub(crate) fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// sample standard deviation (n - 1), as used for SDNN
+pub(crate) fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let variance =
+        values.iter().map(|&x| (x - mean) * (x - mean)).sum::<f64>() / (values.len() - 1) as f64;
+    variance.sqrt()
+}
@@ -0,0 +1,147 @@
+use super::{
+    frequency_domain, time_domain, FrequencyDomainHrv, HrvMetrics, Metric, SpectralMethod,
+    TimeDomainHrv,
+};
+use crate::models::RrInterval;
+
+const DAY: f64 = 86_400.0;
+
+/// A named time of day range such as `night=23:00-06:00`, in seconds after midnight; ranges
+/// ending before they start run past midnight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockPeriod {
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl ClockPeriod {
+    /// Whether the time of day (seconds after midnight) falls in the period.
+    pub fn contains(&self, clock: f64) -> bool {
+        let clock = clock.rem_euclid(DAY);
+        if self.start <= self.end {
+            clock >= self.start && clock < self.end
+        } else {
+            clock >= self.start || clock < self.end
+        }
+    }
+}
+
+impl std::str::FromStr for ClockPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a period like night=23:00-06:00, got {:?}", s);
+        let (name, range) = s.split_once('=').ok_or_else(invalid)?;
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        Ok(ClockPeriod {
+            name: name.to_string(),
+            start: parse_clock(start).ok_or_else(invalid)?,
+            end: parse_clock(end).ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// Seconds after midnight of an `HH:MM` or `HH:MM:SS` time of day.
+pub fn parse_clock(value: &str) -> Option<f64> {
+    let fields: Vec<&str> = value.trim().split(':').collect();
+    if !(2..=3).contains(&fields.len()) {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for (field, (scale, limit)) in fields
+        .iter()
+        .zip([(3600.0, 24.0), (60.0, 60.0), (1.0, 60.0)])
+    {
+        let value: f64 = field.parse().ok()?;
+        if !(0.0..limit).contains(&value) {
+            return None;
+        }
+        seconds += scale * value;
+    }
+    Some(seconds)
+}
+
+/// Time-domain and spectral HRV of the intervals in one period.
+#[derive(Debug, Clone)]
+pub struct PeriodHrv {
+    pub name: String,
+    pub time_domain: TimeDomainHrv,
+    pub frequency_domain: Option<FrequencyDomainHrv>,
+}
+
+impl HrvMetrics for PeriodHrv {
+    fn section(&self) -> &str {
+        &self.name
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let mut metrics = self.time_domain.metrics();
+        if let Some(frequency_domain) = &self.frequency_domain {
+            metrics.extend(frequency_domain.metrics());
+        }
+        metrics
+    }
+}
+
+/// HRV of the intervals whose end falls in `period`, the recording starting `start_clock`
+/// seconds after midnight; over several days the matching stretches are pooled. `None`
+/// with fewer than two intervals in the period. Spectra are computed when `method` is
+/// given, interpolating across the hours between stretches.
+pub fn period_hrv(
+    intervals: &[RrInterval],
+    start_clock: f64,
+    period: &ClockPeriod,
+    method: Option<SpectralMethod>,
+) -> Option<PeriodHrv> {
+    let selected: Vec<RrInterval> = intervals
+        .iter()
+        .filter(|interval| period.contains(start_clock + interval.time))
+        .copied()
+        .collect();
+    Some(PeriodHrv {
+        name: period.name.clone(),
+        time_domain: time_domain(&selected)?,
+        frequency_domain: method.and_then(|method| frequency_domain(&selected, method)),
+    })
+}
+
+/// The change of every metric of a period from a reference period, `period − reference`,
+/// in a section named `<period>_vs_<reference>`.
+#[derive(Debug, Clone)]
+pub struct PeriodDelta {
+    pub name: String,
+    pub deltas: Vec<Metric>,
+}
+
+impl PeriodDelta {
+    pub fn new(period: &PeriodHrv, reference: &PeriodHrv) -> Self {
+        let reference_values = reference.metrics();
+        let deltas = period
+            .metrics()
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let (_, reference) = reference_values.iter().find(|(other, _)| *other == name)?;
+                Some((name, value.zip(*reference).map(|(a, b)| a - b)))
+            })
+            .collect();
+        PeriodDelta {
+            name: format!("{}_vs_{}", period.name, reference.name),
+            deltas,
+        }
+    }
+}
+
+impl HrvMetrics for PeriodDelta {
+    fn section(&self) -> &str {
+        &self.name
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        self.deltas.clone()
+    }
+}
@@ -0,0 +1,77 @@
+use super::{mean, HrvMetrics, Metric};
+use crate::models::RrInterval;
+use std::f64::consts::PI;
+
+/// Poincaré plot descriptors in ms. The fitted ellipse is centred on the mean point, with
+/// SD2 along the line of identity and SD1 perpendicular to it.
+#[derive(Debug, Clone)]
+pub struct PoincarePlot {
+    pub sd1: f64,
+    pub sd2: f64,
+    pub sd1_sd2: f64,
+    pub center_x: f64,
+    pub center_y: f64,
+    /// Ellipse area in ms² (π·SD1·SD2).
+    pub area: f64,
+}
+
+impl HrvMetrics for PoincarePlot {
+    fn section(&self) -> &'static str {
+        "poincare"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("sd1_ms", Some(self.sd1)),
+            ("sd2_ms", Some(self.sd2)),
+            ("sd1_sd2", Some(self.sd1_sd2)),
+            ("center_x_ms", Some(self.center_x)),
+            ("center_y_ms", Some(self.center_y)),
+            ("ellipse_area_ms2", Some(self.area)),
+            ("ellipse_angle_deg", Some(45.0)),
+        ]
+    }
+}
+
+/// The (RRn, RRn+1) point cloud.
+pub fn poincare_points(intervals: &[RrInterval]) -> Vec<(f64, f64)> {
+    intervals
+        .windows(2)
+        .map(|pair| (pair[0].rr_ms, pair[1].rr_ms))
+        .collect()
+}
+
+/// Needs at least two RR intervals.
+pub fn poincare(intervals: &[RrInterval]) -> Option<PoincarePlot> {
+    let points = poincare_points(intervals);
+    if points.is_empty() {
+        return None;
+    }
+
+    let center_x = mean(&points.iter().map(|p| p.0).collect::<Vec<_>>());
+    let center_y = mean(&points.iter().map(|p| p.1).collect::<Vec<_>>());
+
+    // spread across and along the line of identity around the centroid
+    let n = points.len() as f64;
+    let sd1 = (points
+        .iter()
+        .map(|&(x, y)| ((y - x) - (center_y - center_x)).powi(2) / 2.0)
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    let sd2 = (points
+        .iter()
+        .map(|&(x, y)| ((x + y) - (center_x + center_y)).powi(2) / 2.0)
+        .sum::<f64>()
+        / n)
+        .sqrt();
+
+    Some(PoincarePlot {
+        sd1,
+        sd2,
+        sd1_sd2: sd1 / sd2,
+        center_x,
+        center_y,
+        area: PI * sd1 * sd2,
+    })
+}
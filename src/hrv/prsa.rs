@@ -0,0 +1,68 @@
+use super::{HrvMetrics, Metric};
+use crate::models::RrInterval;
+
+// anchors changing by more than this fraction from the previous interval are taken for
+// artifacts and left out
+const MAX_CHANGE: f64 = 0.05;
+
+/// Deceleration and acceleration capacity by phase-rectified signal averaging (Bauer et al.,
+/// 2006), in ms. Every lengthening (DC) or shortening (AC) interval anchors a window of the
+/// RR series; the windows are averaged and the capacity is (X0 + X1 − X−1 − X−2) / 4 of the
+/// average, X0 being the anchor. AC is negative.
+#[derive(Debug, Clone)]
+pub struct PhaseRectifiedAverages {
+    pub deceleration_capacity: Option<f64>,
+    pub acceleration_capacity: Option<f64>,
+    pub deceleration_anchors: usize,
+    pub acceleration_anchors: usize,
+}
+
+impl HrvMetrics for PhaseRectifiedAverages {
+    fn section(&self) -> &'static str {
+        "prsa"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("dc_ms", self.deceleration_capacity),
+            ("ac_ms", self.acceleration_capacity),
+            ("dc_anchors", Some(self.deceleration_anchors as f64)),
+            ("ac_anchors", Some(self.acceleration_anchors as f64)),
+        ]
+    }
+}
+
+/// Needs at least four RR intervals; a capacity is `None` without an anchor of its kind.
+pub fn prsa(intervals: &[RrInterval]) -> Option<PhaseRectifiedAverages> {
+    if intervals.len() < 4 {
+        return None;
+    }
+
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+    let capacity = |decelerating: bool| {
+        let (mut sum, mut anchors) = (0.0, 0);
+        // the window reaches two intervals back and one ahead of the anchor
+        for i in 2..rr.len() - 1 {
+            let (previous, anchor) = (rr[i - 1], rr[i]);
+            let qualifies = if decelerating {
+                anchor > previous
+            } else {
+                anchor < previous
+            };
+            if qualifies && (anchor - previous).abs() <= MAX_CHANGE * previous {
+                sum += (rr[i] + rr[i + 1] - rr[i - 1] - rr[i - 2]) / 4.0;
+                anchors += 1;
+            }
+        }
+        ((anchors > 0).then(|| sum / anchors as f64), anchors)
+    };
+    let (deceleration_capacity, deceleration_anchors) = capacity(true);
+    let (acceleration_capacity, acceleration_anchors) = capacity(false);
+
+    Some(PhaseRectifiedAverages {
+        deceleration_capacity,
+        acceleration_capacity,
+        deceleration_anchors,
+        acceleration_anchors,
+    })
+}
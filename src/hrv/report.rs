@@ -0,0 +1,129 @@
+use super::{Histogram, HrvMetrics, MonotonicRuns, TrendWindow};
+use std::io::{self, Write};
+
+fn json_number(value: Option<f64>) -> String {
+    match value {
+        Some(v) if v.is_finite() => v.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+// empty for values that cannot be computed
+fn csv_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Writes the sections as one JSON object keyed by section name.
+pub fn write_json<W: Write>(writer: &mut W, sections: &[&dyn HrvMetrics]) -> io::Result<()> {
+    writeln!(writer, "{{")?;
+    for (i, section) in sections.iter().enumerate() {
+        writeln!(writer, "  \"{}\": {{", section.section())?;
+        let metrics = section.metrics();
+        for (j, (name, value)) in metrics.iter().enumerate() {
+            let separator = if j + 1 < metrics.len() { "," } else { "" };
+            writeln!(
+                writer,
+                "    \"{}\": {}{}",
+                name,
+                json_number(*value),
+                separator
+            )?;
+        }
+        let separator = if i + 1 < sections.len() { "," } else { "" };
+        writeln!(writer, "  }}{}", separator)?;
+    }
+    writeln!(writer, "}}")
+}
+
+/// Writes the sections as a long-format `section,metric,value` table.
+pub fn write_csv<W: Write>(writer: &mut W, sections: &[&dyn HrvMetrics]) -> io::Result<()> {
+    writeln!(writer, "section,metric,value")?;
+    for section in sections {
+        for (name, value) in section.metrics() {
+            let value = value
+                .filter(|v| v.is_finite())
+                .map(|v| v.to_string())
+                .unwrap_or_default();
+            writeln!(writer, "{},{},{}", section.section(), name, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the runs table, one row per run length.
+pub fn write_runs_csv<W: Write>(writer: &mut W, runs: &MonotonicRuns) -> io::Result<()> {
+    writeln!(
+        writer,
+        "length,deceleration_runs,acceleration_runs,neutral_runs,\
+         deceleration_variance,acceleration_variance,neutral_variance"
+    )?;
+    for row in &runs.rows {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            row.length,
+            row.deceleration_runs,
+            row.acceleration_runs,
+            row.neutral_runs,
+            row.deceleration_variance,
+            row.acceleration_variance,
+            row.neutral_variance
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the Poincaré point cloud as `rr_n,rr_n1` rows.
+pub fn write_poincare_csv<W: Write>(writer: &mut W, points: &[(f64, f64)]) -> io::Result<()> {
+    writeln!(writer, "rr_n,rr_n1")?;
+    for (x, y) in points {
+        writeln!(writer, "{},{}", x, y)?;
+    }
+    Ok(())
+}
+
+/// Writes a histogram as `bin_start,bin_end,count` rows.
+pub fn write_histogram_csv<W: Write>(writer: &mut W, histogram: &Histogram) -> io::Result<()> {
+    writeln!(writer, "bin_start,bin_end,count")?;
+    for (start, end, count) in histogram.bins() {
+        writeln!(writer, "{},{},{}", start, end, count)?;
+    }
+    Ok(())
+}
+
+/// Writes the HRV trend, one row per window; the spectral columns are empty for windows
+/// without a spectrum.
+pub fn write_trend_csv<W: Write>(writer: &mut W, windows: &[TrendWindow]) -> io::Result<()> {
+    writeln!(
+        writer,
+        "start,end,count,mean_rr_ms,sdnn_ms,rmssd_ms,pnn50_percent,mean_hr_bpm,\
+         vlf_ms2,lf_ms2,hf_ms2,total_ms2,lf_hf"
+    )?;
+    for window in windows {
+        let time = &window.time_domain;
+        let spectral = match &window.frequency_domain {
+            Some(f) => [f.vlf_power, f.lf_power, f.hf_power, f.total_power, f.lf_hf]
+                .map(csv_number)
+                .join(","),
+            None => ",,,,".to_string(),
+        };
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{}",
+            window.start,
+            window.end,
+            time.count,
+            time.mean_rr,
+            time.sdnn,
+            time.rmssd,
+            time.pnn50,
+            time.mean_hr,
+            spectral
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::models::RrInterval;
+
+/// Runs of one length: counts per direction and their contributions to the RR variance (ms²).
+#[derive(Debug, Clone, Default)]
+pub struct RunsRow {
+    pub length: usize,
+    pub deceleration_runs: usize,
+    pub acceleration_runs: usize,
+    pub neutral_runs: usize,
+    pub deceleration_variance: f64,
+    pub acceleration_variance: f64,
+    pub neutral_variance: f64,
+}
+
+/// Monotonic runs of lengthening (deceleration), shortening (acceleration) and unchanged
+/// (neutral) RR intervals. A run of length k spans k successive RR changes in one direction;
+/// each RR interval belongs to the run of the change leading to it, so the contributions of
+/// all runs plus the first interval add up to the total variance.
+#[derive(Debug, Clone)]
+pub struct MonotonicRuns {
+    pub rows: Vec<RunsRow>,
+    pub total_variance: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Deceleration,
+    Acceleration,
+    Neutral,
+}
+
+pub fn monotonic_runs(intervals: &[RrInterval]) -> MonotonicRuns {
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+    if rr.len() < 2 {
+        return MonotonicRuns {
+            rows: Vec::new(),
+            total_variance: 0.0,
+        };
+    }
+
+    let n = rr.len() as f64;
+    let mean = rr.iter().sum::<f64>() / n;
+    let contribution = |value: f64| (value - mean) * (value - mean) / n;
+
+    let mut rows: Vec<RunsRow> = Vec::new();
+    let mut record = |direction: Direction, length: usize, variance: f64| {
+        while rows.len() < length {
+            rows.push(RunsRow {
+                length: rows.len() + 1,
+                ..RunsRow::default()
+            });
+        }
+        let row = &mut rows[length - 1];
+        match direction {
+            Direction::Deceleration => {
+                row.deceleration_runs += 1;
+                row.deceleration_variance += variance;
+            }
+            Direction::Acceleration => {
+                row.acceleration_runs += 1;
+                row.acceleration_variance += variance;
+            }
+            Direction::Neutral => {
+                row.neutral_runs += 1;
+                row.neutral_variance += variance;
+            }
+        }
+    };
+
+    let direction_of = |previous: f64, current: f64| {
+        if current > previous {
+            Direction::Deceleration
+        } else if current < previous {
+            Direction::Acceleration
+        } else {
+            Direction::Neutral
+        }
+    };
+
+    let mut direction = direction_of(rr[0], rr[1]);
+    let mut length = 0;
+    let mut variance = 0.0;
+    for pair in rr.windows(2) {
+        let current = direction_of(pair[0], pair[1]);
+        if current != direction {
+            record(direction, length, variance);
+            direction = current;
+            length = 0;
+            variance = 0.0;
+        }
+        length += 1;
+        variance += contribution(pair[1]);
+    }
+    record(direction, length, variance);
+
+    MonotonicRuns {
+        rows,
+        total_variance: rr.iter().map(|&value| contribution(value)).sum(),
+    }
+}
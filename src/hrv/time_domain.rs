@@ -0,0 +1,85 @@
+use super::{mean, std_dev, HrvMetrics, Metric};
+use crate::models::RrInterval;
+
+const SDANN_SEGMENT_SECONDS: f64 = 300.0;
+
+/// Time-domain HRV statistics; intervals in ms, heart rate in beats per minute.
+#[derive(Debug, Clone)]
+pub struct TimeDomainHrv {
+    pub count: usize,
+    pub mean_rr: f64,
+    pub sdnn: f64,
+    pub rmssd: f64,
+    /// Percentage of successive differences larger than 50 ms.
+    pub pnn50: f64,
+    /// SD of the 5-minute segment means; `None` for recordings shorter than two segments.
+    pub sdann: Option<f64>,
+    pub mean_hr: f64,
+}
+
+impl HrvMetrics for TimeDomainHrv {
+    fn section(&self) -> &'static str {
+        "time_domain"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("count", Some(self.count as f64)),
+            ("mean_rr_ms", Some(self.mean_rr)),
+            ("sdnn_ms", Some(self.sdnn)),
+            ("rmssd_ms", Some(self.rmssd)),
+            ("pnn50_percent", Some(self.pnn50)),
+            ("sdann_ms", self.sdann),
+            ("mean_hr_bpm", Some(self.mean_hr)),
+        ]
+    }
+}
+
+/// Needs at least two RR intervals.
+pub fn time_domain(intervals: &[RrInterval]) -> Option<TimeDomainHrv> {
+    if intervals.len() < 2 {
+        return None;
+    }
+
+    let rr: Vec<f64> = intervals.iter().map(|interval| interval.rr_ms).collect();
+    let differences: Vec<f64> = rr.windows(2).map(|pair| pair[1] - pair[0]).collect();
+
+    let rmssd = (differences.iter().map(|d| d * d).sum::<f64>() / differences.len() as f64).sqrt();
+    let nn50 = differences.iter().filter(|d| d.abs() > 50.0).count();
+    let mean_hr = mean(&rr.iter().map(|&r| 60_000.0 / r).collect::<Vec<_>>());
+
+    Some(TimeDomainHrv {
+        count: rr.len(),
+        mean_rr: mean(&rr),
+        sdnn: std_dev(&rr),
+        rmssd,
+        pnn50: 100.0 * nn50 as f64 / differences.len() as f64,
+        sdann: sdann(intervals),
+        mean_hr,
+    })
+}
+
+fn sdann(intervals: &[RrInterval]) -> Option<f64> {
+    let start = intervals[0].time;
+    let mut segment_means = Vec::new();
+    let mut segment = Vec::new();
+    let mut segment_idx = 0;
+
+    for interval in intervals {
+        let idx = ((interval.time - start) / SDANN_SEGMENT_SECONDS) as usize;
+        if idx != segment_idx {
+            // only complete segments count, the trailing partial one is dropped below
+            if !segment.is_empty() {
+                segment_means.push(mean(&segment));
+            }
+            segment.clear();
+            segment_idx = idx;
+        }
+        segment.push(interval.rr_ms);
+    }
+
+    if segment_means.len() < 2 {
+        return None;
+    }
+    Some(std_dev(&segment_means))
+}
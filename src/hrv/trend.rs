@@ -0,0 +1,56 @@
+use super::{frequency_domain, time_domain, FrequencyDomainHrv, SpectralMethod, TimeDomainHrv};
+use crate::models::RrInterval;
+
+/// HRV of the intervals ending from `start` to `end` (seconds).
+#[derive(Debug, Clone)]
+pub struct TrendWindow {
+    pub start: f64,
+    pub end: f64,
+    pub time_domain: TimeDomainHrv,
+    pub frequency_domain: Option<FrequencyDomainHrv>,
+}
+
+/// HRV over windows of `window` seconds advancing by `step` seconds from the first interval,
+/// as long as they fit in the recording. Windows with fewer than two intervals, such as
+/// those within a gap, are left out; the spectral measures are computed when `method` is
+/// given.
+pub fn hrv_trend(
+    intervals: &[RrInterval],
+    window: f64,
+    step: f64,
+    method: Option<SpectralMethod>,
+) -> Vec<TrendWindow> {
+    let (Some(first), Some(last)) = (intervals.first(), intervals.last()) else {
+        return Vec::new();
+    };
+    if window <= 0.0 || step <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut windows = Vec::new();
+    let mut begin = 0;
+    let mut k = 0;
+    loop {
+        let start = first.time + k as f64 * step;
+        let end = start + window;
+        if end > last.time {
+            break;
+        }
+        k += 1;
+        while begin < intervals.len() && intervals[begin].time < start {
+            begin += 1;
+        }
+        let stop = begin + intervals[begin..].partition_point(|interval| interval.time <= end);
+        let selected = &intervals[begin..stop];
+        let Some(time_domain) = time_domain(selected) else {
+            continue;
+        };
+        windows.push(TrendWindow {
+            start,
+            end,
+            time_domain,
+            frequency_domain: method.and_then(|method| frequency_domain(selected, method)),
+        });
+    }
+    windows
+}
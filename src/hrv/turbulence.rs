@@ -0,0 +1,119 @@
+use super::{mean, HrvMetrics, Metric};
+use crate::classification::BeatClass;
+
+// sinus intervals needed before the coupling interval and after the compensatory pause
+const BEFORE: usize = 2;
+const AFTER: usize = 15;
+// sinus intervals of the regression lines of the turbulence slope
+const SLOPE_INTERVALS: usize = 5;
+// a qualifying PVC is at least this much premature, and its pause this much longer, than
+// the reference sinus interval
+const PREMATURITY: f64 = 0.2;
+// sinus intervals outside these limits (ms) or this far from the reference disqualify a PVC
+const MIN_SINUS: f64 = 300.0;
+const MAX_SINUS: f64 = 2000.0;
+const SINUS_TOLERANCE: f64 = 0.2;
+
+/// Heart rate turbulence after isolated ventricular premature beats (Schmidt et al., 1999).
+#[derive(Debug, Clone)]
+pub struct HeartRateTurbulence {
+    /// PVCs qualifying for the analysis.
+    pub pvcs: usize,
+    /// Mean relative change of the two sinus intervals after the compensatory pause from
+    /// the two before the PVC, in percent; negative when the rate speeds up.
+    pub turbulence_onset: f64,
+    /// Steepest regression slope over five consecutive intervals of the averaged tachogram
+    /// after the PVC, in ms per RR interval.
+    pub turbulence_slope: f64,
+}
+
+impl HrvMetrics for HeartRateTurbulence {
+    fn section(&self) -> &'static str {
+        "turbulence"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("pvcs", Some(self.pvcs as f64)),
+            ("turbulence_onset_percent", Some(self.turbulence_onset)),
+            ("turbulence_slope_ms_per_rr", Some(self.turbulence_slope)),
+        ]
+    }
+}
+
+/// Heart rate turbulence from the beat times (seconds) and classes. A ventricular beat
+/// qualifies when three normal beats precede it and sixteen follow it, when it comes at
+/// least 20% early and its pause is at least 20% longer than the mean of the two preceding
+/// sinus intervals, and when all its sinus intervals lie within 300–2000 ms and 20% of that
+/// mean. `None` without a qualifying PVC.
+pub fn turbulence(times: &[f64], classes: &[BeatClass]) -> Option<HeartRateTurbulence> {
+    let beats = times.len().min(classes.len());
+    let interval = |i: usize| 1000.0 * (times[i] - times[i - 1]);
+
+    let mut onsets = Vec::new();
+    let mut tachograms: Vec<Vec<f64>> = Vec::new();
+    for pvc in BEFORE + 1..beats.saturating_sub(AFTER + 1) {
+        if classes[pvc] != BeatClass::Ventricular {
+            continue;
+        }
+        if (pvc - BEFORE - 1..=pvc + AFTER + 1)
+            .filter(|&i| i != pvc)
+            .any(|i| classes[i] != BeatClass::Normal)
+        {
+            continue;
+        }
+
+        // RR-2 and RR-1 before the coupling interval, RR1 to RR15 after the pause
+        let before: Vec<f64> = (pvc - BEFORE..pvc).map(interval).collect();
+        let after: Vec<f64> = (pvc + 2..=pvc + AFTER + 1).map(interval).collect();
+        let reference = mean(&before);
+        let coupling = interval(pvc);
+        let pause = interval(pvc + 1);
+        if coupling > (1.0 - PREMATURITY) * reference || pause < (1.0 + PREMATURITY) * reference {
+            continue;
+        }
+        let sinus_ok = |rr: &f64| {
+            (MIN_SINUS..=MAX_SINUS).contains(rr)
+                && (rr - reference).abs() <= SINUS_TOLERANCE * reference
+        };
+        if !before.iter().chain(&after).all(sinus_ok) {
+            continue;
+        }
+
+        let before_sum: f64 = before.iter().sum();
+        onsets.push(100.0 * (after[0] + after[1] - before_sum) / before_sum);
+        tachograms.push(after);
+    }
+    if tachograms.is_empty() {
+        return None;
+    }
+
+    let averaged: Vec<f64> = (0..AFTER)
+        .map(|i| {
+            tachograms.iter().map(|tachogram| tachogram[i]).sum::<f64>() / tachograms.len() as f64
+        })
+        .collect();
+    let turbulence_slope = averaged
+        .windows(SLOPE_INTERVALS)
+        .map(regression_slope)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    Some(HeartRateTurbulence {
+        pvcs: tachograms.len(),
+        turbulence_onset: mean(&onsets),
+        turbulence_slope,
+    })
+}
+
+// least-squares slope of the values against their index
+fn regression_slope(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let x_mean = (n - 1.0) / 2.0;
+    let y_mean = mean(values);
+    let (mut cross, mut squares) = (0.0, 0.0);
+    for (i, &y) in values.iter().enumerate() {
+        cross += (i as f64 - x_mean) * (y - y_mean);
+        squares += (i as f64 - x_mean).powi(2);
+    }
+    cross / squares
+}
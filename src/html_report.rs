@@ -0,0 +1,297 @@
+use crate::hrv::HrvMetrics;
+use crate::rhythm::{EventSummary, RhythmEvent};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+// size of the charts and their margins, in SVG user units
+const CHART_WIDTH: f64 = 720.0;
+const CHART_HEIGHT: f64 = 240.0;
+const SCATTER_SIZE: f64 = 360.0;
+const MARGIN: f64 = 48.0;
+// the Poincaré plot is thinned to at most this many points
+const MAX_POINTS: usize = 5000;
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:800px;margin:2em auto;color:#222}\
+h1{font-size:1.6em}h2{font-size:1.2em;border-bottom:1px solid #ccc;margin-top:1.6em}\
+table{border-collapse:collapse;margin:0.5em 0}td,th{padding:2px 12px;text-align:left}\
+tr:nth-child(even){background:#f4f4f4}td.number{text-align:right}\
+svg text{font-size:11px;fill:#444}@media print{h2{page-break-after:avoid}}";
+
+/// Contents of an analysis report; the HRV sections and rhythm summaries become tables,
+/// the heart rate trend (time in seconds, bpm) and the Poincaré points (ms) charts.
+pub struct ReportData<'a> {
+    pub title: String,
+    /// Name and value of every recording property.
+    pub metadata: Vec<(String, String)>,
+    /// Name and count of every beat class.
+    pub beats: Vec<(String, usize)>,
+    pub hr_trend: Vec<(f64, f64)>,
+    pub hrv: Vec<&'a dyn HrvMetrics>,
+    pub poincare: Vec<(f64, f64)>,
+    pub summaries: Vec<EventSummary>,
+    pub events: Vec<RhythmEvent>,
+}
+
+/// Writes the report as a self-contained HTML page with inline SVG charts, printable to PDF
+/// from a browser.
+pub fn write_html_report<W: Write>(writer: &mut W, report: &ReportData) -> io::Result<()> {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+        title = escape(&report.title)
+    );
+
+    html.push_str("<h2>Recording</h2>\n");
+    push_table(&mut html, &[], &report.metadata);
+
+    html.push_str("<h2>Beats</h2>\n");
+    let beats: Vec<(String, String)> = report
+        .beats
+        .iter()
+        .map(|(name, count)| (name.clone(), count.to_string()))
+        .collect();
+    push_table(&mut html, &[], &beats);
+
+    html.push_str("<h2>Heart rate</h2>\n");
+    if report.hr_trend.len() < 2 {
+        html.push_str("<p>The recording is too short for a heart rate trend.</p>\n");
+    } else {
+        let minutes: Vec<(f64, f64)> = report
+            .hr_trend
+            .iter()
+            .map(|&(time, hr)| (time / 60.0, hr))
+            .collect();
+        push_line_chart(&mut html, &minutes, "time (min)", "heart rate (bpm)");
+    }
+
+    html.push_str("<h2>Heart rate variability</h2>\n");
+    for section in &report.hrv {
+        let _ = writeln!(
+            html,
+            "<h3>{}</h3>",
+            escape(&section.section().replace('_', " "))
+        );
+        let rows: Vec<(String, String)> = section
+            .metrics()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), format_value(value)))
+            .collect();
+        push_table(&mut html, &["metric", "value"], &rows);
+    }
+
+    html.push_str("<h2>Poincaré plot</h2>\n");
+    if report.poincare.is_empty() {
+        html.push_str("<p>Too few RR intervals for a Poincaré plot.</p>\n");
+    } else {
+        push_scatter(&mut html, &report.poincare, "RRn (ms)", "RRn+1 (ms)");
+    }
+
+    html.push_str("<h2>Rhythm events</h2>\n");
+    let summaries: Vec<(String, String)> = report
+        .summaries
+        .iter()
+        .map(|summary| {
+            (
+                summary.kind.as_str().replace('_', " "),
+                format!(
+                    "{} episodes, {:.1} s in total, {:.2}% of the recording",
+                    summary.episodes, summary.total, summary.burden
+                ),
+            )
+        })
+        .collect();
+    push_table(&mut html, &[], &summaries);
+    if !report.events.is_empty() {
+        html.push_str("<table>\n<tr><th>event</th><th>start</th><th>duration (s)</th></tr>\n");
+        for event in &report.events {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td class=\"number\">{:.1}</td></tr>",
+                event.kind.as_str().replace('_', " "),
+                clock(event.start),
+                event.duration()
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    writer.write_all(html.as_bytes())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_value(value: Option<f64>) -> String {
+    match value {
+        Some(v) if v.is_finite() && v == v.trunc() && v.abs() < 1e9 => format!("{}", v),
+        Some(v) if v.is_finite() => format!("{:.3}", v),
+        _ => "–".to_string(),
+    }
+}
+
+// h:mm:ss from the start of the recording
+fn clock(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    format!("{}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+}
+
+fn push_table(html: &mut String, header: &[&str], rows: &[(String, String)]) {
+    html.push_str("<table>\n");
+    if !header.is_empty() {
+        html.push_str("<tr>");
+        for name in header {
+            let _ = write!(html, "<th>{}</th>", name);
+        }
+        html.push_str("</tr>\n");
+    }
+    for (name, value) in rows {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"number\">{}</td></tr>",
+            escape(name),
+            escape(value)
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+// about five round tick values covering `min..=max`, with their labels
+fn ticks(min: f64, max: f64) -> Vec<(f64, String)> {
+    let span = (max - min).max(f64::EPSILON);
+    let raw = span / 5.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|&step| step >= raw)
+        .unwrap_or(10.0 * magnitude);
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last)
+        .map(|k| {
+            let value = k as f64 * step;
+            (value, format!("{:.*}", decimals, value))
+        })
+        .collect()
+}
+
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+        (lo.min(v), hi.max(v))
+    });
+    if min == max {
+        (min - 1.0, max + 1.0)
+    } else {
+        (min, max)
+    }
+}
+
+// the axes of a chart of `width` × `height` with ticks and labels, returning the mapping
+// from data to SVG coordinates
+fn push_axes(
+    html: &mut String,
+    (width, height): (f64, f64),
+    x: (f64, f64),
+    y: (f64, f64),
+    labels: (&str, &str),
+) -> impl Fn(f64, f64) -> (f64, f64) {
+    let plot_width = width - 2.0 * MARGIN;
+    let plot_height = height - 2.0 * MARGIN;
+    let map = move |vx: f64, vy: f64| {
+        (
+            MARGIN + (vx - x.0) / (x.1 - x.0) * plot_width,
+            height - MARGIN - (vy - y.0) / (y.1 - y.0) * plot_height,
+        )
+    };
+    let _ = writeln!(
+        html,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n\
+         <rect x=\"{MARGIN}\" y=\"{MARGIN}\" width=\"{plot_width}\" height=\"{plot_height}\" \
+         fill=\"none\" stroke=\"#999\"/>"
+    );
+    for (tick, label) in ticks(x.0, x.1) {
+        let (px, _) = map(tick, y.0);
+        let _ = writeln!(
+            html,
+            "<text x=\"{px:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            height - MARGIN + 14.0,
+            label
+        );
+    }
+    for (tick, label) in ticks(y.0, y.1) {
+        let (_, py) = map(x.0, tick);
+        let _ = writeln!(
+            html,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            MARGIN - 4.0,
+            py + 4.0,
+            label
+        );
+    }
+    let _ = writeln!(
+        html,
+        "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n\
+         <text x=\"12\" y=\"{:.1}\" text-anchor=\"middle\" transform=\"rotate(-90 12 {:.1})\">{}</text>",
+        width / 2.0,
+        height - 8.0,
+        escape(labels.0),
+        height / 2.0,
+        height / 2.0,
+        escape(labels.1)
+    );
+    map
+}
+
+fn push_line_chart(html: &mut String, points: &[(f64, f64)], x_label: &str, y_label: &str) {
+    let x = bounds(points.iter().map(|p| p.0));
+    let y = bounds(points.iter().map(|p| p.1));
+    let map = push_axes(html, (CHART_WIDTH, CHART_HEIGHT), x, y, (x_label, y_label));
+    let path: Vec<String> = points
+        .iter()
+        .map(|&(vx, vy)| {
+            let (px, py) = map(vx, vy);
+            format!("{:.1},{:.1}", px, py)
+        })
+        .collect();
+    let _ = writeln!(
+        html,
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"#c0392b\" stroke-width=\"1.5\"/>\n</svg>",
+        path.join(" ")
+    );
+}
+
+fn push_scatter(html: &mut String, points: &[(f64, f64)], x_label: &str, y_label: &str) {
+    // both axes span the same range so the line of identity is the diagonal
+    let range = bounds(points.iter().flat_map(|p| [p.0, p.1]));
+    let map = push_axes(
+        html,
+        (SCATTER_SIZE, SCATTER_SIZE),
+        range,
+        range,
+        (x_label, y_label),
+    );
+    let (x0, y0) = map(range.0, range.0);
+    let (x1, y1) = map(range.1, range.1);
+    let _ = writeln!(
+        html,
+        "<line x1=\"{x0:.1}\" y1=\"{y0:.1}\" x2=\"{x1:.1}\" y2=\"{y1:.1}\" stroke=\"#bbb\"/>"
+    );
+    let stride = points.len().div_ceil(MAX_POINTS);
+    for &(vx, vy) in points.iter().step_by(stride) {
+        let (px, py) = map(vx, vy);
+        let _ = writeln!(
+            html,
+            "<circle cx=\"{px:.1}\" cy=\"{py:.1}\" r=\"1.5\" fill=\"#2c3e50\" fill-opacity=\"0.5\"/>"
+        );
+    }
+    html.push_str("</svg>\n");
+}
@@ -0,0 +1,405 @@
+use crate::compression::open_input;
+use crate::edf_utils::find_label;
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
+use std::error::Error;
+use std::io::{self, Read};
+use std::path::Path;
+use tracing::{info, warn};
+
+// start of every ISHNE Holter file, before the checksum and the fixed header
+const MAGIC: &[u8; 8] = b"ISHNE1.0";
+// the magic number, checksum and fixed header
+const FIXED_HEADER_BYTES: usize = 522;
+// lead specification codes 0 to 19 of the standard
+const LEAD_NAMES: [&str; 20] = [
+    "Unknown", "Bipolar", "X", "Y", "Z", "I", "II", "III", "aVR", "aVL", "aVF", "V1", "V2", "V3",
+    "V4", "V5", "V6", "ES", "AS", "AI",
+];
+
+/// The fixed header of an ISHNE Holter file (ISHNE standard output format 1.0) with its
+/// variable-length comment block.
+#[derive(Debug, Clone)]
+pub struct IshneHeader {
+    /// Samples per lead.
+    pub num_samples: usize,
+    pub file_version: i16,
+    pub first_name: String,
+    pub last_name: String,
+    pub id: String,
+    /// 0 unknown, 1 male, 2 female.
+    pub sex: i16,
+    pub race: i16,
+    /// Day, month and year.
+    pub birth_date: [i16; 3],
+    /// Day, month and year of the recording.
+    pub record_date: [i16; 3],
+    /// Day, month and year the file was made.
+    pub file_date: [i16; 3],
+    /// Hour, minute and second the recording started.
+    pub start_time: [i16; 3],
+    pub leads: Vec<IshneLead>,
+    /// 0 when there is no pacemaker, otherwise its type.
+    pub pacemaker: i16,
+    pub recorder: String,
+    pub sampling_frequency: f64,
+    pub proprietary: String,
+    pub copyright: String,
+    /// The variable-length block, free text such as the recording comments.
+    pub comments: String,
+}
+
+/// Specification of one lead of an ISHNE file.
+#[derive(Debug, Clone)]
+pub struct IshneLead {
+    /// Lead specification code (5 is lead I, 11 to 16 are V1 to V6, ...).
+    pub code: i16,
+    /// Lead quality code (1 no noise, 2 slight, 3 moderate, 4 severe, 5 unreadable, 0
+    /// unknown).
+    pub quality: i16,
+    /// Nanovolts per sample unit.
+    pub resolution: i16,
+}
+
+impl IshneLead {
+    /// Name of the lead after its specification code, e.g. `II` or `V5`.
+    pub fn name(&self) -> String {
+        usize::try_from(self.code)
+            .ok()
+            .and_then(|code| LEAD_NAMES.get(code))
+            .map_or_else(
+                || format!("Lead code {}", self.code),
+                |name| name.to_string(),
+            )
+    }
+
+    pub fn to_millivolts(&self, sample: i16) -> f64 {
+        sample as f64 * self.resolution as f64 * 1e-6
+    }
+}
+
+impl IshneHeader {
+    /// Index of the lead with the given name (`II`, `V5`, ...), ignoring case; the error
+    /// lists the leads there are.
+    pub fn find_lead(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let names: Vec<String> = self.leads.iter().map(IshneLead::name).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        find_label(&names, name)
+    }
+
+    /// Seconds after midnight of the start time, `None` when it is not given (-9).
+    pub fn start_clock(&self) -> Option<f64> {
+        let [hours, minutes, seconds] = self.start_time;
+        if hours < 0 || minutes < 0 || seconds < 0 {
+            return None;
+        }
+        Some(3600.0 * hours as f64 + 60.0 * minutes as f64 + seconds as f64)
+    }
+}
+
+// a little-endian field reader over the fixed header
+struct Fields<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Fields<'_> {
+    fn take(&mut self, len: usize) -> &[u8] {
+        let field = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        field
+    }
+
+    fn short(&mut self) -> i16 {
+        let field = self.take(2);
+        i16::from_le_bytes([field[0], field[1]])
+    }
+
+    fn shorts<const N: usize>(&mut self) -> [i16; N] {
+        std::array::from_fn(|_| self.short())
+    }
+
+    fn long(&mut self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let field = self.take(4);
+        let value = i32::from_le_bytes([field[0], field[1], field[2], field[3]]);
+        usize::try_from(value)
+            .map_err(|_| format!("Invalid ISHNE header field '{}': {}", name, value).into())
+    }
+
+    fn text(&mut self, len: usize) -> String {
+        let field = self.take(len);
+        let end = field.iter().position(|&byte| byte == 0).unwrap_or(len);
+        String::from_utf8_lossy(&field[..end]).trim().to_string()
+    }
+}
+
+// skips `len` bytes of the reader
+fn skip<R: Read>(reader: &mut R, len: usize) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(len as u64), &mut io::sink())?;
+    if skipped < len as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Reads the fixed header and the variable-length block, leaving the reader at the first
+/// sample. The header checksum is not verified.
+pub fn read_ishne_header<R: Read>(reader: &mut R) -> Result<IshneHeader, Box<dyn Error>> {
+    let mut bytes = vec![0u8; FIXED_HEADER_BYTES];
+    reader.read_exact(&mut bytes)?;
+    if &bytes[..8] != MAGIC {
+        return Err("Not an ISHNE Holter file (it does not start with ISHNE1.0)".into());
+    }
+
+    // the checksum takes bytes 8 and 9
+    let mut fields = Fields {
+        bytes: &bytes,
+        position: 10,
+    };
+    let var_block_size = fields.long("size of the variable-length block")?;
+    let num_samples = fields.long("number of samples")?;
+    let var_block_offset = fields.long("offset of the variable-length block")?;
+    let ecg_offset = fields.long("offset of the ECG block")?;
+    let file_version = fields.short();
+    let first_name = fields.text(40);
+    let last_name = fields.text(40);
+    let id = fields.text(20);
+    let sex = fields.short();
+    let race = fields.short();
+    let birth_date = fields.shorts();
+    let record_date = fields.shorts();
+    let file_date = fields.shorts();
+    let start_time = fields.shorts();
+    let num_leads = fields.short();
+    let codes: [i16; 12] = fields.shorts();
+    let qualities: [i16; 12] = fields.shorts();
+    let resolutions: [i16; 12] = fields.shorts();
+    let pacemaker = fields.short();
+    let recorder = fields.text(40);
+    let sampling_rate = fields.short();
+    let proprietary = fields.text(80);
+    let copyright = fields.text(80);
+
+    if !(1..=12).contains(&num_leads) {
+        return Err(format!("Invalid number of ISHNE leads: {}", num_leads).into());
+    }
+    if sampling_rate <= 0 {
+        return Err(format!("Invalid ISHNE sampling rate: {}", sampling_rate).into());
+    }
+    if var_block_offset < FIXED_HEADER_BYTES || ecg_offset < var_block_offset + var_block_size {
+        return Err(format!(
+            "Invalid ISHNE block offsets: variable-length block at {} ({} bytes), ECG at {}",
+            var_block_offset, var_block_size, ecg_offset
+        )
+        .into());
+    }
+    let leads = (0..num_leads as usize)
+        .map(|i| IshneLead {
+            code: codes[i],
+            quality: qualities[i],
+            resolution: resolutions[i],
+        })
+        .collect();
+
+    skip(reader, var_block_offset - FIXED_HEADER_BYTES)?;
+    let mut comments = vec![0u8; var_block_size];
+    reader.read_exact(&mut comments)?;
+    let end = comments
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(var_block_size);
+    let comments = String::from_utf8_lossy(&comments[..end]).trim().to_string();
+    skip(reader, ecg_offset - var_block_offset - var_block_size)?;
+
+    Ok(IshneHeader {
+        num_samples,
+        file_version,
+        first_name,
+        last_name,
+        id,
+        sex,
+        race,
+        birth_date,
+        record_date,
+        file_date,
+        start_time,
+        leads,
+        pacemaker,
+        recorder,
+        sampling_frequency: sampling_rate as f64,
+        proprietary,
+        copyright,
+        comments,
+    })
+}
+
+// reads the interleaved samples after the header, every lead in millivolts
+fn read_ishne_samples<R: Read>(
+    reader: &mut R,
+    header: &IshneHeader,
+) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let num_leads = header.leads.len();
+    let mut bytes = Vec::with_capacity(header.num_samples * num_leads * 2);
+    reader.read_to_end(&mut bytes)?;
+    let frames = bytes.len() / (num_leads * 2);
+    if frames < header.num_samples {
+        warn!(
+            "The ISHNE file holds {} of the {} samples its header gives",
+            frames, header.num_samples
+        );
+    }
+
+    let mut leads = vec![Vec::with_capacity(frames); num_leads];
+    for frame in bytes.chunks_exact(num_leads * 2).take(header.num_samples) {
+        for ((sample, lead), values) in frame.chunks_exact(2).zip(&header.leads).zip(&mut leads) {
+            values.push(lead.to_millivolts(i16::from_le_bytes([sample[0], sample[1]])));
+        }
+    }
+    Ok(leads)
+}
+
+/// Reads one lead of an ISHNE Holter file (.ecg) in millivolts, with times derived from the
+/// sampling rate.
+pub fn read_ishne_ecg_data<P: AsRef<Path>>(
+    path: P,
+    lead: usize,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let mut reader = open_input(path)?;
+
+    let header = read_ishne_header(&mut reader)?;
+    if lead >= header.leads.len() {
+        return Err(format!(
+            "Lead index {} out of range, the file has {} leads",
+            lead,
+            header.leads.len()
+        )
+        .into());
+    }
+    let leads = read_ishne_samples(&mut reader, &header)?;
+    let fs = header.sampling_frequency;
+
+    let data: Vec<EcgPoint> = leads[lead]
+        .iter()
+        .enumerate()
+        .map(|(i, &voltage)| EcgPoint {
+            time: i as f64 / fs,
+            voltage,
+        })
+        .collect();
+
+    info!("Total data points: {}", data.len());
+
+    Ok(data)
+}
+
+/// Reads every lead of an ISHNE Holter file, named after their specification codes.
+pub fn read_ishne_leads<P: AsRef<Path>>(path: P) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    let mut reader = open_input(path)?;
+
+    let header = read_ishne_header(&mut reader)?;
+    let leads = read_ishne_samples(&mut reader, &header)?;
+    let fs = header.sampling_frequency;
+    let num_samples = leads.first().map_or(0, Vec::len);
+    let ecg = MultiLeadEcg {
+        times: (0..num_samples).map(|i| i as f64 / fs).collect(),
+        leads: header
+            .leads
+            .iter()
+            .zip(leads)
+            .map(|(lead, voltage)| Lead {
+                name: lead.name(),
+                voltage,
+            })
+            .collect(),
+    };
+
+    info!(
+        "Total data points: {} in {} leads",
+        ecg.times.len(),
+        ecg.leads.len()
+    );
+
+    Ok(ecg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // a two-lead file (II and V5, 2.5 µV per unit) at 200 Hz with a 16-byte comment block
+    fn ishne_file(frames: &[[i16; 2]]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        let shorts = |bytes: &mut Vec<u8>, values: &[i16]| {
+            for value in values {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+        let text = |bytes: &mut Vec<u8>, value: &str, len: usize| {
+            let start = bytes.len();
+            bytes.extend_from_slice(value.as_bytes());
+            bytes.resize(start + len, 0);
+        };
+        shorts(&mut bytes, &[0]); // checksum
+        for long in [16, frames.len() as i32, 522, 538] {
+            bytes.extend_from_slice(&long.to_le_bytes());
+        }
+        shorts(&mut bytes, &[1]);
+        text(&mut bytes, "Jan", 40);
+        text(&mut bytes, "Kowalski", 40);
+        text(&mut bytes, "P-17", 20);
+        shorts(&mut bytes, &[1, 1]);
+        shorts(&mut bytes, &[3, 4, 1950, 5, 6, 2020, 5, 6, 2020, 8, 30, 0]);
+        shorts(&mut bytes, &[2]);
+        let mut codes = [-9i16; 12];
+        codes[..2].copy_from_slice(&[6, 15]);
+        shorts(&mut bytes, &codes);
+        shorts(&mut bytes, &[1; 12]);
+        shorts(&mut bytes, &[2500; 12]);
+        shorts(&mut bytes, &[0]);
+        text(&mut bytes, "Holter 3000", 40);
+        shorts(&mut bytes, &[200]);
+        text(&mut bytes, "", 80);
+        text(&mut bytes, "", 80);
+        bytes.resize(FIXED_HEADER_BYTES, 0);
+        text(&mut bytes, "resting", 16);
+        for frame in frames {
+            shorts(&mut bytes, frame);
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_a_hand_built_file() {
+        let bytes = ishne_file(&[[400, -400], [0, 800], [-800, 4]]);
+        let mut reader = bytes.as_slice();
+        let header = read_ishne_header(&mut reader).unwrap();
+        assert_eq!(reader.len(), 3 * 2 * 2);
+        assert_eq!(header.num_samples, 3);
+        assert_eq!(header.last_name, "Kowalski");
+        assert_eq!(header.birth_date, [3, 4, 1950]);
+        assert_eq!(header.start_clock(), Some(30_600.0));
+        assert_eq!(header.recorder, "Holter 3000");
+        assert_eq!(header.comments, "resting");
+        assert_eq!(header.find_lead("v5").unwrap(), 1);
+
+        let path = std::env::temp_dir().join(format!("signalweaver_{}.ecg", std::process::id()));
+        fs::write(&path, &bytes).unwrap();
+        let ecg = read_ishne_leads(&path);
+        fs::remove_file(&path).unwrap();
+
+        let ecg = ecg.unwrap();
+        assert_eq!(ecg.times, vec![0.0, 0.005, 0.01]);
+        assert_eq!(ecg.leads[0].name, "II");
+        assert_eq!(ecg.leads[0].voltage, vec![1.0, 0.0, -2.0]);
+        assert_eq!(ecg.leads[1].name, "V5");
+        assert_eq!(ecg.leads[1].voltage, vec![-1.0, 2.0, 0.01]);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = ishne_file(&[]);
+        bytes[..8].copy_from_slice(b"ISHNE2.0");
+        assert!(read_ishne_header(&mut bytes.as_slice()).is_err());
+        assert!(read_ishne_header(&mut &b"ISHNE1.0"[..]).is_err());
+    }
+}
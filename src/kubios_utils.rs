@@ -0,0 +1,31 @@
+use crate::datetime::iso_datetime;
+use crate::models::RrInterval;
+use std::io::{self, Write};
+
+/// Writes RR intervals as a text file Kubios HRV imports: header lines with the recording's
+/// start date and time (`start` in seconds since 1970-01-01, `unknown` when `None`), then a
+/// tab-separated row per interval with the time of its closing beat and the interval, both
+/// in seconds. Kubios skips the header lines and reads the two columns as time and RR.
+pub fn write_kubios_rr<W: Write>(
+    writer: &mut W,
+    intervals: &[RrInterval],
+    start: Option<f64>,
+) -> io::Result<()> {
+    writeln!(writer, "RR interval data")?;
+    match start.map(iso_datetime) {
+        Some(datetime) => {
+            let (date, time) = datetime.split_once('T').unwrap_or((&datetime, ""));
+            writeln!(writer, "Start date: {}", date)?;
+            writeln!(writer, "Start time: {}", time)?;
+        }
+        None => {
+            writeln!(writer, "Start date: unknown")?;
+            writeln!(writer, "Start time: unknown")?;
+        }
+    }
+    writeln!(writer, "Time (s)\tRR (s)")?;
+    for rr in intervals {
+        writeln!(writer, "{:.3}\t{:.3}", rr.time, rr.rr_ms / 1000.0)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+pub mod aecg_utils;
+pub mod apple_health_utils;
+#[cfg(feature = "parquet")]
+pub mod arrow_utils;
+pub mod cancel;
+pub mod classification;
+pub mod compression;
+pub mod config;
+pub mod csv_utils;
+pub mod datetime;
+pub mod delineation;
+pub mod detection;
+pub mod detectors;
+pub mod dicom_utils;
+pub mod edf_utils;
+pub mod editing;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fhir_utils;
+pub mod filters;
+pub mod fit_utils;
+#[cfg(feature = "hdf5")]
+pub mod hdf5_utils;
+pub mod hrv;
+pub mod html_report;
+pub mod ishne_utils;
+pub mod kubios_utils;
+pub mod models;
+pub mod plot;
+pub mod polar_utils;
+pub mod polarity;
+pub mod progress;
+pub mod provenance;
+#[cfg(feature = "python")]
+mod python;
+pub mod qt;
+pub mod quality;
+pub mod resample;
+pub mod respiration;
+pub mod rhythm;
+pub mod rr;
+pub mod saecg;
+pub mod sampling;
+pub mod scp_utils;
+pub mod spectrum;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_utils;
+pub mod st;
+pub mod streaming;
+pub mod template;
+pub mod twa;
+pub mod validation;
+#[cfg(feature = "tui")]
+pub mod view;
+#[cfg(feature = "wasm")]
+mod wasm;
+pub mod wfdb_utils;
+
+pub use cancel::CancelToken;
+pub use csv_utils::{
+    read_beat_times, read_ecg_chunks, read_ecg_column, read_ecg_csv, read_ecg_data, read_ecg_leads,
+    read_rr_csv, BadRows, CsvColumn, CsvLayout, CsvOptions, CsvTime, RrUnit,
+};
+pub use detection::{
+    detect_beats, detect_beats_multi_lead, detect_qrs_complexes, detect_qrs_complexes_with,
+    detect_with_options, DetectionOptions,
+};
+pub use detectors::{DetectorConfig, DetectorRegistry, QrsDetector};
+pub use models::{Detection, EcgPoint, Lead, MultiLeadEcg, RrInterval};
+pub use progress::Progress;
+pub use resample::resample;
+pub use rr::{rr_intervals, rr_intervals_without_gaps};
+pub use sampling::{estimate_sampling, find_gaps, Gap, SamplingEstimate};
+pub use streaming::StreamingDetector;
+pub use validation::{match_beats, validate, BeatMatch, ValidationResult};
@@ -1,237 +1,4384 @@
-use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::ffi::OsString;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::SystemTime;
 
-mod csv_utils;
-mod edf_utils;
-mod models;
-use csv_utils::read_ecg_data;
-use models::EcgPoint;
-use std::io::{self, Write};
-// basic structure to hold our ECG data points
+use clap::{
+    ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use signalweaver::aecg_utils;
+use signalweaver::apple_health_utils;
+#[cfg(feature = "parquet")]
+use signalweaver::arrow_utils;
+use signalweaver::classification::{beat_features, classify_beats, BeatClass};
+use signalweaver::compression;
+use signalweaver::config::{self, ConfigValue};
+use signalweaver::datetime;
+use signalweaver::delineation::{self, BeatWaves, Wave};
+use signalweaver::detectors::EnsembleDetector;
+use signalweaver::dicom_utils;
+use signalweaver::edf_utils::{self, EdfAnnotation, EdfHeader};
+use signalweaver::editing;
+use signalweaver::fhir_utils;
+use signalweaver::filters::FilterConfig;
+use signalweaver::fit_utils;
+#[cfg(feature = "hdf5")]
+use signalweaver::hdf5_utils;
+use signalweaver::hrv::{
+    self, ClockPeriod, EntropyOptions, HrvMetrics, Metric, PeriodDelta, PeriodHrv, RrEdit,
+    RrFilter, SpectralMethod,
+};
+use signalweaver::html_report::{self, ReportData};
+use signalweaver::ishne_utils;
+use signalweaver::kubios_utils;
+use signalweaver::plot::{self, Marker, Strip};
+use signalweaver::polar_utils;
+use signalweaver::polarity::{self, Polarity};
+use signalweaver::provenance::{self, InputFile, Provenance};
+use signalweaver::qt::{self, QtBeat};
+use signalweaver::quality::{
+    find_artifacts, segment_quality, ArtifactKind, QualityLabel, SegmentQuality,
+};
+use signalweaver::resample::{self, resample_points};
+use signalweaver::respiration::{self, BreathingRate, EdrOptions};
+use signalweaver::rhythm::{
+    self, AfOptions, EctopyCounts, EventKind, EventSummary, RateLimits, RhythmEvent,
+};
+use signalweaver::rr::beat_times_from_rr;
+use signalweaver::saecg::{self, AveragingOptions, SignalAverage};
+use signalweaver::sampling;
+use signalweaver::scp_utils;
+#[cfg(feature = "sqlite")]
+use signalweaver::sqlite_utils;
+use signalweaver::st::{self, StBeat, StEpisode, StOptions};
+use signalweaver::twa::{self, TwaOptions, TwaWindow};
+#[cfg(feature = "tui")]
+use signalweaver::view;
+use signalweaver::wfdb_utils;
+use signalweaver::{
+    detect_beats, detect_beats_multi_lead, estimate_sampling, find_gaps, match_beats,
+    read_beat_times, read_ecg_chunks, read_ecg_csv, read_ecg_leads, read_rr_csv,
+    rr_intervals_without_gaps, validate, BadRows, BeatMatch, CsvColumn, CsvOptions, Detection,
+    DetectionOptions, DetectorConfig, DetectorRegistry, EcgPoint, Gap, Lead, MultiLeadEcg,
+    Progress, QrsDetector, RrInterval, RrUnit, StreamingDetector,
+};
+use std::io::{self, IsTerminal, Write};
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, info, warn};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+#[derive(Parser)]
+#[command(
+    name = "signalweaver",
+    version,
+    about = "QRS detection for ECG recordings"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Settings file, ./signalweaver.toml when it exists; options given on the command line
+    /// override it
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Do not write the provenance of the results next to them
+    #[arg(long, global = true)]
+    no_provenance: bool,
+
+    /// More log messages: -v adds debugging messages, -vv tracing
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Fewer log messages: -q leaves warnings, -qq errors
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    quiet: u8,
+
+    /// Format of the log messages on stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Do not show progress bars (shown only when stderr is a terminal)
+    #[arg(long, global = true)]
+    no_progress: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum LogFormat {
+    Text,
+    /// One JSON object per message
+    Json,
+}
+
+// read from the working directory when --config is not given
+const CONFIG_FILE: &str = "signalweaver.toml";
+
+// whether long steps draw a progress bar on stderr, set once the arguments are parsed
+static SHOW_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+// parsed once at startup, so the size of the detect arguments does not matter
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    /// Detect QRS complexes in an ECG recording
+    Detect(DetectArgs),
+    /// Compute HRV and heart rate asymmetry from the detected RR intervals
+    Hrv(HrvArgs),
+    /// Tabulate monotonic runs of accelerations and decelerations as CSV
+    Runs(RunsArgs),
+    /// Find suspected atrial fibrillation, brady- and tachycardia, pauses, asystole and
+    /// ventricular ectopy patterns
+    Rhythm(RhythmArgs),
+    /// Write a self-contained HTML report of beats, heart rate, HRV and rhythm events
+    Report(ReportArgs),
+    /// Draw a stretch of the ECG with the detected beats as SVG or PNG
+    Plot(PlotArgs),
+    /// Browse the ECG with the detected beats in the terminal
+    View(ViewArgs),
+    /// Add, remove and move beats of a positions file with an edit script
+    Edit(EditArgs),
+    /// Compare the beats of two detection or annotation files
+    Diff(DiffArgs),
+    /// Compare detected QRS positions with reference annotations
+    Validate(ValidateArgs),
+    /// Detect QRS complexes in many recordings, writing a summary CSV
+    Batch(BatchArgs),
+    /// List the events of the annotation signals of an EDF+ file as CSV
+    Annotations(AnnotationsArgs),
+    /// Extract EDF or BDF signals to CSV, or package a CSV recording as EDF
+    Convert(ConvertArgs),
+    /// Copy an EDF or BDF file with the patient and recording identification removed
+    Anonymize(AnonymizeArgs),
+    /// Print the header and signals of an EDF or BDF file
+    EdfInfo {
+        /// EDF file to inspect
+        path: PathBuf,
+
+        /// Also print every field and sample the EDF reader finds
+        #[arg(long)]
+        dump: bool,
+    },
+}
+
+// input and detector settings shared by every subcommand that runs detection
+#[derive(Args, Clone)]
+struct InputArgs {
+    /// ECG recording (CSV with time and voltage columns, EDF, a WFDB .hea header, an ISHNE
+    /// Holter .ecg file, an SCP-ECG .scp file, HL7 aECG XML or a DICOM waveform .dcm file);
+    /// `-` reads CSV or EDF from stdin
+    input: PathBuf,
+
+    /// Format of the input file (guessed from the extension when omitted)
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// What the input holds: an ECG, or a text or CSV list of RR intervals analysed without
+    /// QRS detection
+    #[arg(long, value_enum, default_value = "ecg")]
+    input_kind: InputKind,
+
+    /// Unit of the RR intervals of `--input-kind rr`: ms or s (from the header unit, or
+    /// seconds when the median interval is below 10, when omitted)
+    #[arg(long)]
+    rr_unit: Option<RrUnit>,
+
+    /// CSV voltage column to analyse, by header name or 0-based index (found from the
+    /// header names when omitted)
+    #[arg(long)]
+    column: Option<CsvColumn>,
+
+    /// CSV time column, by header name or 0-based index (found from the header names when
+    /// omitted)
+    #[arg(long)]
+    time_column: Option<CsvColumn>,
+
+    /// The CSV holds voltage columns only; times are derived from --fs
+    #[arg(long, requires = "fs")]
+    no_time_column: bool,
+
+    /// CSV field separator, e.g. `;` or `tab`
+    #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+    delimiter: char,
+
+    /// CSV numbers use a decimal comma (`0,002;1,234`)
+    #[arg(long)]
+    decimal_comma: bool,
+
+    /// The CSV has no header line
+    #[arg(long)]
+    no_header: bool,
+
+    /// Rows with unparsable, NaN or infinite numbers: fail (naming the line), skip, or
+    /// impute the previous voltage
+    #[arg(long, default_value = "fail")]
+    bad_rows: BadRows,
+
+    /// Index of the signal to analyse in an EDF, WFDB, ISHNE, SCP-ECG, aECG or DICOM
+    /// recording
+    #[arg(long, default_value_t = 0)]
+    signal_index: usize,
+
+    /// Label of the EDF signal (e.g. "ECG II"), description of the WFDB signal (e.g. MLII)
+    /// or name of the ISHNE, SCP-ECG, aECG or DICOM lead (e.g. V5) to analyse, ignoring case
+    #[arg(long, conflicts_with = "signal_index")]
+    signal: Option<String>,
+
+    /// Sampling frequency in Hz (estimated from the median interval of the time column when
+    /// omitted)
+    #[arg(long)]
+    fs: Option<f64>,
+
+    /// Place every beat on the dominant QRS deflection of its segment (R waves, or S waves in
+    /// leads with negative complexes) instead of the largest absolute deflection; not
+    /// supported with --chunk-size
+    #[arg(long, conflicts_with = "chunk_size")]
+    align_polarity: bool,
+
+    /// Reject detections whose QRS complex correlates with the running average template
+    /// below this coefficient (e.g. 0.8), removing T waves and noise spikes; not supported
+    /// with --chunk-size
+    #[arg(long)]
+    template_threshold: Option<f64>,
+
+    /// Drop beats whose confidence (0 to 1: amplitude relative to the typical beat, share of
+    /// agreeing detectors or leads, times the template correlation) is below this
+    #[arg(long)]
+    min_confidence: Option<f64>,
+
+    /// Classify every beat as normal, ventricular (PVC) or artifact from its QRS width,
+    /// amplitude, area and prematurity, adding a class column to the output and the WFDB
+    /// annotations; not supported with --fuse-leads or --chunk-size
+    #[arg(long)]
+    classify: bool,
+
+    /// Length of the windows whose signal quality is assessed, in seconds
+    #[arg(long, default_value_t = 10.0)]
+    quality_window: f64,
+
+    /// Drop the beats in windows of unusable signal quality (flat, clipped or noise) and
+    /// leave the RR intervals across them out of the RR output and HRV; single-lead input
+    /// only
+    #[arg(long)]
+    exclude_unusable: bool,
+
+    /// Resample the recording to this rate in Hz before detection, e.g. 977 Hz recordings
+    /// to 250 Hz; sample indices in the output then refer to the new rate
+    #[arg(long)]
+    resample: Option<f64>,
+
+    /// QRS detection algorithm
+    #[arg(long, default_value = "threshold")]
+    detector: String,
+
+    /// Detectors fused by `--detector ensemble` (default: all built-in detectors)
+    #[arg(long, value_delimiter = ',')]
+    ensemble: Option<Vec<String>>,
+
+    /// Detectors that must agree on an ensemble beat (default: a majority)
+    #[arg(long)]
+    min_votes: Option<usize>,
+
+    /// Length of the independently processed segments, in seconds
+    #[arg(long, default_value_t = 30.0)]
+    segment_length: f64,
+
+    /// Context shared by neighbouring segments, in seconds
+    #[arg(long, default_value_t = 2.0)]
+    segment_overlap: f64,
+
+    /// Shortest distance between beats of the threshold detector, in seconds
+    #[arg(long, default_value_t = 0.2)]
+    min_peak_distance: f64,
+
+    /// Threshold detector beats also keep this fraction of the running average RR interval
+    /// apart, so the refractory period shortens in tachycardia; 0 for --min-peak-distance
+    /// alone
+    #[arg(long, default_value_t = 0.4)]
+    refractory_fraction: f64,
+
+    /// Weight of each beat in the running average RR interval of --refractory-fraction
+    #[arg(long, default_value_t = 0.125)]
+    refractory_adaptation: f64,
+
+    /// Threshold detector candidates are the extremum of this many seconds on either side
+    #[arg(long, default_value_t = 0.15)]
+    search_window: f64,
+
+    /// Threshold detector beats move to the largest deflection within this many seconds
+    #[arg(long, default_value_t = 0.08)]
+    refinement_window: f64,
+
+    /// Threshold detector candidates exceed this many standard deviations of the signal;
+    /// with adaptation, the beats above it start the running average amplitude
+    #[arg(long, default_value_t = 2.0)]
+    threshold_factor: f64,
+
+    /// Weight of each beat in the running average amplitude the threshold detector follows
+    /// through amplitude changes (posture shifts); 0 for a fixed threshold per segment
+    #[arg(long, default_value_t = 0.125)]
+    threshold_adaptation: f64,
+
+    /// With adaptation, threshold detector candidates exceed this fraction of the running
+    /// average beat amplitude
+    #[arg(long, default_value_t = 0.5)]
+    peak_fraction: f64,
+
+    /// Seconds at the start of each segment over which the threshold detector learns the
+    /// beat amplitude before committing beats
+    #[arg(long, default_value_t = 2.0)]
+    warm_up: f64,
+
+    /// Drop the beats in the warm-up instead of detecting them again with the learnt
+    /// levels (only the start of the recording when the segment overlap is as long)
+    #[arg(long)]
+    skip_warm_up: bool,
+
+    /// Beats closer than this to the previous one, in seconds, are dropped as duplicates
+    /// from segment overlaps
+    #[arg(long, default_value_t = 0.2)]
+    dedup_interval: f64,
+
+    /// Detect on every lead (CSV or EDF) and keep the beats confirmed by several leads
+    #[arg(long)]
+    fuse_leads: bool,
+
+    /// Leads that must agree on a fused beat (default: a majority)
+    #[arg(long)]
+    min_leads: Option<usize>,
+
+    /// Largest distance between the detections of one beat in different leads, in seconds
+    #[arg(long, default_value_t = 0.05)]
+    lead_tolerance: f64,
+
+    /// Read CSV or EDF input in blocks of this many samples (whole data records for EDF)
+    /// and detect incrementally, keeping memory bounded on long recordings (filtering is
+    /// then causal only)
+    #[arg(long)]
+    chunk_size: Option<usize>,
+
+    /// High-pass cutoff in Hz removing baseline wander
+    #[arg(long, default_value_t = 0.5)]
+    highpass: f64,
+
+    /// Low-pass cutoff in Hz removing muscle noise
+    #[arg(long, default_value_t = 40.0)]
+    lowpass: f64,
+
+    /// Powerline frequency to notch out (50 or 60 Hz)
+    #[arg(long)]
+    notch: Option<f64>,
+
+    /// Filter forwards and backwards so R-peak positions are not delayed
+    #[arg(long)]
+    zero_phase: bool,
+
+    /// Run detection on the unfiltered signal
+    #[arg(long)]
+    no_filter: bool,
+}
+
+// seconds from a number with an optional ms, s, m or h unit
+fn parse_seconds(value: &str) -> Result<f64, String> {
+    let (number, scale) = [("ms", 0.001), ("s", 1.0), ("m", 60.0), ("h", 3600.0)]
+        .iter()
+        .find_map(|&(unit, scale)| Some((value.strip_suffix(unit)?, scale)))
+        .unwrap_or((value, 1.0));
+    match number.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(number * scale),
+        _ => Err(format!(
+            "expected a time such as 120s, 2m or 1h, got {:?}",
+            value
+        )),
+    }
+}
+
+fn parse_start_time(value: &str) -> Result<f64, String> {
+    hrv::parse_clock(value).ok_or_else(|| format!("expected HH:MM or HH:MM:SS, got {:?}", value))
+}
+
+fn parse_datetime(value: &str) -> Result<f64, String> {
+    datetime::parse_iso_datetime(value)
+        .ok_or_else(|| format!("expected YYYY-MM-DDTHH:MM:SS, got {:?}", value))
+}
+
+fn parse_utc_offset(value: &str) -> Result<String, String> {
+    let valid = value == "Z"
+        || (value.len() == 6
+            && value.starts_with(['+', '-'])
+            && value.as_bytes()[3] == b':'
+            && value[1..3].parse::<u8>().is_ok_and(|hours| hours <= 14)
+            && value[4..6].parse::<u8>().is_ok_and(|minutes| minutes < 60));
+    if valid {
+        Ok(value.to_string())
+    } else {
+        Err(format!("expected Z, +HH:MM or -HH:MM, got {:?}", value))
+    }
+}
+
+fn parse_sqlite_url(value: &str) -> Result<PathBuf, String> {
+    match value.strip_prefix("sqlite://") {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Err(format!(
+            "expected sqlite://<database file>, got {:?}",
+            value
+        )),
+    }
+}
+
+fn parse_delimiter(value: &str) -> Result<char, String> {
+    match value {
+        "tab" | "\\t" => Ok('\t'),
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!(
+                    "expected a single character or `tab`, got {:?}",
+                    value
+                )),
+            }
+        }
+    }
+}
+
+impl InputArgs {
+    // whether the input holds RR intervals rather than an ECG, see `read_rr_input`
+    fn holds_rr_intervals(&self) -> bool {
+        let input_format = self
+            .input_format
+            .unwrap_or_else(|| InputFormat::from_path(&self.input));
+        self.input_kind == InputKind::Rr
+            || matches!(
+                input_format,
+                InputFormat::PolarHrm | InputFormat::PolarTxt | InputFormat::Fit
+            )
+    }
+
+    fn csv_options(&self) -> CsvOptions {
+        CsvOptions {
+            delimiter: self.delimiter,
+            decimal_comma: self.decimal_comma,
+            has_header: !self.no_header,
+            time_column: self.time_column.clone(),
+            column: self.column.clone(),
+            voltage_only: self.no_time_column,
+            sampling_frequency: self.fs,
+            bad_rows: self.bad_rows,
+        }
+    }
+
+    fn detector_config(&self) -> DetectorConfig {
+        DetectorConfig {
+            min_peak_distance: self.min_peak_distance,
+            refractory_fraction: self.refractory_fraction,
+            refractory_adaptation: self.refractory_adaptation,
+            search_window: self.search_window,
+            refinement_window: self.refinement_window,
+            threshold_factor: self.threshold_factor,
+            adaptation: self.threshold_adaptation,
+            peak_fraction: self.peak_fraction,
+            warm_up: self.warm_up,
+            reprocess_warm_up: !self.skip_warm_up,
+            dedup_interval: self.dedup_interval,
+        }
+    }
+
+    fn filter(&self) -> FilterConfig {
+        if self.no_filter {
+            return FilterConfig::default();
+        }
+        FilterConfig {
+            highpass: Some(self.highpass),
+            lowpass: Some(self.lowpass),
+            notch: self.notch,
+            zero_phase: self.zero_phase,
+        }
+    }
+}
+
+#[derive(Args)]
+struct DetectArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Where to write the detected QRS positions (`-` for stdout)
+    #[arg(short, long, default_value = "positions.txt")]
+    output: PathBuf,
+
+    /// What to write: QRS times, RR intervals, or both
+    #[arg(long, value_enum, default_value_t = Emit::Qrs)]
+    emit: Emit,
+
+    /// RR output file for `--emit both` (default: output name with an `_rr` suffix)
+    #[arg(long)]
+    rr_output: Option<PathBuf>,
+
+    /// Layout of the output file
+    #[arg(long, visible_alias = "output-format", value_enum, default_value_t = OutputFormat::Txt)]
+    format: OutputFormat,
+
+    /// Add the sample index and amplitude of every beat to the txt (tab-separated) and csv
+    /// layouts; sample indices refer to the --resample rate when resampling
+    #[arg(long)]
+    samples: bool,
+
+    /// Add the date and time of every beat (ISO 8601, the EDF start date and time plus the
+    /// beat time) to the csv and JSON layouts; txt lists them instead of seconds. EDF only
+    #[arg(long)]
+    absolute_times: bool,
+
+    /// Detect on every lead of a multi-lead CSV or EDF, writing one output per lead named
+    /// with the lead as a suffix
+    #[arg(long)]
+    all_leads: bool,
+
+    /// Also write the beats as a WFDB annotation file (e.g. 100.qrs), for `bxb` and WFDB
+    /// viewers
+    #[arg(long)]
+    annotations: Option<PathBuf>,
+
+    /// Also write the RR intervals as a Kubios HRV text file, with the recording's start date
+    /// and time in its header
+    #[arg(long)]
+    kubios_output: Option<PathBuf>,
+
+    /// Start date and time of the recording for the Kubios header (YYYY-MM-DDTHH:MM:SS),
+    /// read from the EDF header when omitted
+    #[arg(long, requires = "kubios_output", value_parser = parse_datetime)]
+    recording_start: Option<f64>,
+
+    /// Also write the raw and filtered signal, the beats, RR intervals and the 5-minute HRV
+    /// trend to one HDF5 file, for MATLAB and h5py (needs the `hdf5` feature)
+    #[arg(long)]
+    hdf5_output: Option<PathBuf>,
+
+    /// Also write a copy of the EDF input with the beats (labelled with their classes under
+    /// --classify, `R` otherwise) as an EDF+ annotation signal, for EDFbrowser
+    #[arg(long)]
+    edf_output: Option<PathBuf>,
+
+    /// Also write the gaps in the recording's time column as CSV (start, end, duration)
+    #[arg(long)]
+    gaps_output: Option<PathBuf>,
+
+    /// Also write the signal quality indices and label of each quality window as CSV
+    #[arg(long)]
+    quality_output: Option<PathBuf>,
+
+    /// Also write the flatline, saturation and lead-off stretches as CSV (start, end,
+    /// duration, kind), for masking in downstream analysis
+    #[arg(long)]
+    artifacts_output: Option<PathBuf>,
+
+    /// Also write the onset, peak and offset of the P, QRS and T waves of every beat, with
+    /// the PR, QRS and QT intervals, as CSV; single-lead detection only
+    #[arg(long)]
+    waves_output: Option<PathBuf>,
+
+    /// Also write the QT interval and its Bazett, Fridericia and Framingham corrections of
+    /// every beat as CSV, and report their mean, maximum and beats over 500 ms; single-lead
+    /// detection only
+    #[arg(long)]
+    qt_output: Option<PathBuf>,
+
+    /// Write the QT summary statistics to this JSON file
+    #[arg(long, requires = "qt_output")]
+    qt_summary: Option<PathBuf>,
+
+    /// Also write the ST level of every beat (at J, J+60 and J+80 ms, relative to the PQ
+    /// segment) as CSV, one file per lead with --all-leads; single-lead detection only
+    #[arg(long)]
+    st_output: Option<PathBuf>,
+
+    /// Also write the ST elevation and depression episodes as CSV
+    #[arg(long)]
+    st_episodes_output: Option<PathBuf>,
+
+    /// ST deviation at J+80 ms making an episode, in signal units (0.1 mV)
+    #[arg(long, default_value_t = 0.1)]
+    st_threshold: f64,
+
+    /// Shortest ST episode reported, in seconds
+    #[arg(long, default_value_t = 60.0)]
+    st_min_duration: f64,
+
+    /// Also write the signal-averaged complex and its 40–250 Hz filtered magnitude as CSV,
+    /// and report the filtered QRS duration, RMS40 and LAS40; single-lead detection only
+    #[arg(long)]
+    saecg_output: Option<PathBuf>,
+
+    /// Write the late potential measures to this JSON file
+    #[arg(long, requires = "saecg_output")]
+    saecg_summary: Option<PathBuf>,
+
+    /// Beats correlating less with the QRS template are left out of the signal average
+    #[arg(long, default_value_t = 0.95)]
+    saecg_min_correlation: f64,
+
+    /// Also write the T-wave alternans voltage and k score of every window of 128 beats as
+    /// CSV, and report the episodes of significant alternans; single-lead detection only
+    #[arg(long)]
+    twa_output: Option<PathBuf>,
+
+    /// Also write the breathing rate derived from the R-peak amplitudes and RR intervals
+    /// over 60 s windows as CSV; single-lead detection only
+    #[arg(long)]
+    edr_output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct HrvArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Where to write the HRV results (stdout when omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Layout of the HRV results
+    #[arg(long, value_enum, default_value_t = HrvFormat::Json)]
+    format: HrvFormat,
+
+    /// Also export the Poincaré (RRn, RRn+1) point cloud to this CSV file
+    #[arg(long)]
+    poincare_output: Option<PathBuf>,
+
+    /// Spectral estimator for the frequency-domain measures
+    #[arg(long, value_enum, default_value_t = Spectral::Welch)]
+    spectral: Spectral,
+
+    /// Clean the RR series of ectopic beats and artifacts before computing HRV
+    #[arg(long, value_enum)]
+    rr_filter: Option<RrFilterKind>,
+
+    /// Tolerance of the percentage filter in percent (default 20), or the tail fraction of
+    /// the quantile filter (default 0.01)
+    #[arg(long)]
+    rr_filter_threshold: Option<f64>,
+
+    /// Whether flagged intervals are deleted or interpolated
+    #[arg(long, value_enum, default_value_t = RrEditKind::Delete)]
+    rr_edit: RrEditKind,
+
+    /// Classify the beats and add heart rate turbulence after the ventricular beats
+    #[arg(long)]
+    turbulence: bool,
+
+    /// Add sample, approximate and multiscale entropy (slow on long recordings)
+    #[arg(long)]
+    entropy: bool,
+
+    /// Template length of the entropy measures
+    #[arg(long, default_value_t = 2)]
+    entropy_m: usize,
+
+    /// Tolerance of the entropy measures as a fraction of the RR standard deviation
+    #[arg(long, default_value_t = 0.2)]
+    entropy_r: f64,
+
+    /// Also write the HRV over sliding windows to this CSV file, one row per window
+    #[arg(long)]
+    trend_output: Option<PathBuf>,
+
+    /// Length of the trend windows in seconds
+    #[arg(long, default_value_t = 300.0)]
+    trend_window: f64,
+
+    /// Seconds the trend window advances by
+    #[arg(long, default_value_t = 60.0)]
+    trend_step: f64,
+
+    /// Also report the HRV of a time of day period such as night=23:00-06:00 (repeatable);
+    /// the other periods are compared with the first
+    #[arg(long = "period")]
+    periods: Vec<ClockPeriod>,
+
+    /// Time of day the recording starts (HH:MM or HH:MM:SS), read from the EDF header when
+    /// omitted
+    #[arg(long, value_parser = parse_start_time)]
+    start_time: Option<f64>,
+
+    /// Also write the histogram of the RR intervals to this CSV file
+    #[arg(long)]
+    rr_histogram: Option<PathBuf>,
+
+    /// Bin width of the RR histogram in ms (1/128 s by default)
+    #[arg(long, default_value_t = hrv::GEOMETRIC_BIN_MS)]
+    rr_bin: f64,
+
+    /// Also write the histogram of the instantaneous heart rate to this CSV file
+    #[arg(long)]
+    hr_histogram: Option<PathBuf>,
+
+    /// Bin width of the heart rate histogram in bpm
+    #[arg(long, default_value_t = 1.0)]
+    hr_bin: f64,
+
+    /// Patient the FHIR Observations are about, as a reference such as Patient/123
+    #[arg(long)]
+    patient: Option<String>,
+
+    /// Start date and time of the recording for the FHIR effective period
+    /// (YYYY-MM-DDTHH:MM:SS), read from the EDF header when omitted
+    #[arg(long, value_parser = parse_datetime)]
+    recording_start: Option<f64>,
+
+    /// Time zone of the recording's clock for the FHIR effective period: Z, +HH:MM or -HH:MM
+    #[arg(long, default_value = "Z", value_parser = parse_utc_offset)]
+    utc_offset: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RrFilterKind {
+    /// Intervals changing by more than the threshold (20%) from the last accepted one
+    Percentage,
+    /// Intervals outside the threshold quantiles (1st to 99th percentile)
+    Quantile,
+    /// Intervals changing by more than 20% from the previous one
+    Malik,
+    /// Intervals differing by more than 20% from the mean of their neighbours
+    Karlsson,
+}
+
+impl RrFilterKind {
+    fn filter(self, threshold: Option<f64>) -> RrFilter {
+        match self {
+            RrFilterKind::Percentage => RrFilter::Percentage(threshold.unwrap_or(20.0)),
+            RrFilterKind::Quantile => RrFilter::Quantile(threshold.unwrap_or(0.01)),
+            RrFilterKind::Malik => RrFilter::Malik,
+            RrFilterKind::Karlsson => RrFilter::Karlsson,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RrEditKind {
+    Delete,
+    Interpolate,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InputKind {
+    /// An ECG recording, whose beats are detected
+    Ecg,
+    /// RR intervals, one per line or in a CSV column
+    Rr,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InputFormat {
+    /// CSV with a header line and time,voltage rows
+    Csv,
+    /// European Data Format, EDF+, or BioSemi BDF with 24-bit samples
+    #[value(alias = "bdf")]
+    Edf,
+    /// WFDB record (.hea header with .dat samples in format 212 or 16)
+    Wfdb,
+    /// ISHNE Holter file (.ecg)
+    Ishne,
+    /// SCP-ECG resting ECG (.scp)
+    Scp,
+    /// HL7 annotated ECG XML (.xml)
+    Aecg,
+    /// DICOM waveform object (.dcm)
+    Dicom,
+    /// Apple Health ECG export (CSV with metadata lines, then one sample per line)
+    AppleHealth,
+    /// Polar HRM file recorded in R-R mode (.hrm), RR intervals instead of an ECG
+    PolarHrm,
+    /// Polar RR text export, one interval in ms per line
+    PolarTxt,
+    /// Garmin FIT activity file (.fit) with beat-to-beat intervals
+    Fit,
+}
+
+impl InputFormat {
+    // from the extension; CSV files starting like an Apple Health export are such exports
+    fn from_path(path: &Path) -> Self {
+        let file = path;
+        let path = compression::strip_compression_extension(path);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("edf") || ext.eq_ignore_ascii_case("bdf") => {
+                InputFormat::Edf
+            }
+            Some(ext) if ext.eq_ignore_ascii_case("hea") => InputFormat::Wfdb,
+            Some(ext) if ext.eq_ignore_ascii_case("ecg") => InputFormat::Ishne,
+            Some(ext) if ext.eq_ignore_ascii_case("scp") => InputFormat::Scp,
+            Some(ext) if ext.eq_ignore_ascii_case("xml") => InputFormat::Aecg,
+            Some(ext) if ext.eq_ignore_ascii_case("dcm") => InputFormat::Dicom,
+            Some(ext) if ext.eq_ignore_ascii_case("hrm") => InputFormat::PolarHrm,
+            Some(ext) if ext.eq_ignore_ascii_case("fit") => InputFormat::Fit,
+            _ if !is_stdio(file) && apple_health_utils::is_apple_health_ecg(file) => {
+                InputFormat::AppleHealth
+            }
+            _ => InputFormat::Csv,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Emit {
+    /// QRS times in seconds
+    Qrs,
+    /// RR intervals in milliseconds, stamped with the time of the closing beat
+    Rr,
+    /// QRS times to the output file and RR intervals to the RR output file
+    Both,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// One QRS time in seconds per line
+    Txt,
+    /// CSV with a header, beat number and confidence columns
+    Csv,
+    /// JSON array with one record per beat
+    Json,
+    /// One JSON record per line
+    Jsonl,
+    /// Apache Parquet table with the csv columns (needs the `parquet` feature)
+    Parquet,
+    /// Arrow IPC (Feather) table with the csv columns (needs the `parquet` feature)
+    Arrow,
+}
+
+#[derive(Args)]
+struct RunsArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Where to write the runs table (stdout when omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct RhythmArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Where to write the events as CSV (stdout when omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Also write the episode counts, durations and burden per event kind as JSON
+    #[arg(long)]
+    summary: Option<PathBuf>,
+
+    /// RR intervals per AF analysis window
+    #[arg(long, default_value_t = 64)]
+    af_window: usize,
+
+    /// Shortest AF episode reported, in seconds
+    #[arg(long, default_value_t = 30.0)]
+    af_min_duration: f64,
+
+    /// Heart rate below which beats are bradycardic, in bpm
+    #[arg(long, default_value_t = 50.0)]
+    brady_limit: f64,
+
+    /// Heart rate above which beats are tachycardic, in bpm
+    #[arg(long, default_value_t = 100.0)]
+    tachy_limit: f64,
+
+    /// Consecutive RR intervals beyond a rate limit reported as a brady- or tachycardia
+    #[arg(long, default_value_t = 8)]
+    min_intervals: usize,
+
+    /// RR intervals reported as pauses from this long, in seconds
+    #[arg(long, default_value_t = 2.0)]
+    pause: f64,
+
+    /// RR intervals reported as asystole from this long, in seconds
+    #[arg(long, default_value_t = 3.0)]
+    asystole: f64,
+}
+
+#[derive(Args)]
+struct BatchArgs {
+    /// Detector and CSV settings; the first recording, file pattern or directory is the
+    /// positional input
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Further recordings, patterns such as `data/*.csv` or directories
+    more_inputs: Vec<PathBuf>,
+
+    /// Directory for the per-recording QRS positions (next to each recording when omitted)
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Layout of the per-recording outputs
+    #[arg(long, value_enum, default_value_t = OutputFormat::Txt)]
+    format: OutputFormat,
+
+    /// Add the sample index and amplitude of every beat to txt and csv outputs
+    #[arg(long)]
+    samples: bool,
+
+    /// Add the date and time of every beat from the EDF start date and time
+    #[arg(long)]
+    absolute_times: bool,
+
+    /// Where to write the summary with one row per recording
+    #[arg(long, default_value = "summary.csv")]
+    summary: PathBuf,
+
+    /// Append the recordings, beats, RR intervals, HRV metrics and rhythm events to a SQLite
+    /// database (`sqlite://results.db`) instead of writing per-recording files (needs the
+    /// `sqlite` feature)
+    #[arg(long, value_parser = parse_sqlite_url)]
+    output: Option<PathBuf>,
+
+    /// Process several recordings at a time
+    #[arg(long)]
+    parallel: bool,
+
+    /// Keep watching the inputs and process new recordings as they appear, until interrupted
+    #[arg(long)]
+    watch: bool,
+
+    /// Seconds between scans of the watched inputs
+    #[arg(long, default_value_t = 2.0)]
+    poll_interval: f64,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Where to write the HTML report
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Heading of the report
+    #[arg(long, default_value = "ECG analysis report")]
+    title: String,
+}
+
+#[derive(Args)]
+struct PlotArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Where to write the plot, as SVG or PNG by its extension
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Start of the stretch, e.g. 120s, 2m or 1h (the start of the recording when omitted)
+    #[arg(long, value_parser = parse_seconds)]
+    from: Option<f64>,
+
+    /// End of the stretch (10 s after its start when omitted)
+    #[arg(long, value_parser = parse_seconds)]
+    to: Option<f64>,
+
+    /// Also mark the beats of a reference: a WFDB annotation file or a positions file
+    #[arg(long)]
+    reference: Option<PathBuf>,
+
+    /// Sampling frequency of the annotated record (read from its .hea header when omitted)
+    #[arg(long)]
+    reference_fs: Option<f64>,
+
+    /// Width of the plot in pixels
+    #[arg(long, default_value_t = 1200)]
+    width: u32,
+
+    /// Height of the plot in pixels
+    #[arg(long, default_value_t = 300)]
+    height: u32,
+}
+
+#[derive(Args)]
+struct ViewArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Where to start viewing, e.g. 120s, 2m or 1h (the start of the recording when omitted)
+    #[arg(long, value_parser = parse_seconds)]
+    from: Option<f64>,
+
+    /// Length of the stretch on screen
+    #[arg(long, value_parser = parse_seconds, default_value = "10s")]
+    span: f64,
+
+    /// Also mark the beats of a reference: a WFDB annotation file or a positions file
+    #[arg(long)]
+    reference: Option<PathBuf>,
+
+    /// Sampling frequency of the annotated record (read from its .hea header when omitted)
+    #[arg(long)]
+    reference_fs: Option<f64>,
+
+    /// Show the beats of a positions file instead of detecting them
+    #[arg(long)]
+    beats: Option<PathBuf>,
+
+    /// Edit the beats on screen, saving them as positions to this file
+    #[arg(long)]
+    edit: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct EditArgs {
+    /// Positions file to edit (as written by `detect`, txt or csv)
+    beats: PathBuf,
+
+    /// Edit script: lines of `add <s>`, `remove <s>`, `remove <from>-<to>` and `move <s> <s>`
+    #[arg(long)]
+    script: PathBuf,
+
+    /// Where to write the edited positions (stdout when omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// How far, in seconds, a removed or moved beat may lie from the time given for it
+    #[arg(long, default_value_t = 0.1)]
+    tolerance: f64,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Beats of the first run: a positions file or a WFDB annotation file
+    first: PathBuf,
+
+    /// Beats of the second run, compared with the first
+    second: PathBuf,
+
+    /// Largest distance between two beats counted as the same beat, in seconds
+    #[arg(long, default_value_t = 0.15)]
+    tolerance: f64,
+
+    /// Sampling frequency of annotated records (read from their .hea headers when omitted)
+    #[arg(long)]
+    fs: Option<f64>,
+
+    /// Write every beat as CSV: its time in each run and the offset between them
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Detected positions (as written by `detect`, txt or csv)
+    detected: PathBuf,
+
+    /// Reference beats: a WFDB annotation file (e.g. 100.atr) or a positions file
+    reference: PathBuf,
+
+    /// Largest distance between a detected and a reference beat counted as a match, in
+    /// seconds
+    #[arg(long, default_value_t = 0.15)]
+    tolerance: f64,
+
+    /// Sampling frequency of the annotated record (read from its .hea header when omitted)
+    #[arg(long)]
+    fs: Option<f64>,
+}
+
+#[derive(Args)]
+struct AnnotationsArgs {
+    /// EDF+ file with an `EDF Annotations` signal, or an HL7 aECG file (.xml)
+    input: PathBuf,
+
+    /// Where to write the events (stdout when omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Detected positions (as written by `detect`, txt or csv) to place the events among:
+    /// adds the number of the first beat at or after each onset and the beats during each
+    /// event
+    #[arg(long)]
+    beats: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Recording to convert: an EDF, BDF, ISHNE, SCP-ECG, HL7 aECG, DICOM waveform or CSV file
+    input: PathBuf,
+
+    /// Where to write the converted recording: EDF when it ends in .edf, CSV otherwise (`-`
+    /// writes CSV to stdout)
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Signals to convert, by EDF label or CSV column name, separated by commas (all leads
+    /// sampled like the first when omitted)
+    #[arg(long, value_delimiter = ',')]
+    signals: Vec<String>,
+
+    /// Sampling frequency of the CSV in Hz (estimated from its time column when omitted)
+    #[arg(long)]
+    fs: Option<f64>,
+
+    /// The CSV holds voltage columns only; times are derived from --fs
+    #[arg(long, requires = "fs")]
+    no_time_column: bool,
+
+    /// Patient identification of the EDF written (that of an EDF input, or X)
+    #[arg(long)]
+    patient: Option<String>,
+
+    /// Recording identification of the EDF written (that of an EDF input, or X)
+    #[arg(long)]
+    recording: Option<String>,
+
+    /// Start date of the EDF written, dd.mm.yy (that of an EDF input, or 01.01.85)
+    #[arg(long)]
+    start_date: Option<String>,
+
+    /// Start time of the EDF written, hh.mm.ss (that of an EDF input, or 00.00.00)
+    #[arg(long)]
+    start_time: Option<String>,
+
+    /// Write EDF+, carrying over the annotations of an EDF+ or aECG input
+    #[arg(long)]
+    edf_plus: bool,
+
+    /// High-pass cutoff in Hz applied before writing
+    #[arg(long)]
+    highpass: Option<f64>,
+
+    /// Low-pass cutoff in Hz applied before writing
+    #[arg(long)]
+    lowpass: Option<f64>,
+
+    /// Powerline frequency in Hz notched out before writing
+    #[arg(long)]
+    notch: Option<f64>,
+
+    /// Filter forwards and backwards, without phase shift
+    #[arg(long)]
+    zero_phase: bool,
+
+    /// Resample to this rate in Hz before writing (after filtering)
+    #[arg(long)]
+    resample: Option<f64>,
+}
+
+#[derive(Args)]
+struct AnonymizeArgs {
+    /// EDF or BDF file to anonymize
+    input: PathBuf,
+
+    /// Where to write the anonymized copy
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Also replace the start date by 01.01.85 (the time of day is kept)
+    #[arg(long)]
+    clear_date: bool,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Txt => "txt",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Arrow => "arrow",
+        }
+    }
+
+    // Parquet and Arrow need the `parquet` feature
+    fn check_available(self) -> Result<(), Box<dyn Error>> {
+        if matches!(self, OutputFormat::Parquet | OutputFormat::Arrow)
+            && cfg!(not(feature = "parquet"))
+        {
+            return Err(TABLE_FEATURE.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HrvFormat {
+    Json,
+    /// Long format with section, metric and value columns
+    Csv,
+    /// FHIR R4 Bundle of Observations: the mean heart rate, rhythm findings with the
+    /// default rhythm limits, and the HRV panel
+    Fhir,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Spectral {
+    /// Tachogram resampled at 4 Hz, Welch periodogram
+    Welch,
+    /// Lomb-Scargle periodogram of the unevenly sampled RR series
+    LombScargle,
+    /// Skip the frequency-domain analysis
+    None,
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    // getting the current directory
-    let current_dir = std::env::current_dir()?;
-    println!("Current directory: {:?}", current_dir);
+    let args = with_config(std::env::args_os().collect())?;
+    let started = SystemTime::now();
+    let matches = Cli::command().get_matches_from(args.clone());
+    let cli = Cli::from_arg_matches(&matches)?;
+    let no_provenance = cli.no_provenance;
+    init_logging(cli.verbose, cli.quiet, cli.log_format);
+    // a bar would garble JSON logs and is noise when messages are silenced
+    SHOW_PROGRESS.store(
+        !cli.no_progress
+            && cli.log_format == LogFormat::Text
+            && cli.verbose >= cli.quiet
+            && io::stderr().is_terminal(),
+        Ordering::Relaxed,
+    );
+
+    match cli.command {
+        Command::Detect(args) => run_detect(args),
+        Command::Hrv(args) => run_hrv(args),
+        Command::Runs(args) => run_runs(args),
+        Command::Rhythm(args) => run_rhythm(args),
+        Command::Report(args) => run_report(args),
+        Command::Plot(args) => run_plot(args),
+        Command::View(args) => run_view(args),
+        Command::Edit(args) => run_edit(args),
+        Command::Diff(args) => run_diff(args),
+        Command::Validate(args) => run_validate(args),
+        Command::Annotations(args) => run_annotations(args),
+        Command::Anonymize(args) => run_anonymize(args),
+        Command::Convert(args) => run_convert(args),
+        Command::Batch(args) => run_batch(args),
+        Command::EdfInfo { path, dump } => {
+            let header = edf_utils::read_edf_header(&mut compression::open_input(&path)?)?;
+            edf_utils::print_edf_header(&header);
+            if dump {
+                edf_utils::print_edf_signals(&path.to_string_lossy())?;
+            }
+            Ok(())
+        }
+    }?;
+
+    if let (false, Some((name, matches))) = (no_provenance, matches.subcommand()) {
+        write_provenance(&args, name, matches, started)?;
+    }
+    Ok(())
+}
+
+// log messages to stderr, from INFO up unless -v or -q move the level
+fn init_logging(verbose: u8, quiet: u8, format: LogFormat) {
+    let level = match i16::from(verbose) - i16::from(quiet) {
+        ..=-2 => LevelFilter::ERROR,
+        -1 => LevelFilter::WARN,
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_max_level(level)
+        .with_target(false);
+    match format {
+        LogFormat::Text => builder
+            .with_ansi(io::stderr().is_terminal())
+            .without_time()
+            .init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+// a progress bar counting `unit` with the time left, cleared when the work is done; no
+// reporting when bars are not shown
+fn progress_bar(unit: &str) -> Progress {
+    #[cfg(feature = "progress")]
+    if SHOW_PROGRESS.load(Ordering::Relaxed) {
+        let template = format!(
+            "{{elapsed_precise}} [{{bar:40}}] {{percent:>3}}% {{pos}}/{{len}} {}, ETA {{eta}}",
+            unit
+        );
+        if let Ok(style) = indicatif::ProgressStyle::with_template(&template) {
+            let bar = indicatif::ProgressBar::new(0).with_style(style.progress_chars("=> "));
+            return Progress::new(move |done, total| {
+                bar.set_length(total);
+                bar.set_position(done);
+                if done >= total {
+                    bar.finish_and_clear();
+                }
+            });
+        }
+    }
+    #[cfg(not(feature = "progress"))]
+    let _ = unit;
+    Progress::default()
+}
+
+// the options naming files a subcommand reads, whose digests the provenance records; the
+// others, the outputs among them, are recorded as parameters only
+const INPUT_ARGUMENTS: [&str; 8] = [
+    "input",
+    "more_inputs",
+    "reference",
+    "beats",
+    "script",
+    "first",
+    "second",
+    "detected",
+];
+
+// `<output>.provenance.json` next to the output of a subcommand, or `provenance.json` in its
+// output directory, with every parameter value and the digests of the input files
+fn write_provenance(
+    args: &[OsString],
+    subcommand: &str,
+    matches: &ArgMatches,
+    started: SystemTime,
+) -> Result<(), Box<dyn Error>> {
+    let path = if let Ok(Some(output)) = matches.try_get_one::<PathBuf>("output") {
+        if is_stdio(output) {
+            return Ok(());
+        }
+        let name = output.file_name().unwrap_or_default().to_string_lossy();
+        output.with_file_name(format!("{}.provenance.json", name))
+    } else if let Ok(Some(output_dir)) = matches.try_get_one::<PathBuf>("output_dir") {
+        output_dir.join("provenance.json")
+    } else {
+        return Ok(());
+    };
+
+    // the matches also hold the groups of flattened arguments
+    let cli = Cli::command();
+    let arguments: HashSet<&str> = cli
+        .find_subcommand(subcommand)
+        .into_iter()
+        .flat_map(|command| command.get_arguments())
+        .map(|arg| arg.get_id().as_str())
+        .collect();
+    let mut parameters = Vec::new();
+    let mut inputs = Vec::new();
+    for id in matches.ids() {
+        let name = id.as_str();
+        if !arguments.contains(name) {
+            continue;
+        }
+        let Some(values) = matches.get_raw(name) else {
+            continue;
+        };
+        let values: Vec<String> = values.map(|v| v.to_string_lossy().into_owned()).collect();
+        if INPUT_ARGUMENTS.contains(&name) {
+            // the patterns and directories of `batch` stand for the recordings they expand to
+            let paths: Vec<PathBuf> = values.iter().map(PathBuf::from).collect();
+            for file in expand_inputs(&paths)? {
+                let path = file.to_string_lossy();
+                if file.is_file() && !inputs.iter().any(|input: &InputFile| input.path == path) {
+                    inputs.push(InputFile::read(&path, File::open(&file)?)?);
+                }
+            }
+        }
+        parameters.push((name.to_string(), values.join(",")));
+    }
+
+    let provenance = Provenance {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        command: args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        parameters,
+        inputs,
+        created: provenance::utc_timestamp(started),
+    };
+    debug!("Writing provenance to: {:?}", path);
+    let mut file = io::BufWriter::new(File::create(&path)?);
+    provenance::write_provenance_json(&mut file, &provenance)?;
+    file.flush()?;
+    Ok(())
+}
+
+// the command line with the settings of the configuration file inserted after the subcommand
+// as options: top-level settings for every subcommand taking them, those of a `[subcommand]`
+// section for it alone; settings named by options on the command line are left out
+fn with_config(mut args: Vec<OsString>) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let mut explicit = None;
+    for (i, arg) in args.iter().enumerate() {
+        let arg = arg.to_string_lossy();
+        if arg == "--config" {
+            explicit = args.get(i + 1).map(PathBuf::from);
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            explicit = Some(PathBuf::from(path));
+        }
+    }
+    let path = match explicit {
+        Some(path) => path,
+        None if Path::new(CONFIG_FILE).is_file() => PathBuf::from(CONFIG_FILE),
+        None => return Ok(args),
+    };
+    let entries = config::read_config(&path)?;
+
+    let cli = Cli::command();
+    let Some((position, subcommand)) = args
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find_map(|(i, arg)| Some((i, cli.find_subcommand(arg)?)))
+    else {
+        // clap reports the missing subcommand
+        return Ok(args);
+    };
+    let name = subcommand.get_name();
+    let given = &args[position + 1..];
+
+    let mut options: Vec<OsString> = Vec::new();
+    for entry in &entries {
+        let long = entry.key.replace('_', "-");
+        let known = |command: &clap::Command| {
+            command
+                .get_arguments()
+                .any(|arg| arg.get_long() == Some(long.as_str()))
+        };
+        match &entry.section {
+            Some(section) => match cli.find_subcommand(section) {
+                Some(command) if known(command) => {}
+                Some(_) => {
+                    return Err(format!("{:?}: `{}` has no --{}", path, section, long).into())
+                }
+                None => return Err(format!("{:?}: unknown section [{}]", path, section).into()),
+            },
+            None if cli.get_subcommands().any(known) => {}
+            None => return Err(format!("{:?}: unknown setting {}", path, entry.key).into()),
+        }
+        let applies = match &entry.section {
+            Some(section) => section == name,
+            // unless its section sets it for this subcommand
+            None => !entries.iter().any(|other| {
+                other.section.as_deref() == Some(name) && other.key.replace('_', "-") == long
+            }),
+        };
+        let Some(arg) = subcommand
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+        else {
+            continue;
+        };
+        let named = |given: &OsString| {
+            let given = given.to_string_lossy();
+            given == format!("--{}", long)
+                || given.starts_with(&format!("--{}=", long))
+                || arg.get_short().is_some_and(|short| {
+                    given.starts_with(&format!("-{}", short)) && !given.starts_with("--")
+                })
+        };
+        if !applies || given.iter().any(named) {
+            continue;
+        }
+
+        // as --key=value, so negative numbers are not taken for options
+        let option = |value: &ConfigValue| OsString::from(format!("--{}={}", long, value));
+        match (arg.get_action(), &entry.value) {
+            (ArgAction::SetTrue, ConfigValue::Boolean(true)) => {
+                options.push(format!("--{}", long).into())
+            }
+            (ArgAction::SetTrue, ConfigValue::Boolean(false)) => {}
+            (ArgAction::SetTrue, _) => {
+                return Err(format!("{:?}: {} is true or false", path, entry.key).into())
+            }
+            (ArgAction::Count, ConfigValue::Number(count)) => {
+                options.extend((0..*count as usize).map(|_| format!("--{}", long).into()))
+            }
+            (ArgAction::Append, ConfigValue::Array(values)) => {
+                options.extend(values.iter().map(option))
+            }
+            (_, value) => options.push(option(value)),
+        }
+    }
+    args.splice(position + 1..position + 1, options);
+    Ok(args)
+}
+
+// the registry with a custom ensemble when --ensemble or --min-votes is given
+fn build_registry(args: &InputArgs) -> Result<DetectorRegistry, Box<dyn Error>> {
+    let mut registry = DetectorRegistry::with_config(args.detector_config());
+    if args.ensemble.is_some() || args.min_votes.is_some() {
+        let members: Vec<String> = match &args.ensemble {
+            Some(members) => members.clone(),
+            None => registry
+                .names()
+                .into_iter()
+                .filter(|&name| name != "ensemble")
+                .map(String::from)
+                .collect(),
+        };
+        // members are fresh instances, so their names stay selectable with --detector
+        let mut detectors = Vec::new();
+        for name in &members {
+            let detector = DetectorRegistry::with_config(args.detector_config())
+                .remove(name)
+                .ok_or_else(|| format!("Unknown ensemble member '{}'", name))?;
+            detectors.push(detector);
+        }
+        registry.register(Box::new(EnsembleDetector::new(detectors, args.min_votes)));
+    }
+    Ok(registry)
+}
+
+fn select_detector<'a>(
+    registry: &'a DetectorRegistry,
+    name: &str,
+) -> Result<&'a dyn QrsDetector, Box<dyn Error>> {
+    registry.get(name).ok_or_else(|| {
+        format!(
+            "Unknown detector '{}', available: {}",
+            name,
+            registry.names().join(", ")
+        )
+        .into()
+    })
+}
+
+fn detection_options(args: &InputArgs) -> DetectionOptions {
+    DetectionOptions {
+        fs: args.fs,
+        segment_length: args.segment_length,
+        segment_overlap: args.segment_overlap,
+        filter: args.filter(),
+        align_polarity: args.align_polarity,
+        template_threshold: args.template_threshold,
+        detector_config: args.detector_config(),
+        min_confidence: args.min_confidence,
+        progress: progress_bar("segments"),
+        ..DetectionOptions::default()
+    }
+}
+
+// beats detected in a recording and the gaps in its time column
+struct DetectionRun {
+    detections: Vec<Detection>,
+    gaps: Vec<Gap>,
+    // signal quality per window, for single-lead detection
+    quality: Vec<WindowQuality>,
+    // flat, saturated and lead-off stretches, for single-lead detection
+    artifacts: Vec<ArtifactSpan>,
+    // spans excluded from detection for their poor quality
+    masked: Vec<Gap>,
+    // class of every beat, empty unless --classify
+    classes: Vec<BeatClass>,
+    // the unfiltered lead the beats were detected in, the time of its first sample and its
+    // sampling rate, for the beat analyses; empty for fused and chunked detection
+    signal: Vec<f64>,
+    start: f64,
+    fs: Option<f64>,
+}
+
+// an artifact with its time span
+struct ArtifactSpan {
+    start: f64,
+    end: f64,
+    kind: ArtifactKind,
+}
+
+// quality indices of a window of the recording, with its time span
+struct WindowQuality {
+    start: f64,
+    end: f64,
+    quality: SegmentQuality,
+}
+
+impl DetectionRun {
+    fn new(detections: Vec<Detection>, gaps: Vec<Gap>) -> Self {
+        DetectionRun {
+            detections,
+            gaps,
+            quality: Vec::new(),
+            artifacts: Vec::new(),
+            masked: Vec::new(),
+            classes: Vec::new(),
+            signal: Vec::new(),
+            start: 0.0,
+            fs: None,
+        }
+    }
+
+    fn empty() -> Self {
+        DetectionRun::new(Vec::new(), Vec::new())
+    }
+
+    // RR intervals of consecutive beats, leaving out those spanning a gap or masked span
+    fn rr_intervals(&self) -> Vec<RrInterval> {
+        let excluded: Vec<Gap> = self.gaps.iter().chain(&self.masked).copied().collect();
+        rr_intervals_without_gaps(&beat_times(&self.detections), &excluded)
+    }
+}
+
+// `fs` if given, otherwise the rate estimated from `times`
+fn sampling_rate(times: &[f64], fs: Option<f64>) -> Option<f64> {
+    fs.or_else(|| estimate_sampling(times).map(|estimate| estimate.fs))
+}
+
+// the gaps in `times`, sampled at `fs` or the rate estimated from them
+fn recording_gaps(times: &[f64], fs: Option<f64>) -> Vec<Gap> {
+    let Some(fs) = sampling_rate(times, fs) else {
+        return Vec::new();
+    };
+    let gaps = find_gaps(times, fs);
+    report_gaps(&gaps);
+    gaps
+}
+
+fn report_gaps(gaps: &[Gap]) {
+    if let Some(first) = gaps.first() {
+        info!(
+            "Found {} gaps in the recording ({:.3} s in total), the first at {:.3} s; RR \
+             intervals spanning them are left out",
+            gaps.len(),
+            gaps.iter().map(Gap::duration).sum::<f64>(),
+            first.start
+        );
+    }
+}
+
+const RR_INPUT: &str = "The input holds RR intervals, not an ECG";
+
+// the RR intervals (ms) of an input that holds intervals rather than an ECG, `None` for ECG
+// recordings
+fn read_rr_input(
+    args: &InputArgs,
+    input_format: InputFormat,
+) -> Result<Option<Vec<f64>>, Box<dyn Error>> {
+    let path = &args.input;
+    let intervals = match input_format {
+        InputFormat::PolarHrm => polar_utils::read_polar_hrm(path)?,
+        InputFormat::PolarTxt => polar_utils::read_polar_txt(path)?,
+        InputFormat::Fit => fit_utils::read_fit_rr(path)?,
+        InputFormat::Csv if args.input_kind == InputKind::Rr => {
+            read_rr_csv(path, &args.csv_options(), args.rr_unit)?
+        }
+        _ if args.input_kind == InputKind::Rr => {
+            return Err("--input-kind rr reads CSV or text lists of RR intervals".into())
+        }
+        _ => return Ok(None),
+    };
+    info!(
+        "Read {} RR intervals, skipping QRS detection",
+        intervals.len()
+    );
+    Ok(Some(intervals))
+}
+
+// reads the recording and runs the selected detector
+fn run_detection(args: &InputArgs) -> Result<DetectionRun, Box<dyn Error>> {
+    let mut registry = build_registry(args)?;
+    let detector = select_detector(&registry, &args.detector)?;
+
+    info!("Reading from: {:?}", args.input);
+
+    // reading the data
+    let input_format = args
+        .input_format
+        .unwrap_or_else(|| InputFormat::from_path(&args.input));
+    if (args.column.is_some() || args.time_column.is_some()) && input_format != InputFormat::Csv {
+        return Err("--column is only supported for CSV input, use --signal-index".into());
+    }
+    if let Some(intervals) = read_rr_input(args, input_format)? {
+        if args.classify {
+            warn!("--classify needs an ECG, the beats of RR intervals are left unclassified");
+        }
+        // the beats are placed at samples only when --fs gives the rate of the recording
+        let detections = beat_times_from_rr(&intervals)
+            .into_iter()
+            .map(|time| Detection {
+                time,
+                sample: args.fs.map_or(0, |fs| (time * fs).round() as usize),
+                amplitude: 0.0,
+                confidence: 1.0,
+            })
+            .collect();
+        return Ok(DetectionRun::new(detections, Vec::new()));
+    }
+    if args.exclude_unusable && (args.fuse_leads || args.chunk_size.is_some()) {
+        return Err("--exclude-unusable is only supported for single-lead detection".into());
+    }
+    if args.classify && (args.fuse_leads || args.chunk_size.is_some()) {
+        return Err("--classify is not supported with --fuse-leads or --chunk-size".into());
+    }
+    if args.fuse_leads {
+        let ecg = read_leads(args, input_format)?;
+        let gaps = recording_gaps(&ecg.times, args.fs);
+        let mut options = detection_options(args);
+        let ecg = resample_leads_input(args, ecg, &mut options);
+        info!(
+            "Using detector: {} on {} leads",
+            detector.name(),
+            ecg.leads.len()
+        );
+        let detections = detect_beats_multi_lead(
+            &ecg,
+            detector,
+            &options,
+            args.min_leads,
+            args.lead_tolerance,
+        );
+        info!("Found {} QRS complexes", detections.len());
+        return Ok(DetectionRun::new(detections, gaps));
+    }
+    if let Some(chunk_size) = args.chunk_size {
+        if !matches!(input_format, InputFormat::Csv | InputFormat::Edf) {
+            return Err("--chunk-size is only supported for CSV and EDF input".into());
+        }
+        if args.resample.is_some() {
+            return Err("--resample is not supported with --chunk-size".into());
+        }
+        if args.template_threshold.is_some() {
+            return Err("--template-threshold is not supported with --chunk-size".into());
+        }
+        let detector = registry
+            .remove(&args.detector)
+            .ok_or("detector missing from the registry")?;
+        return run_chunked_detection(args, input_format, detector, chunk_size);
+    }
+    let ecg_data = match input_format {
+        InputFormat::Csv => read_ecg_csv(&args.input, &args.csv_options())?,
+        InputFormat::Edf => edf_utils::read_edf_ecg_data(&args.input, signal_index(args)?)?,
+        InputFormat::Wfdb if is_stdio(&args.input) => {
+            return Err("WFDB records cannot be read from stdin".into())
+        }
+        InputFormat::Wfdb => wfdb_utils::read_wfdb_ecg_data(&args.input, signal_index(args)?)?,
+        InputFormat::Ishne => ishne_utils::read_ishne_ecg_data(&args.input, signal_index(args)?)?,
+        InputFormat::Scp => scp_utils::read_scp_ecg_data(&args.input, signal_index(args)?)?,
+        InputFormat::Aecg => aecg_utils::read_aecg_ecg_data(&args.input, signal_index(args)?)?,
+        InputFormat::Dicom => dicom_utils::read_dicom_ecg_data(&args.input, signal_index(args)?)?,
+        InputFormat::AppleHealth => apple_health_utils::read_apple_health_ecg_data(&args.input)?,
+        InputFormat::PolarHrm | InputFormat::PolarTxt | InputFormat::Fit => {
+            return Err(RR_INPUT.into())
+        }
+    };
+
+    if ecg_data.is_empty() {
+        warn!("No data found in the ECG file");
+        return Ok(DetectionRun::empty());
+    }
+
+    let times: Vec<f64> = ecg_data.iter().map(|point| point.time).collect();
+    let gaps = recording_gaps(&times, args.fs);
+    let artifacts = artifact_spans(&ecg_data, args.fs);
+    let mut options = detection_options(args);
+    let ecg_data = match resample_rates(args, &times) {
+        Some((fs_in, fs_out)) => {
+            options.fs = Some(resample::output_rate(fs_in, fs_out));
+            resample_points(&ecg_data, fs_in, fs_out)
+        }
+        None => ecg_data,
+    };
+
+    report_polarity(&ecg_data, &options);
+
+    // detecting QRS complexes
+    info!("Using detector: {}", detector.name());
+    let mut detections = detect_beats(&ecg_data, detector, &options);
+
+    info!("Found {} QRS complexes", detections.len());
+
+    let quality = window_quality(args, &ecg_data, options.fs);
+    let masked: Vec<Gap> = if args.exclude_unusable {
+        quality
+            .iter()
+            .filter(|window| window.quality.label == QualityLabel::Unusable)
+            .map(|window| Gap {
+                start: window.start,
+                end: window.end,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if !masked.is_empty() {
+        let found = detections.len();
+        detections.retain(|detection| {
+            !masked
+                .iter()
+                .any(|span| detection.time >= span.start && detection.time <= span.end)
+        });
+        info!(
+            "Excluded {} QRS complexes in {} unusable windows",
+            found - detections.len(),
+            masked.len()
+        );
+    }
+
+    let classes = if args.classify {
+        beat_classes(&ecg_data, &detections, &options)
+    } else {
+        Vec::new()
+    };
+
+    let times: Vec<f64> = ecg_data.iter().map(|point| point.time).collect();
+    Ok(DetectionRun {
+        detections,
+        gaps,
+        quality,
+        artifacts,
+        masked,
+        classes,
+        signal: ecg_data.iter().map(|point| point.voltage).collect(),
+        start: times[0],
+        fs: sampling_rate(&times, options.fs),
+    })
+}
+
+// classifies the detected beats on the filtered signal
+fn beat_classes(
+    ecg_data: &[EcgPoint],
+    detections: &[Detection],
+    options: &DetectionOptions,
+) -> Vec<BeatClass> {
+    let times: Vec<f64> = ecg_data.iter().map(|point| point.time).collect();
+    let Some(fs) = sampling_rate(&times, options.fs) else {
+        return Vec::new();
+    };
+    let voltage: Vec<f64> = ecg_data.iter().map(|point| point.voltage).collect();
+    let samples: Vec<usize> = detections
+        .iter()
+        .map(|detection| detection.sample)
+        .collect();
+    let classes = classify_beats(&beat_features(
+        &options.filter.apply(&voltage, fs),
+        fs,
+        &samples,
+    ));
+    let count = |class: BeatClass| classes.iter().filter(|&&c| c == class).count();
+    info!(
+        "Classified beats: {} normal, {} ventricular, {} artifact",
+        count(BeatClass::Normal),
+        count(BeatClass::Ventricular),
+        count(BeatClass::Artifact)
+    );
+    classes
+}
+
+// flat, saturated and lead-off stretches of the recording
+fn artifact_spans(ecg_data: &[EcgPoint], fs: Option<f64>) -> Vec<ArtifactSpan> {
+    let times: Vec<f64> = ecg_data.iter().map(|point| point.time).collect();
+    let Some(fs) = sampling_rate(&times, fs) else {
+        return Vec::new();
+    };
+    let voltage: Vec<f64> = ecg_data.iter().map(|point| point.voltage).collect();
+    let artifacts: Vec<ArtifactSpan> = find_artifacts(&voltage, fs)
+        .into_iter()
+        .map(|artifact| ArtifactSpan {
+            start: times[artifact.start],
+            end: times[artifact.end - 1],
+            kind: artifact.kind,
+        })
+        .collect();
+    if !artifacts.is_empty() {
+        info!(
+            "Found {} flatline, saturation or lead-off stretches",
+            artifacts.len()
+        );
+    }
+    artifacts
+}
+
+// warns about leads whose QRS complexes point downwards, which are often inverted
+fn report_polarity(ecg_data: &[EcgPoint], options: &DetectionOptions) {
+    let times: Vec<f64> = ecg_data.iter().map(|point| point.time).collect();
+    let Some(fs) = sampling_rate(&times, options.fs) else {
+        return;
+    };
+    let voltage: Vec<f64> = ecg_data.iter().map(|point| point.voltage).collect();
+    if polarity::qrs_polarity(&options.filter.apply(&voltage, fs), fs) == Polarity::Negative {
+        warn!(
+            "Dominant QRS polarity is negative (inverted lead?); --align-polarity places the \
+             beats on the S waves consistently"
+        );
+    }
+}
+
+// signal quality indices per --quality-window of the recording
+fn window_quality(args: &InputArgs, ecg_data: &[EcgPoint], fs: Option<f64>) -> Vec<WindowQuality> {
+    let times: Vec<f64> = ecg_data.iter().map(|point| point.time).collect();
+    let Some(fs) = sampling_rate(&times, fs) else {
+        return Vec::new();
+    };
+    let voltage: Vec<f64> = ecg_data.iter().map(|point| point.voltage).collect();
+    segment_quality(&voltage, fs, args.quality_window)
+        .into_iter()
+        .map(|quality| WindowQuality {
+            start: times[quality.start],
+            end: times[quality.end - 1],
+            quality,
+        })
+        .collect()
+}
+
+// the input and requested output rate of --resample, the input rate estimated from `times`
+// unless --fs gives it
+fn resample_rates(args: &InputArgs, times: &[f64]) -> Option<(f64, f64)> {
+    let target = args.resample?;
+    let fs = args
+        .fs
+        .or_else(|| estimate_sampling(times).map(|estimate| estimate.fs))?;
+    info!(
+        "Resampling from {:.2} Hz to {:.2} Hz",
+        fs,
+        resample::output_rate(fs, target)
+    );
+    Some((fs, target))
+}
+
+// all leads at the --resample rate, with `options` updated to it
+fn resample_leads_input(
+    args: &InputArgs,
+    ecg: MultiLeadEcg,
+    options: &mut DetectionOptions,
+) -> MultiLeadEcg {
+    match resample_rates(args, &ecg.times) {
+        Some((fs_in, fs_out)) => {
+            options.fs = Some(resample::output_rate(fs_in, fs_out));
+            resample::resample_leads(&ecg, fs_in, fs_out)
+        }
+        None => ecg,
+    }
+}
+
+fn read_leads(args: &InputArgs, input_format: InputFormat) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    if args.input_kind == InputKind::Rr {
+        return Err(RR_INPUT.into());
+    }
+    match input_format {
+        InputFormat::Csv => read_ecg_leads(&args.input, &args.csv_options()),
+        InputFormat::Edf => edf_utils::read_edf_leads(&args.input),
+        InputFormat::Ishne => ishne_utils::read_ishne_leads(&args.input),
+        InputFormat::Scp => scp_utils::read_scp_leads(&args.input),
+        InputFormat::Aecg => aecg_utils::read_aecg_leads(&args.input),
+        InputFormat::Dicom => dicom_utils::read_dicom_leads(&args.input),
+        InputFormat::AppleHealth => apple_health_utils::read_apple_health_leads(&args.input),
+        InputFormat::PolarHrm | InputFormat::PolarTxt | InputFormat::Fit => Err(RR_INPUT.into()),
+        InputFormat::Wfdb => Err(
+            "Multi-lead detection supports CSV, EDF, ISHNE, SCP-ECG, aECG and DICOM input".into(),
+        ),
+    }
+}
+
+// lead name and the beats detected in it
+type LeadDetections = (String, DetectionRun);
+
+// runs the selected detector on every lead of a multi-lead CSV
+fn run_detection_all_leads(args: &InputArgs) -> Result<Vec<LeadDetections>, Box<dyn Error>> {
+    let registry = build_registry(args)?;
+    let detector = select_detector(&registry, &args.detector)?;
+
+    let input_format = args
+        .input_format
+        .unwrap_or_else(|| InputFormat::from_path(&args.input));
+
+    info!("Reading from: {:?}", args.input);
+    let ecg = read_leads(args, input_format)?;
+    if ecg.times.is_empty() {
+        warn!("No data found in the ECG file");
+        return Ok(Vec::new());
+    }
+
+    let gaps = recording_gaps(&ecg.times, args.fs);
+    let mut options = detection_options(args);
+    let ecg = resample_leads_input(args, ecg, &mut options);
+
+    info!("Using detector: {}", detector.name());
+    let mut results = Vec::new();
+    let progress = options.progress.clone();
+    for (idx, lead) in ecg.leads.iter().enumerate() {
+        let points = ecg.points(idx);
+        options.progress = progress.part(idx as u64, ecg.leads.len() as u64);
+        let detections = detect_beats(&points, detector, &options);
+        info!(
+            "Lead {}: found {} QRS complexes",
+            lead.name,
+            detections.len()
+        );
+        let mut run = DetectionRun::new(detections, gaps.clone());
+        if args.classify {
+            run.classes = beat_classes(&points, &run.detections, &options);
+        }
+        run.signal = lead.voltage.clone();
+        run.start = ecg.times[0];
+        run.fs = sampling_rate(&ecg.times, options.fs);
+        results.push((lead.name.clone(), run));
+    }
+
+    Ok(results)
+}
+
+// feeds the CSV block by block to a streaming detector, so only one block is in memory
+fn run_chunked_detection(
+    args: &InputArgs,
+    input_format: InputFormat,
+    detector: Box<dyn QrsDetector>,
+    chunk_size: usize,
+) -> Result<DetectionRun, Box<dyn Error>> {
+    // EDF knows its sampling frequency, CSV times are examined when --fs is not given
+    type Chunks = Box<dyn Iterator<Item = Result<Vec<EcgPoint>, Box<dyn Error>>>>;
+    let (mut chunks, edf_fs): (Chunks, Option<f64>) = if input_format == InputFormat::Edf {
+        let chunks = edf_utils::read_edf_chunks(&args.input, signal_index(args)?, chunk_size)?;
+        let fs = chunks.sampling_frequency();
+        (Box::new(chunks), Some(fs))
+    } else {
+        let chunks = read_ecg_chunks(&args.input, chunk_size)?.with_options(args.csv_options());
+        (Box::new(chunks), None)
+    };
+    let first = match chunks.next() {
+        Some(chunk) => chunk?,
+        None => {
+            warn!("No data found in the ECG file");
+            return Ok(DetectionRun::empty());
+        }
+    };
+
+    let fs = match edf_fs.or(args.fs) {
+        Some(fs) => fs,
+        None => {
+            let times: Vec<f64> = first.iter().map(|point| point.time).collect();
+            let estimate = estimate_sampling(&times)
+                .ok_or("cannot estimate the sampling frequency, pass --fs")?;
+            sampling::report_sampling(&estimate);
+            estimate.fs
+        }
+    };
+
+    info!("Using detector: {}", detector.name());
+    let mut streaming = StreamingDetector::new(detector, fs)
+        .with_window(args.segment_length, args.segment_overlap)
+        .with_dedup_interval(args.dedup_interval)
+        .with_start_time(first[0].time);
+    let filter = args.filter();
+    if !filter.is_empty() {
+        streaming = streaming.with_filter(&filter);
+    }
+
+    let mut detections = Vec::new();
+    let mut gaps = Vec::new();
+    // the last time of the previous block, to find gaps between blocks
+    let mut last_time = None;
+    for chunk in std::iter::once(Ok(first)).chain(chunks) {
+        let chunk = chunk?;
+        let times: Vec<f64> = last_time
+            .into_iter()
+            .chain(chunk.iter().map(|point| point.time))
+            .collect();
+        gaps.extend(find_gaps(&times, fs));
+        last_time = times.last().copied();
+
+        let voltage: Vec<f64> = chunk.iter().map(|point| point.voltage).collect();
+        detections.extend(streaming.push_samples(&voltage));
+    }
+    detections.extend(streaming.finish());
+    if let Some(min_confidence) = args.min_confidence {
+        detections.retain(|detection| detection.confidence >= min_confidence);
+    }
+
+    info!("Total data points: {}", streaming.samples_seen());
+    info!("Found {} QRS complexes", detections.len());
+    report_gaps(&gaps);
+
+    Ok(DetectionRun::new(detections, gaps))
+}
+
+fn beat_times(detections: &[Detection]) -> Vec<f64> {
+    detections.iter().map(|detection| detection.time).collect()
+}
+
+// beats read from RR intervals have times only: they have no amplitudes, their samples
+// follow from --fs, and there is no signal to copy or measure
+fn check_rr_outputs(args: &DetectArgs) -> Result<(), Box<dyn Error>> {
+    if args.samples {
+        return Err("--samples needs an ECG, RR intervals give no samples or amplitudes".into());
+    }
+    if args.annotations.is_some() && args.input.fs.is_none() {
+        return Err(
+            "--annotations of RR intervals needs --fs to place the beats at samples".into(),
+        );
+    }
+    let signal_outputs = [
+        ("--edf-output", args.edf_output.is_some()),
+        ("--waves-output", args.waves_output.is_some()),
+        ("--qt-output", args.qt_output.is_some()),
+        ("--st-output", args.st_output.is_some()),
+        ("--st-episodes-output", args.st_episodes_output.is_some()),
+        ("--saecg-output", args.saecg_output.is_some()),
+        ("--twa-output", args.twa_output.is_some()),
+        ("--edr-output", args.edr_output.is_some()),
+    ];
+    match signal_outputs.iter().find(|(_, given)| *given) {
+        Some((option, _)) => {
+            Err(format!("{} needs an ECG, the input holds RR intervals only", option).into())
+        }
+        None => Ok(()),
+    }
+}
+
+fn run_detect(args: DetectArgs) -> Result<(), Box<dyn Error>> {
+    if args.all_leads && is_stdio(&args.output) {
+        return Err("--all-leads writes one file per lead and cannot write to stdout".into());
+    }
+    if matches!(args.emit, Emit::Both) && args.rr_output.is_none() && is_stdio(&args.output) {
+        return Err("--emit both to stdout needs an --rr-output file".into());
+    }
+    args.format.check_available()?;
+    if args.hdf5_output.is_some() && cfg!(not(feature = "hdf5")) {
+        return Err(HDF5_FEATURE.into());
+    }
+    if args.input.holds_rr_intervals() {
+        check_rr_outputs(&args)?;
+    }
+
+    let results: Vec<(Option<String>, DetectionRun)> = if args.all_leads {
+        run_detection_all_leads(&args.input)?
+            .into_iter()
+            .map(|(lead, run)| (Some(lead), run))
+            .collect()
+    } else {
+        vec![(None, run_detection(&args.input)?)]
+    };
+
+    // the outputs are written even without beats: the artifact and quality reports explain
+    // flat or lead-off recordings, and the beat files are left empty (or header-only)
+    for (lead, run) in &results {
+        if run.detections.is_empty() {
+            match lead {
+                Some(lead) => warn!("No beats detected in lead {}", lead),
+                None => warn!("No beats detected"),
+            }
+        }
+        write_detections(&args, lead.as_deref(), run)?;
+    }
+
+    info!("Detection complete.");
+
+    Ok(())
+}
+
+// writes the outputs selected by `args`, with paths suffixed by the lead name if given
+fn write_detections(
+    args: &DetectArgs,
+    lead: Option<&str>,
+    run: &DetectionRun,
+) -> Result<(), Box<dyn Error>> {
+    let lead_path = |path: &Path| match lead {
+        Some(lead) => suffixed_path(path, &file_name_safe(lead)),
+        None => path.to_path_buf(),
+    };
+    let output = lead_path(&args.output);
+    let detections = &run.detections;
+    let detector = &args.input.detector;
+    let columns = BeatColumns::new(&args.input, args.samples, args.absolute_times)?;
+
+    // writing results to file
+    match args.emit {
+        Emit::Qrs => {
+            info!("Writing to: {:?}", output);
+            write_positions_to_file(
+                detections,
+                &run.classes,
+                detector,
+                &output,
+                args.format,
+                columns,
+            )?;
+        }
+        Emit::Rr => {
+            info!("Writing to: {:?}", output);
+            write_rr_to_file(&run.rr_intervals(), &output, args.format)?;
+        }
+        Emit::Both => {
+            let rr_output = match &args.rr_output {
+                Some(path) => lead_path(path),
+                None => rr_output_path(&output),
+            };
+            info!("Writing to: {:?} and {:?}", output, rr_output);
+            write_positions_to_file(
+                detections,
+                &run.classes,
+                detector,
+                &output,
+                args.format,
+                columns,
+            )?;
+            write_rr_to_file(&run.rr_intervals(), &rr_output, args.format)?;
+        }
+    }
+
+    if let Some(path) = &args.annotations {
+        let path = lead_path(path);
+        info!("Writing annotations to: {:?}", path);
+        let mut file = io::BufWriter::new(create_writer(Some(&path))?);
+        if run.classes.is_empty() {
+            wfdb_utils::write_wfdb_annotations(&mut file, detections)?;
+        } else {
+            let annotations: Vec<(usize, u16)> = detections
+                .iter()
+                .zip(&run.classes)
+                .map(|(detection, class)| (detection.sample, class.wfdb_code()))
+                .collect();
+            wfdb_utils::write_labelled_wfdb_annotations(&mut file, &annotations)?;
+        }
+        file.flush()?;
+    }
+
+    if let Some(path) = &args.kubios_output {
+        let path = lead_path(path);
+        let start = match args.recording_start {
+            Some(start) => Some(start),
+            None => edf_header(&args.input)?.and_then(|header| header.start_datetime()),
+        };
+        if start.is_none() {
+            warn!("The recording's start date and time are unknown, pass --recording-start");
+        }
+        info!("Writing Kubios RR intervals to: {:?}", path);
+        let mut file = io::BufWriter::new(create_writer(Some(&path))?);
+        kubios_utils::write_kubios_rr(&mut file, &run.rr_intervals(), start)?;
+        file.flush()?;
+    }
+
+    if let Some(path) = &args.hdf5_output {
+        let path = lead_path(path);
+        info!("Writing HDF5 to: {:?}", path);
+        write_hdf5_output(&args.input, run, &path)?;
+    }
+
+    if let Some(path) = &args.edf_output {
+        let path = lead_path(path);
+        info!("Writing the annotated EDF to: {:?}", path);
+        write_annotated_edf(&args.input, run, &path)?;
+    }
+
+    if let Some(path) = &args.gaps_output {
+        let path = lead_path(path);
+        info!("Writing gaps to: {:?}", path);
+        write_gaps_to_file(&run.gaps, &path)?;
+    }
+
+    if let Some(path) = &args.quality_output {
+        let path = lead_path(path);
+        info!("Writing signal quality to: {:?}", path);
+        write_quality_to_file(&run.quality, &path)?;
+    }
+
+    if let Some(path) = &args.artifacts_output {
+        let path = lead_path(path);
+        info!("Writing artifacts to: {:?}", path);
+        write_artifacts_to_file(&run.artifacts, &path)?;
+    }
+
+    if let Some(path) = &args.waves_output {
+        let (waves, fs) =
+            beat_waves(run).ok_or("--waves-output is only supported for single-lead detection")?;
+        let path = lead_path(path);
+        info!("Writing waves to: {:?}", path);
+        write_waves_to_file(&run.detections, &waves, fs, &path)?;
+    }
+
+    if let Some(path) = &args.qt_output {
+        let (waves, fs) =
+            beat_waves(run).ok_or("--qt-output is only supported for single-lead detection")?;
+        let series = qt::qt_series(&waves, &beat_times(&run.detections), fs);
+        let path = lead_path(path);
+        info!("Writing QT intervals to: {:?}", path);
+        write_qt_to_file(&series, &path)?;
+        match qt::qt_summary(&series) {
+            Some(summary) => {
+                info!(
+                    "QTc (Bazett) mean {:.1} ms, max {:.1} ms, {} beats over 500 ms",
+                    summary.mean_bazett, summary.max_bazett, summary.bazett_over_500
+                );
+                if let Some(path) = &args.qt_summary {
+                    let mut file = create_writer(Some(&lead_path(path)))?;
+                    hrv::report::write_json(&mut file, &[&summary])?;
+                }
+            }
+            None => info!("No beat with a delineated T wave for QT measurement"),
+        }
+    }
+
+    if args.st_output.is_some() || args.st_episodes_output.is_some() {
+        let (waves, fs) =
+            beat_waves(run).ok_or("ST analysis is only supported for single-lead detection")?;
+        // a 0.05 Hz high-pass leaves the ST segment undistorted
+        let filter = FilterConfig {
+            highpass: Some(0.05),
+            zero_phase: true,
+            ..FilterConfig::ecg()
+        };
+        let levels = st::st_levels(
+            &filter.apply(&run.signal, fs),
+            fs,
+            &waves,
+            &beat_times(&run.detections),
+        );
+        if let Some(path) = &args.st_output {
+            let path = lead_path(path);
+            info!("Writing ST levels to: {:?}", path);
+            write_st_to_file(&levels, &path)?;
+        }
+        let options = StOptions {
+            threshold: args.st_threshold,
+            min_duration: args.st_min_duration,
+        };
+        let episodes = st::st_episodes(&levels, &options);
+        info!(
+            "Found {} ST elevation and {} ST depression episodes",
+            episodes.iter().filter(|episode| episode.elevation).count(),
+            episodes.iter().filter(|episode| !episode.elevation).count()
+        );
+        if let Some(path) = &args.st_episodes_output {
+            let path = lead_path(path);
+            info!("Writing ST episodes to: {:?}", path);
+            write_st_episodes_to_file(&episodes, &path)?;
+        }
+    }
+
+    if let Some(path) = &args.saecg_output {
+        let fs = run
+            .fs
+            .filter(|_| !run.signal.is_empty())
+            .ok_or("--saecg-output is only supported for single-lead detection")?;
+        let filter = FilterConfig {
+            highpass: Some(0.5),
+            zero_phase: true,
+            ..FilterConfig::default()
+        };
+        let samples: Vec<usize> = run
+            .detections
+            .iter()
+            .map(|detection| detection.sample)
+            .collect();
+        let options = AveragingOptions {
+            min_correlation: args.saecg_min_correlation,
+        };
+        let average = saecg::signal_average(&filter.apply(&run.signal, fs), fs, &samples, &options)
+            .ok_or("no beat matches the QRS template closely enough for signal averaging")?;
+        let magnitude = saecg::filtered_magnitude(&[&average.complex], fs);
+        let path = lead_path(path);
+        info!(
+            "Averaged {} beats, writing the averaged complex to: {:?}",
+            average.beats, path
+        );
+        write_saecg_to_file(&average, &magnitude, fs, &path)?;
+        if fs < 500.0 {
+            warn!(
+                "Late potential analysis needs 500 Hz or more, the recording has {:.0} Hz",
+                fs
+            );
+        }
+        match saecg::late_potentials(&magnitude, fs, &average) {
+            Some(late) => {
+                info!(
+                    "Filtered QRS {:.1} ms, RMS40 {:.4}, LAS40 {:.1} ms",
+                    1000.0 * late.qrs_duration,
+                    late.rms40,
+                    1000.0 * late.las40
+                );
+                if let Some(path) = &args.saecg_summary {
+                    let mut file = create_writer(Some(&lead_path(path)))?;
+                    hrv::report::write_json(&mut file, &[&late])?;
+                }
+            }
+            None => info!("No filtered QRS stands out of the noise of the averaged complex"),
+        }
+    }
+
+    if let Some(path) = &args.twa_output {
+        let fs = run
+            .fs
+            .filter(|_| !run.signal.is_empty())
+            .ok_or("--twa-output is only supported for single-lead detection")?;
+        let filter = FilterConfig {
+            zero_phase: true,
+            ..FilterConfig::ecg()
+        };
+        let samples: Vec<usize> = run
+            .detections
+            .iter()
+            .map(|detection| detection.sample)
+            .collect();
+        let windows = twa::twa_windows(
+            &filter.apply(&run.signal, fs),
+            fs,
+            &samples,
+            &beat_times(&run.detections),
+            &TwaOptions::default(),
+        );
+        let episodes = twa::twa_episodes(&windows);
+        info!(
+            "Found {} episodes of significant T-wave alternans in {} windows",
+            episodes.len(),
+            windows.len()
+        );
+        let path = lead_path(path);
+        info!("Writing T-wave alternans to: {:?}", path);
+        write_twa_to_file(&windows, &path)?;
+    }
+
+    if let Some(path) = &args.edr_output {
+        let fs = run
+            .fs
+            .filter(|_| !run.signal.is_empty())
+            .ok_or("--edr-output is only supported for single-lead detection")?;
+        let filter = FilterConfig {
+            zero_phase: true,
+            ..FilterConfig::ecg()
+        };
+        let filtered = filter.apply(&run.signal, fs);
+        let amplitudes: Vec<f64> = run
+            .detections
+            .iter()
+            .map(|detection| filtered[detection.sample.min(filtered.len() - 1)])
+            .collect();
+        let rates = respiration::breathing_rates(
+            &beat_times(&run.detections),
+            &amplitudes,
+            &EdrOptions::default(),
+        );
+        if !rates.is_empty() {
+            let mean = rates.iter().map(|rate| rate.rate).sum::<f64>() / rates.len() as f64;
+            info!(
+                "Mean breathing rate {:.1} per minute over {} windows",
+                mean,
+                rates.len()
+            );
+        }
+        let path = lead_path(path);
+        info!("Writing the breathing rate to: {:?}", path);
+        write_edr_to_file(&rates, &path)?;
+    }
+
+    Ok(())
+}
+
+// a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn beat_json(
+    beat: usize,
+    detection: &Detection,
+    class: Option<BeatClass>,
+    detector: &str,
+    columns: BeatColumns,
+) -> String {
+    let class = match class {
+        Some(class) => format!(", \"class\": {}", json_string(class.symbol())),
+        None => String::new(),
+    };
+    let datetime = match columns.datetime(detection.time) {
+        Some(datetime) => format!(", \"datetime\": {}", json_string(&datetime)),
+        None => String::new(),
+    };
+    format!(
+        "{{\"beat\": {}, \"time\": {:.6}{}, \"sample\": {}, \"amplitude\": {}, \
+         \"detector\": {}, \"confidence\": {}{}}}",
+        beat,
+        detection.time,
+        datetime,
+        detection.sample,
+        detection.amplitude,
+        json_string(detector),
+        detection.confidence,
+        class
+    )
+}
+
+// optional columns of the beat outputs
+#[derive(Clone, Copy, Default)]
+struct BeatColumns {
+    // the sample index and amplitude of every beat in the text and CSV layouts, which JSON
+    // always has
+    samples: bool,
+    // seconds since 1970-01-01 at time 0, to add the date and time of every beat
+    start_datetime: Option<f64>,
+}
+
+impl BeatColumns {
+    // --samples and --absolute-times, the latter from the EDF start date and time
+    fn new(input: &InputArgs, samples: bool, absolute_times: bool) -> Result<Self, Box<dyn Error>> {
+        let start_datetime = if absolute_times {
+            let start = edf_header(input)?.and_then(|header| header.start_datetime());
+            Some(start.ok_or(
+                "--absolute-times needs an EDF recording with a valid start date and time",
+            )?)
+        } else {
+            None
+        };
+        Ok(BeatColumns {
+            samples,
+            start_datetime,
+        })
+    }
+
+    fn datetime(&self, time: f64) -> Option<String> {
+        self.start_datetime
+            .map(|start| datetime::iso_datetime(start + time))
+    }
+}
+
+// `classes` is empty or holds the class of every beat, written as its WFDB mnemonic; the
+// text layout lists the date and time of the beats instead of seconds when they are known
+fn write_positions_to_file<P: AsRef<Path>>(
+    detections: &[Detection],
+    classes: &[BeatClass],
+    detector: &str,
+    path: P,
+    format: OutputFormat,
+    columns: BeatColumns,
+) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path.as_ref()))?);
+
+    match format {
+        OutputFormat::Txt => {
+            for detection in detections {
+                let time = columns
+                    .datetime(detection.time)
+                    .unwrap_or_else(|| format!("{:.6}", detection.time));
+                if columns.samples {
+                    writeln!(
+                        file,
+                        "{}\t{}\t{}",
+                        time, detection.sample, detection.amplitude
+                    )?;
+                } else {
+                    writeln!(file, "{}", time)?;
+                }
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(
+                file,
+                "beat,time{}{},confidence{}",
+                if columns.start_datetime.is_some() {
+                    ",datetime"
+                } else {
+                    ""
+                },
+                if columns.samples {
+                    ",sample,amplitude"
+                } else {
+                    ""
+                },
+                if classes.is_empty() { "" } else { ",class" }
+            )?;
+            for (beat, detection) in detections.iter().enumerate() {
+                let mut position = columns
+                    .datetime(detection.time)
+                    .map_or(String::new(), |datetime| format!(",{}", datetime));
+                if columns.samples {
+                    position.push_str(&format!(",{},{}", detection.sample, detection.amplitude));
+                }
+                let class = classes
+                    .get(beat)
+                    .map_or(String::new(), |class| format!(",{}", class.symbol()));
+                writeln!(
+                    file,
+                    "{},{:.6}{},{}{}",
+                    beat + 1,
+                    detection.time,
+                    position,
+                    detection.confidence,
+                    class
+                )?;
+            }
+        }
+        OutputFormat::Json => {
+            writeln!(file, "[")?;
+            for (beat, detection) in detections.iter().enumerate() {
+                let separator = if beat + 1 < detections.len() { "," } else { "" };
+                writeln!(
+                    file,
+                    "  {}{}",
+                    beat_json(
+                        beat + 1,
+                        detection,
+                        classes.get(beat).copied(),
+                        detector,
+                        columns
+                    ),
+                    separator
+                )?;
+            }
+            writeln!(file, "]")?;
+        }
+        OutputFormat::Jsonl => {
+            for (beat, detection) in detections.iter().enumerate() {
+                let class = classes.get(beat).copied();
+                writeln!(
+                    file,
+                    "{}",
+                    beat_json(beat + 1, detection, class, detector, columns)
+                )?;
+            }
+        }
+        OutputFormat::Parquet | OutputFormat::Arrow => {
+            write_beat_table(&mut file, detections, classes, detector, format, columns)?;
+        }
+    }
+
+    file.flush()
+}
+
+const HDF5_FEATURE: &str = "HDF5 output needs the `hdf5` feature";
+
+// the signal as detection filters it (without phase shift), the beats, RR intervals and the
+// HRV trend of a run in one HDF5 file
+#[cfg(feature = "hdf5")]
+fn write_hdf5_output(
+    input: &InputArgs,
+    run: &DetectionRun,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let filtered = match run.fs {
+        Some(fs) => FilterConfig {
+            zero_phase: true,
+            ..input.filter()
+        }
+        .apply(&run.signal, fs),
+        None => Vec::new(),
+    };
+    let intervals = run.rr_intervals();
+    let trend = hrv::hrv_trend(&intervals, 300.0, 60.0, Some(SpectralMethod::Welch));
+    let source = input.input.to_string_lossy();
+    let recording = hdf5_utils::Hdf5Recording {
+        source: &source,
+        fs: run.fs,
+        start: run.start,
+        raw: &run.signal,
+        filtered: &filtered,
+        detections: &run.detections,
+        classes: &run.classes,
+        intervals: &intervals,
+        trend: &trend,
+    };
+    hdf5_utils::write_hdf5(path, &recording)
+}
+
+#[cfg(not(feature = "hdf5"))]
+fn write_hdf5_output(
+    _input: &InputArgs,
+    _run: &DetectionRun,
+    _path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    Err(HDF5_FEATURE.into())
+}
+
+const TABLE_FEATURE: &str = "Parquet and Arrow output need the `parquet` feature";
+
+#[cfg(feature = "parquet")]
+fn table_format(format: OutputFormat) -> arrow_utils::TableFormat {
+    match format {
+        OutputFormat::Arrow => arrow_utils::TableFormat::Arrow,
+        _ => arrow_utils::TableFormat::Parquet,
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn write_beat_table<W: Write + Send>(
+    writer: W,
+    detections: &[Detection],
+    classes: &[BeatClass],
+    detector: &str,
+    format: OutputFormat,
+    columns: BeatColumns,
+) -> io::Result<()> {
+    arrow_utils::beats_table(detections, classes, detector, columns.start_datetime)
+        .map_err(io::Error::other)
+        .and_then(|table| {
+            arrow_utils::write_table(writer, &table, table_format(format))
+                .map_err(|err| io::Error::other(err.to_string()))
+        })
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_beat_table<W: Write + Send>(
+    _writer: W,
+    _detections: &[Detection],
+    _classes: &[BeatClass],
+    _detector: &str,
+    _format: OutputFormat,
+    _columns: BeatColumns,
+) -> io::Result<()> {
+    Err(io::Error::other(TABLE_FEATURE))
+}
+
+#[cfg(feature = "parquet")]
+fn write_rr_table<W: Write + Send>(
+    writer: W,
+    intervals: &[RrInterval],
+    format: OutputFormat,
+) -> io::Result<()> {
+    arrow_utils::rr_table(intervals)
+        .map_err(io::Error::other)
+        .and_then(|table| {
+            arrow_utils::write_table(writer, &table, table_format(format))
+                .map_err(|err| io::Error::other(err.to_string()))
+        })
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_rr_table<W: Write + Send>(
+    _writer: W,
+    _intervals: &[RrInterval],
+    _format: OutputFormat,
+) -> io::Result<()> {
+    Err(io::Error::other(TABLE_FEATURE))
+}
+
+fn write_rr_to_file<P: AsRef<Path>>(
+    intervals: &[RrInterval],
+    path: P,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path.as_ref()))?);
+
+    match format {
+        OutputFormat::Txt => {
+            for rr in intervals {
+                writeln!(file, "{:.6}\t{:.3}", rr.time, rr.rr_ms)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(file, "time,rr_ms")?;
+            for rr in intervals {
+                writeln!(file, "{:.6},{:.3}", rr.time, rr.rr_ms)?;
+            }
+        }
+        OutputFormat::Json => {
+            writeln!(file, "[")?;
+            for (i, rr) in intervals.iter().enumerate() {
+                let separator = if i + 1 < intervals.len() { "," } else { "" };
+                writeln!(file, "  {}{}", rr_json(rr), separator)?;
+            }
+            writeln!(file, "]")?;
+        }
+        OutputFormat::Jsonl => {
+            for rr in intervals {
+                writeln!(file, "{}", rr_json(rr))?;
+            }
+        }
+        OutputFormat::Parquet | OutputFormat::Arrow => {
+            write_rr_table(&mut file, intervals, format)?;
+        }
+    }
+
+    file.flush()
+}
+
+fn write_gaps_to_file(gaps: &[Gap], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(file, "start,end,duration")?;
+    for gap in gaps {
+        writeln!(
+            file,
+            "{:.6},{:.6},{:.6}",
+            gap.start,
+            gap.end,
+            gap.duration()
+        )?;
+    }
+    file.flush()
+}
+
+fn write_quality_to_file(quality: &[WindowQuality], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(
+        file,
+        "start,end,kurtosis,baseline_power_ratio,flatline_fraction,clipping_fraction,label"
+    )?;
+    for window in quality {
+        let q = &window.quality;
+        writeln!(
+            file,
+            "{:.6},{:.6},{:.3},{:.4},{:.4},{:.4},{}",
+            window.start,
+            window.end,
+            q.kurtosis,
+            q.baseline_power_ratio,
+            q.flatline_fraction,
+            q.clipping_fraction,
+            q.label.as_str()
+        )?;
+    }
+    file.flush()
+}
+
+fn write_artifacts_to_file(artifacts: &[ArtifactSpan], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(file, "start,end,duration,kind")?;
+    for artifact in artifacts {
+        writeln!(
+            file,
+            "{:.6},{:.6},{:.6},{}",
+            artifact.start,
+            artifact.end,
+            artifact.end - artifact.start,
+            artifact.kind.as_str()
+        )?;
+    }
+    file.flush()
+}
+
+// delineates the beats of a run on its signal, band-pass filtered without phase shift
+fn beat_waves(run: &DetectionRun) -> Option<(Vec<BeatWaves>, f64)> {
+    let fs = run.fs?;
+    if run.signal.is_empty() {
+        return None;
+    }
+    let filter = FilterConfig {
+        zero_phase: true,
+        ..FilterConfig::ecg()
+    };
+    let samples: Vec<usize> = run
+        .detections
+        .iter()
+        .map(|detection| detection.sample)
+        .collect();
+    let waves = delineation::delineate(&filter.apply(&run.signal, fs), fs, &samples);
+    Some((waves, fs))
+}
+
+fn write_waves_to_file(
+    detections: &[Detection],
+    waves: &[BeatWaves],
+    fs: f64,
+    path: &Path,
+) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(
+        file,
+        "beat,time,p_onset,p_peak,p_offset,qrs_onset,qrs_offset,t_onset,t_peak,t_offset,\
+         pr_ms,qrs_ms,qt_ms"
+    )?;
+    for (beat, (detection, waves)) in detections.iter().zip(waves).enumerate() {
+        // times of the wave points, relative to the beat's
+        let time = |sample: usize| detection.time + (sample as f64 - detection.sample as f64) / fs;
+        let wave_times = |wave: Option<Wave>| match wave {
+            Some(wave) => format!(
+                "{:.6},{:.6},{:.6}",
+                time(wave.onset),
+                time(wave.peak),
+                time(wave.offset)
+            ),
+            None => ",,".to_string(),
+        };
+        let milliseconds = |seconds: Option<f64>| {
+            seconds
+                .map(|s| format!("{:.1}", 1000.0 * s))
+                .unwrap_or_default()
+        };
+        writeln!(
+            file,
+            "{},{:.6},{},{:.6},{:.6},{},{},{:.1},{}",
+            beat + 1,
+            detection.time,
+            wave_times(waves.p),
+            time(waves.qrs.onset),
+            time(waves.qrs.offset),
+            wave_times(waves.t),
+            milliseconds(waves.pr_interval(fs)),
+            1000.0 * waves.qrs_duration(fs),
+            milliseconds(waves.qt_interval(fs))
+        )?;
+    }
+    file.flush()
+}
+
+fn write_qt_to_file(series: &[QtBeat], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(
+        file,
+        "time,rr_ms,qt_ms,qtc_bazett_ms,qtc_fridericia_ms,qtc_framingham_ms"
+    )?;
+    for beat in series {
+        writeln!(
+            file,
+            "{:.6},{:.1},{:.1},{:.1},{:.1},{:.1}",
+            beat.time,
+            1000.0 * beat.rr,
+            1000.0 * beat.qt,
+            1000.0 * beat.bazett,
+            1000.0 * beat.fridericia,
+            1000.0 * beat.framingham
+        )?;
+    }
+    file.flush()
+}
+
+fn write_st_to_file(levels: &[StBeat], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(file, "time,baseline,st_j,st_j60,st_j80")?;
+    for beat in levels {
+        writeln!(
+            file,
+            "{:.6},{},{},{},{}",
+            beat.time, beat.baseline, beat.j, beat.st60, beat.st80
+        )?;
+    }
+    file.flush()
+}
+
+fn write_st_episodes_to_file(episodes: &[StEpisode], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(file, "start,end,duration,kind,peak")?;
+    for episode in episodes {
+        let kind = if episode.elevation {
+            "elevation"
+        } else {
+            "depression"
+        };
+        writeln!(
+            file,
+            "{:.6},{:.6},{:.6},{},{}",
+            episode.start,
+            episode.end,
+            episode.duration(),
+            kind,
+            episode.peak
+        )?;
+    }
+    file.flush()
+}
+
+// the averaged complex with times relative to the beat
+fn write_saecg_to_file(
+    average: &SignalAverage,
+    magnitude: &[f64],
+    fs: f64,
+    path: &Path,
+) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(file, "time_ms,average,filtered_magnitude")?;
+    for (i, (value, filtered)) in average.complex.iter().zip(magnitude).enumerate() {
+        let time = 1000.0 * (i as f64 - average.fiducial as f64) / fs;
+        writeln!(file, "{:.3},{},{}", time, value, filtered)?;
+    }
+    file.flush()
+}
+
+fn write_twa_to_file(windows: &[TwaWindow], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(file, "start,end,voltage,k_score,significant")?;
+    for window in windows {
+        writeln!(
+            file,
+            "{:.6},{:.6},{},{},{}",
+            window.start, window.end, window.voltage, window.k_score, window.significant
+        )?;
+    }
+    file.flush()
+}
+
+fn write_edr_to_file(rates: &[BreathingRate], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(file, "start,end,breaths_per_min,amplitude_rate,rr_rate")?;
+    for rate in rates {
+        writeln!(
+            file,
+            "{:.6},{:.6},{:.2},{:.2},{:.2}",
+            rate.start, rate.end, rate.rate, rate.amplitude_rate, rate.rr_rate
+        )?;
+    }
+    file.flush()
+}
 
-    // file paths
-    let input_path = current_dir.join("ecg.csv");
-    let output_path = current_dir.join("positions.txt");
+fn rr_json(rr: &RrInterval) -> String {
+    format!("{{\"time\": {:.6}, \"rr_ms\": {:.3}}}", rr.time, rr.rr_ms)
+}
 
-    println!("Reading from: {:?}", input_path);
+// gaps of the recording, reported with the HRV results they were left out of
+struct GapSummary<'a> {
+    gaps: &'a [Gap],
+    excluded_rr: usize,
+}
 
-    // reading the data
-    let ecg_data = read_ecg_data(&input_path)?;
+impl HrvMetrics for GapSummary<'_> {
+    fn section(&self) -> &'static str {
+        "gaps"
+    }
 
-    if ecg_data.is_empty() {
-        println!("No data found in the ECG file");
-        return Ok(());
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("count", Some(self.gaps.len() as f64)),
+            (
+                "total_seconds",
+                Some(self.gaps.iter().map(Gap::duration).sum()),
+            ),
+            (
+                "longest_seconds",
+                self.gaps.iter().map(Gap::duration).reduce(f64::max),
+            ),
+            ("excluded_rr", Some(self.excluded_rr as f64)),
+        ]
     }
+}
 
-    // detecting QRS complexes
-    let qrs_positions = detect_qrs_complexes(&ecg_data);
+fn run_hrv(args: HrvArgs) -> Result<(), Box<dyn Error>> {
+    let mut input = args.input.clone();
+    input.classify |= args.turbulence;
+    // the ectopy findings of FHIR output need the beat classes, as in `rhythm`; RR input has
+    // none, and its bundle leaves them out
+    let fhir = matches!(args.format, HrvFormat::Fhir);
+    input.classify |=
+        fhir && !input.holds_rr_intervals() && !input.fuse_leads && input.chunk_size.is_none();
+    let run = run_detection(&input)?;
+    let detected = run.rr_intervals();
+    let cleaned = args.rr_filter.map(|kind| {
+        let edit = match args.rr_edit {
+            RrEditKind::Delete => RrEdit::Delete,
+            RrEditKind::Interpolate => RrEdit::Interpolate,
+        };
+        let cleaned = hrv::clean_rr(&detected, kind.filter(args.rr_filter_threshold), edit);
+        info!(
+            "RR cleaning edited {} of {} intervals ({:.2}%)",
+            cleaned.edited,
+            cleaned.total,
+            100.0 * cleaned.edited_fraction()
+        );
+        cleaned
+    });
+    let intervals = match &cleaned {
+        Some(cleaned) => &cleaned.intervals,
+        None => &detected,
+    };
 
-    println!("Writing to: {:?}", output_path);
-    println!("Found {} QRS complexes", qrs_positions.len());
+    let time_domain =
+        hrv::time_domain(intervals).ok_or("At least two RR intervals are needed for HRV")?;
+    let method = match args.spectral {
+        Spectral::Welch => Some(SpectralMethod::Welch),
+        Spectral::LombScargle => Some(SpectralMethod::LombScargle),
+        Spectral::None => None,
+    };
+    let frequency_domain = method.and_then(|method| hrv::frequency_domain(intervals, method));
 
-    // writing results to file
-    write_positions_to_file(&qrs_positions, &output_path)?;
+    let asymmetry = hrv::asymmetry(intervals);
+    let poincare = hrv::poincare(intervals);
+    let geometric = hrv::geometric(intervals);
+    let prsa = hrv::prsa(intervals);
+    let entropy_options = EntropyOptions {
+        m: args.entropy_m,
+        r: args.entropy_r,
+        ..EntropyOptions::default()
+    };
+    let entropy = args
+        .entropy
+        .then(|| hrv::entropy(intervals, &entropy_options))
+        .flatten();
+    let turbulence = if args.turbulence {
+        let turbulence = hrv::turbulence(&beat_times(&run.detections), &run.classes);
+        if turbulence.is_none() {
+            warn!("No ventricular beat qualifies for heart rate turbulence");
+        }
+        turbulence
+    } else {
+        None
+    };
+
+    let periods: Vec<Option<PeriodHrv>> = if args.periods.is_empty() {
+        Vec::new()
+    } else {
+        let start_clock = recording_start_clock(&args)?;
+        args.periods
+            .iter()
+            .map(|period| {
+                let hrv = hrv::period_hrv(intervals, start_clock, period, method);
+                if hrv.is_none() {
+                    info!("Too few RR intervals in period {} for HRV", period.name);
+                }
+                hrv
+            })
+            .collect()
+    };
+    let deltas: Vec<PeriodDelta> = match periods.split_first() {
+        Some((Some(reference), others)) => others
+            .iter()
+            .flatten()
+            .map(|period| PeriodDelta::new(period, reference))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut sections: Vec<&dyn HrvMetrics> = vec![&time_domain];
+    if let Some(frequency_domain) = &frequency_domain {
+        sections.push(frequency_domain);
+    }
+    if let Some(poincare) = &poincare {
+        sections.push(poincare);
+    }
+    if let Some(geometric) = &geometric {
+        sections.push(geometric);
+    }
+    if let Some(asymmetry) = &asymmetry {
+        sections.push(asymmetry);
+    }
+    if let Some(prsa) = &prsa {
+        sections.push(prsa);
+    }
+    if let Some(entropy) = &entropy {
+        sections.push(entropy);
+    }
+    let gaps = GapSummary {
+        gaps: &run.gaps,
+        excluded_rr: run.detections.len().saturating_sub(1) - detected.len(),
+    };
+    if !run.gaps.is_empty() {
+        sections.push(&gaps);
+    }
+    if let Some(cleaned) = &cleaned {
+        sections.push(cleaned);
+    }
+    if let Some(turbulence) = &turbulence {
+        sections.push(turbulence);
+    }
+    for period in periods.iter().flatten() {
+        sections.push(period);
+    }
+    for delta in &deltas {
+        sections.push(delta);
+    }
+
+    let mut writer = create_writer(args.output.as_deref())?;
+    match args.format {
+        HrvFormat::Json => hrv::report::write_json(&mut writer, &sections)?,
+        HrvFormat::Csv => hrv::report::write_csv(&mut writer, &sections)?,
+        HrvFormat::Fhir => {
+            let (events, _) = rhythm_events(&run, &AfOptions::default(), &RateLimits::default());
+            let findings = event_summaries(&run, &events);
+            let start = match args.recording_start {
+                Some(start) => Some(start),
+                None => edf_header(&args.input)?.and_then(|header| header.start_datetime()),
+            };
+            let period = match (start, run.detections.first(), run.detections.last()) {
+                (Some(start), Some(first), Some(last)) => {
+                    Some((start + first.time, start + last.time))
+                }
+                _ => None,
+            };
+            if period.is_none() {
+                warn!("The recording's start date and time are unknown, pass --recording-start");
+            }
+            let context = fhir_utils::FhirContext {
+                subject: args.patient.as_deref(),
+                period,
+                utc_offset: &args.utc_offset,
+            };
+            fhir_utils::write_fhir_bundle(
+                &mut writer,
+                &context,
+                time_domain.mean_hr,
+                &findings,
+                &sections,
+            )?
+        }
+    }
+
+    if let Some(path) = &args.poincare_output {
+        let mut file = File::create(path)?;
+        hrv::report::write_poincare_csv(&mut file, &hrv::poincare_points(intervals))?;
+    }
 
-    println!("Detection complete.");
+    let histograms = [
+        (
+            &args.rr_histogram,
+            hrv::rr_histogram(intervals, args.rr_bin),
+        ),
+        (
+            &args.hr_histogram,
+            hrv::hr_histogram(intervals, args.hr_bin),
+        ),
+    ];
+    for (path, histogram) in histograms {
+        if let Some(path) = path {
+            let histogram = histogram
+                .ok_or("no RR intervals for a histogram, or a bin width not above zero")?;
+            let mut file = create_writer(Some(path))?;
+            hrv::report::write_histogram_csv(&mut file, &histogram)?;
+        }
+    }
 
-    edf_utils::print_edf_signals("example.edf")?;
+    if let Some(path) = &args.trend_output {
+        let windows = hrv::hrv_trend(intervals, args.trend_window, args.trend_step, method);
+        info!("HRV trend over {} windows", windows.len());
+        let mut file = create_writer(Some(path))?;
+        hrv::report::write_trend_csv(&mut file, &windows)?;
+    }
 
     Ok(())
 }
 
-fn detect_qrs_complexes(ecg_data: &[EcgPoint]) -> Vec<f64> {
-    // if no data, return empty vector
-    if ecg_data.is_empty() {
-        return Vec::new();
+// seconds after midnight the recording starts, from --start-time or the EDF header
+fn recording_start_clock(args: &HrvArgs) -> Result<f64, Box<dyn Error>> {
+    if let Some(start_time) = args.start_time {
+        return Ok(start_time);
     }
+    let header = edf_header(&args.input)?
+        .ok_or("--period needs --start-time unless the input is an EDF file")?;
+    header
+        .start_clock()
+        .ok_or_else(|| format!("Invalid EDF start time {:?}", header.start_time).into())
+}
+
+// the index of the signal --signal names in an EDF, WFDB, ISHNE, SCP-ECG, aECG or DICOM
+// input, or --signal-index
+fn signal_index(args: &InputArgs) -> Result<usize, Box<dyn Error>> {
+    let Some(label) = &args.signal else {
+        return Ok(args.signal_index);
+    };
+    let input_format = args
+        .input_format
+        .unwrap_or_else(|| InputFormat::from_path(&args.input));
+    match input_format {
+        InputFormat::Csv => Err("--signal is for EDF and WFDB input, use --column for CSV".into()),
+        InputFormat::AppleHealth => Err("Apple Health exports hold a single lead".into()),
+        InputFormat::PolarHrm | InputFormat::PolarTxt | InputFormat::Fit => Err(RR_INPUT.into()),
+        _ if is_stdio(&args.input) => {
+            Err("--signal cannot be used with stdin, use --signal-index".into())
+        }
+        InputFormat::Edf => edf_utils::read_edf_header(&mut compression::open_input(&args.input)?)?
+            .find_signal(label),
+        InputFormat::Wfdb => wfdb_utils::read_wfdb_header(compression::open_input(
+            wfdb_utils::header_path(&args.input),
+        )?)?
+        .find_signal(label),
+        InputFormat::Ishne => {
+            ishne_utils::read_ishne_header(&mut compression::open_input(&args.input)?)?
+                .find_lead(label)
+        }
+        InputFormat::Scp => scp_utils::read_scp(&args.input)?.find_lead(label),
+        InputFormat::Aecg => aecg_utils::read_aecg(&args.input)?.find_lead(label),
+        InputFormat::Dicom => dicom_utils::read_dicom(&args.input)?.find_lead(label),
+    }
+}
+
+// the header of an EDF input file, `None` for other formats and stdin
+fn edf_header(args: &InputArgs) -> Result<Option<EdfHeader>, Box<dyn Error>> {
+    let input_format = args
+        .input_format
+        .unwrap_or_else(|| InputFormat::from_path(&args.input));
+    if input_format != InputFormat::Edf || is_stdio(&args.input) {
+        return Ok(None);
+    }
+    let header = edf_utils::read_edf_header(&mut compression::open_input(&args.input)?)?;
+    Ok(Some(header))
+}
 
-    // calculating sampling frequency
-    let sample_period = if ecg_data.len() > 1 {
-        ecg_data[1].time - ecg_data[0].time
+fn run_report(args: ReportArgs) -> Result<(), Box<dyn Error>> {
+    // the ectopy counts need the beat classes, which single-lead detection of an ECG provides
+    let mut input = args.input.clone();
+    if input.holds_rr_intervals() {
+        warn!("The input holds RR intervals only, the ectopy counts are left out");
     } else {
-        0.005 // assuming 200Hz as default
+        input.classify |= !input.fuse_leads && input.chunk_size.is_none();
+    }
+    let run = run_detection(&input)?;
+    let intervals = run.rr_intervals();
+    let time_domain =
+        hrv::time_domain(&intervals).ok_or("At least two RR intervals are needed for a report")?;
+
+    let mut metadata = vec![("File".to_string(), input.input.display().to_string())];
+    if let Some(header) = edf_header(&input)? {
+        metadata.push(("Recording".to_string(), header.recording.clone()));
+        metadata.push((
+            "Start".to_string(),
+            format!("{} {}", header.start_date, header.start_time),
+        ));
+    }
+    if let (Some(first), Some(last)) = (run.detections.first(), run.detections.last()) {
+        let seconds = (last.time - first.time).round() as u64;
+        metadata.push((
+            "Analysed".to_string(),
+            format!(
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ),
+        ));
+    }
+    if let Some(fs) = run.fs {
+        metadata.push(("Sampling rate".to_string(), format!("{} Hz", fs)));
+    }
+    metadata.push(("Detector".to_string(), input.detector.clone()));
+    metadata.push((
+        "Software".to_string(),
+        format!("signalweaver {}", env!("CARGO_PKG_VERSION")),
+    ));
+
+    let mut beats = vec![("Detected".to_string(), run.detections.len())];
+    if !run.classes.is_empty() {
+        for class in [
+            BeatClass::Normal,
+            BeatClass::Ventricular,
+            BeatClass::Artifact,
+        ] {
+            let count = run.classes.iter().filter(|&&c| c == class).count();
+            beats.push((class.as_str().to_string(), count));
+        }
+    }
+    beats.push(("RR intervals analysed".to_string(), intervals.len()));
+
+    let hr_trend = hrv::hrv_trend(&intervals, 60.0, 60.0, None)
+        .iter()
+        .map(|window| {
+            (
+                (window.start + window.end) / 2.0,
+                window.time_domain.mean_hr,
+            )
+        })
+        .collect();
+
+    let frequency_domain = hrv::frequency_domain(&intervals, SpectralMethod::Welch);
+    let geometric = hrv::geometric(&intervals);
+    let poincare = hrv::poincare(&intervals);
+    let asymmetry = hrv::asymmetry(&intervals);
+    let prsa = hrv::prsa(&intervals);
+    let (events, ectopy) = rhythm_events(&run, &AfOptions::default(), &RateLimits::default());
+
+    let mut sections: Vec<&dyn HrvMetrics> = vec![&time_domain];
+    if let Some(frequency_domain) = &frequency_domain {
+        sections.push(frequency_domain);
+    }
+    if let Some(geometric) = &geometric {
+        sections.push(geometric);
+    }
+    if let Some(poincare) = &poincare {
+        sections.push(poincare);
+    }
+    if let Some(asymmetry) = &asymmetry {
+        sections.push(asymmetry);
+    }
+    if let Some(prsa) = &prsa {
+        sections.push(prsa);
+    }
+    if let Some(ectopy) = &ectopy {
+        sections.push(ectopy);
+    }
+
+    let report = ReportData {
+        title: args.title.clone(),
+        metadata,
+        beats,
+        hr_trend,
+        hrv: sections,
+        poincare: hrv::poincare_points(&intervals),
+        summaries: event_summaries(&run, &events),
+        events,
     };
-    let fs = 1.0 / sample_period;
+    info!("Writing the report to: {:?}", args.output);
+    let mut file = io::BufWriter::new(create_writer(Some(&args.output))?);
+    html_report::write_html_report(&mut file, &report)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+// the detections of a single lead with its signal filtered for display and the beats to
+// mark on it, the detected ones followed by those of the reference
+struct StripSource {
+    run: DetectionRun,
+    fs: f64,
+    signal: Vec<f64>,
+    markers: Vec<Marker>,
+}
+
+fn strip_source(
+    input: &InputArgs,
+    reference: Option<&Path>,
+    reference_fs: Option<f64>,
+    command: &str,
+) -> Result<StripSource, Box<dyn Error>> {
+    if input.fuse_leads || input.chunk_size.is_some() {
+        return Err(format!(
+            "{} is not supported with --fuse-leads or --chunk-size",
+            command
+        )
+        .into());
+    }
+    let run = run_detection(input)?;
+    let fs = run
+        .fs
+        .ok_or("the sampling rate of the recording is unknown, pass --fs")?;
+
+    let mut markers: Vec<Marker> = run
+        .detections
+        .iter()
+        .enumerate()
+        .map(|(i, detection)| Marker {
+            time: detection.time,
+            label: run
+                .classes
+                .get(i)
+                .map_or(String::new(), |class| class.symbol().to_string()),
+            reference: false,
+        })
+        .collect();
+    if let Some(path) = reference {
+        markers.extend(
+            read_reference_times(path, reference_fs)?
+                .into_iter()
+                .map(|time| Marker {
+                    time,
+                    label: String::new(),
+                    reference: true,
+                }),
+        );
+    }
 
-    println!("Detected sampling frequency: {:.2} Hz", fs);
+    // the signal as detection filters it, without phase shift so the beats line up
+    let filter = FilterConfig {
+        zero_phase: true,
+        ..input.filter()
+    };
+    let signal = filter.apply(&run.signal, fs);
+    Ok(StripSource {
+        run,
+        fs,
+        signal,
+        markers,
+    })
+}
 
-    // Process the data in segments to handle long ECGs
-    let segment_size = (30.0 * fs) as usize; // 30 second segments
-    let mut all_qrs_positions = Vec::new();
+fn run_plot(args: PlotArgs) -> Result<(), Box<dyn Error>> {
+    let extension = args.output.extension().and_then(|ext| ext.to_str());
+    let png = match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("svg") => false,
+        Some("png") => true,
+        _ => return Err("--output must end in .svg or .png".into()),
+    };
+    if png && cfg!(not(feature = "png")) {
+        return Err("PNG plots need the `png` feature, write an .svg instead".into());
+    }
+    let source = strip_source(
+        &args.input,
+        args.reference.as_deref(),
+        args.reference_fs,
+        "plot",
+    )?;
+    let from = args.from.unwrap_or(source.run.start);
+    let to = args.to.unwrap_or(from + 10.0);
+    if to <= from {
+        return Err("--to must come after --from".into());
+    }
 
-    let total_segments = (ecg_data.len() + segment_size - 1) / segment_size;
+    let strip = Strip {
+        signal: &source.signal,
+        fs: source.fs,
+        start: source.run.start,
+        from,
+        to,
+        width: args.width.max(1),
+        height: args.height.max(1),
+    };
+    info!(
+        "Plotting {:.1} s to {:.1} s to: {:?}",
+        from, to, args.output
+    );
+    let mut file = io::BufWriter::new(create_writer(Some(&args.output))?);
+    if png {
+        #[cfg(feature = "png")]
+        plot::write_strip_png(&mut file, &strip, &source.markers)?;
+    } else {
+        file.write_all(plot::strip_svg(&strip, &source.markers).as_bytes())?;
+    }
+    file.flush()?;
 
-    for segment_idx in 0..total_segments {
-        let start_idx = segment_idx * segment_size;
-        let end_idx = std::cmp::min((segment_idx + 1) * segment_size, ecg_data.len());
+    Ok(())
+}
 
-        if end_idx - start_idx < (2.0 * fs) as usize {
-            // Skip segments shorter than 2 seconds
-            continue;
+#[cfg(feature = "tui")]
+fn run_view(args: ViewArgs) -> Result<(), Box<dyn Error>> {
+    let source = strip_source(
+        &args.input,
+        args.reference.as_deref(),
+        args.reference_fs,
+        "view",
+    )?;
+    let mut markers = source.markers;
+    if let Some(path) = &args.beats {
+        markers.retain(|marker| marker.reference);
+        markers.extend(read_beat_times(path)?.into_iter().map(|time| Marker {
+            time,
+            label: String::new(),
+            reference: false,
+        }));
+    }
+    let mut recording = view::Recording {
+        title: format!(" {} ", args.input.input.display()),
+        signal: &source.signal,
+        fs: source.fs,
+        start: source.run.start,
+        markers,
+    };
+    let from = args.from.unwrap_or(source.run.start);
+    let saved = view::view(&mut recording, from, args.span, args.edit.is_some())?;
+
+    if let Some(path) = &args.edit {
+        if !saved {
+            info!("Quit without saving, {:?} is unchanged", path);
+            return Ok(());
         }
+        let mut times: Vec<f64> = recording
+            .markers
+            .iter()
+            .filter(|marker| !marker.reference)
+            .map(|marker| marker.time)
+            .collect();
+        times.sort_by(f64::total_cmp);
+        info!("Writing {} beats to: {:?}", times.len(), path);
+        write_beat_times(Some(path), &times)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_view(_args: ViewArgs) -> Result<(), Box<dyn Error>> {
+    Err("The viewer needs the `tui` feature".into())
+}
+
+fn run_edit(args: EditArgs) -> Result<(), Box<dyn Error>> {
+    let mut times = read_beat_times(&args.beats)?;
+    let script = io::BufReader::new(File::open(&args.script)?);
+    let edits = editing::read_edit_script(script)?;
+    let counts = editing::apply_edits(&mut times, &edits, args.tolerance)?;
+    info!(
+        "Added {}, removed {} and moved {} beats, {} beats in total",
+        counts.added,
+        counts.removed,
+        counts.moved,
+        times.len()
+    );
+    write_beat_times(args.output.as_deref(), &times)?;
+
+    Ok(())
+}
+
+// beat times as a positions file, one per line
+fn write_beat_times(path: Option<&Path>, times: &[f64]) -> io::Result<()> {
+    let mut file = io::BufWriter::new(create_writer(path)?);
+    for time in times {
+        writeln!(file, "{:.6}", time)?;
+    }
+    file.flush()
+}
+
+fn run_runs(args: RunsArgs) -> Result<(), Box<dyn Error>> {
+    let run = run_detection(&args.input)?;
+    let runs = hrv::monotonic_runs(&run.rr_intervals());
+
+    let mut writer = create_writer(args.output.as_deref())?;
+    hrv::report::write_runs_csv(&mut writer, &runs)?;
+
+    Ok(())
+}
+
+fn run_rhythm(args: RhythmArgs) -> Result<(), Box<dyn Error>> {
+    // the ectopy patterns need the beat classes, which single-lead detection of an ECG provides
+    let mut input = args.input.clone();
+    if input.holds_rr_intervals() {
+        warn!("The input holds RR intervals only, ventricular ectopy is not detected");
+    } else {
+        input.classify |= !input.fuse_leads && input.chunk_size.is_none();
+    }
+    let run = run_detection(&input)?;
+
+    let af_options = AfOptions {
+        window: args.af_window,
+        min_duration: args.af_min_duration,
+    };
+    let limits = RateLimits {
+        bradycardia: args.brady_limit,
+        tachycardia: args.tachy_limit,
+        min_intervals: args.min_intervals,
+        pause: args.pause,
+        asystole: args.asystole,
+    };
+    let (events, ectopy) = rhythm_events(&run, &af_options, &limits);
+    let summaries = event_summaries(&run, &events);
+    for summary in &summaries {
+        info!(
+            "{}: {} episodes, {:.2}% of the recording",
+            summary.kind.as_str(),
+            summary.episodes,
+            summary.burden
+        );
+    }
 
-        // Process this segment
-        let segment_positions = process_segment(&ecg_data[start_idx..end_idx], fs);
+    let mut writer = create_writer(args.output.as_deref())?;
+    rhythm::write_events_csv(&mut writer, &events)?;
 
-        // Add segment positions to overall list
-        for pos in segment_positions {
-            all_qrs_positions.push(pos);
+    if let Some(path) = &args.summary {
+        let mut file = create_writer(Some(path))?;
+        let mut sections: Vec<&dyn HrvMetrics> = summaries
+            .iter()
+            .map(|summary| summary as &dyn HrvMetrics)
+            .collect();
+        if let Some(ectopy) = &ectopy {
+            sections.push(ectopy);
         }
+        hrv::report::write_json(&mut file, &sections)?;
     }
 
-    // Sort all positions in case segments were processed out of order
-    all_qrs_positions.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Ok(())
+}
+
+// the rhythm events of a run sorted by start, with the ventricular beat counts when its
+// beats are classified
+fn rhythm_events(
+    run: &DetectionRun,
+    af_options: &AfOptions,
+    limits: &RateLimits,
+) -> (Vec<RhythmEvent>, Option<EctopyCounts>) {
+    let intervals = run.rr_intervals();
+    let mut events = rhythm::detect_af(&intervals, af_options);
+    events.extend(rhythm::rate_events(&intervals, limits));
+    let ectopy = (!run.classes.is_empty()).then(|| {
+        let (ectopy_events, counts) =
+            rhythm::ectopy_events(&beat_times(&run.detections), &run.classes);
+        events.extend(ectopy_events);
+        counts
+    });
+    events.sort_by(|a, b| a.start.total_cmp(&b.start));
+    (events, ectopy)
+}
+
+// the summary of every event kind, the recording spanning from the first to the last beat
+fn event_summaries(run: &DetectionRun, events: &[RhythmEvent]) -> Vec<EventSummary> {
+    let duration = match (run.detections.first(), run.detections.last()) {
+        (Some(first), Some(last)) => last.time - first.time,
+        _ => 0.0,
+    };
+    EventKind::ALL
+        .into_iter()
+        .map(|kind| EventSummary::new(kind, events, duration))
+        .collect()
+}
 
-    // Remove duplicates from segment overlaps
-    let mut final_positions = Vec::new();
-    if !all_qrs_positions.is_empty() {
-        final_positions.push(all_qrs_positions[0]);
+// recordings named by the batch inputs, with directories and wildcard patterns expanded
+fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let file_name = input
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (dir, pattern) = if input.is_dir() {
+            (input.as_path(), "*".to_string())
+        } else if file_name.contains(['*', '?']) {
+            let dir = input.parent().filter(|dir| !dir.as_os_str().is_empty());
+            (dir.unwrap_or(Path::new(".")), file_name)
+        } else {
+            files.push(input.clone());
+            continue;
+        };
 
-        for i in 1..all_qrs_positions.len() {
-            if all_qrs_positions[i] - final_positions.last().unwrap() >= 0.2 {
-                // 200ms minimum
-                final_positions.push(all_qrs_positions[i]);
+        let mut matches = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned());
+            if path.is_file() && name.is_some_and(|name| wildcard_match(&pattern, &name)) {
+                matches.push(path);
             }
         }
+        matches.sort();
+        files.extend(matches);
     }
+    Ok(files)
+}
 
-    final_positions
+// `*` matches any run of characters and `?` any single character
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+            Some((&c, rest)) => name
+                .split_first()
+                .is_some_and(|(&n, name)| (c == '?' || c == n) && matches(rest, name)),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
 }
 
-fn process_segment(segment_data: &[EcgPoint], fs: f64) -> Vec<f64> {
-    // Extract voltage values
-    let voltage: Vec<f64> = segment_data.iter().map(|point| point.voltage).collect();
+// one row of the batch summary
+struct BatchEntry {
+    input: PathBuf,
+    beats: usize,
+    gaps: usize,
+    mean_hr: Option<f64>,
+    error: Option<String>,
+}
 
-    // Step 1: Normalization
-    let mean: f64 = voltage.iter().sum::<f64>() / voltage.len() as f64;
-    let normalized: Vec<f64> = voltage.iter().map(|&v| v - mean).collect();
+fn run_batch(args: BatchArgs) -> Result<(), Box<dyn Error>> {
+    args.format.check_available()?;
+    let store = args.output.as_deref().map(open_store).transpose()?;
+    let mut inputs = vec![args.input.input.clone()];
+    inputs.extend(args.more_inputs.iter().cloned());
+    if let Some(dir) = &args.output_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    if args.watch {
+        SHOW_PROGRESS.store(false, Ordering::Relaxed);
+        return watch_batch(&args, &inputs, store.as_ref());
+    }
 
-    // Step 2: Find QRS complexes directly
-    find_qrs_peaks_direct(&normalized, segment_data, fs)
-}
+    let files = expand_inputs(&inputs)?;
+    if files.is_empty() {
+        return Err(format!("No recordings match {:?}", inputs).into());
+    }
 
-fn find_qrs_peaks_direct(voltage: &[f64], ecg_data: &[EcgPoint], fs: f64) -> Vec<f64> {
-    let mut qrs_positions = Vec::new();
+    // one bar over the recordings instead of one per recording
+    let progress = progress_bar("recordings");
+    SHOW_PROGRESS.store(false, Ordering::Relaxed);
+    let done = AtomicU64::new(0);
+    let process = |input: &PathBuf| {
+        let entry = process_recording(&args, input, store.as_ref());
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        progress.report(done, files.len() as u64);
+        entry
+    };
+
+    #[cfg(feature = "parallel")]
+    let entries: Vec<BatchEntry> = if args.parallel {
+        files.par_iter().map(process).collect()
+    } else {
+        files.iter().map(process).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let entries: Vec<BatchEntry> = {
+        if args.parallel {
+            info!("Built without the `parallel` feature, processing one recording at a time");
+        }
+        files.iter().map(process).collect()
+    };
 
-    // Constants adjusted for physiological values
-    let min_peak_distance = (0.5 * fs) as usize; // 500ms minimum between QRS complexes
-    let window_size = (0.15 * fs) as usize; // 150ms search window
+    write_batch_summary(&args.summary, &entries)?;
+    let failed = entries.iter().filter(|entry| entry.error.is_some()).count();
+    info!(
+        "Processed {} recordings ({} failed), summary in {:?}",
+        entries.len(),
+        failed,
+        args.summary
+    );
+
+    Ok(())
+}
 
-    // Calculate voltage variability
-    let std_dev = calculate_std_dev(voltage);
-    let threshold = 2.0 * std_dev; // Threshold based on signal variability
+// rescans the inputs every poll interval and processes recordings that have appeared, once
+// their size stopped changing between two scans (so files still being copied are skipped)
+fn watch_batch(
+    args: &BatchArgs,
+    inputs: &[PathBuf],
+    store: Option<&Store>,
+) -> Result<(), Box<dyn Error>> {
+    let interval = std::time::Duration::from_secs_f64(args.poll_interval);
+    let mut done: HashSet<PathBuf> = HashSet::new();
+    let mut sizes: HashMap<PathBuf, u64> = HashMap::new();
+    let mut entries = Vec::new();
+    done.insert(args.summary.clone());
+    done.extend(args.output.clone());
 
-    // Find all potential peaks (both positive and negative)
-    let mut peak_candidates = Vec::new();
+    info!("Watching {:?} for new recordings", inputs);
+    loop {
+        let mut processed = false;
+        for input in expand_inputs(inputs)? {
+            if done.contains(&input) {
+                continue;
+            }
+            // a recording removed since the scan is picked up again if it reappears
+            let Ok(metadata) = std::fs::metadata(&input) else {
+                continue;
+            };
+            let size = metadata.len();
+            if sizes.insert(input.clone(), size) != Some(size) {
+                continue;
+            }
 
-    for i in window_size..(voltage.len() - window_size) {
-        // Check if this point is a significant local extrema
-        let is_positive_peak = voltage[i] > 0.0
-            && voltage[i]
-                >= *voltage[i - window_size..i]
-                    .iter()
-                    .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
-                    .unwrap_or(&f64::NEG_INFINITY)
-            && voltage[i]
-                >= *voltage[i + 1..i + window_size]
-                    .iter()
-                    .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
-                    .unwrap_or(&f64::NEG_INFINITY)
-            && voltage[i].abs() > threshold;
+            entries.push(process_recording(args, &input, store));
+            // our own outputs may land in a watched directory
+            done.insert(batch_output_path(
+                &input,
+                args.output_dir.as_deref(),
+                args.format,
+            ));
+            sizes.remove(&input);
+            done.insert(input);
+            processed = true;
+        }
+        if processed {
+            write_batch_summary(&args.summary, &entries)?;
+        }
+        std::thread::sleep(interval);
+    }
+}
 
-        let is_negative_peak = voltage[i] < 0.0
-            && voltage[i]
-                <= *voltage[i - window_size..i]
-                    .iter()
-                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
-                    .unwrap_or(&f64::INFINITY)
-            && voltage[i]
-                <= *voltage[i + 1..i + window_size]
-                    .iter()
-                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
-                    .unwrap_or(&f64::INFINITY)
-            && voltage[i].abs() > threshold;
+// detects beats in one batch recording and writes its positions, or adds its results to the
+// database
+fn process_recording(args: &BatchArgs, input: &Path, store: Option<&Store>) -> BatchEntry {
+    let mut input_args = args.input.clone();
+    input_args.input = input.to_path_buf();
+    let result = run_detection(&input_args).and_then(|run| {
+        if let Some(store) = store {
+            store_recording(store, input, &args.input.detector, Ok(&run))?;
+            return Ok(run);
+        }
+        let output = batch_output_path(input, args.output_dir.as_deref(), args.format);
+        info!("Writing to: {:?}", output);
+        write_positions_to_file(
+            &run.detections,
+            &run.classes,
+            &args.input.detector,
+            &output,
+            args.format,
+            BeatColumns::new(&input_args, args.samples, args.absolute_times)?,
+        )?;
+        Ok(run)
+    });
 
-        if is_positive_peak || is_negative_peak {
-            peak_candidates.push((i, voltage[i].abs()));
+    match result {
+        Ok(run) => BatchEntry {
+            input: input.to_path_buf(),
+            beats: run.detections.len(),
+            gaps: run.gaps.len(),
+            mean_hr: hrv::time_domain(&run.rr_intervals()).map(|hrv| hrv.mean_hr),
+            error: None,
+        },
+        Err(e) => {
+            info!("Failed on {:?}: {}", input, e);
+            if let Some(store) = store {
+                let error = e.to_string();
+                if let Err(e) = store_recording(store, input, &args.input.detector, Err(&error)) {
+                    warn!("Could not store the failure on {:?}: {}", input, e);
+                }
+            }
+            BatchEntry {
+                input: input.to_path_buf(),
+                beats: 0,
+                gaps: 0,
+                mean_hr: None,
+                error: Some(e.to_string()),
+            }
         }
     }
+}
 
-    // Sort peaks by amplitude (largest first)
-    peak_candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+// the --output database of a batch run, shared by the recordings processed in parallel
+#[cfg(feature = "sqlite")]
+type Store = std::sync::Mutex<sqlite_utils::ResultsStore>;
+// never opened without the feature
+#[cfg(not(feature = "sqlite"))]
+type Store = std::convert::Infallible;
 
-    // Filter peaks keeping only the strongest ones that are sufficiently far apart
-    let mut selected_peaks = Vec::new();
+#[cfg(feature = "sqlite")]
+fn open_store(path: &Path) -> Result<Store, Box<dyn Error>> {
+    info!("Storing the results in: {:?}", path);
+    Ok(std::sync::Mutex::new(sqlite_utils::ResultsStore::open(
+        path,
+    )?))
+}
 
-    for &(idx, _) in &peak_candidates {
-        // Check if this peak is far enough from all previously selected peaks
-        let is_isolated = selected_peaks
-            .iter()
-            .all(|&prev_idx| (idx as isize - prev_idx as isize).abs() > min_peak_distance as isize);
+#[cfg(not(feature = "sqlite"))]
+fn open_store(_path: &Path) -> Result<Store, Box<dyn Error>> {
+    Err("SQLite output needs the `sqlite` feature".into())
+}
 
-        if is_isolated {
-            selected_peaks.push(idx);
-        }
+// adds a recording to the database: its beats, RR intervals, HRV and rhythm events, or
+// only the error it failed with
+#[cfg(feature = "sqlite")]
+fn store_recording(
+    store: &Store,
+    input: &Path,
+    detector: &str,
+    run: Result<&DetectionRun, &str>,
+) -> Result<(), Box<dyn Error>> {
+    let empty = DetectionRun::empty();
+    let (run, error) = match run {
+        Ok(run) => (run, None),
+        Err(error) => (&empty, Some(error)),
+    };
+    let intervals = run.rr_intervals();
+    let time_domain = hrv::time_domain(&intervals);
+    let frequency_domain = hrv::frequency_domain(&intervals, SpectralMethod::Welch);
+    let asymmetry = hrv::asymmetry(&intervals);
+    let poincare = hrv::poincare(&intervals);
+    let geometric = hrv::geometric(&intervals);
+    let mut metrics: Vec<&dyn HrvMetrics> = Vec::new();
+    if let Some(time_domain) = &time_domain {
+        metrics.push(time_domain);
     }
+    if let Some(frequency_domain) = &frequency_domain {
+        metrics.push(frequency_domain);
+    }
+    if let Some(asymmetry) = &asymmetry {
+        metrics.push(asymmetry);
+    }
+    if let Some(poincare) = &poincare {
+        metrics.push(poincare);
+    }
+    if let Some(geometric) = &geometric {
+        metrics.push(geometric);
+    }
+    let (events, _) = rhythm_events(run, &AfOptions::default(), &RateLimits::default());
 
-    // Sort selected peaks by position
-    selected_peaks.sort_unstable();
+    let path = input.to_string_lossy();
+    let recording = sqlite_utils::StoredRecording {
+        path: &path,
+        error,
+        detector,
+        detections: &run.detections,
+        classes: &run.classes,
+        intervals: &intervals,
+        gaps: run.gaps.len(),
+        metrics: &metrics,
+        events: &events,
+    };
+    store
+        .lock()
+        .map_err(|_| "the results database is poisoned")?
+        .add_recording(&recording)?;
+    Ok(())
+}
 
-    // For each selected peak, find the exact R or S wave
-    // by looking in a window centered on the peak
-    for &idx in &selected_peaks {
-        // Determine the window to search for the R or S wave
-        let search_window = (0.08 * fs) as usize; // 80ms window
-        let start = if idx > search_window {
-            idx - search_window
-        } else {
-            0
-        };
-        let end = std::cmp::min(idx + search_window, ecg_data.len());
+#[cfg(not(feature = "sqlite"))]
+fn store_recording(
+    store: &Store,
+    _input: &Path,
+    _detector: &str,
+    _run: Result<&DetectionRun, &str>,
+) -> Result<(), Box<dyn Error>> {
+    match *store {}
+}
 
-        if start >= end {
-            continue;
+fn write_batch_summary(path: &Path, entries: &[BatchEntry]) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(create_writer(Some(path))?);
+    writeln!(writer, "file,beats,gaps,mean_hr_bpm,status")?;
+    for entry in entries {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            csv_field(&entry.input.to_string_lossy()),
+            entry.beats,
+            entry.gaps,
+            entry
+                .mean_hr
+                .map(|hr| format!("{:.2}", hr))
+                .unwrap_or_default(),
+            csv_field(entry.error.as_deref().unwrap_or("ok"))
+        )?;
+    }
+    writer.flush()
+}
+
+// data/100.csv -> <output_dir or data>/100_qrs.<format>
+fn batch_output_path(input: &Path, output_dir: Option<&Path>, format: OutputFormat) -> PathBuf {
+    let input = compression::strip_compression_extension(input);
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = format!("{}_qrs.{}", stem, format.extension());
+    match output_dir {
+        Some(dir) => dir.join(file_name),
+        None => input.with_file_name(file_name),
+    }
+}
+
+// quotes CSV fields containing separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// beat times of the reference, from WFDB annotations unless it is a txt or csv file
+fn read_reference_times(path: &Path, fs: Option<f64>) -> Result<Vec<f64>, Box<dyn Error>> {
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    if matches!(extension, Some("txt") | Some("csv")) {
+        return read_beat_times(path);
+    }
+
+    let fs = match fs {
+        Some(fs) => fs,
+        None => {
+            let header_path = wfdb_utils::header_path(path);
+            let header = File::open(&header_path).map_err(|e| {
+                format!(
+                    "Cannot read {:?} for the sampling frequency ({}), pass --fs",
+                    header_path, e
+                )
+            })?;
+            wfdb_utils::read_wfdb_header(io::BufReader::new(header))?.sampling_frequency
+        }
+    };
+
+    let mut file = io::BufReader::new(File::open(path)?);
+    Ok(wfdb_utils::read_wfdb_annotations(&mut file)?
+        .into_iter()
+        .filter(|&(_, code)| wfdb_utils::is_beat(code))
+        .map(|(sample, _)| sample as f64 / fs)
+        .collect())
+}
+
+fn run_validate(args: ValidateArgs) -> Result<(), Box<dyn Error>> {
+    let detected = read_beat_times(&args.detected)?;
+    let reference = read_reference_times(&args.reference, args.fs)?;
+    let result = validate(&detected, &reference, args.tolerance);
+
+    println!("Reference beats: {}", reference.len());
+    println!("Detected beats: {}", detected.len());
+    println!(
+        "TP: {}, FP: {}, FN: {}",
+        result.true_positives, result.false_positives, result.false_negatives
+    );
+    println!("Sensitivity: {:.2}%", 100.0 * result.sensitivity);
+    println!(
+        "Positive predictivity: {:.2}%",
+        100.0 * result.positive_predictivity
+    );
+    println!("F1: {:.4}", result.f1);
+    println!(
+        "Timing error: {:.2} ± {:.2} ms",
+        result.mean_error_ms, result.sd_error_ms
+    );
+
+    Ok(())
+}
+
+// copies the EDF input with the beats of the run as EDF+ annotations
+fn write_annotated_edf(
+    input: &InputArgs,
+    run: &DetectionRun,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let input_format = input
+        .input_format
+        .unwrap_or_else(|| InputFormat::from_path(&input.input));
+    if input_format != InputFormat::Edf || is_stdio(&input.input) {
+        return Err("--edf-output needs an EDF input file".into());
+    }
+    let annotations: Vec<EdfAnnotation> = run
+        .detections
+        .iter()
+        .enumerate()
+        .map(|(beat, detection)| EdfAnnotation {
+            onset: detection.time,
+            duration: None,
+            text: run
+                .classes
+                .get(beat)
+                .map_or("R", |class| class.symbol())
+                .to_string(),
+        })
+        .collect();
+
+    let mut reader = compression::open_input(&input.input)?;
+    let mut writer = io::BufWriter::new(create_writer(Some(path))?);
+    edf_utils::write_edf_with_annotations(&mut reader, &mut writer, &annotations)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn run_convert(args: ConvertArgs) -> Result<(), Box<dyn Error>> {
+    let input_format = InputFormat::from_path(&args.input);
+    let ecg = match input_format {
+        InputFormat::Edf => edf_utils::read_edf_leads(&args.input)?,
+        InputFormat::Ishne => ishne_utils::read_ishne_leads(&args.input)?,
+        InputFormat::Scp => scp_utils::read_scp_leads(&args.input)?,
+        InputFormat::Aecg => aecg_utils::read_aecg_leads(&args.input)?,
+        InputFormat::Dicom => dicom_utils::read_dicom_leads(&args.input)?,
+        InputFormat::AppleHealth => apple_health_utils::read_apple_health_leads(&args.input)?,
+        InputFormat::PolarHrm | InputFormat::PolarTxt | InputFormat::Fit => {
+            return Err(RR_INPUT.into())
+        }
+        InputFormat::Csv => {
+            let options = CsvOptions {
+                voltage_only: args.no_time_column,
+                sampling_frequency: args.fs,
+                ..CsvOptions::default()
+            };
+            read_ecg_leads(&args.input, &options)?
+        }
+        InputFormat::Wfdb => {
+            return Err(
+                "convert reads EDF, BDF, ISHNE, SCP-ECG, aECG, DICOM and CSV recordings".into(),
+            )
         }
+    };
+    let mut ecg = select_leads(ecg, &args.signals)?;
+    if ecg.times.is_empty() {
+        return Err(format!("No data found in {}", args.input.display()).into());
+    }
+    let mut fs = match args.fs {
+        Some(fs) => fs,
+        None => {
+            let estimate = estimate_sampling(&ecg.times)
+                .ok_or("cannot estimate the sampling frequency, pass --fs")?;
+            sampling::report_sampling(&estimate);
+            estimate.fs
+        }
+    };
 
-        // Find the absolute maximum voltage in the window
-        // This will be either the R peak or S peak
-        let mut max_abs_idx = start;
-        let mut max_abs_value = ecg_data[start].voltage.abs();
+    let filter = FilterConfig {
+        highpass: args.highpass,
+        lowpass: args.lowpass,
+        notch: args.notch,
+        zero_phase: args.zero_phase,
+    };
+    if !filter.is_empty() {
+        for lead in &mut ecg.leads {
+            lead.voltage = filter.apply(&lead.voltage, fs);
+        }
+    }
+    if let Some(target) = args.resample {
+        info!(
+            "Resampling from {:.2} Hz to {:.2} Hz",
+            fs,
+            resample::output_rate(fs, target)
+        );
+        ecg = resample::resample_leads(&ecg, fs, target);
+        fs = resample::output_rate(fs, target);
+    }
 
-        for i in start + 1..end {
-            let abs_voltage = ecg_data[i].voltage.abs();
-            if abs_voltage > max_abs_value {
-                max_abs_idx = i;
-                max_abs_value = abs_voltage;
+    let to_edf = InputFormat::from_path(&args.output) == InputFormat::Edf;
+    if to_edf && is_stdio(&args.output) {
+        return Err("EDF cannot be written to stdout".into());
+    }
+    info!("Writing {} leads to: {:?}", ecg.leads.len(), args.output);
+    let mut writer = io::BufWriter::new(create_writer(Some(&args.output))?);
+    if !to_edf {
+        write!(writer, "time")?;
+        for lead in &ecg.leads {
+            write!(writer, ",{}", csv_field(&lead.name))?;
+        }
+        writeln!(writer)?;
+        for (sample, time) in ecg.times.iter().enumerate() {
+            write!(writer, "{:.6}", time)?;
+            for lead in &ecg.leads {
+                write!(writer, ",{}", lead.voltage[sample])?;
+            }
+            writeln!(writer)?;
+        }
+    } else {
+        // the identification and start of an EDF input carry over unless given
+        let mut info = edf_utils::EdfRecordingInfo {
+            prefiltering: edf_utils::edf_prefiltering(&filter),
+            ..edf_utils::EdfRecordingInfo::default()
+        };
+        let mut annotations = Vec::new();
+        if input_format == InputFormat::Edf {
+            let header = edf_utils::read_edf_header(&mut compression::open_input(&args.input)?)?;
+            info.patient = header.patient;
+            info.recording = header.recording;
+            info.start_date = header.start_date;
+            info.start_time = header.start_time;
+            if args.edf_plus {
+                annotations = edf_utils::read_edf_annotations(&args.input)?;
             }
+        } else if input_format == InputFormat::Aecg && args.edf_plus {
+            annotations = aecg_utils::read_aecg(&args.input)?.annotations;
+        }
+        info.patient = args.patient.unwrap_or(info.patient);
+        info.recording = args.recording.unwrap_or(info.recording);
+        info.start_date = args.start_date.unwrap_or(info.start_date);
+        info.start_time = args.start_time.unwrap_or(info.start_time);
+        if args.edf_plus {
+            edf_utils::write_edf_plus(&mut writer, &ecg, fs, &info, &annotations)?;
+        } else {
+            edf_utils::write_edf(&mut writer, &ecg, fs, &info)?;
         }
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-        qrs_positions.push(ecg_data[max_abs_idx].time);
+// the leads named in `names`, in their order, or all of them when no name is given
+fn select_leads(mut ecg: MultiLeadEcg, names: &[String]) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    if names.is_empty() {
+        return Ok(ecg);
     }
+    let indices = {
+        let labels: Vec<&str> = ecg.leads.iter().map(|lead| lead.name.as_str()).collect();
+        names
+            .iter()
+            .map(|name| edf_utils::find_label(&labels, name))
+            .collect::<Result<Vec<usize>, _>>()?
+    };
+    let mut leads: Vec<Option<Lead>> = ecg.leads.drain(..).map(Some).collect();
+    ecg.leads = indices
+        .into_iter()
+        .zip(names)
+        .map(|(index, name)| {
+            leads[index]
+                .take()
+                .ok_or_else(|| format!("Signal {:?} is selected twice", name).into())
+        })
+        .collect::<Result<_, Box<dyn Error>>>()?;
+    Ok(ecg)
+}
 
-    qrs_positions
+fn run_anonymize(args: AnonymizeArgs) -> Result<(), Box<dyn Error>> {
+    if !is_stdio(&args.output) && args.output == args.input {
+        return Err("The anonymized copy cannot overwrite its input".into());
+    }
+    let mut reader = compression::open_input(&args.input)?;
+    let mut writer = io::BufWriter::new(create_writer(Some(&args.output))?);
+    edf_utils::anonymize_edf(&mut reader, &mut writer, args.clear_date)?;
+    writer.flush()?;
+    info!("Anonymized copy written to: {:?}", args.output);
+    Ok(())
 }
 
-fn calculate_std_dev(data: &[f64]) -> f64 {
-    let mean: f64 = data.iter().sum::<f64>() / data.len() as f64;
-    let variance: f64 =
-        data.iter().map(|&x| (x - mean) * (x - mean)).sum::<f64>() / data.len() as f64;
-    variance.sqrt()
+fn run_annotations(args: AnnotationsArgs) -> Result<(), Box<dyn Error>> {
+    let annotations = if InputFormat::from_path(&args.input) == InputFormat::Aecg {
+        aecg_utils::read_aecg(&args.input)?.annotations
+    } else {
+        edf_utils::read_edf_annotations(&args.input)?
+    };
+    if annotations.is_empty() {
+        warn!("No annotations found in {}", args.input.display());
+    }
+    let beats = args.beats.as_deref().map(read_beat_times).transpose()?;
+
+    let mut writer = create_writer(args.output.as_deref())?;
+    write!(writer, "onset,duration,text")?;
+    if beats.is_some() {
+        write!(writer, ",first_beat,beats")?;
+    }
+    writeln!(writer)?;
+    for annotation in &annotations {
+        write!(
+            writer,
+            "{:.6},{},{}",
+            annotation.onset,
+            annotation
+                .duration
+                .map_or(String::new(), |duration| format!("{:.6}", duration)),
+            csv_field(&annotation.text)
+        )?;
+        if let Some(beats) = &beats {
+            // beat numbers count from 1 as in the detect output
+            let first = beats.partition_point(|&time| time < annotation.onset);
+            let during = annotation.duration.map_or(String::new(), |duration| {
+                let end = beats.partition_point(|&time| time < annotation.onset + duration);
+                (end - first).to_string()
+            });
+            let first = if first < beats.len() {
+                (first + 1).to_string()
+            } else {
+                String::new()
+            };
+            write!(writer, ",{},{}", first, during)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
 }
 
-fn write_positions_to_file<P: AsRef<Path>>(positions: &[f64], path: P) -> io::Result<()> {
-    let mut file = File::create(path)?;
+fn run_diff(args: DiffArgs) -> Result<(), Box<dyn Error>> {
+    let first = read_reference_times(&args.first, args.fs)?;
+    let second = read_reference_times(&args.second, args.fs)?;
+    let matches = match_beats(&first, &second, args.tolerance);
+    // the second run scored against the first: its misses are beats only in the first
+    let result = validate(&second, &first, args.tolerance);
+    let largest_offset = matches
+        .iter()
+        .filter_map(|beat| match *beat {
+            BeatMatch::Both(a, b) => Some(1000.0 * (b - a)),
+            _ => None,
+        })
+        .max_by(|a, b| a.abs().total_cmp(&b.abs()));
+
+    println!("Beats: {} and {}", first.len(), second.len());
+    println!("Matched: {}", result.true_positives);
+    println!("Only in {:?}: {}", args.first, result.false_negatives);
+    println!("Only in {:?}: {}", args.second, result.false_positives);
+    println!("Agreement (F1): {:.4}", result.f1);
+    println!(
+        "Offset (second - first): {:.2} ± {:.2} ms, largest {}",
+        result.mean_error_ms,
+        result.sd_error_ms,
+        largest_offset.map_or("-".to_string(), |offset| format!("{:.2} ms", offset))
+    );
 
-    for &pos in positions {
-        writeln!(file, "{:.6}", pos)?;
+    if let Some(path) = &args.output {
+        let mut file = io::BufWriter::new(create_writer(Some(path))?);
+        writeln!(file, "first,second,offset_ms")?;
+        for beat in &matches {
+            match *beat {
+                BeatMatch::Both(a, b) => {
+                    writeln!(file, "{:.6},{:.6},{:.3}", a, b, 1000.0 * (b - a))?
+                }
+                BeatMatch::OnlyFirst(a) => writeln!(file, "{:.6},,", a)?,
+                BeatMatch::OnlySecond(b) => writeln!(file, ",{:.6},", b)?,
+            }
+        }
+        file.flush()?;
     }
 
     Ok(())
 }
+
+// the file at `path`, or stdout when it is omitted or `-`
+fn create_writer(path: Option<&Path>) -> io::Result<Box<dyn Write + Send>> {
+    Ok(match path {
+        Some(path) if !is_stdio(path) => Box::new(File::create(path)?),
+        _ => Box::new(io::stdout()),
+    })
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new(compression::STDIO_PATH)
+}
+
+// positions.txt -> positions_rr.txt
+fn rr_output_path(output: &Path) -> PathBuf {
+    suffixed_path(output, "rr")
+}
+
+// `positions.txt` -> `positions_<suffix>.txt`
+fn suffixed_path(output: &Path, suffix: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match output.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, suffix),
+    };
+    output.with_file_name(file_name)
+}
+
+// lead names such as `aVR` or `V1-V2` used in file names
+fn file_name_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -2,3 +2,48 @@ pub struct EcgPoint {
     pub time: f64,
     pub voltage: f64,
 }
+
+/// One named lead of a multi-lead recording.
+#[derive(Debug, Clone)]
+pub struct Lead {
+    pub name: String,
+    pub voltage: Vec<f64>,
+}
+
+/// Several leads sampled at common times.
+#[derive(Debug, Clone, Default)]
+pub struct MultiLeadEcg {
+    pub times: Vec<f64>,
+    pub leads: Vec<Lead>,
+}
+
+impl MultiLeadEcg {
+    /// The given lead as time and voltage points.
+    pub fn points(&self, lead: usize) -> Vec<EcgPoint> {
+        self.times
+            .iter()
+            .zip(&self.leads[lead].voltage)
+            .map(|(&time, &voltage)| EcgPoint { time, voltage })
+            .collect()
+    }
+}
+
+/// Interval between two consecutive beats, stamped with the time of the later beat.
+#[derive(Debug, Clone, Copy)]
+pub struct RrInterval {
+    pub time: f64,
+    pub rr_ms: f64,
+}
+
+/// A detected beat: time in seconds, sample index into the recording, amplitude of the
+/// (pre-filtered) signal at the beat and the confidence in it, from 0 to 1: the amplitude
+/// relative to the typical beat, the share of agreeing detectors for the ensemble or of
+/// agreeing leads for fused leads, times the correlation with the QRS template when beats
+/// are verified against it.
+#[derive(Debug, Clone, Copy)]
+pub struct Detection {
+    pub time: f64,
+    pub sample: usize,
+    pub amplitude: f64,
+    pub confidence: f64,
+}
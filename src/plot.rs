@@ -0,0 +1,344 @@
+#[cfg(feature = "png")]
+use std::error::Error;
+use std::fmt::Write as _;
+#[cfg(feature = "png")]
+use std::io::Write;
+
+// ECG paper: minor and major grid lines every 40 and 200 ms, and every 0.1 and 0.5 signal
+// units (1 and 5 mm at standard gain for mV)
+const MINOR_TIME: f64 = 0.04;
+const MAJOR_TIME: f64 = 0.2;
+const MINOR_VOLTAGE: f64 = 0.1;
+const MAJOR_VOLTAGE: f64 = 0.5;
+// grid lines closer than this many pixels are left out
+const MIN_GRID_SPACING: f64 = 3.0;
+// time labels are at least this many pixels apart
+const MIN_LABEL_SPACING: f64 = 60.0;
+// space below the strip for the time labels, in pixels
+const AXIS_HEIGHT: u32 = 20;
+
+type Rgb = [u8; 3];
+const BACKGROUND: Rgb = [255, 255, 255];
+const MINOR_GRID: Rgb = [252, 228, 228];
+const MAJOR_GRID: Rgb = [244, 180, 180];
+const TRACE: Rgb = [20, 20, 20];
+const BEAT: Rgb = [31, 97, 181];
+const ECTOPIC: Rgb = [200, 40, 40];
+const REFERENCE: Rgb = [30, 140, 70];
+
+/// A beat drawn over the strip: detected beats are marked at the top, labelled with their
+/// class symbol when there is one, reference annotations at the bottom.
+#[derive(Debug, Clone)]
+pub struct Marker {
+    pub time: f64,
+    pub label: String,
+    pub reference: bool,
+}
+
+/// A stretch of a signal from `from` to `to` seconds rendered `width` × `height` pixels, the
+/// signal starting at `start` seconds.
+#[derive(Debug, Clone)]
+pub struct Strip<'a> {
+    pub signal: &'a [f64],
+    pub fs: f64,
+    pub start: f64,
+    pub from: f64,
+    pub to: f64,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Strip<'_> {
+    // the lowest and highest sample in every pixel column, `None` for columns without one
+    pub(crate) fn columns(&self) -> Vec<Option<(f64, f64)>> {
+        let seconds_per_pixel = (self.to - self.from) / self.width as f64;
+        let sample = |time: f64| ((time - self.start) * self.fs).round().max(0.0) as usize;
+        (0..self.width)
+            .map(|x| {
+                let first = sample(self.from + x as f64 * seconds_per_pixel);
+                let last = sample(self.from + (x + 1) as f64 * seconds_per_pixel).max(first + 1);
+                let samples = self.signal.get(first..last.min(self.signal.len()))?;
+                samples.iter().fold(None, |range, &v| match range {
+                    None => Some((v, v)),
+                    Some((low, high)) => Some((f64::min(low, v), f64::max(high, v))),
+                })
+            })
+            .collect()
+    }
+
+    fn frame(&self, columns: &[Option<(f64, f64)>]) -> Frame {
+        let (low, high) = value_range(columns);
+        Frame {
+            from: self.from,
+            to: self.to,
+            low,
+            high,
+            width: self.width as f64,
+            height: self.height.saturating_sub(AXIS_HEIGHT).max(1) as f64,
+        }
+    }
+}
+
+// the range of the columns with some room above and below
+pub(crate) fn value_range(columns: &[Option<(f64, f64)>]) -> (f64, f64) {
+    let (low, high) = columns.iter().flatten().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(low, high), &(a, b)| (low.min(a), high.max(b)),
+    );
+    if low < high {
+        let padding = 0.1 * (high - low);
+        (low - padding, high + padding)
+    } else if low.is_finite() {
+        (low - 1.0, low + 1.0)
+    } else {
+        (-1.0, 1.0)
+    }
+}
+
+// mapping from time and signal value to pixels
+struct Frame {
+    from: f64,
+    to: f64,
+    low: f64,
+    high: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Frame {
+    fn x(&self, time: f64) -> f64 {
+        (time - self.from) / (self.to - self.from) * self.width
+    }
+
+    fn y(&self, value: f64) -> f64 {
+        (self.high - value) / (self.high - self.low) * self.height
+    }
+
+    // multiples of `step` within `low..=high`, if they are at least `spacing` pixels apart
+    fn lines(low: f64, high: f64, step: f64, pixels: f64, spacing: f64) -> Vec<f64> {
+        if step * pixels / (high - low) < spacing {
+            return Vec::new();
+        }
+        let first = (low / step).ceil() as i64;
+        let last = (high / step).floor() as i64;
+        (first..=last).map(|k| k as f64 * step).collect()
+    }
+
+    // grid lines as (time or value, colour), minor first so the major ones are drawn over them
+    fn time_grid(&self) -> Vec<(f64, Rgb)> {
+        let lines = |step, colour| {
+            Self::lines(self.from, self.to, step, self.width, MIN_GRID_SPACING)
+                .into_iter()
+                .map(move |t| (t, colour))
+        };
+        lines(MINOR_TIME, MINOR_GRID)
+            .chain(lines(MAJOR_TIME, MAJOR_GRID))
+            .collect()
+    }
+
+    fn voltage_grid(&self) -> Vec<(f64, Rgb)> {
+        let lines = |step, colour| {
+            Self::lines(self.low, self.high, step, self.height, MIN_GRID_SPACING)
+                .into_iter()
+                .map(move |v| (v, colour))
+        };
+        lines(MINOR_VOLTAGE, MINOR_GRID)
+            .chain(lines(MAJOR_VOLTAGE, MAJOR_GRID))
+            .collect()
+    }
+
+    // the times labelled on the axis: whole seconds, or multiples of 5, 10, 30 or 60 s
+    fn labels(&self) -> Vec<f64> {
+        [1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 300.0, 600.0, 3600.0]
+            .into_iter()
+            .map(|step| Self::lines(self.from, self.to, step, self.width, MIN_LABEL_SPACING))
+            .find(|lines| !lines.is_empty())
+            .unwrap_or_default()
+    }
+}
+
+fn colour(marker: &Marker) -> Rgb {
+    match (marker.reference, marker.label.as_str()) {
+        (true, _) => REFERENCE,
+        (false, "" | "N") => BEAT,
+        _ => ECTOPIC,
+    }
+}
+
+fn hex(colour: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour[0], colour[1], colour[2])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The strip as an SVG document on ECG paper, with the markers in the strip.
+pub fn strip_svg(strip: &Strip, markers: &[Marker]) -> String {
+    let columns = strip.columns();
+    let frame = strip.frame(&columns);
+    let (width, height) = (strip.width, strip.height);
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n<rect width=\"{width}\" height=\"{height}\" fill=\"{}\"/>",
+        hex(BACKGROUND)
+    );
+    for (time, colour) in frame.time_grid() {
+        let x = frame.x(time);
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{x:.1}\" y1=\"0\" x2=\"{x:.1}\" y2=\"{:.1}\" stroke=\"{}\"/>",
+            frame.height,
+            hex(colour)
+        );
+    }
+    for (value, colour) in frame.voltage_grid() {
+        let y = frame.y(value);
+        let _ = writeln!(
+            svg,
+            "<line x1=\"0\" y1=\"{y:.1}\" x2=\"{width}\" y2=\"{y:.1}\" stroke=\"{}\"/>",
+            hex(colour)
+        );
+    }
+
+    // each column's range as a vertical stroke joined to the next column
+    let points: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .filter_map(|(x, range)| {
+            let (low, high) = (*range)?;
+            Some(format!("{x},{:.1} {x},{:.1}", frame.y(high), frame.y(low)))
+        })
+        .collect();
+    let _ = writeln!(
+        svg,
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1\"/>",
+        points.join(" "),
+        hex(TRACE)
+    );
+
+    for marker in markers
+        .iter()
+        .filter(|marker| marker.time >= strip.from && marker.time <= strip.to)
+    {
+        let x = frame.x(marker.time);
+        let (y1, y2, text_y) = if marker.reference {
+            (0.9 * frame.height, frame.height, 0.88 * frame.height)
+        } else {
+            (0.0, 0.1 * frame.height, 0.1 * frame.height + 12.0)
+        };
+        let colour = hex(colour(marker));
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{x:.1}\" y1=\"{y1:.1}\" x2=\"{x:.1}\" y2=\"{y2:.1}\" stroke=\"{colour}\" \
+             stroke-width=\"1.5\"/>"
+        );
+        if !marker.label.is_empty() {
+            let _ = writeln!(
+                svg,
+                "<text x=\"{x:.1}\" y=\"{text_y:.1}\" fill=\"{colour}\" font-size=\"11\" \
+                 font-family=\"sans-serif\" text-anchor=\"middle\">{}</text>",
+                escape(&marker.label)
+            );
+        }
+    }
+
+    for time in frame.labels() {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{}\" font-size=\"11\" font-family=\"sans-serif\" \
+             text-anchor=\"middle\" fill=\"#444\">{} s</text>",
+            frame.x(time),
+            height - 5,
+            time
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// The strip as RGB pixels, row by row, on ECG paper with the markers; labels are left out.
+pub fn strip_pixels(strip: &Strip, markers: &[Marker]) -> Vec<u8> {
+    let columns = strip.columns();
+    let frame = strip.frame(&columns);
+    let (width, height) = (strip.width as usize, strip.height as usize);
+    let mut pixels = BACKGROUND.repeat(width * height);
+    let mut vertical = |x: isize, y1: isize, y2: isize, colour: Rgb| {
+        if x < 0 || x as usize >= width {
+            return;
+        }
+        let top = y1.min(y2).max(0) as usize;
+        let bottom = (y1.max(y2).max(0) as usize).min(height.saturating_sub(1));
+        for y in top..=bottom {
+            let i = 3 * (y * width + x as usize);
+            pixels[i..i + 3].copy_from_slice(&colour);
+        }
+    };
+    let strip_bottom = frame.height as isize - 1;
+
+    for (time, colour) in frame.time_grid() {
+        vertical(frame.x(time) as isize, 0, strip_bottom, colour);
+    }
+    for (value, colour) in frame.voltage_grid() {
+        let y = frame.y(value) as isize;
+        for x in 0..width as isize {
+            vertical(x, y, y, colour);
+        }
+    }
+
+    let mut previous: Option<(f64, f64)> = None;
+    for (x, range) in columns.iter().enumerate() {
+        let Some((low, high)) = *range else {
+            previous = None;
+            continue;
+        };
+        // joined to the previous column so steep edges stay continuous
+        let (top, bottom) = match previous {
+            Some((previous_low, previous_high)) => (high.max(previous_low), low.min(previous_high)),
+            None => (high, low),
+        };
+        vertical(
+            x as isize,
+            frame.y(top) as isize,
+            frame.y(bottom) as isize,
+            TRACE,
+        );
+        previous = Some((low, high));
+    }
+
+    for marker in markers
+        .iter()
+        .filter(|marker| marker.time >= strip.from && marker.time <= strip.to)
+    {
+        let x = frame.x(marker.time) as isize;
+        let length = (0.1 * frame.height) as isize;
+        let (y1, y2) = if marker.reference {
+            (strip_bottom - length, strip_bottom)
+        } else {
+            (0, length)
+        };
+        for dx in 0..2 {
+            vertical(x + dx, y1, y2, colour(marker));
+        }
+    }
+    pixels
+}
+
+/// Writes the strip as a PNG image (the `png` feature).
+#[cfg(feature = "png")]
+pub fn write_strip_png<W: Write>(
+    writer: W,
+    strip: &Strip,
+    markers: &[Marker],
+) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(writer, strip.width, strip.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&strip_pixels(strip, markers))?;
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use crate::compression::open_input;
+use std::error::Error;
+use std::io::BufRead;
+use std::path::Path;
+use tracing::info;
+
+// the [Params] interval of HRM files recording R-R intervals instead of heart rate samples
+const HRM_RR_INTERVAL: &str = "238";
+
+/// Reads the RR intervals (ms) of a Polar HRM file recorded in R-R mode (`Interval=238`):
+/// the first column of its `[HRData]` lines.
+pub fn read_polar_hrm<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, Box<dyn Error>> {
+    let reader = open_input(path)?;
+
+    let mut section = String::new();
+    let mut interval = None;
+    let mut intervals = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            section = name.to_string();
+            continue;
+        }
+        match section.as_str() {
+            "Params" => {
+                if let Some(value) = line.strip_prefix("Interval=") {
+                    interval = Some(value.trim().to_string());
+                }
+            }
+            "HRData" => {
+                let field = line.split_whitespace().next().unwrap_or_default();
+                let rr: f64 = field.parse().map_err(|_| {
+                    format!("Invalid R-R interval on line {}: {:?}", number + 1, field)
+                })?;
+                intervals.push(rr);
+            }
+            _ => {}
+        }
+    }
+
+    match interval.as_deref() {
+        Some(HRM_RR_INTERVAL) => {}
+        Some(interval) => {
+            return Err(format!(
+                "The HRM file holds heart rate samples every {} s, not R-R intervals",
+                interval
+            )
+            .into())
+        }
+        None => return Err("HRM file without an Interval in its [Params] section".into()),
+    }
+    // zeros mark lost beats
+    intervals.retain(|&rr| rr > 0.0);
+
+    info!("Total RR intervals: {}", intervals.len());
+
+    Ok(intervals)
+}
+
+/// Reads the RR intervals (ms) of a Polar text export, one interval per line (the first
+/// field of lines with several), skipping header lines before the first interval.
+pub fn read_polar_txt<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, Box<dyn Error>> {
+    let reader = open_input(path)?;
+
+    let mut intervals = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let field = line
+            .split([',', ';', '\t'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        if field.is_empty() {
+            continue;
+        }
+        match field.parse::<f64>() {
+            Ok(rr) if rr > 0.0 => intervals.push(rr),
+            Ok(_) => {}
+            Err(_) if intervals.is_empty() => {}
+            Err(_) => {
+                return Err(
+                    format!("Invalid RR interval on line {}: {:?}", number + 1, field).into(),
+                )
+            }
+        }
+    }
+
+    info!("Total RR intervals: {}", intervals.len());
+
+    Ok(intervals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn read_with<T>(name: &str, contents: &str, read: fn(&Path) -> T) -> T {
+        let path =
+            std::env::temp_dir().join(format!("signalweaver_{}_{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        let result = read(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn reads_hrm_rr_intervals() {
+        let hrm = "[Params]\nVersion=106\nMonitor=22\nInterval=238\n\n[Note]\nrest\n\n\
+                   [HRData]\n812\n0\n790 75\n805\n";
+        let intervals = read_with("rr.hrm", hrm, |path| read_polar_hrm(path));
+        // the lost beat (0) is left out
+        assert_eq!(intervals.unwrap(), vec![812.0, 790.0, 805.0]);
+
+        let heart_rate = "[Params]\nInterval=5\n[HRData]\n72\n";
+        assert!(read_with("hr.hrm", heart_rate, |path| read_polar_hrm(path)).is_err());
+    }
+
+    #[test]
+    fn reads_text_exports() {
+        let txt = "RR interval (ms);time\n812;0.812\n\n0;\n790\t1.602\n805,2.407\n";
+        let intervals = read_with("rr.txt", txt, |path| read_polar_txt(path));
+        assert_eq!(intervals.unwrap(), vec![812.0, 790.0, 805.0]);
+
+        let broken = "812\n790\nlost\n";
+        assert!(read_with("broken.txt", broken, |path| read_polar_txt(path)).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+// beats are moved to the dominant extremum at most this far away, in seconds
+const ALIGN_RADIUS: f64 = 0.05;
+
+/// Direction of the dominant QRS deflection of a lead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    /// Tall R waves, as in most leads.
+    Positive,
+    /// Deep S or QS complexes, as in aVR or an inverted lead.
+    Negative,
+}
+
+impl Polarity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Polarity::Positive => "positive",
+            Polarity::Negative => "negative",
+        }
+    }
+}
+
+/// The dominant QRS polarity: in every second of the signal the largest rise above its median
+/// is compared with the largest drop below it, and the direction winning most seconds wins.
+pub fn qrs_polarity(signal: &[f64], fs: f64) -> Polarity {
+    let window = (fs as usize).max(1);
+    let mut votes: isize = 0;
+    for chunk in signal.chunks(window) {
+        let mut sorted = chunk.to_vec();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let median = sorted[sorted.len() / 2];
+        let rise = sorted[sorted.len() - 1] - median;
+        let drop = median - sorted[0];
+        votes += if rise >= drop { 1 } else { -1 };
+    }
+    if votes >= 0 {
+        Polarity::Positive
+    } else {
+        Polarity::Negative
+    }
+}
+
+/// Moves a beat to the largest (for positive polarity) or smallest sample within 50 ms, so
+/// every fiducial point lands on the same wave of the complex.
+pub fn align_to_polarity(signal: &[f64], fs: f64, beat: usize, polarity: Polarity) -> usize {
+    if signal.is_empty() {
+        return beat;
+    }
+    let radius = (ALIGN_RADIUS * fs) as usize;
+    let start = beat.saturating_sub(radius);
+    let end = (beat + radius).min(signal.len() - 1);
+    let window = signal[start..=end].iter().enumerate();
+    let best = match polarity {
+        Polarity::Positive => window.max_by(|a, b| a.1.total_cmp(b.1)),
+        Polarity::Negative => window.min_by(|a, b| a.1.total_cmp(b.1)),
+    };
+    best.map_or(beat, |(i, _)| start + i)
+}
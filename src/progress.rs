@@ -0,0 +1,47 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// A hook through which long analyses report how far they are, e.g. for a progress bar or a
+/// completion percentage in an application embedding the crate. The callback receives the
+/// units of work done so far and their total; it may be called from several threads at once
+/// and should return quickly.
+#[derive(Clone, Default)]
+pub struct Progress {
+    callback: Option<Arc<dyn Fn(u64, u64) + Send + Sync>>,
+}
+
+impl Progress {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        Progress {
+            callback: Some(Arc::new(callback)),
+        }
+    }
+
+    /// Reports that `done` of `total` units are finished.
+    pub fn report(&self, done: u64, total: u64) {
+        if let Some(callback) = &self.callback {
+            callback(done, total);
+        }
+    }
+
+    /// The progress of the `index`-th of `parts` equal parts of the work, reported as a
+    /// share of the whole, e.g. one lead of a multi-lead recording.
+    pub fn part(&self, index: u64, parts: u64) -> Progress {
+        let Some(callback) = self.callback.clone() else {
+            return Progress::default();
+        };
+        Progress::new(move |done, total| callback(index * total + done, parts * total))
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.callback {
+            Some(_) => f.write_str("Progress(callback)"),
+            None => f.write_str("Progress(none)"),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::datetime::civil_from_days;
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where a result came from: the software and command that made it, every parameter value
+/// (defaults included), the input files and when it was made.
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    pub version: String,
+    pub command: Vec<String>,
+    pub parameters: Vec<(String, String)>,
+    pub inputs: Vec<InputFile>,
+    /// UTC, as [`utc_timestamp`] writes it.
+    pub created: String,
+}
+
+/// An input file with its size and SHA-256 digest (lowercase hex).
+#[derive(Debug, Clone)]
+pub struct InputFile {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+impl InputFile {
+    pub fn read<R: Read>(path: &str, reader: R) -> io::Result<Self> {
+        let (sha256, bytes) = sha256(reader)?;
+        Ok(InputFile {
+            path: path.to_string(),
+            bytes,
+            sha256,
+        })
+    }
+}
+
+/// Writes the provenance as a JSON object.
+pub fn write_provenance_json<W: Write>(writer: &mut W, provenance: &Provenance) -> io::Result<()> {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"software\": \"signalweaver\",")?;
+    writeln!(
+        writer,
+        "  \"version\": {},",
+        json_string(&provenance.version)
+    )?;
+    let command: Vec<String> = provenance
+        .command
+        .iter()
+        .map(|arg| json_string(arg))
+        .collect();
+    writeln!(writer, "  \"command\": [{}],", command.join(", "))?;
+    writeln!(writer, "  \"parameters\": {{")?;
+    for (i, (name, value)) in provenance.parameters.iter().enumerate() {
+        let separator = if i + 1 < provenance.parameters.len() {
+            ","
+        } else {
+            ""
+        };
+        writeln!(
+            writer,
+            "    {}: {}{}",
+            json_string(name),
+            json_string(value),
+            separator
+        )?;
+    }
+    writeln!(writer, "  }},")?;
+    writeln!(writer, "  \"inputs\": [")?;
+    for (i, input) in provenance.inputs.iter().enumerate() {
+        let separator = if i + 1 < provenance.inputs.len() {
+            ","
+        } else {
+            ""
+        };
+        writeln!(
+            writer,
+            "    {{\"path\": {}, \"bytes\": {}, \"sha256\": \"{}\"}}{}",
+            json_string(&input.path),
+            input.bytes,
+            input.sha256,
+            separator
+        )?;
+    }
+    writeln!(writer, "  ],")?;
+    writeln!(
+        writer,
+        "  \"created\": {}",
+        json_string(&provenance.created)
+    )?;
+    writeln!(writer, "}}")
+}
+
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The time as `YYYY-MM-DDTHH:MM:SSZ` in UTC.
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, of_day) = (seconds / 86_400, seconds % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day / 60 % 60,
+        of_day % 60
+    )
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(choice)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(majority);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+    for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *value = value.wrapping_add(add);
+    }
+}
+
+/// SHA-256 digest (lowercase hex) of everything the reader yields, with its length in bytes.
+pub fn sha256<R: Read>(mut reader: R) -> io::Result<(String, u64)> {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut pending: Vec<u8> = Vec::with_capacity(64);
+    let mut length: u64 = 0;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        length += read as u64;
+        let mut data = &buffer[..read];
+        if !pending.is_empty() {
+            let take = (64 - pending.len()).min(data.len());
+            pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if pending.len() == 64 {
+                compress(&mut state, &pending);
+                pending.clear();
+            }
+        }
+        let blocks = data.chunks_exact(64);
+        pending.extend_from_slice(blocks.remainder());
+        for block in blocks {
+            compress(&mut state, block);
+        }
+    }
+
+    // a one bit, zeros up to 56 bytes of the last block and the length in bits
+    pending.push(0x80);
+    while pending.len() % 64 != 56 {
+        pending.push(0);
+    }
+    pending.extend_from_slice(&(length * 8).to_be_bytes());
+    for block in pending.chunks_exact(64) {
+        compress(&mut state, block);
+    }
+
+    let digest: String = state.iter().map(|word| format!("{:08x}", word)).collect();
+    Ok((digest, length))
+}
@@ -0,0 +1,260 @@
+use crate::csv_utils::{read_beat_times, read_ecg_csv, read_rr_csv, CsvColumn, CsvOptions, RrUnit};
+use crate::detection::{detect_beats, DetectionOptions};
+use crate::detectors::DetectorRegistry;
+use crate::edf_utils::read_edf_ecg_data;
+use crate::filters::FilterConfig;
+use crate::hrv::{self, HrvMetrics, SpectralMethod};
+use crate::models::{Detection, EcgPoint, RrInterval};
+use crate::rr::rr_intervals as beat_intervals;
+use crate::wfdb_utils::read_wfdb_ecg_data;
+use numpy::{PyArray1, PyReadonlyArray1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::error::Error;
+use std::io;
+
+// I/O errors keep their Python counterparts (FileNotFoundError, PermissionError, ...)
+fn py_error(error: Box<dyn Error>) -> PyErr {
+    match error.downcast::<io::Error>() {
+        Ok(error) => (*error).into(),
+        Err(error) => PyValueError::new_err(error.to_string()),
+    }
+}
+
+fn to_vec(array: &PyReadonlyArray1<f64>) -> Vec<f64> {
+    array.as_array().to_vec()
+}
+
+fn intervals(beats: &PyReadonlyArray1<f64>) -> Vec<RrInterval> {
+    beat_intervals(&to_vec(beats))
+}
+
+// a recording as `time` and `voltage` arrays
+fn points_dict<'py>(py: Python<'py>, points: &[EcgPoint]) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    let times: Vec<f64> = points.iter().map(|point| point.time).collect();
+    let voltage: Vec<f64> = points.iter().map(|point| point.voltage).collect();
+    dict.set_item("time", PyArray1::from_vec_bound(py, times))?;
+    dict.set_item("voltage", PyArray1::from_vec_bound(py, voltage))?;
+    Ok(dict)
+}
+
+// the measures of an HRV section by name, None where one could not be computed
+fn metrics_dict<'py>(py: Python<'py>, section: &dyn HrvMetrics) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (name, value) in section.metrics() {
+        dict.set_item(name, value)?;
+    }
+    Ok(dict)
+}
+
+/// Detects the beats of one lead sampled at `fs` Hz, optionally pre-filtered (cutoffs in
+/// Hz). Returns a dict of NumPy arrays `time` (s from the first sample), `sample`,
+/// `amplitude` and `confidence`, ready for `pandas.DataFrame`.
+#[pyfunction]
+#[pyo3(signature = (signal, fs, detector = "threshold", highpass = None, lowpass = None, notch = None))]
+fn detect<'py>(
+    py: Python<'py>,
+    signal: PyReadonlyArray1<f64>,
+    fs: f64,
+    detector: &str,
+    highpass: Option<f64>,
+    lowpass: Option<f64>,
+    notch: Option<f64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    if !(fs.is_finite() && fs > 0.0) {
+        return Err(PyValueError::new_err("fs must be positive"));
+    }
+    let registry = DetectorRegistry::default();
+    let detector = registry.get(detector).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown detector {:?}, expected one of {}",
+            detector,
+            registry.names().join(", ")
+        ))
+    })?;
+    let points: Vec<EcgPoint> = to_vec(&signal)
+        .into_iter()
+        .enumerate()
+        .map(|(i, voltage)| EcgPoint {
+            time: i as f64 / fs,
+            voltage,
+        })
+        .collect();
+    let options = DetectionOptions {
+        fs: Some(fs),
+        filter: FilterConfig {
+            highpass,
+            lowpass,
+            notch,
+            zero_phase: false,
+        },
+        ..DetectionOptions::default()
+    };
+    // the GIL is released so other Python threads run during detection
+    let detections = py.allow_threads(|| detect_beats(&points, detector, &options));
+
+    let dict = PyDict::new_bound(py);
+    let column = |value: fn(&Detection) -> f64| {
+        PyArray1::from_vec_bound(py, detections.iter().map(value).collect())
+    };
+    dict.set_item("time", column(|detection| detection.time))?;
+    let samples: Vec<u64> = detections
+        .iter()
+        .map(|detection| detection.sample as u64)
+        .collect();
+    dict.set_item("sample", PyArray1::from_vec_bound(py, samples))?;
+    dict.set_item("amplitude", column(|detection| detection.amplitude))?;
+    dict.set_item("confidence", column(|detection| detection.confidence))?;
+    Ok(dict)
+}
+
+/// Reads the time column and one voltage column (by header name or index, else the one
+/// recognised as voltage) of an ECG CSV into `time` and `voltage` arrays.
+#[pyfunction]
+#[pyo3(signature = (path, column = None))]
+fn read_csv<'py>(
+    py: Python<'py>,
+    path: &str,
+    column: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let options = CsvOptions {
+        column: column.and_then(|column| column.parse::<CsvColumn>().ok()),
+        ..CsvOptions::default()
+    };
+    let points = read_ecg_csv(path, &options).map_err(py_error)?;
+    points_dict(py, &points)
+}
+
+/// Reads one EDF signal in mV into `time` and `voltage` arrays.
+#[pyfunction]
+#[pyo3(signature = (path, signal = 0))]
+fn read_edf<'py>(py: Python<'py>, path: &str, signal: usize) -> PyResult<Bound<'py, PyDict>> {
+    let points = read_edf_ecg_data(path, signal).map_err(py_error)?;
+    points_dict(py, &points)
+}
+
+/// Reads one signal of a WFDB record (its name or .hea path) into `time` and `voltage`
+/// arrays.
+#[pyfunction]
+#[pyo3(signature = (record, signal = 0))]
+fn read_wfdb<'py>(py: Python<'py>, record: &str, signal: usize) -> PyResult<Bound<'py, PyDict>> {
+    let points = read_wfdb_ecg_data(record, signal).map_err(py_error)?;
+    points_dict(py, &points)
+}
+
+/// Reads a list of RR intervals (`unit` `"ms"` or `"s"`, by default from the header or the
+/// values) as an array in ms.
+#[pyfunction]
+#[pyo3(signature = (path, unit = None))]
+fn read_rr<'py>(
+    py: Python<'py>,
+    path: &str,
+    unit: Option<&str>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let unit = unit
+        .map(|unit| unit.parse::<RrUnit>())
+        .transpose()
+        .map_err(PyValueError::new_err)?;
+    let rr = read_rr_csv(path, &CsvOptions::default(), unit).map_err(py_error)?;
+    Ok(PyArray1::from_vec_bound(py, rr))
+}
+
+/// Reads beat times in seconds, one per line, as an array.
+#[pyfunction]
+fn read_beats<'py>(py: Python<'py>, path: &str) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let times = read_beat_times(path).map_err(py_error)?;
+    Ok(PyArray1::from_vec_bound(py, times))
+}
+
+/// The RR intervals between beat times (s) as `time` (of the closing beat) and `rr_ms`
+/// arrays.
+#[pyfunction]
+fn rr_intervals<'py>(
+    py: Python<'py>,
+    beats: PyReadonlyArray1<f64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let intervals = intervals(&beats);
+    let dict = PyDict::new_bound(py);
+    let times: Vec<f64> = intervals.iter().map(|rr| rr.time).collect();
+    let rr_ms: Vec<f64> = intervals.iter().map(|rr| rr.rr_ms).collect();
+    dict.set_item("time", PyArray1::from_vec_bound(py, times))?;
+    dict.set_item("rr_ms", PyArray1::from_vec_bound(py, rr_ms))?;
+    Ok(dict)
+}
+
+/// Time domain HRV of beat times (s), or None below three beats.
+#[pyfunction]
+fn time_domain<'py>(
+    py: Python<'py>,
+    beats: PyReadonlyArray1<f64>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    hrv::time_domain(&intervals(&beats))
+        .map(|metrics| metrics_dict(py, &metrics))
+        .transpose()
+}
+
+/// Spectral HRV (`method` `"welch"` or `"lomb-scargle"`) of beat times (s), or None when
+/// the recording is too short for a spectrum.
+#[pyfunction]
+#[pyo3(signature = (beats, method = "welch"))]
+fn frequency_domain<'py>(
+    py: Python<'py>,
+    beats: PyReadonlyArray1<f64>,
+    method: &str,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let method = match method {
+        "welch" => SpectralMethod::Welch,
+        "lomb-scargle" => SpectralMethod::LombScargle,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "unknown spectral method {:?}, expected welch or lomb-scargle",
+                method
+            )))
+        }
+    };
+    hrv::frequency_domain(&intervals(&beats), method)
+        .map(|metrics| metrics_dict(py, &metrics))
+        .transpose()
+}
+
+/// Poincaré plot descriptors of beat times (s), or None for too few beats.
+#[pyfunction]
+fn poincare<'py>(
+    py: Python<'py>,
+    beats: PyReadonlyArray1<f64>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    hrv::poincare(&intervals(&beats))
+        .map(|metrics| metrics_dict(py, &metrics))
+        .transpose()
+}
+
+/// Heart rate asymmetry of beat times (s), or None for too few beats.
+#[pyfunction]
+fn asymmetry<'py>(
+    py: Python<'py>,
+    beats: PyReadonlyArray1<f64>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    hrv::asymmetry(&intervals(&beats))
+        .map(|metrics| metrics_dict(py, &metrics))
+        .transpose()
+}
+
+/// The `signalweaver` Python module, built with maturin (see pyproject.toml).
+#[pymodule]
+fn signalweaver(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    module.add_function(wrap_pyfunction!(detect, module)?)?;
+    module.add_function(wrap_pyfunction!(read_csv, module)?)?;
+    module.add_function(wrap_pyfunction!(read_edf, module)?)?;
+    module.add_function(wrap_pyfunction!(read_wfdb, module)?)?;
+    module.add_function(wrap_pyfunction!(read_rr, module)?)?;
+    module.add_function(wrap_pyfunction!(read_beats, module)?)?;
+    module.add_function(wrap_pyfunction!(rr_intervals, module)?)?;
+    module.add_function(wrap_pyfunction!(time_domain, module)?)?;
+    module.add_function(wrap_pyfunction!(frequency_domain, module)?)?;
+    module.add_function(wrap_pyfunction!(poincare, module)?)?;
+    module.add_function(wrap_pyfunction!(asymmetry, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use crate::delineation::BeatWaves;
+use crate::hrv::{mean, HrvMetrics, Metric};
+
+// QTc above which a beat counts as prolonged, in seconds
+const PROLONGED_QTC: f64 = 0.5;
+
+/// QT and heart-rate corrected QT of one beat, all in seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct QtBeat {
+    pub time: f64,
+    /// The RR interval preceding the beat.
+    pub rr: f64,
+    pub qt: f64,
+    /// QT / √RR.
+    pub bazett: f64,
+    /// QT / ∛RR.
+    pub fridericia: f64,
+    /// QT + 0.154 (1 − RR).
+    pub framingham: f64,
+}
+
+/// QT and QTc of every beat with a delineated T wave and a preceding beat. `times` are the
+/// beat times in seconds, in the order of `waves`.
+pub fn qt_series(waves: &[BeatWaves], times: &[f64], fs: f64) -> Vec<QtBeat> {
+    (1..waves.len().min(times.len()))
+        .filter_map(|i| {
+            let qt = waves[i].qt_interval(fs)?;
+            let rr = times[i] - times[i - 1];
+            if rr <= 0.0 {
+                return None;
+            }
+            Some(QtBeat {
+                time: times[i],
+                rr,
+                qt,
+                bazett: qt / rr.sqrt(),
+                fridericia: qt / rr.cbrt(),
+                framingham: qt + 0.154 * (1.0 - rr),
+            })
+        })
+        .collect()
+}
+
+/// Summary statistics of a QT series, in ms.
+#[derive(Debug, Clone)]
+pub struct QtSummary {
+    pub beats: usize,
+    pub mean_qt: f64,
+    pub mean_bazett: f64,
+    pub max_bazett: f64,
+    /// Beats with a Bazett QTc above 500 ms.
+    pub bazett_over_500: usize,
+    pub mean_fridericia: f64,
+    pub max_fridericia: f64,
+    pub fridericia_over_500: usize,
+    pub mean_framingham: f64,
+    pub max_framingham: f64,
+    pub framingham_over_500: usize,
+}
+
+impl HrvMetrics for QtSummary {
+    fn section(&self) -> &'static str {
+        "qt"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("beats", Some(self.beats as f64)),
+            ("mean_qt_ms", Some(self.mean_qt)),
+            ("mean_qtc_bazett_ms", Some(self.mean_bazett)),
+            ("max_qtc_bazett_ms", Some(self.max_bazett)),
+            ("qtc_bazett_over_500", Some(self.bazett_over_500 as f64)),
+            ("mean_qtc_fridericia_ms", Some(self.mean_fridericia)),
+            ("max_qtc_fridericia_ms", Some(self.max_fridericia)),
+            (
+                "qtc_fridericia_over_500",
+                Some(self.fridericia_over_500 as f64),
+            ),
+            ("mean_qtc_framingham_ms", Some(self.mean_framingham)),
+            ("max_qtc_framingham_ms", Some(self.max_framingham)),
+            (
+                "qtc_framingham_over_500",
+                Some(self.framingham_over_500 as f64),
+            ),
+        ]
+    }
+}
+
+/// Needs at least one beat.
+pub fn qt_summary(series: &[QtBeat]) -> Option<QtSummary> {
+    if series.is_empty() {
+        return None;
+    }
+    let stats = |value: fn(&QtBeat) -> f64| {
+        let values: Vec<f64> = series.iter().map(value).collect();
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let prolonged = values.iter().filter(|&&v| v > PROLONGED_QTC).count();
+        (1000.0 * mean(&values), 1000.0 * max, prolonged)
+    };
+    let (mean_qt, _, _) = stats(|beat| beat.qt);
+    let (mean_bazett, max_bazett, bazett_over_500) = stats(|beat| beat.bazett);
+    let (mean_fridericia, max_fridericia, fridericia_over_500) = stats(|beat| beat.fridericia);
+    let (mean_framingham, max_framingham, framingham_over_500) = stats(|beat| beat.framingham);
+    Some(QtSummary {
+        beats: series.len(),
+        mean_qt,
+        mean_bazett,
+        max_bazett,
+        bazett_over_500,
+        mean_fridericia,
+        max_fridericia,
+        fridericia_over_500,
+        mean_framingham,
+        max_framingham,
+        framingham_over_500,
+    })
+}
@@ -0,0 +1,255 @@
+use crate::filters::{filtfilt, Biquad};
+
+// upper edge of the baseline band and of the ECG band, in Hz
+const BASELINE_CUTOFF: f64 = 1.0;
+const ECG_CUTOFF: f64 = 40.0;
+// consecutive samples closer than this fraction of the recording's range do not change
+const FLAT_TOLERANCE: f64 = 1e-4;
+// shortest run of unchanged samples counted as flat, in seconds
+const MIN_FLAT_RUN: f64 = 0.05;
+// samples within this fraction of the range from the recording's extremes are clipped
+const CLIP_TOLERANCE: f64 = 1e-3;
+
+/// Usability of an ECG segment for beat detection, after the kurtosis and baseline SQIs of
+/// Li and Clifford.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QualityLabel {
+    /// Detections are likely garbage: flat, clipped or noise-like.
+    Unusable,
+    /// Noisy or wandering, but QRS complexes stand out.
+    Acceptable,
+    Good,
+}
+
+impl QualityLabel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QualityLabel::Unusable => "unusable",
+            QualityLabel::Acceptable => "acceptable",
+            QualityLabel::Good => "good",
+        }
+    }
+}
+
+/// Signal quality indices of one analysis window.
+#[derive(Debug, Clone)]
+pub struct SegmentQuality {
+    /// First sample of the window and one past its last.
+    pub start: usize,
+    pub end: usize,
+    /// Fourth standardized moment; above 5 for a clean ECG, about 3 for Gaussian noise.
+    pub kurtosis: f64,
+    /// Share of the 0–40 Hz power below 1 Hz, i.e. baseline wander.
+    pub baseline_power_ratio: f64,
+    /// Share of the samples in runs of at least 50 ms without change.
+    pub flatline_fraction: f64,
+    /// Share of the samples at the recording's extremes, as left by a saturated ADC.
+    pub clipping_fraction: f64,
+    pub label: QualityLabel,
+}
+
+impl SegmentQuality {
+    fn label(kurtosis: f64, baseline: f64, flatline: f64, clipping: f64) -> QualityLabel {
+        if flatline > 0.5 || clipping > 0.05 || kurtosis < 3.0 || baseline > 0.5 {
+            QualityLabel::Unusable
+        } else if kurtosis < 5.0 || baseline > 0.1 || flatline > 0.1 || clipping > 0.01 {
+            QualityLabel::Acceptable
+        } else {
+            QualityLabel::Good
+        }
+    }
+}
+
+/// Splits the signal into windows of `window` seconds (the last one may be shorter) and
+/// computes their quality indices.
+pub fn segment_quality(signal: &[f64], fs: f64, window: f64) -> Vec<SegmentQuality> {
+    if signal.is_empty() {
+        return Vec::new();
+    }
+    let window_size = ((window * fs) as usize).max(1);
+    let min_flat_run = ((MIN_FLAT_RUN * fs) as usize).max(2);
+
+    let (min, max) = signal
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let range = max - min;
+
+    // the baseline and ECG bands, zero-phase so the two line up sample by sample
+    let offset = signal[0];
+    let shifted: Vec<f64> = signal.iter().map(|v| v - offset).collect();
+    let baseline = filtfilt(
+        &[Biquad::lowpass(fs, BASELINE_CUTOFF)],
+        &shifted,
+        fs as usize,
+    );
+    let band = if ECG_CUTOFF < fs / 2.0 {
+        filtfilt(&[Biquad::lowpass(fs, ECG_CUTOFF)], &shifted, fs as usize)
+    } else {
+        shifted
+    };
+
+    (0..signal.len())
+        .step_by(window_size)
+        .map(|start| {
+            let end = (start + window_size).min(signal.len());
+            let segment = &signal[start..end];
+
+            let kurtosis = kurtosis(segment);
+            let baseline_power_ratio =
+                centered_energy(&baseline[start..end]) / centered_energy(&band[start..end]);
+            let flatline_fraction = flat_samples(segment, FLAT_TOLERANCE * range, min_flat_run)
+                as f64
+                / segment.len() as f64;
+            let clip = CLIP_TOLERANCE * range;
+            let clipping_fraction = if range > 0.0 {
+                segment
+                    .iter()
+                    .filter(|&&v| v <= min + clip || v >= max - clip)
+                    .count() as f64
+                    / segment.len() as f64
+            } else {
+                0.0
+            };
+
+            // a constant window has no kurtosis or band power to speak of
+            let baseline_power_ratio = if baseline_power_ratio.is_finite() {
+                baseline_power_ratio
+            } else {
+                0.0
+            };
+            SegmentQuality {
+                start,
+                end,
+                kurtosis,
+                baseline_power_ratio,
+                flatline_fraction,
+                clipping_fraction,
+                label: SegmentQuality::label(
+                    kurtosis,
+                    baseline_power_ratio,
+                    flatline_fraction,
+                    clipping_fraction,
+                ),
+            }
+        })
+        .collect()
+}
+
+// fourth standardized moment, 0 for a constant segment
+fn kurtosis(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    if variance <= 0.0 {
+        return 0.0;
+    }
+    values.iter().map(|v| (v - mean).powi(4)).sum::<f64>() / n / (variance * variance)
+}
+
+// energy around the mean
+fn centered_energy(values: &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - mean).powi(2)).sum()
+}
+
+// samples in runs of at least `min_run` with consecutive differences within `tolerance`
+fn flat_samples(values: &[f64], tolerance: f64, min_run: usize) -> usize {
+    let mut flat = 0;
+    let mut run = 1;
+    for i in 1..=values.len() {
+        if i < values.len() && (values[i] - values[i - 1]).abs() <= tolerance {
+            run += 1;
+            continue;
+        }
+        if run >= min_run {
+            flat += run;
+        }
+        run = 1;
+    }
+    flat
+}
+
+// artifacts hold their value to this fraction of the recording's range, tighter than
+// FLAT_TOLERANCE so slowly turning waves are not taken for them
+const ARTIFACT_TOLERANCE: f64 = 1e-6;
+// shortest unchanged run reported as a flatline, in seconds
+const MIN_FLATLINE: f64 = 0.3;
+// shortest run pinned at the extremes reported as saturation, in seconds
+const MIN_SATURATION: f64 = 0.05;
+// saturation lasting this long is taken for a detached electrode, in seconds
+const MIN_LEAD_OFF: f64 = 1.0;
+
+/// Kind of a stretch of signal that carries no ECG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// The signal does not change.
+    Flatline,
+    /// The signal is pinned at the recording's extremes, as by a saturated ADC.
+    Saturation,
+    /// Saturation lasting a second or more, as when an electrode comes off and the amplifier
+    /// runs to its rail.
+    LeadOff,
+}
+
+impl ArtifactKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ArtifactKind::Flatline => "flatline",
+            ArtifactKind::Saturation => "saturation",
+            ArtifactKind::LeadOff => "lead_off",
+        }
+    }
+}
+
+/// A flat, saturated or lead-off stretch, from sample `start` to one past `end`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Finds the runs of unchanged samples: those at the recording's extremes lasting 50 ms or
+/// more are saturation (lead-off from 1 s), others lasting 300 ms or more flatlines.
+pub fn find_artifacts(signal: &[f64], fs: f64) -> Vec<Artifact> {
+    let (min, max) = signal
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let range = max - min;
+    let tolerance = ARTIFACT_TOLERANCE * range;
+    let clip = CLIP_TOLERANCE * range;
+    let at_rail = |v: f64| range > 0.0 && (v <= min + clip || v >= max - clip);
+
+    let mut artifacts = Vec::new();
+    let mut start = 0;
+    for i in 1..=signal.len() {
+        if i < signal.len() && (signal[i] - signal[start]).abs() <= tolerance {
+            continue;
+        }
+        let duration = (i - start) as f64 / fs;
+        let kind = if at_rail(signal[start]) {
+            match duration {
+                d if d >= MIN_LEAD_OFF => Some(ArtifactKind::LeadOff),
+                d if d >= MIN_SATURATION => Some(ArtifactKind::Saturation),
+                _ => None,
+            }
+        } else if duration >= MIN_FLATLINE {
+            Some(ArtifactKind::Flatline)
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            artifacts.push(Artifact {
+                kind,
+                start,
+                end: i,
+            });
+        }
+        start = i;
+    }
+    artifacts
+}
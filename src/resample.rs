@@ -0,0 +1,133 @@
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
+use std::f64::consts::PI;
+
+// zero crossings of the interpolating sinc on each side, at the lower of the two rates
+const ZERO_CROSSINGS: usize = 16;
+// largest up or down factor; rate ratios needing more are approximated
+const MAX_FACTOR: u64 = 1000;
+
+/// Resamples `signal` from `fs_in` to `fs_out` Hz by polyphase filtering: conceptually the
+/// signal is upsampled by `up`, low-pass filtered below the lower Nyquist frequency (which
+/// removes both the imaging and, when decimating, the aliasing) and downsampled by `down`,
+/// with `up / down` the rate ratio. Ratios with factors above 1000 are replaced by the
+/// closest fraction that has none, see `rational_ratio`. The signal is extended with its
+/// edge values, so there are no transients at either end.
+pub fn resample(signal: &[f64], fs_in: f64, fs_out: f64) -> Vec<f64> {
+    if signal.is_empty() || fs_in <= 0.0 || fs_out <= 0.0 {
+        return Vec::new();
+    }
+    let (up, down) = rational_ratio(fs_out / fs_in);
+    if up == down {
+        return signal.to_vec();
+    }
+    let (up, down) = (up as usize, down as usize);
+
+    // windowed-sinc low-pass at the upsampled rate, with zero crossings every `spacing` taps
+    let spacing = up.max(down);
+    let half = ZERO_CROSSINGS * spacing;
+    let taps: Vec<f64> = (0..=2 * half)
+        .map(|k| {
+            let x = (k as f64 - half as f64) / spacing as f64;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            // Blackman window
+            let phase = 2.0 * PI * k as f64 / (2 * half) as f64;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            sinc * window * up as f64 / spacing as f64
+        })
+        .collect();
+
+    let last = signal.len() as isize - 1;
+    let (up, half) = (up as isize, half as isize);
+    let output_len = (signal.len() * up as usize).div_ceil(down);
+    (0..output_len)
+        .map(|m| {
+            // output sample m sits at `m * down` on the upsampled grid and input sample n at
+            // `n * up`, so n contributes through tap `m * down - n * up + half`
+            let center = (m * down) as isize;
+            ((center - half).div_euclid(up)..=(center + half).div_euclid(up))
+                .filter_map(|n| {
+                    let tap = taps.get(usize::try_from(center - n * up + half).ok()?)?;
+                    Some(tap * signal[n.clamp(0, last) as usize])
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// `ratio` as `(up, down)` in lowest terms with neither above 1000, exact when possible and
+/// otherwise the closest such fraction (continued-fraction convergents).
+pub fn rational_ratio(ratio: f64) -> (u64, u64) {
+    let (mut p0, mut q0, mut p1, mut q1) = (0u64, 1u64, 1u64, 0u64);
+    let mut x = ratio;
+    loop {
+        let a = x.floor();
+        let (p2, q2) = (a as u64 * p1 + p0, a as u64 * q1 + q0);
+        if p2 > MAX_FACTOR || q2 > MAX_FACTOR {
+            break;
+        }
+        (p0, q0, p1, q1) = (p1, q1, p2, q2);
+        let fraction = x - a;
+        if fraction < 1e-9 || (p1 as f64 / q1 as f64 - ratio).abs() < 1e-12 * ratio {
+            break;
+        }
+        x = 1.0 / fraction;
+    }
+    // ratios beyond the limits themselves
+    if q1 == 0 {
+        return (MAX_FACTOR, 1);
+    }
+    if p1 == 0 {
+        return (1, MAX_FACTOR);
+    }
+    (p1, q1)
+}
+
+/// The rate `resample` actually produces for `fs_out`, which differs from it when the ratio
+/// had to be approximated.
+pub fn output_rate(fs_in: f64, fs_out: f64) -> f64 {
+    let (up, down) = rational_ratio(fs_out / fs_in);
+    fs_in * up as f64 / down as f64
+}
+
+/// Resamples a recording to `fs_out`, its times continuing from the first point at the new
+/// rate.
+pub fn resample_points(points: &[EcgPoint], fs_in: f64, fs_out: f64) -> Vec<EcgPoint> {
+    let Some(first) = points.first() else {
+        return Vec::new();
+    };
+    let voltage: Vec<f64> = points.iter().map(|point| point.voltage).collect();
+    let fs = output_rate(fs_in, fs_out);
+    resample(&voltage, fs_in, fs_out)
+        .into_iter()
+        .enumerate()
+        .map(|(i, voltage)| EcgPoint {
+            time: first.time + i as f64 / fs,
+            voltage,
+        })
+        .collect()
+}
+
+/// Resamples every lead of a recording to `fs_out`.
+pub fn resample_leads(ecg: &MultiLeadEcg, fs_in: f64, fs_out: f64) -> MultiLeadEcg {
+    let Some(&start) = ecg.times.first() else {
+        return MultiLeadEcg::default();
+    };
+    let leads: Vec<Lead> = ecg
+        .leads
+        .iter()
+        .map(|lead| Lead {
+            name: lead.name.clone(),
+            voltage: resample(&lead.voltage, fs_in, fs_out),
+        })
+        .collect();
+    let fs = output_rate(fs_in, fs_out);
+    let samples = leads.first().map_or(0, |lead| lead.voltage.len());
+    MultiLeadEcg {
+        times: (0..samples).map(|i| start + i as f64 / fs).collect(),
+        leads,
+    }
+}
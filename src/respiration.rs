@@ -0,0 +1,116 @@
+use crate::spectrum::{lomb_scargle, Spectrum};
+
+// breathing frequencies searched, in Hz (6 to 42 breaths per minute)
+const BAND: (f64, f64) = (0.1, 0.7);
+const FREQUENCY_STEP: f64 = 0.005;
+// windows with fewer beats are left out
+const MIN_BEATS: usize = 8;
+
+/// Settings of the ECG-derived respiration.
+#[derive(Debug, Clone)]
+pub struct EdrOptions {
+    /// Window the breathing rate is estimated over, in seconds.
+    pub window: f64,
+    /// Seconds the window advances by.
+    pub step: f64,
+}
+
+impl Default for EdrOptions {
+    fn default() -> Self {
+        EdrOptions {
+            window: 60.0,
+            step: 10.0,
+        }
+    }
+}
+
+/// Breathing rate over a window from `start` to `end` (seconds), in breaths per minute.
+#[derive(Debug, Clone, Copy)]
+pub struct BreathingRate {
+    pub start: f64,
+    pub end: f64,
+    /// From both surrogates, their normalized spectra averaged.
+    pub rate: f64,
+    /// From the R-peak amplitude modulation.
+    pub amplitude_rate: f64,
+    /// From the respiratory sinus arrhythmia of the RR intervals.
+    pub rr_rate: f64,
+}
+
+/// Breathing rate trend from the R-peak amplitudes (baseline-corrected) at the beat `times`
+/// (seconds): in every window the Lomb–Scargle spectra of the amplitude series and of the RR
+/// series are searched for their peak between 6 and 42 breaths per minute. As the beats
+/// sample the respiration, rates above half the heart rate cannot be told apart.
+pub fn breathing_rates(
+    times: &[f64],
+    amplitudes: &[f64],
+    options: &EdrOptions,
+) -> Vec<BreathingRate> {
+    let beats = times.len().min(amplitudes.len());
+    let (Some(&first), Some(&last)) = (times.first(), times[..beats].last()) else {
+        return Vec::new();
+    };
+    if options.window <= 0.0 || options.step <= 0.0 {
+        return Vec::new();
+    }
+    let count = ((BAND.1 - BAND.0) / FREQUENCY_STEP) as usize;
+    let frequencies: Vec<f64> = (0..=count)
+        .map(|k| BAND.0 + k as f64 * FREQUENCY_STEP)
+        .collect();
+
+    let mut rates = Vec::new();
+    let mut k = 0;
+    loop {
+        let start = first + k as f64 * options.step;
+        let end = start + options.window;
+        if end > last {
+            break;
+        }
+        k += 1;
+        let begin = times[..beats].partition_point(|&t| t < start);
+        let stop = times[..beats].partition_point(|&t| t <= end);
+        if stop < begin + MIN_BEATS {
+            continue;
+        }
+
+        let window_times = &times[begin..stop];
+        let amplitude = lomb_scargle(window_times, &amplitudes[begin..stop], &frequencies);
+        // each RR interval stamped with the beat closing it
+        let rr: Vec<f64> = window_times
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        let rr = lomb_scargle(&window_times[1..], &rr, &frequencies);
+
+        let (amplitude, rr) = (normalized(&amplitude), normalized(&rr));
+        let fused: Vec<f64> = amplitude.iter().zip(&rr).map(|(a, b)| a + b).collect();
+        rates.push(BreathingRate {
+            start,
+            end,
+            rate: peak_rate(&frequencies, &fused),
+            amplitude_rate: peak_rate(&frequencies, &amplitude),
+            rr_rate: peak_rate(&frequencies, &rr),
+        });
+    }
+    rates
+}
+
+// the power as fractions of its total, so both surrogates weigh alike
+fn normalized(spectrum: &Spectrum) -> Vec<f64> {
+    let total: f64 = spectrum.power.iter().sum();
+    spectrum
+        .power
+        .iter()
+        .map(|p| if total > 0.0 { p / total } else { 0.0 })
+        .collect()
+}
+
+// breaths per minute at the peak of the power
+fn peak_rate(frequencies: &[f64], power: &[f64]) -> f64 {
+    let peak = power
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(i, _)| i);
+    60.0 * frequencies[peak]
+}
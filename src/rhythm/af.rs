@@ -0,0 +1,153 @@
+use super::{EventKind, RhythmEvent};
+use crate::models::RrInterval;
+
+// normalized RMSSD, normalized Shannon entropy and turning point ratio bounds of Dash et al.
+const MIN_NRMSSD: f64 = 0.1;
+const MIN_ENTROPY: f64 = 0.7;
+// turning point ratio within this many standard deviations of its value for a random series
+const TPR_DEVIATIONS: f64 = 1.96;
+// histogram bins of the Shannon entropy
+const ENTROPY_BINS: usize = 16;
+
+/// Settings of the AF screening.
+#[derive(Debug, Clone)]
+pub struct AfOptions {
+    /// RR intervals per analysis window.
+    pub window: usize,
+    /// Shortest episode reported, in seconds (30 s by the clinical definition).
+    pub min_duration: f64,
+}
+
+impl Default for AfOptions {
+    fn default() -> Self {
+        AfOptions {
+            window: 64,
+            min_duration: 30.0,
+        }
+    }
+}
+
+/// Irregularity measures of one window of RR intervals, from the beat before its first
+/// interval to the beat ending its last (seconds).
+#[derive(Debug, Clone)]
+pub struct AfWindow {
+    pub start: f64,
+    pub end: f64,
+    /// RMSSD over the mean RR interval.
+    pub nrmssd: f64,
+    /// Shannon entropy of the RR histogram, normalized to 0–1.
+    pub entropy: f64,
+    /// Turning points over the number of intervals that can be one.
+    pub tpr: f64,
+    pub af: bool,
+}
+
+/// Measures windows of `window` RR intervals, advancing by a quarter window. The RMSSD and
+/// entropy leave out the longest and shortest sixteenth of each window, so single ectopic
+/// beats do not pass for AF; a window is AF when all three measures point to a random
+/// rhythm (Dash et al., 2009).
+pub fn af_windows(intervals: &[RrInterval], window: usize) -> Vec<AfWindow> {
+    let window = window.max(8);
+    if intervals.len() < window {
+        return Vec::new();
+    }
+    let step = (window / 4).max(1);
+    (0..=intervals.len() - window)
+        .step_by(step)
+        .map(|start| {
+            let segment = &intervals[start..start + window];
+            let rr: Vec<f64> = segment.iter().map(|interval| interval.rr_ms).collect();
+
+            let mut trimmed = rr.clone();
+            trimmed.sort_unstable_by(f64::total_cmp);
+            let outliers = window / 16;
+            let trimmed = &trimmed[outliers..window - outliers];
+            let kept: Vec<f64> = rr
+                .iter()
+                .copied()
+                .filter(|&value| value >= trimmed[0] && value <= trimmed[trimmed.len() - 1])
+                .collect();
+
+            let nrmssd = {
+                let differences: Vec<f64> = kept.windows(2).map(|pair| pair[1] - pair[0]).collect();
+                let rmssd = (differences.iter().map(|d| d * d).sum::<f64>()
+                    / differences.len().max(1) as f64)
+                    .sqrt();
+                rmssd / (kept.iter().sum::<f64>() / kept.len() as f64)
+            };
+            let entropy = normalized_entropy(trimmed);
+            let tpr = turning_point_ratio(&rr);
+
+            // turning points of an i.i.d. series: mean (2n - 4) / 3, variance (16n - 29) / 90
+            let n = window as f64;
+            let mean = (2.0 * n - 4.0) / 3.0 / (n - 2.0);
+            let deviation = ((16.0 * n - 29.0) / 90.0).sqrt() / (n - 2.0);
+            let random_tpr = (tpr - mean).abs() <= TPR_DEVIATIONS * deviation;
+
+            AfWindow {
+                start: segment[0].time - segment[0].rr_ms / 1000.0,
+                end: segment[window - 1].time,
+                nrmssd,
+                entropy,
+                tpr,
+                af: nrmssd >= MIN_NRMSSD && entropy >= MIN_ENTROPY && random_tpr,
+            }
+        })
+        .collect()
+}
+
+// Shannon entropy of a 16-bin histogram over the value range, over its maximum
+fn normalized_entropy(values: &[f64]) -> f64 {
+    let (min, max) = (values[0], values[values.len() - 1]);
+    if max <= min {
+        return 0.0;
+    }
+    let mut counts = [0usize; ENTROPY_BINS];
+    for &value in values {
+        let bin = ((value - min) / (max - min) * ENTROPY_BINS as f64) as usize;
+        counts[bin.min(ENTROPY_BINS - 1)] += 1;
+    }
+    let total = values.len() as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.ln()
+        })
+        .sum();
+    entropy / (ENTROPY_BINS as f64).ln()
+}
+
+// share of the inner intervals that are a strict local maximum or minimum
+fn turning_point_ratio(rr: &[f64]) -> f64 {
+    let turning = rr
+        .windows(3)
+        .filter(|triple| {
+            (triple[1] > triple[0] && triple[1] > triple[2])
+                || (triple[1] < triple[0] && triple[1] < triple[2])
+        })
+        .count();
+    turning as f64 / (rr.len() - 2) as f64
+}
+
+/// Suspected AF episodes: overlapping AF windows merged, kept when lasting at least
+/// `min_duration`.
+pub fn detect_af(intervals: &[RrInterval], options: &AfOptions) -> Vec<RhythmEvent> {
+    let mut episodes: Vec<RhythmEvent> = Vec::new();
+    for window in af_windows(intervals, options.window) {
+        if !window.af {
+            continue;
+        }
+        match episodes.last_mut() {
+            Some(last) if window.start <= last.end => last.end = last.end.max(window.end),
+            _ => episodes.push(RhythmEvent {
+                kind: EventKind::AtrialFibrillation,
+                start: window.start,
+                end: window.end,
+            }),
+        }
+    }
+    episodes.retain(|episode| episode.duration() >= options.min_duration);
+    episodes
+}
@@ -0,0 +1,111 @@
+use super::{EventKind, RhythmEvent};
+use crate::classification::BeatClass;
+use crate::hrv::{HrvMetrics, Metric};
+
+// ectopic beats in a bigeminal or trigeminal cycle needed for an episode
+const MIN_CYCLES: usize = 3;
+
+/// Counts of ventricular ectopy in a recording.
+#[derive(Debug, Clone, Default)]
+pub struct EctopyCounts {
+    pub ventricular_beats: usize,
+    /// Ventricular beats between two normal ones.
+    pub isolated: usize,
+    pub couplets: usize,
+    /// Runs of three or more ventricular beats.
+    pub runs: usize,
+}
+
+impl HrvMetrics for EctopyCounts {
+    fn section(&self) -> &'static str {
+        "ectopy"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("ventricular_beats", Some(self.ventricular_beats as f64)),
+            ("isolated", Some(self.isolated as f64)),
+            ("couplets", Some(self.couplets as f64)),
+            ("runs", Some(self.runs as f64)),
+        ]
+    }
+}
+
+/// Ventricular ectopy patterns in classified beats (times in seconds, artifacts left out):
+/// couplets, runs of three or more ventricular beats, and bigeminy and trigeminy, i.e.
+/// isolated ventricular beats after every one or two normal beats, for at least three cycles.
+/// Each event spans from its first to its last ventricular beat.
+pub fn ectopy_events(times: &[f64], classes: &[BeatClass]) -> (Vec<RhythmEvent>, EctopyCounts) {
+    let beats: Vec<(f64, BeatClass)> = times
+        .iter()
+        .copied()
+        .zip(classes.iter().copied())
+        .filter(|&(_, class)| class != BeatClass::Artifact)
+        .collect();
+
+    // runs of consecutive ventricular beats as (first beat, length)
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, &(_, class)) in beats.iter().enumerate() {
+        if class != BeatClass::Ventricular {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((first, length)) if *first + *length == i => *length += 1,
+            _ => runs.push((i, 1)),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut counts = EctopyCounts {
+        ventricular_beats: runs.iter().map(|&(_, length)| length).sum(),
+        ..EctopyCounts::default()
+    };
+    let span = |kind, first: usize, last: usize| RhythmEvent {
+        kind,
+        start: beats[first].0,
+        end: beats[last].0,
+    };
+    for &(first, length) in &runs {
+        match length {
+            1 => counts.isolated += 1,
+            2 => {
+                counts.couplets += 1;
+                events.push(span(EventKind::Couplet, first, first + 1));
+            }
+            _ => {
+                counts.runs += 1;
+                events.push(span(EventKind::VentricularRun, first, first + length - 1));
+            }
+        }
+    }
+
+    // chains of isolated ventricular beats at a constant spacing of two or three beats
+    let isolated: Vec<usize> = runs
+        .iter()
+        .filter(|&&(_, length)| length == 1)
+        .map(|&(first, _)| first)
+        .collect();
+    let mut start = 0;
+    while start < isolated.len() {
+        let spacing = isolated.get(start + 1).map(|next| next - isolated[start]);
+        let mut end = start;
+        while end + 1 < isolated.len() && Some(isolated[end + 1] - isolated[end]) == spacing {
+            end += 1;
+        }
+        let kind = match spacing {
+            Some(2) => Some(EventKind::Bigeminy),
+            Some(3) => Some(EventKind::Trigeminy),
+            _ => None,
+        };
+        match kind {
+            Some(kind) if end - start + 1 >= MIN_CYCLES => {
+                events.push(span(kind, isolated[start], isolated[end]));
+                start = end + 1;
+            }
+            _ => start += 1,
+        }
+    }
+
+    events.sort_by(|a, b| a.start.total_cmp(&b.start));
+    (events, counts)
+}
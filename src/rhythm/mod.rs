@@ -0,0 +1,141 @@
+mod af;
+mod ectopy;
+mod rate;
+
+use crate::hrv::{HrvMetrics, Metric};
+use std::io::{self, Write};
+
+pub use af::{af_windows, detect_af, AfOptions, AfWindow};
+pub use ectopy::{ectopy_events, EctopyCounts};
+pub use rate::{rate_events, RateLimits};
+
+/// Kind of a rhythm event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// Suspected atrial fibrillation, from irregular RR intervals.
+    AtrialFibrillation,
+    /// Sustained heart rate below the bradycardia limit.
+    Bradycardia,
+    /// Sustained heart rate above the tachycardia limit.
+    Tachycardia,
+    /// A long RR interval.
+    Pause,
+    /// An RR interval long enough to count as cardiac standstill.
+    Asystole,
+    /// Two ventricular beats in a row.
+    Couplet,
+    /// Three or more ventricular beats in a row.
+    VentricularRun,
+    /// A ventricular beat after every normal one.
+    Bigeminy,
+    /// A ventricular beat after every two normal ones.
+    Trigeminy,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 9] = [
+        EventKind::AtrialFibrillation,
+        EventKind::Bradycardia,
+        EventKind::Tachycardia,
+        EventKind::Pause,
+        EventKind::Asystole,
+        EventKind::Couplet,
+        EventKind::VentricularRun,
+        EventKind::Bigeminy,
+        EventKind::Trigeminy,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::AtrialFibrillation => "af",
+            EventKind::Bradycardia => "bradycardia",
+            EventKind::Tachycardia => "tachycardia",
+            EventKind::Pause => "pause",
+            EventKind::Asystole => "asystole",
+            EventKind::Couplet => "couplet",
+            EventKind::VentricularRun => "ventricular_run",
+            EventKind::Bigeminy => "bigeminy",
+            EventKind::Trigeminy => "trigeminy",
+        }
+    }
+}
+
+/// A rhythm event from `start` to `end` (seconds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RhythmEvent {
+    pub kind: EventKind,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl RhythmEvent {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Episodes of one kind summarized for a recording of `duration` seconds.
+#[derive(Debug, Clone)]
+pub struct EventSummary {
+    pub kind: EventKind,
+    pub episodes: usize,
+    /// Summed duration of the episodes, in seconds.
+    pub total: f64,
+    /// Longest episode, in seconds.
+    pub longest: f64,
+    /// Share of the recording spent in the episodes, in percent.
+    pub burden: f64,
+}
+
+impl EventSummary {
+    pub fn new(kind: EventKind, events: &[RhythmEvent], duration: f64) -> Self {
+        let durations: Vec<f64> = events
+            .iter()
+            .filter(|event| event.kind == kind)
+            .map(RhythmEvent::duration)
+            .collect();
+        let total: f64 = durations.iter().sum();
+        EventSummary {
+            kind,
+            episodes: durations.len(),
+            total,
+            longest: durations.iter().cloned().fold(0.0, f64::max),
+            burden: if duration > 0.0 {
+                100.0 * total / duration
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl HrvMetrics for EventSummary {
+    fn section(&self) -> &'static str {
+        self.kind.as_str()
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("episodes", Some(self.episodes as f64)),
+            ("total_seconds", Some(self.total)),
+            ("longest_seconds", Some(self.longest)),
+            ("burden_percent", Some(self.burden)),
+        ]
+    }
+}
+
+/// Writes the events as `event,start,end,duration_s` rows.
+pub fn write_events_csv<W: Write>(writer: &mut W, events: &[RhythmEvent]) -> io::Result<()> {
+    writeln!(writer, "event,start,end,duration_s")?;
+    for event in events {
+        writeln!(
+            writer,
+            "{},{:.6},{:.6},{:.6}",
+            event.kind.as_str(),
+            event.start,
+            event.end,
+            event.duration()
+        )?;
+    }
+    Ok(())
+}
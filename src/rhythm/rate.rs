@@ -0,0 +1,89 @@
+use super::{EventKind, RhythmEvent};
+use crate::models::RrInterval;
+
+/// Heart rate and pause limits of the rate events.
+#[derive(Debug, Clone)]
+pub struct RateLimits {
+    /// Heart rate below which beats are bradycardic, in beats per minute.
+    pub bradycardia: f64,
+    /// Heart rate above which beats are tachycardic, in beats per minute.
+    pub tachycardia: f64,
+    /// Consecutive RR intervals beyond a rate limit that make a sustained episode.
+    pub min_intervals: usize,
+    /// RR intervals at least this long are pauses, in seconds.
+    pub pause: f64,
+    /// RR intervals at least this long are asystole rather than pauses, in seconds.
+    pub asystole: f64,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            bradycardia: 50.0,
+            tachycardia: 100.0,
+            min_intervals: 8,
+            pause: 2.0,
+            asystole: 3.0,
+        }
+    }
+}
+
+/// Sustained bradycardia and tachycardia (runs of at least `min_intervals` intervals beyond
+/// the limits, from the beat before the first to the last) and single pauses and asystoles,
+/// in the order they start. A pause is no bradycardic interval.
+pub fn rate_events(intervals: &[RrInterval], limits: &RateLimits) -> Vec<RhythmEvent> {
+    let kind_of = |interval: &RrInterval| {
+        let seconds = interval.rr_ms / 1000.0;
+        let rate = 60.0 / seconds;
+        if seconds >= limits.pause {
+            None
+        } else if rate < limits.bradycardia {
+            Some(EventKind::Bradycardia)
+        } else if rate > limits.tachycardia {
+            Some(EventKind::Tachycardia)
+        } else {
+            None
+        }
+    };
+
+    let mut events = Vec::new();
+    let mut run: Option<(EventKind, usize)> = None;
+    for i in 0..=intervals.len() {
+        let kind = intervals.get(i).and_then(kind_of);
+        if let Some((run_kind, first)) = run {
+            if kind != Some(run_kind) {
+                if i - first >= limits.min_intervals.max(1) {
+                    events.push(RhythmEvent {
+                        kind: run_kind,
+                        start: intervals[first].time - intervals[first].rr_ms / 1000.0,
+                        end: intervals[i - 1].time,
+                    });
+                }
+                run = None;
+            }
+        }
+        if let (None, Some(kind)) = (run, kind) {
+            run = Some((kind, i));
+        }
+
+        if let Some(interval) = intervals.get(i) {
+            let seconds = interval.rr_ms / 1000.0;
+            let kind = if seconds >= limits.asystole {
+                Some(EventKind::Asystole)
+            } else if seconds >= limits.pause {
+                Some(EventKind::Pause)
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                events.push(RhythmEvent {
+                    kind,
+                    start: interval.time - seconds,
+                    end: interval.time,
+                });
+            }
+        }
+    }
+    events.sort_by(|a, b| a.start.total_cmp(&b.start));
+    events
+}
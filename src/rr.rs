@@ -0,0 +1,45 @@
+use crate::models::RrInterval;
+use crate::sampling::Gap;
+
+/// RR intervals from QRS positions given in seconds.
+pub fn rr_intervals(qrs_positions: &[f64]) -> Vec<RrInterval> {
+    qrs_positions
+        .windows(2)
+        .map(|pair| RrInterval {
+            time: pair[1],
+            rr_ms: (pair[1] - pair[0]) * 1000.0,
+        })
+        .collect()
+}
+
+/// RR intervals leaving out those spanning a gap in the recording, whose beats between the
+/// two detected ones may have been missed.
+pub fn rr_intervals_without_gaps(qrs_positions: &[f64], gaps: &[Gap]) -> Vec<RrInterval> {
+    qrs_positions
+        .windows(2)
+        .filter(|pair| {
+            !gaps
+                .iter()
+                .any(|gap| pair[0] <= gap.start && pair[1] >= gap.end)
+        })
+        .map(|pair| RrInterval {
+            time: pair[1],
+            rr_ms: (pair[1] - pair[0]) * 1000.0,
+        })
+        .collect()
+}
+
+/// Beat times in seconds from RR intervals in milliseconds, the first beat at 0 s; the
+/// inverse of [`rr_intervals`] for recordings that hold intervals only.
+pub fn beat_times_from_rr(rr_ms: &[f64]) -> Vec<f64> {
+    if rr_ms.is_empty() {
+        return Vec::new();
+    }
+    let mut time = 0.0;
+    std::iter::once(0.0)
+        .chain(rr_ms.iter().map(|rr| {
+            time += rr / 1000.0;
+            time
+        }))
+        .collect()
+}
@@ -0,0 +1,208 @@
+use crate::filters::{filtfilt, Biquad};
+use crate::hrv::{HrvMetrics, Metric};
+use crate::template::QrsTemplate;
+
+// the averaged window around every beat, in seconds
+const BEFORE: f64 = 0.3;
+const AFTER: f64 = 0.3;
+// the noise is measured over the first 40 ms of the window, before the P wave
+const NOISE_WINDOW: f64 = 0.04;
+// the filtered QRS is sought within this distance of the beat, in seconds
+const QRS_SEARCH: f64 = 0.15;
+// the filtered QRS spans the samples above the noise mean plus this many deviations
+const NOISE_DEVIATIONS: f64 = 3.0;
+// band of the late potential analysis, in Hz
+const HIGHPASS: f64 = 40.0;
+const LOWPASS: f64 = 250.0;
+// the terminal QRS below this level is low-amplitude signal (40 µV in mV)
+const LAS_LEVEL: f64 = 0.04;
+// length of the terminal QRS portion of RMS40 and LAS40, in seconds
+const TERMINAL: f64 = 0.04;
+
+/// Settings of the signal averaging.
+#[derive(Debug, Clone)]
+pub struct AveragingOptions {
+    /// Beats correlating less with the QRS template are left out of the average.
+    pub min_correlation: f64,
+}
+
+impl Default for AveragingOptions {
+    fn default() -> Self {
+        AveragingOptions {
+            min_correlation: 0.95,
+        }
+    }
+}
+
+/// The averaged complex of a lead, from 300 ms before to 300 ms after the aligned beats.
+#[derive(Debug, Clone)]
+pub struct SignalAverage {
+    pub complex: Vec<f64>,
+    /// Index of the beat in `complex`.
+    pub fiducial: usize,
+    pub beats: usize,
+    /// RMS of the standard error of the mean over the complex, in signal units.
+    pub noise: f64,
+}
+
+/// Averages the complexes of `signal` (baseline-corrected) at `beats`, each aligned to the
+/// QRS template by cross-correlation (see `template::QrsTemplate`) and left out when
+/// matching it below `min_correlation`. `None` when no beat qualifies.
+pub fn signal_average(
+    signal: &[f64],
+    fs: f64,
+    beats: &[usize],
+    options: &AveragingOptions,
+) -> Option<SignalAverage> {
+    let template = QrsTemplate::initial(signal, fs, beats)?;
+    let before = (BEFORE * fs) as usize;
+    let after = (AFTER * fs) as usize;
+
+    let mut sum = vec![0.0; before + after + 1];
+    let mut sum_squares = vec![0.0; before + after + 1];
+    let mut count = 0;
+    for &beat in beats {
+        let Some((r, position)) = template.best_match(signal, beat) else {
+            continue;
+        };
+        if r < options.min_correlation || position < before || position + after >= signal.len() {
+            continue;
+        }
+        for (i, &value) in signal[position - before..=position + after]
+            .iter()
+            .enumerate()
+        {
+            sum[i] += value;
+            sum_squares[i] += value * value;
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+
+    let n = count as f64;
+    let complex: Vec<f64> = sum.iter().map(|s| s / n).collect();
+    let noise = if count > 1 {
+        let variance: f64 = sum_squares
+            .iter()
+            .zip(&complex)
+            .map(|(squares, mean)| (squares / n - mean * mean).max(0.0) / (n - 1.0))
+            .sum::<f64>()
+            / complex.len() as f64;
+        variance.sqrt()
+    } else {
+        f64::NAN
+    };
+    Some(SignalAverage {
+        complex,
+        fiducial: before,
+        beats: count,
+        noise,
+    })
+}
+
+/// The vector magnitude of averaged complexes (one per lead, e.g. X, Y and Z) after zero-phase
+/// 40–250 Hz band-pass filtering, the basis of the late potential analysis. The low-pass
+/// stage is skipped at rates of 500 Hz or below.
+pub fn filtered_magnitude(complexes: &[&[f64]], fs: f64) -> Vec<f64> {
+    let mut sections = vec![
+        Biquad::highpass(fs, HIGHPASS),
+        Biquad::highpass(fs, HIGHPASS),
+    ];
+    if LOWPASS < fs / 2.0 {
+        sections.push(Biquad::lowpass(fs, LOWPASS));
+    }
+    let length = complexes
+        .iter()
+        .map(|complex| complex.len())
+        .min()
+        .unwrap_or(0);
+    let filtered: Vec<Vec<f64>> = complexes
+        .iter()
+        .map(|complex| filtfilt(&sections, &complex[..length], length / 4))
+        .collect();
+    (0..length)
+        .map(|i| {
+            filtered
+                .iter()
+                .map(|lead| lead[i] * lead[i])
+                .sum::<f64>()
+                .sqrt()
+        })
+        .collect()
+}
+
+/// Late potential measures of a filtered QRS complex.
+#[derive(Debug, Clone)]
+pub struct LatePotentials {
+    pub beats: usize,
+    /// Noise of the averaged complex, in signal units.
+    pub noise: f64,
+    /// Duration of the filtered QRS, in seconds.
+    pub qrs_duration: f64,
+    /// RMS of the last 40 ms of the filtered QRS, in signal units.
+    pub rms40: f64,
+    /// Duration of the terminal filtered QRS below 40 µV (0.04 signal units in mV), in
+    /// seconds.
+    pub las40: f64,
+}
+
+impl HrvMetrics for LatePotentials {
+    fn section(&self) -> &'static str {
+        "saecg"
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            ("beats", Some(self.beats as f64)),
+            ("noise", Some(self.noise)),
+            ("filtered_qrs_ms", Some(1000.0 * self.qrs_duration)),
+            ("rms40", Some(self.rms40)),
+            ("las40_ms", Some(1000.0 * self.las40)),
+        ]
+    }
+}
+
+/// Measures the filtered QRS in `magnitude`, the `filtered_magnitude` of `average`: it spans
+/// the samples within 150 ms of the beat exceeding the mean plus three standard deviations of
+/// the first 40 ms, which precede the P wave.
+pub fn late_potentials(
+    magnitude: &[f64],
+    fs: f64,
+    average: &SignalAverage,
+) -> Option<LatePotentials> {
+    let fiducial = average.fiducial;
+    let noise_window = &magnitude[..((NOISE_WINDOW * fs) as usize).min(magnitude.len())];
+    if noise_window.len() < 2 {
+        return None;
+    }
+    let mean = noise_window.iter().sum::<f64>() / noise_window.len() as f64;
+    let deviation = (noise_window.iter().map(|v| (v - mean).powi(2)).sum::<f64>()
+        / (noise_window.len() - 1) as f64)
+        .sqrt();
+    let threshold = mean + NOISE_DEVIATIONS * deviation;
+
+    let search = (QRS_SEARCH * fs) as usize;
+    let start = fiducial.saturating_sub(search);
+    let end = (fiducial + search).min(magnitude.len() - 1);
+    let above = |&i: &usize| magnitude[i] > threshold;
+    let onset = (start..=end).find(above)?;
+    let offset = (start..=end).rev().find(above)?;
+
+    let terminal = ((TERMINAL * fs) as usize).max(1);
+    let last = &magnitude[(offset + 1).saturating_sub(terminal).max(onset)..=offset];
+    let rms40 = (last.iter().map(|v| v * v).sum::<f64>() / last.len() as f64).sqrt();
+    let las_start = (onset..=offset)
+        .rev()
+        .find(|&i| magnitude[i] >= LAS_LEVEL)
+        .map_or(onset, |i| i + 1);
+
+    Some(LatePotentials {
+        beats: average.beats,
+        noise: average.noise,
+        qrs_duration: (offset + 1 - onset) as f64 / fs,
+        rms40,
+        las40: (offset + 1 - las_start) as f64 / fs,
+    })
+}
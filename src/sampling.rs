@@ -0,0 +1,103 @@
+use tracing::{info, warn};
+
+// sample intervals further than this fraction from the median count as irregular
+const IRREGULAR_TOLERANCE: f64 = 0.1;
+
+/// Sampling frequency of a recording estimated from its whole time vector.
+#[derive(Debug, Clone)]
+pub struct SamplingEstimate {
+    /// Reciprocal of the median sample interval, in Hz.
+    pub fs: f64,
+    /// Number of sample intervals.
+    pub intervals: usize,
+    /// Intervals more than 10% off the median, including repeated or decreasing times.
+    pub irregular: usize,
+    /// Longest sample interval, in seconds.
+    pub longest_interval: f64,
+}
+
+impl SamplingEstimate {
+    /// Fraction of the sample intervals that are irregular.
+    pub fn irregular_fraction(&self) -> f64 {
+        self.irregular as f64 / self.intervals as f64
+    }
+
+    pub fn is_regular(&self) -> bool {
+        self.irregular == 0
+    }
+}
+
+/// Estimates the sampling frequency from the median interval between consecutive times, so
+/// single timestamp glitches do not bias it. Needs at least two increasing times.
+pub fn estimate_sampling(times: &[f64]) -> Option<SamplingEstimate> {
+    let mut intervals: Vec<f64> = times.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    if intervals.is_empty() {
+        return None;
+    }
+
+    let longest_interval = intervals.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let middle = intervals.len() / 2;
+    let (_, &mut median, _) = intervals.select_nth_unstable_by(middle, f64::total_cmp);
+    if median <= 0.0 || !median.is_finite() {
+        return None;
+    }
+
+    let irregular = intervals
+        .iter()
+        .filter(|&&interval| (interval - median).abs() > IRREGULAR_TOLERANCE * median)
+        .count();
+
+    Some(SamplingEstimate {
+        fs: 1.0 / median,
+        intervals: intervals.len(),
+        irregular,
+        longest_interval,
+    })
+}
+
+/// Logs the estimated frequency, with a warning when the sampling is irregular.
+pub fn report_sampling(estimate: &SamplingEstimate) {
+    info!("Detected sampling frequency: {:.2} Hz", estimate.fs);
+    if !estimate.is_regular() {
+        warn!(
+            "Irregular sampling, {} of {} sample intervals ({:.2}%) differ from the \
+             median by more than {}%, longest {:.3} s",
+            estimate.irregular,
+            estimate.intervals,
+            100.0 * estimate.irregular_fraction(),
+            100.0 * IRREGULAR_TOLERANCE,
+            estimate.longest_interval
+        );
+    }
+}
+
+// a sample interval this many times the sampling period is a gap
+const GAP_FACTOR: f64 = 1.5;
+
+/// A discontinuity in the time column: no samples between `start` and `end` (seconds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gap {
+    /// Time of the last sample before the gap.
+    pub start: f64,
+    /// Time of the first sample after the gap.
+    pub end: f64,
+}
+
+impl Gap {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// The sample intervals longer than 1.5 sampling periods at `fs`.
+pub fn find_gaps(times: &[f64], fs: f64) -> Vec<Gap> {
+    let limit = GAP_FACTOR / fs;
+    times
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] > limit)
+        .map(|pair| Gap {
+            start: pair[0],
+            end: pair[1],
+        })
+        .collect()
+}
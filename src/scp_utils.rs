@@ -0,0 +1,662 @@
+use crate::compression::open_input;
+use crate::edf_utils::find_label;
+use crate::models::{EcgPoint, Lead, MultiLeadEcg};
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+// number of Huffman tables that stands for the default table of the standard
+const DEFAULT_HUFFMAN_TABLES: u16 = 19999;
+// CRC, section ID, length, section and protocol versions and reserved bytes
+const SECTION_HEADER_BYTES: usize = 16;
+
+/// A resting ECG read from an SCP-ECG file (EN 1064), with the patient data of section 1.
+#[derive(Debug, Clone)]
+pub struct ScpRecord {
+    pub last_name: String,
+    pub first_name: String,
+    pub patient_id: String,
+    /// Year, month and day of the acquisition.
+    pub acquisition_date: Option<(u16, u8, u8)>,
+    /// Hour, minute and second of the acquisition.
+    pub acquisition_time: Option<(u8, u8, u8)>,
+    pub sampling_frequency: f64,
+    /// The rhythm data of section 6, every lead in millivolts.
+    pub ecg: MultiLeadEcg,
+}
+
+impl ScpRecord {
+    /// Index of the lead with the given name (`II`, `V5`, ...), ignoring case; the error
+    /// lists the leads there are.
+    pub fn find_lead(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let names: Vec<&str> = self
+            .ecg
+            .leads
+            .iter()
+            .map(|lead| lead.name.as_str())
+            .collect();
+        find_label(&names, name)
+    }
+}
+
+// name of a lead after its section 3 lead ID, e.g. 2 is II and 7 is V5
+fn lead_name(id: u8) -> String {
+    const NAMES: [&str; 19] = [
+        "Unspecified",
+        "I",
+        "II",
+        "V1",
+        "V2",
+        "V3",
+        "V4",
+        "V5",
+        "V6",
+        "V7",
+        "V2R",
+        "V3R",
+        "V4R",
+        "V5R",
+        "V6R",
+        "V7R",
+        "X",
+        "Y",
+        "Z",
+    ];
+    match id {
+        61 => "III".to_string(),
+        62 => "aVR".to_string(),
+        63 => "aVL".to_string(),
+        64 => "aVF".to_string(),
+        65 => "-aVR".to_string(),
+        66 => "V8".to_string(),
+        67 => "V9".to_string(),
+        68 => "V8R".to_string(),
+        69 => "V9R".to_string(),
+        id => NAMES
+            .get(id as usize)
+            .map_or_else(|| format!("Lead ID {}", id), |name| name.to_string()),
+    }
+}
+
+fn short(bytes: &[u8], at: usize) -> Result<u16, Box<dyn Error>> {
+    let field = bytes
+        .get(at..at + 2)
+        .ok_or("SCP-ECG file ends inside a section")?;
+    Ok(u16::from_le_bytes([field[0], field[1]]))
+}
+
+fn long(bytes: &[u8], at: usize) -> Result<u32, Box<dyn Error>> {
+    let field = bytes
+        .get(at..at + 4)
+        .ok_or("SCP-ECG file ends inside a section")?;
+    Ok(u32::from_le_bytes([field[0], field[1], field[2], field[3]]))
+}
+
+// a section ID with the section data
+type Section<'a> = (u16, &'a [u8]);
+
+// the data of every section section 0 points to
+fn sections(bytes: &[u8]) -> Result<Vec<Section<'_>>, Box<dyn Error>> {
+    // the record starts with its CRC and size, section 0 follows
+    let pointers = section_at(bytes, 6)?;
+    let mut sections = Vec::new();
+    for pointer in pointers.chunks_exact(10) {
+        let id = u16::from_le_bytes([pointer[0], pointer[1]]);
+        let length = u32::from_le_bytes([pointer[2], pointer[3], pointer[4], pointer[5]]);
+        let index = u32::from_le_bytes([pointer[6], pointer[7], pointer[8], pointer[9]]);
+        if length == 0 || id == 0 {
+            continue;
+        }
+        // indices count from 1
+        let start = (index as usize)
+            .checked_sub(1)
+            .ok_or_else(|| format!("Invalid SCP-ECG pointer to section {}", id))?;
+        sections.push((id, section_at(bytes, start)?));
+    }
+    Ok(sections)
+}
+
+// CRC-CCITT (polynomial 0x1021, initial value 0xFFFF) as stored before the record and
+// every section
+fn crc_ccitt(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+// the data of the section starting at the byte offset, after its ID header, once the CRC
+// of the section matches
+fn section_at(bytes: &[u8], start: usize) -> Result<&[u8], Box<dyn Error>> {
+    let length = long(bytes, start + 4)? as usize;
+    if length < SECTION_HEADER_BYTES {
+        return Err(format!(
+            "Invalid SCP-ECG section length {} at byte {}",
+            length, start
+        )
+        .into());
+    }
+    let section = bytes
+        .get(start..start + length)
+        .ok_or_else(|| format!("SCP-ECG section at byte {} runs past the file end", start))?;
+    // the CRC covers the section after its own two bytes
+    let crc = short(section, 0)?;
+    if crc != crc_ccitt(&section[2..]) {
+        return Err(format!("SCP-ECG section at byte {} fails its CRC check", start).into());
+    }
+    Ok(&section[SECTION_HEADER_BYTES..])
+}
+
+#[derive(Default)]
+struct Patient {
+    last_name: String,
+    first_name: String,
+    patient_id: String,
+    acquisition_date: Option<(u16, u8, u8)>,
+    acquisition_time: Option<(u8, u8, u8)>,
+}
+
+// the tagged fields of section 1 that identify the patient and the acquisition
+fn parse_patient(section: &[u8]) -> Patient {
+    let text = |value: &[u8]| {
+        let end = value
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(value.len());
+        String::from_utf8_lossy(&value[..end]).trim().to_string()
+    };
+    let mut patient = Patient::default();
+    let mut at = 0;
+    while at + 3 <= section.len() {
+        let tag = section[at];
+        let length = u16::from_le_bytes([section[at + 1], section[at + 2]]) as usize;
+        let Some(value) = section.get(at + 3..at + 3 + length) else {
+            break;
+        };
+        match tag {
+            0 => patient.last_name = text(value),
+            1 => patient.first_name = text(value),
+            2 => patient.patient_id = text(value),
+            25 if length >= 4 => {
+                patient.acquisition_date =
+                    Some((u16::from_le_bytes([value[0], value[1]]), value[2], value[3]));
+            }
+            26 if length >= 3 => patient.acquisition_time = Some((value[0], value[1], value[2])),
+            255 => break,
+            _ => {}
+        }
+        at += 3 + length;
+    }
+    patient
+}
+
+// one code of a Huffman table: a prefix of `prefix_bits` bits (stored first bit lowest)
+// standing for `value`, followed by `total_bits - prefix_bits` bits of the value itself,
+// or switching to table `value` when `switch` is set
+#[derive(Clone, Copy)]
+struct HuffmanCode {
+    prefix_bits: u8,
+    total_bits: u8,
+    switch: bool,
+    value: i16,
+    code: u32,
+}
+
+// the default table of the standard: 0, then +-1 to +-8 in codes of 3 to 10 bits, and
+// 8 or 16-bit values after the two 10-bit escape codes
+fn default_huffman_table() -> Vec<HuffmanCode> {
+    let mut table = vec![HuffmanCode {
+        prefix_bits: 1,
+        total_bits: 1,
+        switch: false,
+        value: 0,
+        code: 0,
+    }];
+    for magnitude in 1..=8u8 {
+        let bits = magnitude + 2;
+        // `magnitude` ones and a zero, then the sign bit
+        let ones = (1u32 << magnitude) - 1;
+        for (sign, value) in [(0, magnitude as i16), (1, -(magnitude as i16))] {
+            table.push(HuffmanCode {
+                prefix_bits: bits,
+                total_bits: bits,
+                switch: false,
+                value,
+                code: ones | (sign << (bits - 1)),
+            });
+        }
+    }
+    for (total_bits, code) in [(18, 0x1FF), (26, 0x3FF)] {
+        table.push(HuffmanCode {
+            prefix_bits: 10,
+            total_bits,
+            switch: false,
+            value: 0,
+            code,
+        });
+    }
+    table
+}
+
+// the Huffman tables of section 2
+fn parse_huffman_tables(section: &[u8]) -> Result<Vec<Vec<HuffmanCode>>, Box<dyn Error>> {
+    let count = short(section, 0)?;
+    if count == DEFAULT_HUFFMAN_TABLES {
+        return Ok(vec![default_huffman_table()]);
+    }
+    let mut tables = Vec::with_capacity(count as usize);
+    let mut at = 2;
+    for _ in 0..count {
+        let codes = short(section, at)?;
+        at += 2;
+        let mut table = Vec::with_capacity(codes as usize);
+        for _ in 0..codes {
+            let field = section
+                .get(at..at + 9)
+                .ok_or("SCP-ECG Huffman table ends early")?;
+            let code = HuffmanCode {
+                prefix_bits: field[0],
+                total_bits: field[1],
+                switch: field[2] == 0,
+                value: i16::from_le_bytes([field[3], field[4]]),
+                code: u32::from_le_bytes([field[5], field[6], field[7], field[8]]),
+            };
+            if code.prefix_bits == 0 || code.prefix_bits > 32 || code.total_bits > 48 {
+                return Err("Invalid code in an SCP-ECG Huffman table".into());
+            }
+            table.push(code);
+            at += 9;
+        }
+        tables.push(table);
+    }
+    if tables.is_empty() {
+        return Err("SCP-ECG section 2 holds no Huffman tables".into());
+    }
+    Ok(tables)
+}
+
+// reads bits from the most significant bit of each byte on
+struct Bits<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn next(&mut self) -> Option<u32> {
+        let byte = self.bytes.get(self.position / 8)?;
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Some(bit as u32)
+    }
+
+    // `count` bits as a two's complement number, first bit highest
+    fn signed(&mut self, count: u8) -> Option<i32> {
+        let mut value: i64 = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.next()? as i64;
+        }
+        if count > 0 && (value >> (count - 1)) & 1 == 1 {
+            value -= 1 << count;
+        }
+        Some(value as i32)
+    }
+}
+
+// decodes `count` values of one lead
+fn huffman_decode(
+    bytes: &[u8],
+    tables: &[Vec<HuffmanCode>],
+    count: usize,
+) -> Result<Vec<i32>, Box<dyn Error>> {
+    let mut bits = Bits { bytes, position: 0 };
+    let mut table = &tables[0];
+    let mut values = Vec::with_capacity(count);
+    'values: while values.len() < count {
+        let mut code = 0u32;
+        let mut length = 0u8;
+        loop {
+            let Some(bit) = bits.next() else {
+                break 'values;
+            };
+            // codes are stored with their first bit lowest
+            code |= bit << length;
+            length += 1;
+            if let Some(entry) = table
+                .iter()
+                .find(|entry| entry.prefix_bits == length && entry.code == code)
+            {
+                if entry.switch {
+                    // tables count from 1
+                    table = usize::try_from(entry.value - 1)
+                        .ok()
+                        .and_then(|index| tables.get(index))
+                        .ok_or("SCP-ECG data switch to a missing Huffman table")?;
+                } else if entry.total_bits > entry.prefix_bits {
+                    let Some(value) = bits.signed(entry.total_bits - entry.prefix_bits) else {
+                        break 'values;
+                    };
+                    values.push(value);
+                } else {
+                    values.push(entry.value as i32);
+                }
+                break;
+            }
+            if length >= 32 {
+                return Err("Invalid Huffman code in SCP-ECG data".into());
+            }
+        }
+    }
+    if values.len() < count {
+        return Err(format!(
+            "SCP-ECG lead data ends after {} of {} samples",
+            values.len(),
+            count
+        )
+        .into());
+    }
+    Ok(values)
+}
+
+// undoes first (1) or second (2) difference encoding
+fn integrate(values: &mut [i32], differences: u8) {
+    match differences {
+        1 => {
+            for i in 1..values.len() {
+                values[i] += values[i - 1];
+            }
+        }
+        2 => {
+            for i in 2..values.len() {
+                values[i] += 2 * values[i - 1] - values[i - 2];
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reads an SCP-ECG file: the patient data of section 1 and the rhythm data of section 6,
+/// decoded with the Huffman tables of section 2 (or read as 16-bit samples without one) in
+/// the leads section 3 defines. Files compressed with reference beat subtraction are not
+/// supported; a section failing its CRC check is an error.
+pub fn read_scp<P: AsRef<Path>>(path: P) -> Result<ScpRecord, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    open_input(path)?.read_to_end(&mut bytes)?;
+    let sections = sections(&bytes)?;
+    let section = |id: u16| {
+        sections
+            .iter()
+            .find(|&&(section_id, _)| section_id == id)
+            .map(|&(_, data)| data)
+    };
+
+    let patient = section(1).map(parse_patient).unwrap_or_default();
+    let tables = section(2).map(parse_huffman_tables).transpose()?;
+    let leads_section = section(3).ok_or("SCP-ECG file without lead definitions (section 3)")?;
+    let rhythm = section(6).ok_or("SCP-ECG file without rhythm data (section 6)")?;
+
+    let num_leads = *leads_section.first().ok_or("Empty SCP-ECG section 3")? as usize;
+    let flags = leads_section.get(1).copied().unwrap_or(0);
+    if flags & 1 != 0 {
+        return Err("SCP-ECG files with reference beat subtraction are not supported".into());
+    }
+    let mut leads = Vec::with_capacity(num_leads);
+    for i in 0..num_leads {
+        let at = 2 + 9 * i;
+        let start = long(leads_section, at)?;
+        let end = long(leads_section, at + 4)?;
+        let id = *leads_section
+            .get(at + 8)
+            .ok_or("SCP-ECG section 3 ends early")?;
+        if start == 0 || end < start {
+            return Err(format!("Invalid SCP-ECG lead samples {} to {}", start, end).into());
+        }
+        leads.push((lead_name(id), (end - start + 1) as usize));
+    }
+
+    let multiplier = short(rhythm, 0)? as f64;
+    let interval = short(rhythm, 2)?;
+    let differences = *rhythm.get(4).ok_or("SCP-ECG section 6 ends early")?;
+    if rhythm.get(5).copied().unwrap_or(0) != 0 {
+        return Err("SCP-ECG files with bimodal compression are not supported".into());
+    }
+    if interval == 0 {
+        return Err("SCP-ECG sample interval of 0 µs".into());
+    }
+    let fs = 1e6 / interval as f64;
+
+    let mut at = 6 + 2 * num_leads;
+    let mut voltages = Vec::with_capacity(num_leads);
+    for (i, &(_, count)) in leads.iter().enumerate() {
+        let length = short(rhythm, 6 + 2 * i)? as usize;
+        let data = rhythm
+            .get(at..at + length)
+            .ok_or("SCP-ECG rhythm data runs past its section")?;
+        at += length;
+        let mut values = match &tables {
+            Some(tables) => huffman_decode(data, tables, count)?,
+            None => data
+                .chunks_exact(2)
+                .take(count)
+                .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as i32)
+                .collect(),
+        };
+        integrate(&mut values, differences);
+        // the multiplier is in nV
+        voltages.push(
+            values
+                .iter()
+                .map(|&value| value as f64 * multiplier * 1e-6)
+                .collect::<Vec<f64>>(),
+        );
+    }
+
+    let num_samples = voltages.iter().map(Vec::len).min().unwrap_or(0);
+    let ecg = MultiLeadEcg {
+        times: (0..num_samples).map(|i| i as f64 / fs).collect(),
+        leads: leads
+            .into_iter()
+            .zip(voltages)
+            .map(|((name, _), mut voltage)| {
+                voltage.truncate(num_samples);
+                Lead { name, voltage }
+            })
+            .collect(),
+    };
+
+    info!(
+        "Total data points: {} in {} leads",
+        ecg.times.len(),
+        ecg.leads.len()
+    );
+
+    Ok(ScpRecord {
+        last_name: patient.last_name,
+        first_name: patient.first_name,
+        patient_id: patient.patient_id,
+        acquisition_date: patient.acquisition_date,
+        acquisition_time: patient.acquisition_time,
+        sampling_frequency: fs,
+        ecg,
+    })
+}
+
+/// Reads one lead of an SCP-ECG file in millivolts.
+pub fn read_scp_ecg_data<P: AsRef<Path>>(
+    path: P,
+    lead: usize,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let record = read_scp(path)?;
+    let ecg = record.ecg;
+    let lead = ecg.leads.get(lead).ok_or_else(|| {
+        format!(
+            "Lead index {} out of range, the file has {} leads",
+            lead,
+            ecg.leads.len()
+        )
+    })?;
+
+    Ok(ecg
+        .times
+        .iter()
+        .zip(&lead.voltage)
+        .map(|(&time, &voltage)| EcgPoint { time, voltage })
+        .collect())
+}
+
+/// Reads every lead of an SCP-ECG file.
+pub fn read_scp_leads<P: AsRef<Path>>(path: P) -> Result<MultiLeadEcg, Box<dyn Error>> {
+    Ok(read_scp(path)?.ecg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // a section with its ID header and CRC
+    fn section(id: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0, 0];
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.extend_from_slice(&((SECTION_HEADER_BYTES + data.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(&[20, 20, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(data);
+        let crc = crc_ccitt(&bytes[2..]);
+        bytes[..2].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    // an uncompressed record of leads I and II at 500 Hz, 5 µV per unit
+    fn scp_record(samples: [&[i16]; 2]) -> Vec<u8> {
+        let mut patient = Vec::new();
+        for (tag, value) in [
+            (0u8, &b"Nowak\0"[..]),
+            (2, b"P-17\0"),
+            (25, &[0xE4, 0x07, 6, 5]),
+            (26, &[8, 30, 0]),
+            (255, &[]),
+        ] {
+            patient.push(tag);
+            patient.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            patient.extend_from_slice(value);
+        }
+        let mut leads = vec![2, 0];
+        for (id, lead) in [(1, samples[0]), (2, samples[1])] {
+            leads.extend_from_slice(&1u32.to_le_bytes());
+            leads.extend_from_slice(&(lead.len() as u32).to_le_bytes());
+            leads.push(id);
+        }
+        let mut rhythm = Vec::new();
+        for field in [5000, 2000, 0] {
+            rhythm.extend_from_slice(&u16::to_le_bytes(field));
+        }
+        for lead in samples {
+            rhythm.extend_from_slice(&(2 * lead.len() as u16).to_le_bytes());
+        }
+        for sample in samples.concat() {
+            rhythm.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        let sections = [
+            (1, section(1, &patient)),
+            (3, section(3, &leads)),
+            (6, section(6, &rhythm)),
+        ];
+        // the ID, length and index (from 1) of every section, section 0 first
+        let mut pointers = Vec::new();
+        let mut start = 6;
+        let lengths = sections.iter().map(|(id, bytes)| (*id, bytes.len()));
+        for (id, length) in [(0u16, SECTION_HEADER_BYTES + 40)]
+            .into_iter()
+            .chain(lengths)
+        {
+            pointers.extend_from_slice(&id.to_le_bytes());
+            pointers.extend_from_slice(&(length as u32).to_le_bytes());
+            pointers.extend_from_slice(&(start as u32 + 1).to_le_bytes());
+            start += length;
+        }
+        let mut record = vec![0; 6];
+        record.extend(section(0, &pointers));
+        for (_, bytes) in &sections {
+            record.extend_from_slice(bytes);
+        }
+        let size = record.len() as u32;
+        record[2..6].copy_from_slice(&size.to_le_bytes());
+        let crc = crc_ccitt(&record[2..]);
+        record[..2].copy_from_slice(&crc.to_le_bytes());
+        record
+    }
+
+    fn read_bytes(bytes: &[u8], name: &str) -> Result<ScpRecord, Box<dyn Error>> {
+        let path =
+            std::env::temp_dir().join(format!("signalweaver_{}_{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        let record = read_scp(&path);
+        fs::remove_file(&path).unwrap();
+        record
+    }
+
+    #[test]
+    fn computes_the_ccitt_check_value() {
+        assert_eq!(crc_ccitt(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn reads_an_uncompressed_record() {
+        let bytes = scp_record([&[200, -200, 0], &[400, 0, -1000]]);
+        let record = read_bytes(&bytes, "plain.scp").unwrap();
+        assert_eq!(record.last_name, "Nowak");
+        assert_eq!(record.patient_id, "P-17");
+        assert_eq!(record.acquisition_date, Some((2020, 6, 5)));
+        assert_eq!(record.acquisition_time, Some((8, 30, 0)));
+        assert_eq!(record.sampling_frequency, 500.0);
+        assert_eq!(record.ecg.times, vec![0.0, 0.002, 0.004]);
+        assert_eq!(record.find_lead("ii").unwrap(), 1);
+        assert_eq!(record.ecg.leads[0].voltage, vec![1.0, -1.0, 0.0]);
+        assert_eq!(record.ecg.leads[1].voltage, vec![2.0, 0.0, -5.0]);
+    }
+
+    #[test]
+    fn rejects_a_section_failing_its_crc() {
+        let mut bytes = scp_record([&[200, -200, 0], &[400, 0, -1000]]);
+        // the last sample of lead II, inside section 6
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        let error = read_bytes(&bytes, "corrupt.scp").unwrap_err();
+        assert!(error.to_string().contains("CRC"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_truncated_records() {
+        let bytes = scp_record([&[200, -200, 0], &[400, 0, -1000]]);
+        for len in [0, 5, 20, 100, bytes.len() - 1] {
+            assert!(sections(&bytes[..len]).is_err(), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn decodes_the_default_huffman_table() {
+        let table = vec![default_huffman_table()];
+        // 0, +1 (a one, a zero and the sign bit), then an 8-bit -100 after its escape code
+        let bits = ["0", "100", "1111111110", &format!("{:08b}", -100i8 as u8)].concat();
+        let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+        for (i, bit) in bits.bytes().enumerate() {
+            if bit == b'1' {
+                bytes[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        assert_eq!(huffman_decode(&bytes, &table, 3).unwrap(), vec![0, 1, -100]);
+        // the two padding bits decode as zeros, then the data runs out
+        assert_eq!(huffman_decode(&bytes, &table, 5).unwrap()[3..], [0, 0]);
+        assert!(huffman_decode(&bytes, &table, 6).is_err());
+
+        let mut values = vec![5, 1, 1, -2];
+        integrate(&mut values, 1);
+        assert_eq!(values, vec![5, 6, 7, 5]);
+    }
+}
@@ -0,0 +1,167 @@
+use std::f64::consts::PI;
+
+/// In-place iterative radix-2 FFT; the length must be a power of two.
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    assert!(
+        n.is_power_of_two() && im.len() == n,
+        "FFT length must be a power of two"
+    );
+
+    // bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// One-sided power spectral density.
+pub struct Spectrum {
+    pub frequencies: Vec<f64>,
+    pub power: Vec<f64>,
+}
+
+impl Spectrum {
+    /// Integrated power in `[low, high)`.
+    pub fn band_power(&self, low: f64, high: f64) -> f64 {
+        if self.frequencies.len() < 2 {
+            return 0.0;
+        }
+        let df = self.frequencies[1] - self.frequencies[0];
+        self.frequencies
+            .iter()
+            .zip(&self.power)
+            .filter(|(&f, _)| f >= low && f < high)
+            .map(|(_, &p)| p * df)
+            .sum()
+    }
+}
+
+/// Welch's method with Hann-windowed, mean-detrended segments overlapping by 50%.
+/// Segments are shortened to the largest power of two fitting the signal.
+pub fn welch(signal: &[f64], fs: f64, segment_len: usize) -> Spectrum {
+    let mut n = segment_len.next_power_of_two();
+    while n > signal.len() && n > 1 {
+        n >>= 1;
+    }
+    if n < 2 {
+        return Spectrum {
+            frequencies: Vec::new(),
+            power: Vec::new(),
+        };
+    }
+
+    let window: Vec<f64> = (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / n as f64).cos())
+        .collect();
+    let window_power: f64 = window.iter().map(|w| w * w).sum();
+
+    let mut accumulated = vec![0.0; n / 2 + 1];
+    let mut segments = 0;
+    let step = n / 2;
+    let mut start = 0;
+    while start + n <= signal.len() {
+        let segment = &signal[start..start + n];
+        let mean = segment.iter().sum::<f64>() / n as f64;
+        let mut re: Vec<f64> = segment
+            .iter()
+            .zip(&window)
+            .map(|(x, w)| (x - mean) * w)
+            .collect();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+        for (k, acc) in accumulated.iter_mut().enumerate() {
+            *acc += re[k] * re[k] + im[k] * im[k];
+        }
+        segments += 1;
+        start += step;
+    }
+
+    let scale = 1.0 / (fs * window_power * segments as f64);
+    let power = accumulated
+        .iter()
+        .enumerate()
+        .map(|(k, &p)| {
+            // one-sided: everything except DC and Nyquist appears twice
+            let factor = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+            p * scale * factor
+        })
+        .collect();
+    let frequencies = (0..=n / 2).map(|k| k as f64 * fs / n as f64).collect();
+
+    Spectrum { frequencies, power }
+}
+
+/// Lomb–Scargle periodogram of unevenly sampled data on an evenly spaced frequency grid,
+/// scaled so the integrated power approximates the signal variance.
+pub fn lomb_scargle(times: &[f64], values: &[f64], frequencies: &[f64]) -> Spectrum {
+    let n = values.len();
+    if n < 2 {
+        return Spectrum {
+            frequencies: frequencies.to_vec(),
+            power: vec![0.0; frequencies.len()],
+        };
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let centered: Vec<f64> = values.iter().map(|v| v - mean).collect();
+    let duration = times[n - 1] - times[0];
+
+    let power = frequencies
+        .iter()
+        .map(|&f| {
+            let omega = 2.0 * PI * f;
+            if omega == 0.0 {
+                return 0.0;
+            }
+            let (sin_sum, cos_sum) = times.iter().fold((0.0, 0.0), |(s, c), &t| {
+                let (sin, cos) = (2.0 * omega * t).sin_cos();
+                (s + sin, c + cos)
+            });
+            let tau = sin_sum.atan2(cos_sum) / (2.0 * omega);
+
+            let (mut yc, mut ys, mut cc, mut ss) = (0.0, 0.0, 0.0, 0.0);
+            for (&t, &y) in times.iter().zip(&centered) {
+                let (sin, cos) = (omega * (t - tau)).sin_cos();
+                yc += y * cos;
+                ys += y * sin;
+                cc += cos * cos;
+                ss += sin * sin;
+            }
+            let periodogram = 0.5 * (yc * yc / cc + ys * ys / ss);
+            2.0 * periodogram * duration / n as f64
+        })
+        .collect();
+
+    Spectrum {
+        frequencies: frequencies.to_vec(),
+        power,
+    }
+}
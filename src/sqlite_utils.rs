@@ -0,0 +1,155 @@
+use crate::classification::BeatClass;
+use crate::hrv::HrvMetrics;
+use crate::models::{Detection, RrInterval};
+use crate::provenance::utc_timestamp;
+use crate::rhythm::RhythmEvent;
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::path::Path;
+use std::time::SystemTime;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS recordings (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,
+    processed_at TEXT NOT NULL,
+    detector TEXT NOT NULL,
+    beats INTEGER NOT NULL,
+    gaps INTEGER NOT NULL,
+    status TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS beats (
+    recording_id INTEGER NOT NULL REFERENCES recordings (id),
+    beat INTEGER NOT NULL,
+    time REAL NOT NULL,
+    sample INTEGER NOT NULL,
+    amplitude REAL NOT NULL,
+    confidence REAL NOT NULL,
+    class TEXT,
+    PRIMARY KEY (recording_id, beat)
+);
+CREATE TABLE IF NOT EXISTS rr_intervals (
+    recording_id INTEGER NOT NULL REFERENCES recordings (id),
+    time REAL NOT NULL,
+    rr_ms REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS hrv_metrics (
+    recording_id INTEGER NOT NULL REFERENCES recordings (id),
+    section TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    value REAL
+);
+CREATE TABLE IF NOT EXISTS events (
+    recording_id INTEGER NOT NULL REFERENCES recordings (id),
+    kind TEXT NOT NULL,
+    start REAL NOT NULL,
+    end REAL NOT NULL,
+    duration REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS rr_intervals_recording ON rr_intervals (recording_id);
+CREATE INDEX IF NOT EXISTS hrv_metrics_recording ON hrv_metrics (recording_id);
+CREATE INDEX IF NOT EXISTS events_recording ON events (recording_id);
+";
+
+/// The results of one recording of a batch run, as they are stored.
+pub struct StoredRecording<'a> {
+    pub path: &'a str,
+    /// Why the recording could not be processed, `None` when it was.
+    pub error: Option<&'a str>,
+    pub detector: &'a str,
+    pub detections: &'a [Detection],
+    /// Empty, or the class of every beat.
+    pub classes: &'a [BeatClass],
+    pub intervals: &'a [RrInterval],
+    pub gaps: usize,
+    pub metrics: &'a [&'a dyn HrvMetrics],
+    pub events: &'a [RhythmEvent],
+}
+
+/// A SQLite database collecting the results of batch runs: one `recordings` row per
+/// processed recording (appended, so a database can gather several runs) with its `beats`,
+/// `rr_intervals`, `hrv_metrics` (long format, `section`, `metric`, `value`) and rhythm
+/// `events` keyed by `recording_id`.
+pub struct ResultsStore {
+    connection: Connection,
+}
+
+impl ResultsStore {
+    /// Opens the database, creating it and its tables if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(ResultsStore { connection })
+    }
+
+    /// Appends a recording with its results in one transaction, returning its id.
+    pub fn add_recording(&mut self, recording: &StoredRecording) -> Result<i64, Box<dyn Error>> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO recordings (path, processed_at, detector, beats, gaps, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                recording.path,
+                utc_timestamp(SystemTime::now()),
+                recording.detector,
+                recording.detections.len() as i64,
+                recording.gaps as i64,
+                recording.error.unwrap_or("ok"),
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO beats (recording_id, beat, time, sample, amplitude, confidence, class)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (beat, detection) in recording.detections.iter().enumerate() {
+                insert.execute(params![
+                    id,
+                    beat as i64 + 1,
+                    detection.time,
+                    detection.sample as i64,
+                    detection.amplitude,
+                    detection.confidence,
+                    recording.classes.get(beat).map(|class| class.symbol()),
+                ])?;
+            }
+
+            let mut insert = transaction.prepare(
+                "INSERT INTO rr_intervals (recording_id, time, rr_ms) VALUES (?1, ?2, ?3)",
+            )?;
+            for rr in recording.intervals {
+                insert.execute(params![id, rr.time, rr.rr_ms])?;
+            }
+
+            let mut insert = transaction.prepare(
+                "INSERT INTO hrv_metrics (recording_id, section, metric, value)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for section in recording.metrics {
+                for (name, value) in section.metrics() {
+                    let value = value.filter(|value| value.is_finite());
+                    insert.execute(params![id, section.section(), name, value])?;
+                }
+            }
+
+            let mut insert = transaction.prepare(
+                "INSERT INTO events (recording_id, kind, start, end, duration)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for event in recording.events {
+                insert.execute(params![
+                    id,
+                    event.kind.as_str(),
+                    event.start,
+                    event.end,
+                    event.duration()
+                ])?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(id)
+    }
+}
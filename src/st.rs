@@ -0,0 +1,133 @@
+use crate::delineation::BeatWaves;
+
+// the isoelectric level is the mean over this long, ending this long before the QRS onset
+const BASELINE_WINDOW: f64 = 0.02;
+const BASELINE_GAP: f64 = 0.01;
+// beats in the running median the episodes are found on
+const MEDIAN_BEATS: usize = 9;
+
+/// Settings of the ST episode detection.
+#[derive(Debug, Clone)]
+pub struct StOptions {
+    /// Deviation at J+80 ms beyond which a beat is elevated or depressed, in signal units
+    /// (0.1 mV, i.e. 1 mm at standard gain).
+    pub threshold: f64,
+    /// Shortest episode reported, in seconds.
+    pub min_duration: f64,
+}
+
+impl Default for StOptions {
+    fn default() -> Self {
+        StOptions {
+            threshold: 0.1,
+            min_duration: 60.0,
+        }
+    }
+}
+
+/// ST levels of one beat relative to its isoelectric PQ level, in signal units.
+#[derive(Debug, Clone, Copy)]
+pub struct StBeat {
+    pub time: f64,
+    /// Mean level of the PQ segment.
+    pub baseline: f64,
+    /// At the J point (the QRS offset).
+    pub j: f64,
+    /// At J+60 ms.
+    pub st60: f64,
+    /// At J+80 ms.
+    pub st80: f64,
+}
+
+/// ST levels of every beat of `signal` (filtered without phase shift and with a high-pass
+/// cutoff low enough not to distort the ST segment, such as 0.05 Hz), at the delineated QRS
+/// bounds. `times` are the beat times in the order of `waves`; beats whose ST segment runs
+/// past the end of the signal are left out.
+pub fn st_levels(signal: &[f64], fs: f64, waves: &[BeatWaves], times: &[f64]) -> Vec<StBeat> {
+    let seconds = |value: f64| (value * fs).round() as usize;
+    waves
+        .iter()
+        .zip(times)
+        .filter_map(|(waves, &time)| {
+            let end = waves.qrs.onset.checked_sub(seconds(BASELINE_GAP))?;
+            let start = end.checked_sub(seconds(BASELINE_WINDOW).max(1))?;
+            let pq = &signal[start..end];
+            let baseline = pq.iter().sum::<f64>() / pq.len() as f64;
+            let j = waves.qrs.offset;
+            let level = |offset: f64| Some(signal.get(j + seconds(offset))? - baseline);
+            Some(StBeat {
+                time,
+                baseline,
+                j: level(0.0)?,
+                st60: level(0.06)?,
+                st80: level(0.08)?,
+            })
+        })
+        .collect()
+}
+
+/// An ST elevation or depression episode from `start` to `end` (seconds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StEpisode {
+    pub start: f64,
+    pub end: f64,
+    /// Elevation rather than depression.
+    pub elevation: bool,
+    /// Largest deviation at J+80 ms in the episode, signed.
+    pub peak: f64,
+}
+
+impl StEpisode {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Stretches where the running median of nine beats of the J+80 ms level stays beyond
+/// ±`threshold`, lasting at least `min_duration`.
+pub fn st_episodes(beats: &[StBeat], options: &StOptions) -> Vec<StEpisode> {
+    let half = MEDIAN_BEATS / 2;
+    let smoothed: Vec<f64> = (0..beats.len())
+        .map(|i| {
+            let mut window: Vec<f64> = beats
+                [i.saturating_sub(half)..(i + half + 1).min(beats.len())]
+                .iter()
+                .map(|beat| beat.st80)
+                .collect();
+            window.sort_unstable_by(f64::total_cmp);
+            window[window.len() / 2]
+        })
+        .collect();
+
+    let mut episodes: Vec<StEpisode> = Vec::new();
+    let mut current: Option<StEpisode> = None;
+    for (beat, &level) in beats.iter().zip(&smoothed) {
+        let direction = if level > options.threshold {
+            Some(true)
+        } else if level < -options.threshold {
+            Some(false)
+        } else {
+            None
+        };
+        match (&mut current, direction) {
+            (Some(episode), Some(elevation)) if episode.elevation == elevation => {
+                episode.end = beat.time;
+                if beat.st80.abs() > episode.peak.abs() && (beat.st80 > 0.0) == elevation {
+                    episode.peak = beat.st80;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        episodes.extend(current.take());
+        current = direction.map(|elevation| StEpisode {
+            start: beat.time,
+            end: beat.time,
+            elevation,
+            peak: level,
+        });
+    }
+    episodes.extend(current);
+    episodes.retain(|episode| episode.duration() >= options.min_duration);
+    episodes
+}
@@ -0,0 +1,144 @@
+use crate::detectors::{DetectorConfig, QrsDetector};
+use crate::filters::{FilterConfig, StreamingFilter};
+use crate::models::Detection;
+
+/// Incremental detection for samples arriving in real time. Samples are buffered into
+/// windows of `window` seconds that are processed as soon as `overlap` seconds of context
+/// after them have arrived, so detections lag the input by about `window + overlap`; only
+/// the overlap context of past windows is kept in memory.
+pub struct StreamingDetector {
+    detector: Box<dyn QrsDetector>,
+    fs: f64,
+    // time of the first sample, in seconds
+    start_time: f64,
+    window: usize,
+    overlap: usize,
+    filter: Option<StreamingFilter>,
+    buffer: Vec<f64>,
+    // absolute sample index of buffer[0]
+    buffer_start: usize,
+    // absolute sample index up to which windows have been processed
+    processed_until: usize,
+    // beats closer than this to the previously emitted one are duplicates from window
+    // overlaps, in seconds
+    dedup_interval: f64,
+    last_emitted: Option<Detection>,
+}
+
+impl StreamingDetector {
+    /// 5 second windows with 2 seconds of overlap, no filtering.
+    pub fn new(detector: Box<dyn QrsDetector>, fs: f64) -> Self {
+        StreamingDetector {
+            detector,
+            fs,
+            start_time: 0.0,
+            window: (5.0 * fs) as usize,
+            overlap: (2.0 * fs) as usize,
+            filter: None,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            processed_until: 0,
+            dedup_interval: DetectorConfig::default().dedup_interval,
+            last_emitted: None,
+        }
+    }
+
+    /// Window length and overlap, in seconds.
+    pub fn with_window(mut self, window: f64, overlap: f64) -> Self {
+        self.window = ((window * self.fs) as usize).max(1);
+        self.overlap = (overlap.max(0.0) * self.fs) as usize;
+        self
+    }
+
+    /// Shortest distance between emitted beats, in seconds (see
+    /// [`DetectorConfig::dedup_interval`]).
+    pub fn with_dedup_interval(mut self, dedup_interval: f64) -> Self {
+        self.dedup_interval = dedup_interval;
+        self
+    }
+
+    /// Time of the first sample, added to the times of the detections (0 by default).
+    pub fn with_start_time(mut self, start_time: f64) -> Self {
+        self.start_time = start_time;
+        self
+    }
+
+    /// Causal pre-filtering of the incoming samples.
+    pub fn with_filter(mut self, config: &FilterConfig) -> Self {
+        self.filter = Some(StreamingFilter::new(config, self.fs));
+        self
+    }
+
+    /// Number of samples pushed so far.
+    pub fn samples_seen(&self) -> usize {
+        self.buffer_start + self.buffer.len()
+    }
+
+    /// Adds samples and returns the beats of every window completed by them.
+    pub fn push_samples(&mut self, samples: &[f64]) -> Vec<Detection> {
+        match &mut self.filter {
+            Some(filter) => self
+                .buffer
+                .extend(samples.iter().map(|&x| filter.process(x))),
+            None => self.buffer.extend_from_slice(samples),
+        }
+
+        let mut detections = Vec::new();
+        while self.samples_seen() >= self.processed_until + self.window + self.overlap {
+            let window_end = self.processed_until + self.window;
+            self.process_window(window_end, &mut detections);
+        }
+        detections
+    }
+
+    /// Processes whatever is left in the buffer, e.g. at the end of a recording.
+    pub fn finish(&mut self) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        let end = self.samples_seen();
+        if end > self.processed_until {
+            self.process_window(end, &mut detections);
+        }
+        detections
+    }
+
+    fn process_window(&mut self, window_end: usize, detections: &mut Vec<Detection>) {
+        let window_start = self.processed_until;
+        let context_start = window_start
+            .saturating_sub(self.overlap)
+            .max(self.buffer_start);
+        let context_end = std::cmp::min(window_end + self.overlap, self.samples_seen());
+
+        // windows too short for the detectors are skipped, as in the batch pipeline
+        if context_end - context_start >= (2.0 * self.fs) as usize {
+            let segment =
+                &self.buffer[context_start - self.buffer_start..context_end - self.buffer_start];
+            for (idx, confidence) in self.detector.detect_scored(segment, self.fs) {
+                let sample = context_start + idx;
+                if sample < window_start || sample >= window_end {
+                    continue;
+                }
+                let detection = Detection {
+                    time: self.start_time + sample as f64 / self.fs,
+                    sample,
+                    amplitude: self.buffer[sample - self.buffer_start],
+                    confidence,
+                };
+                if let Some(last) = self.last_emitted {
+                    if detection.time - last.time < self.dedup_interval {
+                        continue;
+                    }
+                }
+                self.last_emitted = Some(detection);
+                detections.push(detection);
+            }
+        }
+        self.processed_until = window_end;
+
+        // only the context needed by the next window stays in memory
+        let keep_from = window_end
+            .saturating_sub(self.overlap)
+            .max(self.buffer_start);
+        self.buffer.drain(..keep_from - self.buffer_start);
+        self.buffer_start = keep_from;
+    }
+}
@@ -0,0 +1,129 @@
+// half-width of the QRS template, in seconds
+const TEMPLATE_RADIUS: f64 = 0.06;
+// candidates are matched at offsets up to this far from their position, in seconds
+const MAX_LAG: f64 = 0.01;
+// beats whose median forms the initial template
+const INITIAL_BEATS: usize = 8;
+// weight of every accepted beat in the running average
+const UPDATE_WEIGHT: f64 = 0.1;
+
+/// A running average QRS complex, centred on the beat.
+#[derive(Debug, Clone)]
+pub struct QrsTemplate {
+    samples: Vec<f64>,
+    lag: usize,
+}
+
+impl QrsTemplate {
+    /// The sample-wise median of the complexes at the first eight beats that lie fully inside
+    /// the signal, so a false detection among them does not spoil the template. `None` when
+    /// there is no such beat.
+    pub fn initial(signal: &[f64], fs: f64, beats: &[usize]) -> Option<Self> {
+        let radius = ((TEMPLATE_RADIUS * fs) as usize).max(1);
+        let complexes: Vec<&[f64]> = beats
+            .iter()
+            .filter_map(|&beat| window(signal, beat as isize, radius))
+            .take(INITIAL_BEATS)
+            .collect();
+        if complexes.is_empty() {
+            return None;
+        }
+        let samples = (0..=2 * radius)
+            .map(|i| {
+                let mut values: Vec<f64> = complexes.iter().map(|complex| complex[i]).collect();
+                values.sort_unstable_by(f64::total_cmp);
+                values[values.len() / 2]
+            })
+            .collect();
+        Some(QrsTemplate {
+            samples,
+            lag: (MAX_LAG * fs) as usize,
+        })
+    }
+
+    pub fn samples(&self) -> &[f64] {
+        &self.samples
+    }
+
+    /// The best correlation coefficient of the template with the signal around `beat`, and
+    /// the position it is reached at; `None` when the complex does not fit in the signal.
+    pub fn best_match(&self, signal: &[f64], beat: usize) -> Option<(f64, usize)> {
+        let radius = self.samples.len() / 2;
+        let lag = self.lag as isize;
+        (-lag..=lag)
+            .filter_map(|offset| {
+                let center = beat as isize + offset;
+                let complex = window(signal, center, radius)?;
+                Some((correlation(&self.samples, complex), center as usize))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    /// Moves the template a tenth of the way towards the complex at `beat`.
+    pub fn update(&mut self, signal: &[f64], beat: usize) {
+        let radius = self.samples.len() / 2;
+        if let Some(complex) = window(signal, beat as isize, radius) {
+            for (value, &sample) in self.samples.iter_mut().zip(complex) {
+                *value += UPDATE_WEIGHT * (sample - *value);
+            }
+        }
+    }
+}
+
+// the samples within `radius` of `center`, if they all exist
+fn window(signal: &[f64], center: isize, radius: usize) -> Option<&[f64]> {
+    let start = usize::try_from(center - radius as isize).ok()?;
+    signal.get(start..start + 2 * radius + 1)
+}
+
+// Pearson correlation coefficient, 0 when either side is constant
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut cross, mut energy_a, mut energy_b) = (0.0, 0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b) {
+        cross += (x - mean_a) * (y - mean_b);
+        energy_a += (x - mean_a).powi(2);
+        energy_b += (y - mean_b).powi(2);
+    }
+    if energy_a <= 0.0 || energy_b <= 0.0 {
+        return 0.0;
+    }
+    cross / (energy_a * energy_b).sqrt()
+}
+
+/// Checks every candidate beat (increasing sample indices) against a running average
+/// template: a beat is accepted when its complex correlates with the template at
+/// `threshold` or above, and every accepted beat is averaged into the template, which thus
+/// follows slow morphology changes. Beats too close to the ends of the signal to be compared
+/// are accepted. Returns whether each beat was accepted.
+pub fn verify_beats(signal: &[f64], fs: f64, beats: &[usize], threshold: f64) -> Vec<bool> {
+    template_correlations(signal, fs, beats, threshold)
+        .into_iter()
+        .map(|r| r.is_none_or(|r| r >= threshold))
+        .collect()
+}
+
+/// The correlation of every beat with the running template of [`verify_beats`], `None` for
+/// the beats that could not be compared.
+pub fn template_correlations(
+    signal: &[f64],
+    fs: f64,
+    beats: &[usize],
+    threshold: f64,
+) -> Vec<Option<f64>> {
+    let Some(mut template) = QrsTemplate::initial(signal, fs, beats) else {
+        return vec![None; beats.len()];
+    };
+    beats
+        .iter()
+        .map(|&beat| {
+            let (r, position) = template.best_match(signal, beat)?;
+            if r >= threshold {
+                template.update(signal, position);
+            }
+            Some(r)
+        })
+        .collect()
+}
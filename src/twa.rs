@@ -0,0 +1,171 @@
+use std::f64::consts::PI;
+
+// the T wave is taken from this long after the beat to this long after it or 70% of the
+// median RR interval, whichever is shorter, in seconds
+const T_START: f64 = 0.1;
+const T_END: f64 = 0.4;
+const T_FRACTION: f64 = 0.7;
+// every beat's level is the mean over this window before it, in seconds
+const BASELINE_START: f64 = 0.08;
+const BASELINE_END: f64 = 0.05;
+// the T window is sampled about every 4 ms
+const T_STEP: f64 = 0.004;
+// noise band of the beat spectra, in cycles per beat
+const NOISE_BAND: (f64, f64) = (0.44, 0.49);
+
+/// Settings of the spectral T-wave alternans analysis.
+#[derive(Debug, Clone)]
+pub struct TwaOptions {
+    /// Beats per analysis window.
+    pub beats: usize,
+    /// Beats the window advances by.
+    pub step: usize,
+    /// Alternans ratio (k score) from which alternans is significant.
+    pub min_k: f64,
+    /// Alternans voltage from which alternans is significant, in signal units (1.9 µV in mV).
+    pub min_voltage: f64,
+}
+
+impl Default for TwaOptions {
+    fn default() -> Self {
+        TwaOptions {
+            beats: 128,
+            step: 16,
+            min_k: 3.0,
+            min_voltage: 0.0019,
+        }
+    }
+}
+
+/// Alternans in a window of beats, from the first beat to the last (seconds).
+#[derive(Debug, Clone, Copy)]
+pub struct TwaWindow {
+    pub start: f64,
+    pub end: f64,
+    /// Alternans voltage: the root of the 0.5 cycles/beat power above the noise mean.
+    pub voltage: f64,
+    /// Alternans power above the noise mean over the noise standard deviation.
+    pub k_score: f64,
+    pub significant: bool,
+}
+
+/// Spectral T-wave alternans (Smith et al.): in every window of `beats` beats, the T waves
+/// (relative to each beat's PQ level) form one beat-to-beat series per sample of the T wave;
+/// their power spectra are averaged, and the power at 0.5 cycles/beat is compared with the
+/// 0.44–0.49 cycles/beat noise band. `signal` should be band-pass filtered without phase
+/// shift, `beats` are increasing sample indices with their `times` in seconds.
+pub fn twa_windows(
+    signal: &[f64],
+    fs: f64,
+    beats: &[usize],
+    times: &[f64],
+    options: &TwaOptions,
+) -> Vec<TwaWindow> {
+    let window = options.beats.max(8);
+    if beats.len() < window || times.len() < beats.len() {
+        return Vec::new();
+    }
+    let seconds = |value: f64| (value * fs).round() as usize;
+
+    let mut rr: Vec<usize> = beats.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let middle = rr.len() / 2;
+    let (_, &mut median_rr, _) = rr.select_nth_unstable(middle);
+    let t_start = seconds(T_START);
+    let t_end = seconds(T_END).min((T_FRACTION * median_rr as f64) as usize);
+    if t_end <= t_start {
+        return Vec::new();
+    }
+    let offsets: Vec<usize> = (t_start..t_end).step_by(seconds(T_STEP).max(1)).collect();
+
+    // the T wave of every beat relative to its PQ level, `None` at the ends of the signal
+    let t_waves: Vec<Option<Vec<f64>>> = beats
+        .iter()
+        .map(|&beat| {
+            let baseline = signal
+                .get(beat.checked_sub(seconds(BASELINE_START))?..beat - seconds(BASELINE_END))?;
+            let level = baseline.iter().sum::<f64>() / baseline.len().max(1) as f64;
+            offsets
+                .iter()
+                .map(|&offset| Some(signal.get(beat + offset)? - level))
+                .collect()
+        })
+        .collect();
+
+    let n = window as f64;
+    let noise_bins: Vec<usize> =
+        ((NOISE_BAND.0 * n).ceil() as usize..=(NOISE_BAND.1 * n).floor() as usize).collect();
+    (0..=beats.len() - window)
+        .step_by(options.step.max(1))
+        .filter_map(|first| {
+            let waves: Vec<&Vec<f64>> = t_waves[first..first + window]
+                .iter()
+                .map(Option::as_ref)
+                .collect::<Option<_>>()?;
+
+            // beat spectra at the noise bins and at 0.5 cycles/beat, averaged over the T wave
+            let mut noise = vec![0.0; noise_bins.len()];
+            let mut alternans = 0.0;
+            for sample in 0..offsets.len() {
+                let series: Vec<f64> = waves.iter().map(|wave| wave[sample]).collect();
+                let mean = series.iter().sum::<f64>() / n;
+                for (power, &bin) in noise.iter_mut().zip(&noise_bins) {
+                    *power += periodogram(&series, mean, bin as f64 / n);
+                }
+                alternans += periodogram(&series, mean, 0.5);
+            }
+            let samples = offsets.len() as f64;
+            let alternans = alternans / samples;
+            let noise: Vec<f64> = noise.iter().map(|power| power / samples).collect();
+            let noise_mean = noise.iter().sum::<f64>() / noise.len() as f64;
+            let noise_deviation = (noise.iter().map(|p| (p - noise_mean).powi(2)).sum::<f64>()
+                / (noise.len().max(2) - 1) as f64)
+                .sqrt();
+
+            let excess = alternans - noise_mean;
+            let voltage = excess.max(0.0).sqrt();
+            let k_score = if noise_deviation > 0.0 {
+                excess / noise_deviation
+            } else {
+                0.0
+            };
+            Some(TwaWindow {
+                start: times[first],
+                end: times[first + window - 1],
+                voltage,
+                k_score,
+                significant: k_score >= options.min_k && voltage >= options.min_voltage,
+            })
+        })
+        .collect()
+}
+
+// power of the mean-removed series at `frequency` cycles per beat, scaled so alternation of
+// ±a has power a²
+fn periodogram(series: &[f64], mean: f64, frequency: f64) -> f64 {
+    let (mut re, mut im) = (0.0, 0.0);
+    for (k, &value) in series.iter().enumerate() {
+        let phase = 2.0 * PI * frequency * k as f64;
+        re += (value - mean) * phase.cos();
+        im -= (value - mean) * phase.sin();
+    }
+    (re * re + im * im) / (series.len() * series.len()) as f64
+}
+
+/// Episodes of significant alternans: overlapping significant windows merged, as start and
+/// end times with the largest alternans voltage.
+pub fn twa_episodes(windows: &[TwaWindow]) -> Vec<TwaWindow> {
+    let mut episodes: Vec<TwaWindow> = Vec::new();
+    for window in windows.iter().filter(|window| window.significant) {
+        match episodes.last_mut() {
+            Some(last) if window.start <= last.end => {
+                last.end = last.end.max(window.end);
+                if window.voltage > last.voltage {
+                    last.voltage = window.voltage;
+                    last.k_score = window.k_score;
+                }
+            }
+            _ => episodes.push(*window),
+        }
+    }
+    episodes
+}
@@ -0,0 +1,92 @@
+use crate::hrv::{mean, std_dev};
+
+/// Beat-by-beat comparison of detected QRS positions with reference annotations.
+#[derive(Debug, Clone)]
+pub struct ValidationResult {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    /// TP / (TP + FN)
+    pub sensitivity: f64,
+    /// TP / (TP + FP)
+    pub positive_predictivity: f64,
+    /// 2TP / (2TP + FP + FN)
+    pub f1: f64,
+    /// Mean of detected minus reference time over the matched beats, in ms.
+    pub mean_error_ms: f64,
+    pub sd_error_ms: f64,
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        f64::NAN
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// A beat of one or both of two beat lists being compared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeatMatch {
+    Both(f64, f64),
+    OnlyFirst(f64),
+    OnlySecond(f64),
+}
+
+/// Pairs the beat times (seconds, ascending) of two lists one to one when they are at most
+/// `tolerance` seconds apart, in time order.
+pub fn match_beats(first: &[f64], second: &[f64], tolerance: f64) -> Vec<BeatMatch> {
+    let mut matches = Vec::with_capacity(first.len().max(second.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < first.len() && j < second.len() {
+        let error = first[i] - second[j];
+        if error.abs() <= tolerance {
+            matches.push(BeatMatch::Both(first[i], second[j]));
+            i += 1;
+            j += 1;
+        } else if error < 0.0 {
+            matches.push(BeatMatch::OnlyFirst(first[i]));
+            i += 1;
+        } else {
+            matches.push(BeatMatch::OnlySecond(second[j]));
+            j += 1;
+        }
+    }
+    matches.extend(first[i..].iter().map(|&t| BeatMatch::OnlyFirst(t)));
+    matches.extend(second[j..].iter().map(|&t| BeatMatch::OnlySecond(t)));
+    matches
+}
+
+/// Matches detected and reference beat times (seconds, ascending) one to one when they are
+/// at most `tolerance` seconds apart; unmatched detections are false positives and unmatched
+/// reference beats false negatives.
+pub fn validate(detected: &[f64], reference: &[f64], tolerance: f64) -> ValidationResult {
+    let mut errors = Vec::new();
+    let (mut false_positives, mut false_negatives) = (0, 0);
+    for beat in match_beats(detected, reference, tolerance) {
+        match beat {
+            BeatMatch::Both(detected, reference) => errors.push((detected - reference) * 1000.0),
+            BeatMatch::OnlyFirst(_) => false_positives += 1,
+            BeatMatch::OnlySecond(_) => false_negatives += 1,
+        }
+    }
+
+    let true_positives = errors.len();
+    ValidationResult {
+        true_positives,
+        false_positives,
+        false_negatives,
+        sensitivity: ratio(true_positives, true_positives + false_negatives),
+        positive_predictivity: ratio(true_positives, true_positives + false_positives),
+        f1: ratio(
+            2 * true_positives,
+            2 * true_positives + false_positives + false_negatives,
+        ),
+        mean_error_ms: if errors.is_empty() {
+            f64::NAN
+        } else {
+            mean(&errors)
+        },
+        sd_error_ms: std_dev(&errors),
+    }
+}
@@ -0,0 +1,333 @@
+use crate::plot::{self, Marker, Strip};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::symbols;
+use ratatui::text::Span;
+use ratatui::widgets::canvas::{Canvas, Line};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::error::Error;
+
+// shortest stretch the viewer zooms in to, in seconds
+const MIN_SPAN: f64 = 0.5;
+// a beat added at the cursor moves to the largest deflection this close to it, in seconds
+const SNAP: f64 = 0.075;
+const HELP: &str =
+    "←/→ scroll  PgUp/PgDn page  +/- zoom  n/p next/previous ectopic  Home/End  q quit";
+const EDIT_HELP: &str = "a add  d delete  ,/. nudge  u undo  w save and quit";
+
+/// A signal (sampled at `fs`, starting at `start` seconds) with the beats marked on it, as
+/// shown by [`view`].
+pub struct Recording<'a> {
+    pub title: String,
+    pub signal: &'a [f64],
+    pub fs: f64,
+    pub start: f64,
+    pub markers: Vec<Marker>,
+}
+
+impl Recording<'_> {
+    fn end(&self) -> f64 {
+        self.start + self.signal.len() as f64 / self.fs
+    }
+
+    // the time of the largest deflection within `SNAP` of `time`
+    fn peak_near(&self, time: f64) -> f64 {
+        let sample = |time: f64| ((time - self.start) * self.fs).round().max(0.0) as usize;
+        let first = sample(time - SNAP).min(self.signal.len());
+        let last = sample(time + SNAP).min(self.signal.len());
+        self.signal[first..last]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .map_or(time, |(i, _)| self.start + (first + i) as f64 / self.fs)
+    }
+}
+
+// the stretch on screen
+struct Window {
+    from: f64,
+    span: f64,
+}
+
+impl Window {
+    fn clamp(&mut self, recording: &Recording) {
+        let duration = recording.end() - recording.start;
+        self.span = self
+            .span
+            .clamp(MIN_SPAN.min(duration), duration.max(MIN_SPAN));
+        self.from = self
+            .from
+            .min(recording.end() - self.span)
+            .max(recording.start);
+    }
+
+    fn centre(&self) -> f64 {
+        self.from + self.span / 2.0
+    }
+
+    fn zoom(&mut self, factor: f64) {
+        let centre = self.centre();
+        self.span *= factor;
+        self.from = centre - self.span / 2.0;
+    }
+}
+
+/// Shows the recording in the terminal from `from` seconds, `span` seconds at a time, until
+/// the user quits: the waveform in braille dots scaled to the stretch on screen, detected
+/// beats marked at the top and reference beats at the bottom.
+///
+/// When `editable`, detected beats can be added and deleted at a cursor in the middle of the
+/// screen; returns whether the user saved the edits, which are left in `recording.markers`.
+pub fn view(
+    recording: &mut Recording,
+    from: f64,
+    span: f64,
+    editable: bool,
+) -> Result<bool, Box<dyn Error>> {
+    if recording.signal.is_empty() || recording.fs <= 0.0 {
+        return Err("There is no signal to view".into());
+    }
+    let mut window = Window { from, span };
+    window.clamp(recording);
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, recording, &mut window, editable);
+    ratatui::restore();
+    result
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    recording: &mut Recording,
+    window: &mut Window,
+    editable: bool,
+) -> Result<bool, Box<dyn Error>> {
+    // the markers before every edit, for undo
+    let mut history: Vec<Vec<Marker>> = Vec::new();
+    loop {
+        let edits = editable.then_some(history.len());
+        terminal.draw(|frame| draw(frame, recording, window, edits))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if editable {
+            let cursor = window.centre();
+            match key.code {
+                KeyCode::Char('a') => {
+                    history.push(recording.markers.clone());
+                    recording.markers.push(Marker {
+                        time: recording.peak_near(cursor),
+                        label: String::new(),
+                        reference: false,
+                    });
+                    continue;
+                }
+                KeyCode::Char('d') | KeyCode::Delete => {
+                    let nearest = recording
+                        .markers
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, marker)| {
+                            !marker.reference && (marker.time - cursor).abs() <= window.span / 20.0
+                        })
+                        .min_by(|a, b| {
+                            (a.1.time - cursor)
+                                .abs()
+                                .total_cmp(&(b.1.time - cursor).abs())
+                        })
+                        .map(|(i, _)| i);
+                    if let Some(i) = nearest {
+                        history.push(recording.markers.clone());
+                        recording.markers.remove(i);
+                    }
+                    continue;
+                }
+                KeyCode::Char('u') => {
+                    if let Some(markers) = history.pop() {
+                        recording.markers = markers;
+                    }
+                    continue;
+                }
+                KeyCode::Char('w') => return Ok(true),
+                KeyCode::Char(',') => window.from -= window.span / 100.0,
+                KeyCode::Char('.') => window.from += window.span / 100.0,
+                _ => {}
+            }
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Left | KeyCode::Char('h') => window.from -= window.span / 4.0,
+            KeyCode::Right | KeyCode::Char('l') => window.from += window.span / 4.0,
+            KeyCode::PageUp => window.from -= window.span,
+            KeyCode::PageDown | KeyCode::Char(' ') => window.from += window.span,
+            KeyCode::Home => window.from = recording.start,
+            KeyCode::End => window.from = recording.end(),
+            KeyCode::Char('+') | KeyCode::Char('=') => window.zoom(0.5),
+            KeyCode::Char('-') => window.zoom(2.0),
+            KeyCode::Char('n') => {
+                let centre = window.centre();
+                if let Some(marker) = recording
+                    .markers
+                    .iter()
+                    .filter(|marker| is_ectopic(marker) && marker.time > centre + 1e-6)
+                    .min_by(|a, b| a.time.total_cmp(&b.time))
+                {
+                    window.from = marker.time - window.span / 2.0;
+                }
+            }
+            KeyCode::Char('p') => {
+                let centre = window.centre();
+                if let Some(marker) = recording
+                    .markers
+                    .iter()
+                    .filter(|marker| is_ectopic(marker) && marker.time < centre - 1e-6)
+                    .max_by(|a, b| a.time.total_cmp(&b.time))
+                {
+                    window.from = marker.time - window.span / 2.0;
+                }
+            }
+            _ => {}
+        }
+        window.clamp(recording);
+    }
+}
+
+fn is_ectopic(marker: &Marker) -> bool {
+    !marker.reference && !matches!(marker.label.as_str(), "" | "N")
+}
+
+fn colour(marker: &Marker) -> Color {
+    if marker.reference {
+        Color::Green
+    } else if is_ectopic(marker) {
+        Color::Red
+    } else {
+        Color::Cyan
+    }
+}
+
+// `edits` is the number of edits made so far, `None` when not editing
+fn draw(frame: &mut Frame, recording: &Recording, window: &Window, edits: Option<usize>) {
+    let [chart, status] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+    let to = window.from + window.span;
+
+    // a braille cell is two dots wide
+    let dots = (chart.width.saturating_sub(2).max(1) as u32) * 2;
+    let strip = Strip {
+        signal: recording.signal,
+        fs: recording.fs,
+        start: recording.start,
+        from: window.from,
+        to,
+        width: dots,
+        height: 1,
+    };
+    let columns = strip.columns();
+    let (low, high) = plot::value_range(&columns);
+    let seconds_per_dot = window.span / dots as f64;
+    let visible: Vec<&Marker> = recording
+        .markers
+        .iter()
+        .filter(|marker| marker.time >= window.from && marker.time <= to)
+        .collect();
+
+    let canvas = Canvas::default()
+        .block(Block::bordered().title(recording.title.as_str()))
+        .marker(symbols::Marker::Braille)
+        .x_bounds([window.from, to])
+        .y_bounds([low, high])
+        .paint(|ctx| {
+            let mut previous: Option<(f64, f64)> = None;
+            for (i, range) in columns.iter().enumerate() {
+                let Some((low, high)) = *range else {
+                    previous = None;
+                    continue;
+                };
+                // joined to the previous column so steep edges stay continuous
+                let (top, bottom) = match previous {
+                    Some((previous_low, previous_high)) => {
+                        (high.max(previous_low), low.min(previous_high))
+                    }
+                    None => (high, low),
+                };
+                let x = window.from + (i as f64 + 0.5) * seconds_per_dot;
+                ctx.draw(&Line::new(x, bottom, x, top, Color::Reset));
+                previous = Some((low, high));
+            }
+            ctx.layer();
+
+            if edits.is_some() {
+                let cursor = window.centre();
+                ctx.draw(&Line::new(cursor, low, cursor, high, Color::Yellow));
+            }
+            let tick = 0.1 * (high - low);
+            for marker in &visible {
+                let colour = colour(marker);
+                if marker.reference {
+                    ctx.draw(&Line::new(
+                        marker.time,
+                        low,
+                        marker.time,
+                        low + tick,
+                        colour,
+                    ));
+                } else {
+                    ctx.draw(&Line::new(
+                        marker.time,
+                        high - tick,
+                        marker.time,
+                        high,
+                        colour,
+                    ));
+                    if !marker.label.is_empty() {
+                        ctx.print(
+                            marker.time,
+                            high - tick,
+                            Span::styled(marker.label.clone(), Style::default().fg(colour)),
+                        );
+                    }
+                }
+            }
+        });
+    frame.render_widget(canvas, chart);
+
+    let mut beats: Vec<f64> = visible
+        .iter()
+        .filter(|marker| !marker.reference)
+        .map(|marker| marker.time)
+        .collect();
+    beats.sort_by(f64::total_cmp);
+    let heart_rate = match (beats.first(), beats.last()) {
+        (Some(first), Some(last)) if beats.len() > 1 && last > first => format!(
+            ", {:.0} bpm",
+            60.0 * (beats.len() - 1) as f64 / (last - first)
+        ),
+        _ => String::new(),
+    };
+    let editing = match edits {
+        Some(edits) => format!(
+            ", cursor at {:.3} s, {} edits   {}",
+            window.centre(),
+            edits,
+            EDIT_HELP
+        ),
+        None => String::new(),
+    };
+    let text = format!(
+        "{:.1}–{:.1} s of {:.1} s, {} beats{}{}   {}",
+        window.from,
+        to,
+        recording.end(),
+        beats.len(),
+        heart_rate,
+        editing,
+        HELP
+    );
+    frame.render_widget(Paragraph::new(text), status);
+}
@@ -0,0 +1,152 @@
+use crate::detection::{detect_beats, DetectionOptions};
+use crate::detectors::DetectorRegistry;
+use crate::filters::FilterConfig;
+use crate::hrv::{self, HrvMetrics, SpectralMethod};
+use crate::models::EcgPoint;
+use crate::rr::rr_intervals as beat_intervals;
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+/// The beats `detect` found, as typed arrays.
+#[wasm_bindgen]
+pub struct Beats {
+    time: Vec<f64>,
+    sample: Vec<u32>,
+    amplitude: Vec<f64>,
+    confidence: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Beats {
+    /// Beat times in seconds from the first sample.
+    #[wasm_bindgen(getter)]
+    pub fn time(&self) -> Vec<f64> {
+        self.time.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn sample(&self) -> Vec<u32> {
+        self.sample.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn amplitude(&self) -> Vec<f64> {
+        self.amplitude.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn confidence(&self) -> Vec<f64> {
+        self.confidence.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.time.len()
+    }
+}
+
+// the measures of an HRV section as a plain object, null where one could not be computed
+fn metrics_object(section: &dyn HrvMetrics) -> Object {
+    let object = Object::new();
+    for (name, value) in section.metrics() {
+        let value = value.map_or(JsValue::NULL, JsValue::from_f64);
+        Reflect::set(&object, &JsValue::from_str(name), &value).unwrap_throw();
+    }
+    object
+}
+
+/// Detects the beats of one lead sampled at `fs` Hz (`detector` `threshold` by default),
+/// optionally pre-filtered with cutoffs in Hz. Works on the samples alone, so strips read
+/// in the browser (`File`, `fetch`) need no filesystem.
+#[wasm_bindgen]
+pub fn detect(
+    signal: &[f64],
+    fs: f64,
+    detector: Option<String>,
+    highpass: Option<f64>,
+    lowpass: Option<f64>,
+    notch: Option<f64>,
+) -> Result<Beats, JsError> {
+    if !(fs.is_finite() && fs > 0.0) {
+        return Err(JsError::new("fs must be positive"));
+    }
+    let registry = DetectorRegistry::default();
+    let name = detector.as_deref().unwrap_or("threshold");
+    let detector = registry.get(name).ok_or_else(|| {
+        JsError::new(&format!(
+            "unknown detector {:?}, expected one of {}",
+            name,
+            registry.names().join(", ")
+        ))
+    })?;
+    let points: Vec<EcgPoint> = signal
+        .iter()
+        .enumerate()
+        .map(|(i, &voltage)| EcgPoint {
+            time: i as f64 / fs,
+            voltage,
+        })
+        .collect();
+    let options = DetectionOptions {
+        fs: Some(fs),
+        filter: FilterConfig {
+            highpass,
+            lowpass,
+            notch,
+            zero_phase: false,
+        },
+        ..DetectionOptions::default()
+    };
+    let detections = detect_beats(&points, detector, &options);
+    Ok(Beats {
+        time: detections.iter().map(|detection| detection.time).collect(),
+        sample: detections
+            .iter()
+            .map(|detection| detection.sample as u32)
+            .collect(),
+        amplitude: detections
+            .iter()
+            .map(|detection| detection.amplitude)
+            .collect(),
+        confidence: detections
+            .iter()
+            .map(|detection| detection.confidence)
+            .collect(),
+    })
+}
+
+/// RR intervals in ms between beat times in seconds.
+#[wasm_bindgen(js_name = rrIntervals)]
+pub fn rr_intervals(beats: &[f64]) -> Vec<f64> {
+    beat_intervals(beats).iter().map(|rr| rr.rr_ms).collect()
+}
+
+/// Time domain HRV of beat times in seconds, undefined below three beats.
+#[wasm_bindgen(js_name = timeDomain)]
+pub fn time_domain(beats: &[f64]) -> Option<Object> {
+    hrv::time_domain(&beat_intervals(beats)).map(|metrics| metrics_object(&metrics))
+}
+
+/// Spectral HRV (`method` `welch`, the default, or `lomb-scargle`) of beat times in seconds,
+/// undefined when the strip is too short for a spectrum.
+#[wasm_bindgen(js_name = frequencyDomain)]
+pub fn frequency_domain(beats: &[f64], method: Option<String>) -> Result<Option<Object>, JsError> {
+    let method = match method.as_deref().unwrap_or("welch") {
+        "welch" => SpectralMethod::Welch,
+        "lomb-scargle" => SpectralMethod::LombScargle,
+        method => {
+            return Err(JsError::new(&format!(
+                "unknown spectral method {:?}, expected welch or lomb-scargle",
+                method
+            )))
+        }
+    };
+    Ok(hrv::frequency_domain(&beat_intervals(beats), method)
+        .map(|metrics| metrics_object(&metrics)))
+}
+
+/// Poincaré plot descriptors of beat times in seconds, undefined for too few beats.
+#[wasm_bindgen]
+pub fn poincare(beats: &[f64]) -> Option<Object> {
+    hrv::poincare(&beat_intervals(beats)).map(|metrics| metrics_object(&metrics))
+}
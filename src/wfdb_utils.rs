@@ -0,0 +1,494 @@
+use crate::compression::open_input;
+use crate::models::{Detection, EcgPoint};
+use std::error::Error;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+// WFDB defaults for fields missing from the header
+const DEFAULT_FS: f64 = 250.0;
+const DEFAULT_GAIN: f64 = 200.0;
+
+/// One signal line of a WFDB header (.hea).
+#[derive(Debug, Clone)]
+pub struct WfdbSignalInfo {
+    /// Data file holding the samples, relative to the header.
+    pub file_name: String,
+    /// Storage format, 16 or 212 are supported.
+    pub format: u16,
+    /// Bytes to skip at the start of the data file.
+    pub byte_offset: usize,
+    /// ADC units per physical unit.
+    pub gain: f64,
+    /// ADC value corresponding to 0 physical units.
+    pub baseline: i32,
+    pub units: String,
+    pub adc_resolution: Option<u32>,
+    pub adc_zero: Option<i32>,
+    pub initial_value: Option<i32>,
+    pub checksum: Option<i32>,
+    pub block_size: Option<usize>,
+    pub description: String,
+}
+
+impl WfdbSignalInfo {
+    pub fn to_physical(&self, digital: i32) -> f64 {
+        (digital - self.baseline) as f64 / self.gain
+    }
+}
+
+/// The record line of a WFDB header followed by its signals.
+#[derive(Debug, Clone)]
+pub struct WfdbHeader {
+    pub record_name: String,
+    pub sampling_frequency: f64,
+    /// Samples per signal; `None` when the header leaves it out.
+    pub num_samples: Option<usize>,
+    pub base_time: Option<String>,
+    pub base_date: Option<String>,
+    pub signals: Vec<WfdbSignalInfo>,
+    /// Lines starting with `#`, without the marker.
+    pub comments: Vec<String>,
+}
+
+impl WfdbHeader {
+    /// Index of the signal with the given description (e.g. `MLII`), ignoring case and
+    /// surrounding spaces; the error lists the descriptions there are.
+    pub fn find_signal(&self, description: &str) -> Result<usize, Box<dyn Error>> {
+        let descriptions: Vec<&str> = self
+            .signals
+            .iter()
+            .map(|signal| signal.description.as_str())
+            .collect();
+        crate::edf_utils::find_label(&descriptions, description)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(field: &str, name: &str) -> Result<T, Box<dyn Error>> {
+    field
+        .parse::<T>()
+        .map_err(|_| format!("Invalid WFDB header field '{}': {:?}", name, field).into())
+}
+
+fn parse_optional<T: std::str::FromStr>(
+    field: Option<&&str>,
+    name: &str,
+) -> Result<Option<T>, Box<dyn Error>> {
+    field.map(|field| parse_number(field, name)).transpose()
+}
+
+// `fs[/counter_frequency][(base_counter)]`
+fn parse_sampling_frequency(field: &str) -> Result<f64, Box<dyn Error>> {
+    let end = field.find(['/', '(']).unwrap_or(field.len());
+    parse_number(&field[..end], "sampling frequency")
+}
+
+// `format[xsamples_per_frame][:skew][+byte_offset]`
+fn parse_format(field: &str) -> Result<(u16, usize), Box<dyn Error>> {
+    let end = field.find(['x', ':', '+']).unwrap_or(field.len());
+    let format = parse_number(&field[..end], "format")?;
+    if let Some(pos) = field.find('x') {
+        let samples_end = field[pos + 1..]
+            .find([':', '+'])
+            .map_or(field.len(), |i| pos + 1 + i);
+        let samples_per_frame: usize =
+            parse_number(&field[pos + 1..samples_end], "samples per frame")?;
+        if samples_per_frame > 1 {
+            return Err("WFDB signals with several samples per frame are not supported".into());
+        }
+    }
+    let byte_offset = match field.find('+') {
+        Some(pos) => parse_number(&field[pos + 1..], "byte offset")?,
+        None => 0,
+    };
+    Ok((format, byte_offset))
+}
+
+// gain, baseline and units of a signal line
+type GainField = (f64, Option<i32>, Option<String>);
+
+// `gain[(baseline)][/units]`
+fn parse_gain(field: &str) -> Result<GainField, Box<dyn Error>> {
+    let end = field.find(['(', '/']).unwrap_or(field.len());
+    let gain = parse_number(&field[..end], "gain")?;
+    let baseline = match (field.find('('), field.find(')')) {
+        (Some(open), Some(close)) if open < close => {
+            Some(parse_number(&field[open + 1..close], "baseline")?)
+        }
+        _ => None,
+    };
+    let units = field.find('/').map(|pos| field[pos + 1..].to_string());
+    Ok((gain, baseline, units))
+}
+
+fn parse_signal_line(line: &str) -> Result<WfdbSignalInfo, Box<dyn Error>> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 2 {
+        return Err(format!("Invalid WFDB signal line: {:?}", line).into());
+    }
+
+    let (format, byte_offset) = parse_format(fields[1])?;
+    let (gain, baseline, units) = match fields.get(2) {
+        Some(field) => parse_gain(field)?,
+        None => (DEFAULT_GAIN, None, None),
+    };
+    let adc_zero = parse_optional(fields.get(4), "ADC zero")?;
+
+    Ok(WfdbSignalInfo {
+        file_name: fields[0].to_string(),
+        format,
+        byte_offset,
+        // a gain of zero means uncalibrated, stored with the default gain
+        gain: if gain == 0.0 { DEFAULT_GAIN } else { gain },
+        baseline: baseline.or(adc_zero).unwrap_or(0),
+        units: units.unwrap_or_else(|| "mV".to_string()),
+        adc_resolution: parse_optional(fields.get(3), "ADC resolution")?,
+        adc_zero,
+        initial_value: parse_optional(fields.get(5), "initial value")?,
+        checksum: parse_optional(fields.get(6), "checksum")?,
+        block_size: parse_optional(fields.get(7), "block size")?,
+        description: fields.get(8..).unwrap_or_default().join(" "),
+    })
+}
+
+pub fn read_wfdb_header<R: BufRead>(reader: R) -> Result<WfdbHeader, Box<dyn Error>> {
+    let mut comments = Vec::new();
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            comments.push(comment.trim().to_string());
+        } else if !trimmed.is_empty() {
+            lines.push(trimmed.to_string());
+        }
+    }
+
+    let record_line = lines.first().ok_or("Empty WFDB header")?;
+    let fields: Vec<&str> = record_line.split_whitespace().collect();
+    if fields[0].contains('/') {
+        return Err("Multi-segment WFDB records are not supported".into());
+    }
+    let num_signals: usize = match fields.get(1) {
+        Some(field) => parse_number(field, "number of signals")?,
+        None => 0,
+    };
+    let sampling_frequency = match fields.get(2) {
+        Some(field) => parse_sampling_frequency(field)?,
+        None => DEFAULT_FS,
+    };
+
+    if lines.len() < num_signals + 1 {
+        return Err(format!(
+            "WFDB header declares {} signals but describes {}",
+            num_signals,
+            lines.len() - 1
+        )
+        .into());
+    }
+    let signals = lines[1..=num_signals]
+        .iter()
+        .map(|line| parse_signal_line(line))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(WfdbHeader {
+        record_name: fields[0].to_string(),
+        sampling_frequency,
+        num_samples: parse_optional(fields.get(3), "number of samples")?,
+        base_time: fields.get(4).map(|field| field.to_string()),
+        base_date: fields.get(5).map(|field| field.to_string()),
+        signals,
+        comments,
+    })
+}
+
+// 12-bit two's complement samples packed in pairs into 3 bytes
+fn decode_212(bytes: &[u8]) -> Vec<i32> {
+    let sign_extend = |value: i32| if value > 2047 { value - 4096 } else { value };
+    let mut samples = Vec::with_capacity(bytes.len() * 2 / 3);
+    for triple in bytes.chunks(3) {
+        if triple.len() < 2 {
+            break;
+        }
+        samples.push(sign_extend(
+            triple[0] as i32 | ((triple[1] as i32 & 0x0f) << 8),
+        ));
+        if triple.len() == 3 {
+            samples.push(sign_extend(
+                triple[2] as i32 | ((triple[1] as i32 & 0xf0) << 4),
+            ));
+        }
+    }
+    samples
+}
+
+fn decode_16(bytes: &[u8]) -> Vec<i32> {
+    bytes
+        .chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as i32)
+        .collect()
+}
+
+/// The header of a record given by its name or the path of its .hea or .dat file.
+pub fn header_path<P: AsRef<Path>>(record: P) -> PathBuf {
+    record.as_ref().with_extension("hea")
+}
+
+/// Reads one signal of a WFDB record converted to physical units, with times derived from
+/// the sampling frequency. `record` is the record name or the path of its .hea file.
+pub fn read_wfdb_ecg_data<P: AsRef<Path>>(
+    record: P,
+    signal_index: usize,
+) -> Result<Vec<EcgPoint>, Box<dyn Error>> {
+    let header_path = header_path(record);
+    let header = read_wfdb_header(open_input(&header_path)?)?;
+    if signal_index >= header.signals.len() {
+        return Err(format!(
+            "Signal index {} out of range, the record has {} signals",
+            signal_index,
+            header.signals.len()
+        )
+        .into());
+    }
+    let signal = &header.signals[signal_index];
+
+    // signals stored in the same file are interleaved frame by frame
+    let group: Vec<&WfdbSignalInfo> = header
+        .signals
+        .iter()
+        .filter(|s| s.file_name == signal.file_name)
+        .collect();
+    if group.iter().any(|s| s.format != signal.format) {
+        return Err("Signals of one WFDB data file must share a format".into());
+    }
+    let position = header.signals[..signal_index]
+        .iter()
+        .filter(|s| s.file_name == signal.file_name)
+        .count();
+
+    let data_path = header_path.with_file_name(&signal.file_name);
+    let mut bytes = Vec::new();
+    open_input(&data_path)
+        .map_err(|e| format!("Cannot open WFDB data file {:?}: {}", data_path, e))?
+        .read_to_end(&mut bytes)?;
+    let bytes = bytes.get(group[0].byte_offset..).unwrap_or_default();
+
+    let samples = match signal.format {
+        212 => decode_212(bytes),
+        16 => decode_16(bytes),
+        format => return Err(format!("Unsupported WFDB format {}", format).into()),
+    };
+
+    let fs = header.sampling_frequency;
+    let limit = header.num_samples.unwrap_or(usize::MAX);
+    let data: Vec<EcgPoint> = samples
+        .chunks_exact(group.len())
+        .take(limit)
+        .enumerate()
+        .map(|(i, frame)| EcgPoint {
+            time: i as f64 / fs,
+            voltage: signal.to_physical(frame[position]),
+        })
+        .collect();
+
+    info!("Total data points: {}", data.len());
+
+    Ok(data)
+}
+
+/// Annotation code of a normal beat (`N`).
+pub const NORMAL_BEAT: u16 = 1;
+// pseudo-annotation whose 32-bit argument carries intervals too long for 10 bits
+const SKIP: u16 = 59;
+// pseudo-annotations setting the num, subtyp and chan fields of the next annotation
+const NUM: u16 = 60;
+const SUB: u16 = 61;
+const CHN: u16 = 62;
+// pseudo-annotation followed by an auxiliary string
+const AUX: u16 = 63;
+const MAX_INTERVAL: usize = 0x3ff;
+
+/// Whether an annotation code labels a QRS complex (the `isqrs` table of the WFDB library).
+pub fn is_beat(code: u16) -> bool {
+    matches!(code, 1..=13 | 25 | 30 | 34 | 35 | 38 | 41)
+}
+
+fn read_word<R: Read>(reader: &mut R) -> io::Result<Option<u16>> {
+    let mut bytes = [0u8; 2];
+    match reader.read_exact(&mut bytes) {
+        Ok(()) => Ok(Some(u16::from_le_bytes(bytes))),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads a WFDB annotation file in MIT format, returning the sample and code of every
+/// annotation; pseudo-annotations and auxiliary strings are skipped.
+pub fn read_wfdb_annotations<R: Read>(reader: &mut R) -> Result<Vec<(usize, u16)>, Box<dyn Error>> {
+    let mut annotations = Vec::new();
+    let mut sample: i64 = 0;
+
+    while let Some(word) = read_word(reader)? {
+        if word == 0 {
+            break;
+        }
+        let code = word >> 10;
+        let interval = (word & 0x3ff) as i64;
+        match code {
+            SKIP => {
+                let high = read_word(reader)?.ok_or("Truncated WFDB annotation file")?;
+                let low = read_word(reader)?.ok_or("Truncated WFDB annotation file")?;
+                sample += (((high as u32) << 16) | low as u32) as i32 as i64;
+            }
+            NUM | SUB | CHN => {}
+            AUX => {
+                // the string is padded to an even number of bytes
+                let mut aux = vec![0u8; (interval as usize + 1) & !1];
+                reader.read_exact(&mut aux)?;
+            }
+            _ => {
+                sample += interval;
+                annotations.push((sample.max(0) as usize, code));
+            }
+        }
+    }
+
+    Ok(annotations)
+}
+
+fn write_annotation_word<W: Write>(writer: &mut W, code: u16, interval: usize) -> io::Result<()> {
+    let word = (code << 10) | interval as u16;
+    writer.write_all(&word.to_le_bytes())
+}
+
+/// Writes the beats as a WFDB annotation file in MIT format (as read by `rdann` and `bxb`),
+/// every beat labelled normal. Samples are relative to the start of the record.
+pub fn write_wfdb_annotations<W: Write>(
+    writer: &mut W,
+    detections: &[Detection],
+) -> io::Result<()> {
+    let annotations: Vec<(usize, u16)> = detections
+        .iter()
+        .map(|detection| (detection.sample, NORMAL_BEAT))
+        .collect();
+    write_labelled_wfdb_annotations(writer, &annotations)
+}
+
+/// Writes annotations given as sample and code, as returned by `read_wfdb_annotations`, in
+/// MIT format.
+pub fn write_labelled_wfdb_annotations<W: Write>(
+    writer: &mut W,
+    annotations: &[(usize, u16)],
+) -> io::Result<()> {
+    let mut previous = 0;
+    for &(sample, code) in annotations {
+        let interval = sample.saturating_sub(previous);
+        if interval > MAX_INTERVAL {
+            // the 32-bit interval is stored as two little-endian words, high word first
+            write_annotation_word(writer, SKIP, 0)?;
+            let interval = interval as u32;
+            writer.write_all(&((interval >> 16) as u16).to_le_bytes())?;
+            writer.write_all(&(interval as u16).to_le_bytes())?;
+            write_annotation_word(writer, code, 0)?;
+        } else {
+            write_annotation_word(writer, code, interval)?;
+        }
+        previous = sample.max(previous);
+    }
+    // end of file marker
+    write_annotation_word(writer, 0, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // packs pairs of 12-bit samples as format 212 does
+    fn encode_212(samples: &[i32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for pair in samples.chunks(2) {
+            let first = pair[0] & 0xfff;
+            let second = pair.get(1).map_or(0, |sample| sample & 0xfff);
+            bytes.push(first as u8);
+            bytes.push(((first >> 8) | ((second >> 8) << 4)) as u8);
+            if pair.len() == 2 {
+                bytes.push(second as u8);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn decodes_212_sign_extended() {
+        assert_eq!(decode_212(&[0x64, 0xf0, 0x9c]), vec![100, -100]);
+        assert_eq!(decode_212(&[0xff, 0x07]), vec![2047]);
+    }
+
+    #[test]
+    fn reads_back_a_212_record() {
+        let dir = std::env::temp_dir().join(format!("signalweaver_wfdb_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // two interleaved signals, the second with a baseline of 1024
+        let frames = [(100, 1024), (-200, 1224), (2047, 824), (-2048, 1024)];
+        let samples: Vec<i32> = frames.iter().flat_map(|&(a, b)| [a, b]).collect();
+        fs::write(dir.join("rec.dat"), encode_212(&samples)).unwrap();
+        fs::write(
+            dir.join("rec.hea"),
+            "rec 2 360 4\n\
+             rec.dat 212 200 11 0 100 0 0 MLII\n\
+             rec.dat 212 100(1024)/mV 11 1024 1024 0 0 V5\n",
+        )
+        .unwrap();
+
+        let first = read_wfdb_ecg_data(dir.join("rec"), 0).unwrap();
+        let second = read_wfdb_ecg_data(dir.join("rec.hea"), 1).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let voltages: Vec<f64> = first.iter().map(|point| point.voltage).collect();
+        assert_eq!(voltages, vec![0.5, -1.0, 2047.0 / 200.0, -10.24]);
+        let voltages: Vec<f64> = second.iter().map(|point| point.voltage).collect();
+        assert_eq!(voltages, vec![0.0, 2.0, -2.0, 0.0]);
+        assert_eq!(second[3].time, 3.0 / 360.0);
+    }
+
+    #[test]
+    fn round_trips_annotations_with_skip() {
+        let annotations = vec![
+            (10, NORMAL_BEAT),
+            (300, 5),
+            (100_000, NORMAL_BEAT),
+            (100_001, 8),
+        ];
+        let mut bytes = Vec::new();
+        write_labelled_wfdb_annotations(&mut bytes, &annotations).unwrap();
+        // the 99 700 sample gap needs a SKIP pseudo-annotation
+        assert!(bytes
+            .chunks_exact(2)
+            .any(|word| u16::from_le_bytes([word[0], word[1]]) >> 10 == SKIP));
+        assert_eq!(
+            read_wfdb_annotations(&mut bytes.as_slice()).unwrap(),
+            annotations
+        );
+    }
+
+    #[test]
+    fn parses_header_fields() {
+        let header = read_wfdb_header(
+            "# recorded at rest\n100 1 360/1(0) 650000 10:00:00 01/01/2000\n\
+             100.dat 16+24 0 16\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(header.record_name, "100");
+        assert_eq!(header.sampling_frequency, 360.0);
+        assert_eq!(header.num_samples, Some(650_000));
+        assert_eq!(header.base_date.as_deref(), Some("01/01/2000"));
+        assert_eq!(header.comments, vec!["recorded at rest"]);
+        let signal = &header.signals[0];
+        assert_eq!((signal.format, signal.byte_offset), (16, 24));
+        assert_eq!(signal.gain, DEFAULT_GAIN);
+        assert_eq!(signal.adc_resolution, Some(16));
+        assert!(read_wfdb_header("a/b 2 360\n".as_bytes()).is_err());
+    }
+}